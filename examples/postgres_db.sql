@@ -46,7 +46,7 @@ CREATE TABLE things.inventory(
 	id SERIAL,
 	count_in_stock SMALLINT NOT NULL,
 	item_type things.item_type,
-	--price MONEY,
+	price MONEY,
 	price2 NUMERIC,
 	price3 NUMERIC(7,3),
 
@@ -79,14 +79,14 @@ CREATE TYPE other.type_testing AS (
 	-- t_float8 float8,
 	-- t_inet inet,
 	t_integer integer,
-	-- t_interval interval,
+	t_interval interval,
 	-- t_json json,
 	-- t_jsonb jsonb,
 	-- t_line line,
 	-- t_lseg lseg,
 	-- t_macaddr macaddr,
 	-- t_macaddr8 macaddr8,
-	-- t_money money,
+	t_money money,
 	t_numeric numeric(5,5),
 	-- t_path path,
 	-- t_pg_lsn pg_lsn,
@@ -112,6 +112,14 @@ CREATE TABLE other.type_testing2(
 );
 
 
+-- Domains with constraints checked by the generated TryFrom
+CREATE DOMAIN other.percent AS float8 NOT NULL DEFAULT 0 CHECK (VALUE BETWEEN 0 AND 100);
+CREATE DOMAIN other.color AS text CHECK (VALUE IN ('red', 'green', 'blue'));
+CREATE DOMAIN other.short_name AS varchar(20)
+	CONSTRAINT not_empty CHECK (length(VALUE) > 0)
+	CONSTRAINT lower CHECK (VALUE ~ '^[a-z_]+$');
+
+
 CREATE TYPE other.array_test AS (
 	some_ints integer[5]
 );
@@ -1,15 +1,31 @@
 //! Turn the AST of the database from sql_tree into a Rust syntax tree fron syn
 
-use super::{format_rust, sql_tree::*, Opt};
-use heck::*;
+use super::{
+	cargo_toml::apply_template, format_pretty, format_rust, generator::write_if_changed, parse_file, sql_tree::*, Backend,
+	Error, ExistingCargoToml, Formatter, GeneratedOutput, Opt, Warnings,
+};
+use heck::{CamelCase as _, ShoutySnakeCase as _, SnakeCase as _};
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote};
-use std::{fs::File, io::Write};
+use std::{
+	collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+	path::PathBuf,
+};
 
+mod diesel_schema;
+mod domain_check;
+mod mysql_procs;
+mod rusqlite_crud;
+mod schema_check;
+mod sql_copy;
+mod sql_mock;
 mod sql_procs;
+mod sql_query;
 mod sql_types;
+mod sqlx_procs;
 
 /// Type of capitalization to do with heck
+#[allow(clippy::enum_variant_names)]
 enum Case {
 	SnakeCase,
 	CamelCase,
@@ -32,166 +48,408 @@ fn format_ident_h(s: &str) -> proc_macro2::Ident {
 	if let Some(c) = s.chars().next() {
 		if c.is_ascii_digit() {
 			format_ident!("_{}", s)
-		} else {
+		} else if syn::parse_str::<syn::Ident>(s).is_ok() {
 			format_ident!("{}", s)
+		} else if matches!(s, "self" | "Self" | "super" | "crate" | "_") {
+			// keywords which can't be raw identifiers
+			format_ident!("{}_", s)
+		} else {
+			// other keywords i.e. a column named type
+			proc_macro2::Ident::new_raw(s, proc_macro2::Span::call_site())
 		}
 	} else {
 		panic!("formatted identifier should not be empty")
 	}
 }
 
-/// Optionally Format the tokens with rustfmt
-fn maybe_format(input: &TokenStream, opt: &Opt) -> String {
-	let output = input.to_string();
+/// Checks the tokens are a valid rust file then optionally formats them with the chosen formatter, prettyplease is used
+/// if rustfmt isn't installed
+fn maybe_format(input: &TokenStream, opt: &Opt, warnings: &Warnings) -> Result<String, Error> {
 	if opt.ugly {
-		output
+		parse_file(input)?;
+		Ok(input.to_string())
 	} else {
-		format_rust(
-			&output,
-			opt.rustfmt_config.as_deref(),
-			opt.rustfmt_config_path.as_deref(),
-		)
+		match opt.formatter {
+			Formatter::Rustfmt => {
+				parse_file(input)?;
+				let formatted = format_rust(
+					&input.to_string(),
+					opt.rustfmt_config.as_deref(),
+					opt.rustfmt_config_path.as_deref(),
+				);
+				match formatted {
+					Err(Error::NoRustfmt) => {
+						warnings.push(String::from("rustfmt isn't installed, the output was formatted with prettyplease"));
+						format_pretty(input)
+					},
+					formatted => formatted,
+				}
+			},
+			Formatter::Prettyplease => format_pretty(input),
+		}
 	}
 }
 
 impl FullDB {
-	//writes the output text to either a file, directory, or stdout
-	pub fn make_output(&self, opt: &Opt) {
-		let toml_content = opt.get_cargo_toml();
-		if let Some(output_file) = &opt.output {
-			let output_file = output_file.clone();
-			if opt.dir {
-				self.make_full_crate(opt, toml_content, output_file);
-			} else {
-				println!("{}\n", toml_content);
-				let mut f = File::create(output_file).unwrap();
-				let lib_rs_content = maybe_format(&self.to_rust_tokens(opt), &opt);
-				f.write_all(lib_rs_content.as_bytes()).expect("failed to write to file");
+	/// Generate the mapping in memory, see [`GeneratedOutput`] for the layout of the files
+	///
+	/// Fails if a file couldn't be formatted or isn't valid rust
+	pub fn generate(&self, opt: &Opt) -> Result<GeneratedOutput, Error> {
+		self.generate_with_warnings(opt, Warnings::default())
+	}
+
+	/// Generate the mapping in memory, the output's warnings start with those in `warnings` (i.e. from reading the
+	/// database)
+	pub fn generate_with_warnings(&self, opt: &Opt, warnings: Warnings) -> Result<GeneratedOutput, Error> {
+		let warnings = &warnings;
+		if opt.backend.dialect() != self.dialect {
+			return Err(Error::Dialect(opt.backend, self.dialect));
+		}
+		if opt.backend != Backend::Postgres {
+			for (used, option) in [
+				(opt.schema_check, "--schema-check"),
+				(opt.query_builder, "--query-builder"),
+				(opt.copy, "--copy"),
+				(opt.mock, "--mock"),
+				(opt.serde_feature, "--serde-feature"),
+			] {
+				if used {
+					warnings.push(format!(
+						"{} isn't supported by the {} backend and was ignored",
+						option,
+						opt.backend.to_str()
+					));
+				}
 			}
+		}
+		if opt.backend == Backend::Diesel && !self.no_procs() {
+			warnings.push(String::from("Functions aren't mapped by the diesel backend"));
+		}
+		for (used, option) in [
+			(opt.file_per_type, "--file-per-type"),
+			(opt.file_per_function, "--file-per-function"),
+			(opt.schema_features, "--schema-features"),
+		] {
+			if used && !opt.dir {
+				warnings.push(format!("{} only applies with --dir and was ignored", option));
+			}
+		}
+		if opt.existing_cargo_toml != ExistingCargoToml::Overwrite && !opt.dir {
+			warnings.push(String::from("--existing-cargo-toml only applies with --dir and was ignored"));
+		}
+		if opt.dir_only(opt.schema_features) && opt.backend == Backend::Diesel {
+			warnings.push(String::from(
+				"--schema-features isn't supported by the diesel backend and was ignored",
+			));
+		}
+		if opt.uses_schema_features() {
+			let mut features = HashMap::new();
+			for schema in &self.schemas {
+				let feature = schema_feature(&schema.name);
+				if feature != format!("schema-{}", schema.name) {
+					warnings.push(format!(
+						"schema `{}` is behind the feature `{}` as its name has characters that can't be in one",
+						schema.name, feature
+					));
+				}
+				if let Some(other) = features.insert(feature.clone(), &schema.name) {
+					warnings.push(format!(
+						"schemas `{}` and `{}` are both behind the feature `{}`",
+						other, schema.name, feature
+					));
+				}
+			}
+		}
+
+		let mut files = BTreeMap::new();
+		files.insert(
+			PathBuf::from("src/lib.rs"),
+			maybe_format(&self.to_rust_tokens(opt, warnings), opt, warnings)
+				.map_err(|e| Error::InFile(PathBuf::from("src/lib.rs"), Box::new(e)))?,
+		);
+		if opt.dir {
+			self.make_full_crate(opt, warnings, &mut files)?;
+		}
+
+		let mut cargo_toml = if opt.backend == Backend::Diesel {
+			opt.get_diesel_cargo_toml(diesel_schema::column_feature(self, opt, warnings))
 		} else {
-			println!("{}\n", toml_content);
-			let lib_rs_content = maybe_format(&self.to_rust_tokens(opt), &opt);
-			println!("{}", lib_rs_content);
-		}
-	}
-
-	/// Makes a full crate for the mapping into a directory
-	fn make_full_crate(&self, opt: &Opt, toml_content: String, mut output_file: std::path::PathBuf) {
-		//create crate directory
-		std::fs::create_dir_all(&output_file).unwrap();
-
-		//generate Cargo.toml
-		let toml_path = path_push_helper(&output_file, "Cargo.toml");
-		File::create(toml_path)
-			.expect("failed to create Cargo.toml")
-			.write_all(toml_content.as_bytes())
-			.expect("failed to write to file");
-
-		//generate src directory
-		output_file.push("src/");
-		std::fs::create_dir_all(&output_file).unwrap();
-
-		//generate lib.rs file
-		let lib_rs = path_push_helper(&output_file, "lib.rs");
-		File::create(lib_rs)
-			.unwrap()
-			.write_all(
-				maybe_format(&self.to_rust_tokens(opt), &opt).as_bytes(), // maybe_format(&self.to_rust_tokens(opt), &opt).as_bytes()
-			)
-			.expect("failed to write to file");
-
-		//generate types.rs file and types folder
-		let types_rs = path_push_helper(&output_file, "types.rs");
-		File::create(types_rs)
-			.unwrap()
-			.write_all(maybe_format(&self.types_content(opt), &opt).as_bytes())
-			.expect("failed to write to file");
-		let types_folder = path_push_helper(&output_file, "types/");
-		std::fs::create_dir_all(&types_folder).unwrap();
-
-		let sync_folder = path_push_helper(&output_file, "sync_fns/");
-		let async_folder = path_push_helper(&output_file, "async_fns/");
-
-		//generate sync/async files and folders
-		if !self.no_procs() {
-			//generate sync_fns.rs file/folder
-			let sync_rs = path_push_helper(&output_file, "sync_fns.rs");
-			File::create(sync_rs)
-				.unwrap()
-				.write_all(maybe_format(&self.sync_content(opt), &opt).as_bytes())
-				.expect("failed to write to file");
-			std::fs::create_dir_all(&sync_folder).unwrap();
-
-			//generate async_fns.rs file/folder
-			let async_rs = path_push_helper(&output_file, "async_fns.rs");
-			File::create(async_rs)
-				.unwrap()
-				.write_all(maybe_format(&self.async_content(opt), &opt).as_bytes())
-				.expect("failed to write to file");
-			std::fs::create_dir_all(&async_folder).unwrap();
+			opt.get_cargo_toml()
+		};
+		if opt.uses_schema_features() {
+			if !cargo_toml.contains("\n[features]\n") {
+				cargo_toml += "\n[features]\n";
+			}
+			cargo_toml += &self.schema_features_toml();
+		}
+		if let Some(template) = &opt.cargo_template {
+			cargo_toml = apply_template(&cargo_toml, template, opt.package_name.as_deref())?;
+		}
+
+		// the sync and async modules are generated from the same procs so each warning can show up twice, as can those
+		// of the diesel tables which are read again for the Cargo.toml
+		let mut warnings = warnings.take();
+		let mut seen = std::collections::HashSet::new();
+		warnings.retain(|v| seen.insert(v.clone()));
+
+		Ok(GeneratedOutput {
+			files,
+			cargo_toml,
+			existing_cargo_toml: opt.existing_cargo_toml,
+			warnings,
+		})
+	}
+
+	/// The features of the generated Cargo.toml gating each schema, a schema's feature enables those of the schemas whose
+	/// types it uses. Schemas whose names only differ in characters that can't be in a feature share one
+	fn schema_features_toml(&self) -> String {
+		let mut features: Vec<(String, BTreeSet<String>)> = Vec::new();
+		for schema in &self.schemas {
+			let feature = schema_feature(&schema.name);
+			let dependencies: BTreeSet<_> = schema
+				.used_schemas()
+				.into_iter()
+				.filter(|v| self.schemas.iter().any(|schema| schema.name == *v))
+				.map(schema_feature)
+				.filter(|v| *v != feature)
+				.collect();
+			match features.iter_mut().find(|v| v.0 == feature) {
+				Some((_, v)) => v.extend(dependencies),
+				None => features.push((feature, dependencies)),
+			}
+		}
+		let quoted = |v: &String| format!("\"{}\"", v);
+		let mut ret = String::from("# the modules of each schema, which enable those of the schemas whose types they use\n");
+		ret += &format!("default = [{}]\n", features.iter().map(|v| quoted(&v.0)).collect::<Vec<_>>().join(", "));
+		for (feature, dependencies) in &features {
+			let dependencies: Vec<_> = dependencies.iter().map(quoted).collect();
+			ret += &format!("{} = [{}]\n", feature, dependencies.join(", "));
+		}
+		ret
+	}
+
+	/// writes the output text to either a file, directory, or stdout, `warnings` are printed along with those of
+	/// generating in debug mode
+	pub fn make_output(&self, opt: &Opt, warnings: Warnings) -> Result<(), Error> {
+		let output = self.generate_with_warnings(opt, warnings)?;
+		if opt.debug {
+			for warning in &output.warnings {
+				eprintln!("{}", warning);
+			}
+		}
+		match &opt.output {
+			Some(output_file) if opt.dir => {
+				output.write_to(output_file)?;
+				Ok(())
+			},
+			Some(output_file) => {
+				println!("{}\n", output.cargo_toml);
+				write_if_changed(output_file, output.lib_rs())?;
+				Ok(())
+			},
+			None => {
+				println!("{}\n", output.cargo_toml);
+				println!("{}", output.lib_rs());
+				Ok(())
+			},
 		}
+	}
+
+	/// Adds the rest of the files of a full crate to `files`
+	fn make_full_crate(
+		&self,
+		opt: &Opt,
+		warnings: &Warnings,
+		files: &mut BTreeMap<PathBuf, String>,
+	) -> Result<(), Error> {
+		let mut add_file = |path: String, tokens: TokenStream| -> Result<(), Error> {
+			let path = PathBuf::from(path);
+			let content = maybe_format(&tokens, opt, warnings).map_err(|e| Error::InFile(path.clone(), Box::new(e)))?;
+			files.insert(path, content);
+			Ok(())
+		};
 
+		add_file(String::from("src/types.rs"), self.types_content(opt, warnings))?;
+		if opt.backend == Backend::Diesel {
+			add_file(String::from("src/schema.rs"), self.schema_content(opt, warnings))?;
+		} else if opt.backend == Backend::Rusqlite {
+			add_file(String::from("src/crud.rs"), self.crud_content(opt))?;
+		} else if !self.no_procs() {
+			if matches!(opt.backend, Backend::Postgres | Backend::Mysql) {
+				add_file(String::from("src/sync_fns.rs"), self.sync_content(opt, warnings))?;
+			}
+			add_file(String::from("src/async_fns.rs"), self.async_content(opt, warnings))?;
+		}
+		if opt.postgres_only(opt.schema_check) {
+			add_file(
+				String::from("src/schema_check.rs"),
+				schema_check::schema_check_content(self, opt),
+			)?;
+		}
+		if opt.postgres_only(opt.query_builder) {
+			add_file(String::from("src/query.rs"), self.query_content(opt))?;
+		}
+		if opt.postgres_only(opt.copy) {
+			add_file(String::from("src/copy.rs"), self.copy_content(opt))?;
+		}
+		if opt.postgres_only(opt.mock) {
+			add_file(String::from("src/mock.rs"), self.mock_content(opt, warnings))?;
+		}
 
 		// make file for each schema's module
 		for schema in &self.schemas {
-			let file_name = format!("{}.rs", schema.name);
+			let module_name = format_heck(&schema.name, opt, SnakeCase);
 
-			if !schema.no_types() {
-				let schema_t = path_push_helper(&types_folder, &file_name);
-				File::create(schema_t)
-					.unwrap()
-					.write_all(maybe_format(&schema.types_content(opt), &opt).as_bytes())
-					.expect("failed to write to file");
+			if !schema.no_types() && opt.file_per_type {
+				let files = schema.type_files(self, opt, warnings);
+				// the types refer to each other as `super::{schema}::{type}` so those schemas are imported
+				let mut schemas = schema.used_schemas();
+				schemas.insert(&schema.name);
+				let schemas: Vec<_> = schemas.into_iter().map(|v| format_heck(v, opt, SnakeCase)).collect();
+				let taken = schema
+					.types
+					.iter()
+					.map(|v| format_heck(&v.name, opt, CamelCase))
+					.chain(schemas.iter().cloned())
+					.map(|v| file_name(&v))
+					.collect();
+				let modules = submodules(&module_name, &files, &taken);
+				add_file(format!("src/types/{}.rs", module_name), quote! { #(use super::#schemas;)* #modules })?;
+				for (name, content) in files {
+					add_file(
+						format!("src/types/{}/{}.rs", file_name(&module_name), name),
+						quote! { use super::super::*; #content },
+					)?;
+				}
+			} else if !schema.no_types() {
+				add_file(format!("src/types/{}.rs", module_name), schema.types_content(self, opt, warnings))?;
 			}
 
-			if !schema.no_procs() {
-				let schema_s = path_push_helper(&sync_folder, &file_name);
-				let schema_a = path_push_helper(&async_folder, &file_name);
+			if opt.backend == Backend::Diesel && diesel_schema::has_items(schema) {
+				add_file(
+					format!("src/schema/{}.rs", module_name),
+					diesel_schema::schema_content(schema, self, opt, warnings),
+				)?;
+			}
 
-				File::create(schema_s)
-					.unwrap()
-					.write_all(maybe_format(&schema.funcs_content(opt, true), &opt).as_bytes())
-					.expect("failed to write to file");
+			if opt.backend == Backend::Rusqlite && rusqlite_crud::has_relations(schema) {
+				add_file(format!("src/crud/{}.rs", module_name), rusqlite_crud::crud_content(schema, opt))?;
+			}
+
+			if !matches!(opt.backend, Backend::Diesel | Backend::Rusqlite) && !schema.no_procs() {
+				let mut modules = vec![("async_fns", false)];
+				if matches!(opt.backend, Backend::Postgres | Backend::Mysql) {
+					modules.insert(0, ("sync_fns", true));
+				}
+				for (module, is_sync) in modules {
+					if opt.file_per_function {
+						let files = schema.func_files(self, opt, warnings, is_sync);
+						// functions aren't in the namespace of modules so they can have the same names
+						let modules = submodules(&module_name, &files, &HashSet::new());
+						add_file(format!("src/{}/{}.rs", module, module_name), modules)?;
+						for (name, content) in files {
+							add_file(
+								format!("src/{}/{}/{}.rs", module, file_name(&module_name), name),
+								quote! { use super::super::*; #content },
+							)?;
+						}
+					} else {
+						add_file(
+							format!("src/{}/{}.rs", module, module_name),
+							schema.funcs_content(self, opt, warnings, is_sync),
+						)?;
+					}
+				}
+			}
+
+			if opt.postgres_only(opt.query_builder) && sql_query::has_relations(schema) {
+				add_file(
+					format!("src/query/{}.rs", module_name),
+					sql_query::query_content(schema, opt),
+				)?;
+			}
+
+			if opt.postgres_only(opt.copy) && sql_copy::has_relations(schema) {
+				add_file(format!("src/copy/{}.rs", module_name), sql_copy::copy_content(schema, opt))?;
+			}
 
-				File::create(schema_a)
-					.unwrap()
-					.write_all(maybe_format(&schema.funcs_content(opt, false), &opt).as_bytes())
-					.expect("failed to write to file");
+			if opt.postgres_only(opt.mock) && sql_mock::has_methods(schema, opt, warnings) {
+				add_file(format!("src/mock/{}.rs", module_name), sql_mock::mock_content(schema, opt, warnings))?;
 			}
 		}
+		Ok(())
 	}
 
 	/// Get the rust tokens for the top level of the mapping (it changes depending on whether the dir option is used)
-	fn to_rust_tokens(&self, opt: &Opt) -> TokenStream {
+	fn to_rust_tokens(&self, opt: &Opt, warnings: &Warnings) -> TokenStream {
 		if opt.dir {
-			self.to_dir_tokens(opt)
+			self.to_dir_tokens(opt, warnings)
 		} else {
-			self.to_flat_tokens(opt)
+			self.to_flat_tokens(opt, warnings)
 		}
 	}
 
 	/// builds the contents of the types module
-	pub fn types_content(&self, opt: &Opt) -> TokenStream {
-		let schemas = self.schemas.iter().map(|v| v.get_types_module(opt));
+	pub fn types_content(&self, opt: &Opt, warnings: &Warnings) -> TokenStream {
+		let schemas = self.schemas.iter().map(|v| v.get_types_module(self, opt, warnings));
+
+		let serde_cfg = if opt.serde_feature {
+			quote! { #[cfg(feature = "serde")] }
+		} else {
+			quote! {}
+		};
+
+		let backend_uses = match opt.backend {
+			Backend::Postgres => quote! {
+				#serde_cfg
+				use serde::{
+					Serialize,
+					Deserialize,
+				};
+				use postgres_types::{
+					FromSql,
+					ToSql,
+				};
+			},
+			Backend::Sqlx | Backend::Diesel | Backend::Rusqlite | Backend::Mysql => quote! {},
+		};
 
 		quote! {
-			use serde::{
-				Serialize,
-				Deserialize,
-			};
-			use postgres_types::{
-				FromSql,
-				ToSql,
-			};
+			#backend_uses
 			use super::orm::*;
 
 			#(#schemas)*
 		}
 	}
 
+	/// builds the contents of the schema module of the diesel backend
+	pub fn schema_content(&self, opt: &Opt, warnings: &Warnings) -> TokenStream {
+		let schemas = self.schemas.iter().map(|v| v.get_schema_module(self, opt, warnings));
+		let joins = diesel_schema::joins(self, opt, warnings);
+
+		quote! {
+			#(#schemas)*
+
+			#joins
+		}
+	}
+
+	/// builds the contents of the crud module of the rusqlite backend
+	pub fn crud_content(&self, opt: &Opt) -> TokenStream {
+		let schemas = self.schemas.iter().map(|v| v.get_crud_module(opt));
+
+		quote! {
+			#(#schemas)*
+		}
+	}
+
 	/// builds the contents of the sync_fns module
-	pub fn sync_content(&self, opt: &Opt) -> TokenStream {
-		let schemas = self.schemas.iter().map(|v| v.get_funcs_module(opt, true));
+	pub fn sync_content(&self, opt: &Opt, warnings: &Warnings) -> TokenStream {
+		let schemas = self.schemas.iter().map(|v| v.get_funcs_module(self, opt, warnings, true));
+
+		if opt.backend == Backend::Mysql {
+			return quote! { #(#schemas)* };
+		}
 
 		quote! {
 			pub use super::orm::{
@@ -205,12 +463,17 @@ impl FullDB {
 	}
 
 	/// builds the contents of the async_fns module
-	pub fn async_content(&self, opt: &Opt) -> TokenStream {
-		let schemas = self.schemas.iter().map(|v| v.get_funcs_module(opt, false));
+	pub fn async_content(&self, opt: &Opt, warnings: &Warnings) -> TokenStream {
+		let schemas = self.schemas.iter().map(|v| v.get_funcs_module(self, opt, warnings, false));
+
+		if matches!(opt.backend, Backend::Sqlx | Backend::Mysql) {
+			return quote! { #(#schemas)* };
+		}
 
 		quote! {
 			pub use super::orm::{
 				AsyncClient as Client,
+				AsyncConnection,
 				SqlError,
 			};
 			use sql_db_mapper_core::TryFromRow;
@@ -220,20 +483,147 @@ impl FullDB {
 		}
 	}
 
+	/// builds the contents of the query module
+	pub fn query_content(&self, opt: &Opt) -> TokenStream {
+		let schemas = self.schemas.iter().map(|v| v.get_query_module(opt));
+
+		quote! {
+			use super::orm::query::{
+				Query,
+				Table,
+				TypedColumn,
+			};
+
+			#(#schemas)*
+		}
+	}
+
+	/// builds the contents of the copy module
+	pub fn copy_content(&self, opt: &Opt) -> TokenStream {
+		let schemas = self.schemas.iter().map(|v| v.get_copy_module(opt));
+
+		quote! {
+			use super::orm::{
+				copy::{
+					BinaryCopyOutRow,
+					CopyIn,
+					CopyOut,
+					CopyTable,
+					Stream,
+				},
+				AsyncClient,
+				SqlError,
+				SyncClient,
+				ToSql,
+			};
+
+			#(#schemas)*
+		}
+	}
+
+	/// builds the contents of the mock module
+	pub fn mock_content(&self, opt: &Opt, warnings: &Warnings) -> TokenStream {
+		let schemas = self.schemas.iter().map(|v| v.get_mock_module(opt, warnings));
+
+		quote! {
+			use super::orm::{
+				mock::{
+					MockFn,
+					QueryCall,
+				},
+				AsyncClient,
+				SqlError,
+				SyncClient,
+				TryFromRow,
+			};
+			#[cfg(feature = "async")]
+			use async_trait::async_trait;
+
+			#(#schemas)*
+		}
+	}
+
 	/// The tokens for FullDb when the whole mapping is being made into one file
-	fn to_flat_tokens(&self, opt: &Opt) -> TokenStream {
-		let opt_tokens = crate_root_start(opt);
+	fn to_flat_tokens(&self, opt: &Opt, warnings: &Warnings) -> TokenStream {
+		let opt_tokens = crate_root_start(self, opt, warnings);
+		let opt_tokens = if opt.postgres_only(opt.schema_check) {
+			let schema_check_tokens = schema_check::schema_check_content(self, opt);
+			quote! {
+				#opt_tokens
 
-		let types_tokens = self.types_content(opt);
-		if self.no_procs() {
+				pub mod schema_check{ use super::*; #schema_check_tokens }
+			}
+		} else {
+			opt_tokens
+		};
+		let opt_tokens = if opt.postgres_only(opt.query_builder) {
+			let query_tokens = self.query_content(opt);
+			quote! {
+				#opt_tokens
+
+				pub mod query{ use super::*; #query_tokens }
+			}
+		} else {
+			opt_tokens
+		};
+		let opt_tokens = if opt.postgres_only(opt.copy) {
+			let copy_tokens = self.copy_content(opt);
 			quote! {
 				#opt_tokens
 
+				pub mod copy{ use super::*; #copy_tokens }
+			}
+		} else {
+			opt_tokens
+		};
+		let opt_tokens = if opt.postgres_only(opt.mock) {
+			let mock_tokens = self.mock_content(opt, warnings);
+			quote! {
+				#opt_tokens
+
+				pub mod mock{ use super::*; #mock_tokens }
+			}
+		} else {
+			opt_tokens
+		};
+
+		let types_tokens = self.types_content(opt, warnings);
+		if opt.backend == Backend::Diesel {
+			let schema_tokens = self.schema_content(opt, warnings);
+
+			quote! {
+				#opt_tokens
+
+				pub mod schema{ use super::*; #schema_tokens }
 				pub mod types{ use super::*; #types_tokens }
 			}
+		} else if opt.backend == Backend::Rusqlite {
+			let crud_tokens = self.crud_content(opt);
+
+			quote! {
+				#opt_tokens
+
+				pub mod types{ use super::*; #types_tokens }
+				pub mod crud{ use super::*; #crud_tokens }
+			}
+		} else if self.no_procs() {
+			quote! {
+				#opt_tokens
+
+				pub mod types{ use super::*; #types_tokens }
+			}
+		} else if opt.backend == Backend::Sqlx {
+			let async_tokens = self.async_content(opt, warnings);
+
+			quote! {
+				#opt_tokens
+
+				pub mod types{ use super::*; #types_tokens }
+				pub mod async_fns{ use super::*; #async_tokens }
+			}
 		} else {
-			let sync_tokens = self.sync_content(opt);
-			let async_tokens = self.async_content(opt);
+			let sync_tokens = self.sync_content(opt, warnings);
+			let async_tokens = self.async_content(opt, warnings);
 
 			quote! {
 				#opt_tokens
@@ -248,14 +638,71 @@ impl FullDB {
 	}
 
 	/// The tokens for FullDb when a directory structure is being created
-	fn to_dir_tokens(&self, opt: &Opt) -> TokenStream {
-		let opt_tokens = crate_root_start(opt);
-		if self.no_procs() {
+	fn to_dir_tokens(&self, opt: &Opt, warnings: &Warnings) -> TokenStream {
+		let opt_tokens = crate_root_start(self, opt, warnings);
+		let opt_tokens = if opt.postgres_only(opt.schema_check) {
+			quote! {
+				#opt_tokens
+
+				pub mod schema_check;
+			}
+		} else {
+			opt_tokens
+		};
+		let opt_tokens = if opt.postgres_only(opt.query_builder) {
+			quote! {
+				#opt_tokens
+
+				pub mod query;
+			}
+		} else {
+			opt_tokens
+		};
+		let opt_tokens = if opt.postgres_only(opt.copy) {
+			quote! {
+				#opt_tokens
+
+				pub mod copy;
+			}
+		} else {
+			opt_tokens
+		};
+		let opt_tokens = if opt.postgres_only(opt.mock) {
+			quote! {
+				#opt_tokens
+
+				pub mod mock;
+			}
+		} else {
+			opt_tokens
+		};
+		if opt.backend == Backend::Diesel {
+			quote! {
+				#opt_tokens
+
+				pub mod schema;
+				pub mod types;
+			}
+		} else if opt.backend == Backend::Rusqlite {
+			quote! {
+				#opt_tokens
+
+				pub mod types;
+				pub mod crud;
+			}
+		} else if self.no_procs() {
 			quote! {
 				#opt_tokens
 
 				pub mod types;
 			}
+		} else if opt.backend == Backend::Sqlx {
+			quote! {
+				#opt_tokens
+
+				pub mod types;
+				pub mod async_fns;
+			}
 		} else {
 			quote! {
 				#opt_tokens
@@ -269,22 +716,18 @@ impl FullDB {
 		}
 	}
 }
-fn path_push_helper(path: &std::path::PathBuf, extention: &str) -> std::path::PathBuf {
-	let mut p = path.clone();
-	p.push(extention);
-	p
-}
 
 impl Schema {
 	///gets the content for this schema as it would appears in the `types` module
-	fn get_types_module(&self, opt: &Opt) -> TokenStream {
+	fn get_types_module(&self, db: &FullDB, opt: &Opt, warnings: &Warnings) -> TokenStream {
 		let name = format_heck(&self.name, opt, SnakeCase);
+		let cfg = self.feature_cfg(opt);
 		if self.no_types() {
 			quote! {}
 		} else if opt.dir {
-			quote! { pub mod #name; }
+			quote! { #cfg pub mod #name; }
 		} else {
-			let content = self.types_content(opt);
+			let content = self.types_content(db, opt, warnings);
 			quote! {
 				pub mod #name {
 					#content
@@ -293,23 +736,77 @@ impl Schema {
 		}
 	}
 
-	fn types_content(&self, opt: &Opt) -> TokenStream {
-		let type_defs = self.types.iter().map(|v| sql_types::type_to_rust(&v, opt));
+	fn types_content(&self, db: &FullDB, opt: &Opt, warnings: &Warnings) -> TokenStream {
+		let type_defs = self.types.iter().map(|v| sql_types::type_to_rust(v, &self.name, db, opt, warnings));
 		quote! {
 			use super::*;
 			#(#type_defs)*
 		}
 	}
 
+	///gets the content for this schema as it would appears in the `schema` module of the diesel backend
+	fn get_schema_module(&self, db: &FullDB, opt: &Opt, warnings: &Warnings) -> TokenStream {
+		let name = format_heck(&self.name, opt, SnakeCase);
+		if !diesel_schema::has_items(self) {
+			quote! {}
+		} else if opt.dir {
+			quote! { pub mod #name; }
+		} else {
+			let content = diesel_schema::schema_content(self, db, opt, warnings);
+			quote! {
+				pub mod #name {
+					#content
+				}
+			}
+		}
+	}
+
 	///gets the content for this schema as it would appears in the `sync_fns` and `async_fns` module
-	fn get_funcs_module(&self, opt: &Opt, is_sync: bool) -> TokenStream {
+	fn get_funcs_module(&self, db: &FullDB, opt: &Opt, warnings: &Warnings, is_sync: bool) -> TokenStream {
 		let name = format_heck(&self.name, opt, SnakeCase);
+		let cfg = self.feature_cfg(opt);
 		if self.no_procs() {
 			quote! {}
 		} else if opt.dir {
-			quote! { pub mod #name; }
+			quote! { #cfg pub mod #name; }
+		} else {
+			let content = self.funcs_content(db, opt, warnings, is_sync);
+			quote! {
+				pub mod #name {
+					#content
+				}
+			}
+		}
+	}
+
+	///gets the content for this schema as it would appears in the `query` module
+	fn get_query_module(&self, opt: &Opt) -> TokenStream {
+		let name = format_heck(&self.name, opt, SnakeCase);
+		let cfg = self.feature_cfg(opt);
+		if !sql_query::has_relations(self) {
+			quote! {}
+		} else if opt.dir {
+			quote! { #cfg pub mod #name; }
+		} else {
+			let content = sql_query::query_content(self, opt);
+			quote! {
+				pub mod #name {
+					#content
+				}
+			}
+		}
+	}
+
+	///gets the content for this schema as it would appears in the `copy` module
+	fn get_copy_module(&self, opt: &Opt) -> TokenStream {
+		let name = format_heck(&self.name, opt, SnakeCase);
+		let cfg = self.feature_cfg(opt);
+		if !sql_copy::has_relations(self) {
+			quote! {}
+		} else if opt.dir {
+			quote! { #cfg pub mod #name; }
 		} else {
-			let content = self.funcs_content(opt, is_sync);
+			let content = sql_copy::copy_content(self, opt);
 			quote! {
 				pub mod #name {
 					#content
@@ -318,8 +815,103 @@ impl Schema {
 		}
 	}
 
-	fn funcs_content(&self, opt: &Opt, is_sync: bool) -> TokenStream {
-		let proc_defs = self.procs.iter().map(|v| sql_procs::proc_to_rust(&v, opt, is_sync));
+	///gets the content for this schema as it would appears in the `crud` module of the rusqlite backend
+	fn get_crud_module(&self, opt: &Opt) -> TokenStream {
+		let name = format_heck(&self.name, opt, SnakeCase);
+		let cfg = self.feature_cfg(opt);
+		if !rusqlite_crud::has_relations(self) {
+			quote! {}
+		} else if opt.dir {
+			quote! { #cfg pub mod #name; }
+		} else {
+			let content = rusqlite_crud::crud_content(self, opt);
+			quote! {
+				pub mod #name {
+					#content
+				}
+			}
+		}
+	}
+
+	///gets the content for this schema as it would appears in the `mock` module
+	fn get_mock_module(&self, opt: &Opt, warnings: &Warnings) -> TokenStream {
+		let name = format_heck(&self.name, opt, SnakeCase);
+		let cfg = self.feature_cfg(opt);
+		if !sql_mock::has_methods(self, opt, warnings) {
+			quote! {}
+		} else if opt.dir {
+			quote! { #cfg pub mod #name; }
+		} else {
+			let content = sql_mock::mock_content(self, opt, warnings);
+			quote! {
+				pub mod #name {
+					#content
+				}
+			}
+		}
+	}
+
+	/// The `#[cfg]` putting the schema's modules behind its feature
+	fn feature_cfg(&self, opt: &Opt) -> TokenStream {
+		if opt.uses_schema_features() {
+			let feature = schema_feature(&self.name);
+			quote! { #[cfg(feature = #feature)] }
+		} else {
+			quote! {}
+		}
+	}
+
+	/// The names of the other schemas whose types are used by this schema's types and functions
+	fn used_schemas(&self) -> BTreeSet<&str> {
+		let mut ret = BTreeSet::new();
+		for typ in &self.types {
+			match &typ.typ {
+				PsqlTypType::Composite(c) => ret.extend(c.cols.iter().map(|v| v.type_ns_name.as_str())),
+				PsqlTypType::Domain(d) => {
+					ret.insert(d.base_ns_name.as_str());
+				},
+				PsqlTypType::SimpleComposite(v) => ret.extend(v.0.iter().map(|v| v.typ.schema.as_str())),
+				PsqlTypType::Enum(_) | PsqlTypType::Base(_) | PsqlTypType::Other(_) => {},
+			}
+		}
+		for proc in self.procs.iter().flatten() {
+			ret.extend(proc.inputs.0.iter().map(|v| v.typ.schema.as_str()));
+			ret.insert(proc.outputs.schema.as_str());
+		}
+		ret.remove(self.name.as_str());
+		ret
+	}
+
+	/// Each type that's mapped as the name of its file and its contents
+	fn type_files(&self, db: &FullDB, opt: &Opt, warnings: &Warnings) -> Vec<(String, TokenStream)> {
+		let files = self
+			.types
+			.iter()
+			.map(|v| (v.name.as_str(), sql_types::type_to_rust(v, &self.name, db, opt, warnings)));
+		item_files(files, opt)
+	}
+
+	/// Each function that's mapped (with its overloads) as the name of its file and its contents
+	fn func_files(&self, db: &FullDB, opt: &Opt, warnings: &Warnings, is_sync: bool) -> Vec<(String, TokenStream)> {
+		let files = self.procs.iter().map(|v| {
+			let content = match opt.backend {
+				Backend::Postgres => sql_procs::proc_to_rust(v, opt, warnings, is_sync),
+				Backend::Sqlx => sqlx_procs::proc_to_rust(v, db, opt, warnings),
+				Backend::Mysql => mysql_procs::proc_to_rust(v, db, opt, is_sync),
+				Backend::Diesel | Backend::Rusqlite => quote! {},
+			};
+			(v[0].name.as_str(), content)
+		});
+		item_files(files, opt)
+	}
+
+	fn funcs_content(&self, db: &FullDB, opt: &Opt, warnings: &Warnings, is_sync: bool) -> TokenStream {
+		let proc_defs = self.procs.iter().map(|v| match opt.backend {
+			Backend::Postgres => sql_procs::proc_to_rust(v, opt, warnings, is_sync),
+			Backend::Sqlx => sqlx_procs::proc_to_rust(v, db, opt, warnings),
+			Backend::Mysql => mysql_procs::proc_to_rust(v, db, opt, is_sync),
+			Backend::Diesel | Backend::Rusqlite => quote! {},
+		});
 		quote! {
 			use super::*;
 			#(#proc_defs)*
@@ -350,7 +942,7 @@ impl NamesAndTypes {
 }
 
 /// Get the tokens that go at the top of the mapping, some uses, docs, and attributes
-fn crate_root_start(opt: &Opt) -> TokenStream {
+fn crate_root_start(db: &FullDB, opt: &Opt, warnings: &Warnings) -> TokenStream {
 	//allows if case isn't fixed
 	let fixed_case = if opt.rust_case {
 		quote! {}
@@ -361,6 +953,12 @@ fn crate_root_start(opt: &Opt) -> TokenStream {
 		}
 	};
 
+	let backend_attrs = if opt.backend == Backend::Diesel {
+		diesel_schema::crate_attrs(db, opt, warnings)
+	} else {
+		quote! {}
+	};
+
 	let doc_str = format!("Generated by sql_db_mapper version={}", super::VERSION);
 	let call_params = format!("Called with arguments `{}`", opt.get_call_string());
 
@@ -370,7 +968,159 @@ fn crate_root_start(opt: &Opt) -> TokenStream {
 		#![doc = #call_params]
 		#![allow(unused_imports)]
 		#fixed_case
+		#backend_attrs
 		pub use sql_db_mapper_core as orm;
 		use orm::*;
 	}
 }
+
+/// The feature of the generated crate a schema's modules are behind, characters that can't be in a feature name are
+/// replaced with `_`
+fn schema_feature(schema: &str) -> String {
+	let name: String = schema
+		.chars()
+		.map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+		.collect();
+	format!("schema-{}", name)
+}
+
+/// The name of a file or directory for a module's identifier
+fn file_name(module_name: &proc_macro2::Ident) -> String {
+	module_name.to_string().trim_start_matches("r#").to_string()
+}
+
+/// The items with any contents, named after the snake case form of the item's name. Names which are the same once
+/// formatted get a number after them
+fn item_files<'a>(items: impl Iterator<Item = (&'a str, TokenStream)>, opt: &Opt) -> Vec<(String, TokenStream)> {
+	let mut seen = std::collections::HashSet::new();
+	items
+		.filter(|(_, content)| !content.is_empty())
+		.map(|(name, content)| {
+			let name = file_name(&format_heck(name, opt, SnakeCase));
+			let mut unique = name.clone();
+			let mut i = 1;
+			while !seen.insert(unique.to_lowercase()) {
+				unique = format!("{}_{}", name, i);
+				i += 1;
+			}
+			(unique, content)
+		})
+		.collect()
+}
+
+/// The modules of the files of a schema's items, which are re-exported so the items are still in the schema's module.
+/// Each module is its own unit of incremental compilation so changing one item doesn't recompile the others
+///
+/// The schema's module doesn't glob import its parent as the items would then be ambiguous with the parent's items of
+/// the same name (i.e. `pg_catalog::Cidr` and `orm::Cidr`), the files import the parent's parent instead. A module
+/// can't have the same name as a type it re-exports (i.e. `int4` without `rust_case`) or a schema imported into the
+/// schema's module so a file named the same as one of `taken` is given a module named `{file}_type` with a `#[path]`
+/// to the file
+fn submodules(
+	module_name: &proc_macro2::Ident,
+	files: &[(String, TokenStream)],
+	taken: &HashSet<String>,
+) -> TokenStream {
+	let taken_by = |module: &String| taken.contains(module) || files.iter().any(|(name, _)| name == module);
+	let modules = files.iter().map(|(name, _)| {
+		if taken.contains(name) {
+			let mut module = format!("{}_type", name);
+			while taken_by(&module) {
+				module += "_";
+			}
+			let module = format_ident_h(&module);
+			let path = format!("{}/{}.rs", file_name(module_name), name);
+			quote! {
+				#[path = #path]
+				mod #module;
+				pub use #module::*;
+			}
+		} else {
+			let module = format_ident_h(name);
+			quote! {
+				mod #module;
+				pub use #module::*;
+			}
+		}
+	});
+	quote! { #(#modules)* }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn db(schemas: &[&str]) -> FullDB {
+		let mut db = FullDB::new(Dialect::Postgres);
+		for (id, name) in schemas.iter().enumerate() {
+			db.add_schema(Schema {
+				id: id as u32,
+				name: String::from(*name),
+				owner_name: String::from("postgres"),
+				types: Vec::new(),
+				procs: Vec::new(),
+			});
+		}
+		db
+	}
+
+	#[test]
+	fn schema_feature_names() {
+		assert_eq!(schema_feature("people"), "schema-people");
+		assert_eq!(schema_feature("my-schema_2"), "schema-my-schema_2");
+		assert_eq!(schema_feature("my schema"), "schema-my_schema");
+		assert_eq!(schema_feature("app.v1"), "schema-app_v1");
+		assert_eq!(schema_feature("café"), "schema-caf_");
+	}
+
+	#[test]
+	fn clashing_submodules_get_a_path() {
+		let files = vec![
+			("int4".to_string(), TokenStream::new()),
+			("int4_type".to_string(), TokenStream::new()),
+			("person".to_string(), TokenStream::new()),
+		];
+		let taken = vec!["int4".to_string(), "people".to_string()].into_iter().collect();
+		let modules = submodules(&format_ident_h("pg_catalog"), &files, &taken);
+		let expected = quote! {
+			#[path = "pg_catalog/int4.rs"]
+			mod int4_type_;
+			pub use int4_type_::*;
+			mod int4_type;
+			pub use int4_type::*;
+			mod person;
+			pub use person::*;
+		};
+		assert_eq!(modules.to_string(), expected.to_string());
+	}
+
+	#[test]
+	fn schemas_can_share_a_feature() {
+		let db = db(&["a b", "a_b", "c"]);
+		assert_eq!(
+			db.schema_features_toml(),
+			"# the modules of each schema, which enable those of the schemas whose types they use
+default = [\"schema-a_b\", \"schema-c\"]
+schema-a_b = []
+schema-c = []
+"
+		);
+
+		let opt = Opt {
+			dir: true,
+			schema_features: true,
+			// the names of the modules are made valid identifiers
+			rust_case: true,
+			ugly: true,
+			..Opt::default()
+		};
+		let output = db.generate(&opt).unwrap();
+		assert_eq!(
+			output.warnings,
+			[
+				"schema `a b` is behind the feature `schema-a_b` as its name has characters that can't be in one",
+				"schemas `a b` and `a_b` are both behind the feature `schema-a_b`",
+			]
+		);
+	}
+}
@@ -0,0 +1,758 @@
+//! Functions for generating the `schema` module of the diesel backend, the `table!` macros and the sql types they use
+use super::{
+	super::{sql_tree::*, Opt, ThirdParty::*, Warnings},
+	format_heck,
+	Case::*,
+};
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use std::collections::HashSet;
+
+/// Oids below this are built into postgres, the rest are made by users or extensions
+const FIRST_NORMAL_OBJECT_ID: u32 = 16384;
+
+/// The most columns diesel's `table!` takes, with its `128-column-tables` feature
+const MAX_COLUMNS: usize = 128;
+
+/// A table or view which can be given a `table!`
+pub struct Table<'a> {
+	/// The columns diesel has a sql type for, with that type
+	pub cols: Vec<(&'a Column, TokenStream)>,
+	/// The primary key, or the first column of a view
+	pub key: Vec<&'a str>,
+}
+
+/// Whether the schema has any enums or relations to put in its module of `schema`
+pub fn has_items(schema: &Schema) -> bool {
+	schema
+		.types
+		.iter()
+		.any(|v| matches!(&v.typ, PsqlTypType::Enum(_)) || matches!(&v.typ, PsqlTypType::Composite(c) if c.kind.is_relation()))
+}
+
+fn relations(schema: &Schema) -> impl Iterator<Item = (&PsqlType, &PsqlCompositeType)> {
+	schema.types.iter().filter_map(|typ| match &typ.typ {
+		PsqlTypType::Composite(c) if c.kind.is_relation() => Some((typ, c)),
+		_ => None,
+	})
+}
+
+/// Builds the contents of a schema's module in `schema`, the sql types of its enums and a `table!` for each table or view
+///
+/// ```ignore
+/// pub mod sql_types {
+/// 	#[derive(Debug, Clone, ::diesel::sql_types::SqlType, ::diesel::query_builder::QueryId)]
+/// 	#[diesel(postgres_type(name = "item_type", schema = "things"))]
+/// 	pub struct ItemType;
+/// }
+/// ::diesel::table! {
+/// 	things.inventory (id) {
+/// 		id -> ::diesel::sql_types::Int4,
+/// 		item_type -> ::diesel::sql_types::Nullable<crate::schema::things::sql_types::ItemType>,
+/// 	}
+/// }
+/// ```
+pub fn schema_content(schema: &Schema, db: &FullDB, opt: &Opt, warnings: &Warnings) -> TokenStream {
+	let markers: Vec<_> = schema
+		.types
+		.iter()
+		.filter(|v| matches!(v.typ, PsqlTypType::Enum(_)))
+		.map(|typ| {
+			let name_type = type_ident(&typ.name, opt);
+			let sql_name = &typ.name;
+			let schema_name = &schema.name;
+			let doc = format!(" The sql type of the enum `{}.{}`", schema_name, sql_name);
+			quote! {
+				#[doc = #doc]
+				#[derive(Debug, Clone, ::diesel::sql_types::SqlType, ::diesel::query_builder::QueryId)]
+				#[diesel(postgres_type(name = #sql_name, schema = #schema_name))]
+				pub struct #name_type;
+			}
+		})
+		.collect();
+	let sql_types = if markers.is_empty() {
+		quote! {}
+	} else {
+		quote! {
+			pub mod sql_types {
+				#(#markers)*
+			}
+		}
+	};
+
+	let tables = relations(schema).filter_map(|(typ, c)| {
+		let table = table(&schema.name, &typ.name, c, db, opt, warnings)?;
+		let table_name = format_heck(&typ.name, opt, SnakeCase);
+		let table_attr = sql_name_attr(&table_name, &typ.name);
+		let schema_prefix = if schema.name == "public" {
+			quote! {}
+		} else {
+			let schema_name = format_ident!("{}", schema.name);
+			quote! { #schema_name. }
+		};
+		let doc = if c.kind == RelKind::Table {
+			format!(" `{}.{}`", schema.name, typ.name)
+		} else {
+			format!(" `{}.{}`, its first column is used as the primary key", schema.name, typ.name)
+		};
+		let key = table.key.iter().map(|v| format_heck(v, opt, SnakeCase));
+		let columns = table.cols.iter().map(|(col, sql_type)| {
+			let column_name = format_heck(&col.name, opt, SnakeCase);
+			let column_attr = sql_name_attr(&column_name, &col.name);
+			quote! {
+				#column_attr
+				#column_name -> #sql_type
+			}
+		});
+
+		Some(quote! {
+			::diesel::table! {
+				#[doc = #doc]
+				#table_attr
+				#schema_prefix #table_name (#(#key),*) {
+					#(#columns,)*
+				}
+			}
+		})
+	});
+
+	quote! {
+		#sql_types
+		#(#tables)*
+	}
+}
+
+/// `joinable!` for the foreign keys diesel can join on and `allow_tables_to_appear_in_same_query!` for every table
+///
+/// diesel only joins on a foreign key of one column referencing the whole primary key and only allows one between two tables,
+/// the others are left out with a warning
+pub fn joins(db: &FullDB, opt: &Opt, warnings: &Warnings) -> TokenStream {
+	let tables = tables(db, opt, warnings);
+	let path = |schema: &str, name: &str| {
+		let schema = format_heck(schema, opt, SnakeCase);
+		let name = format_heck(name, opt, SnakeCase);
+		quote! { #schema::#name }
+	};
+
+	let mut joined = HashSet::new();
+	let mut joinables = Vec::new();
+	for (schema, name, c, table) in &tables {
+		for fk in &c.foreign_keys {
+			let parent = match tables.iter().find(|v| v.0 == fk.ref_schema && v.1 == fk.ref_table) {
+				Some(v) => v,
+				// the table that isn't mapped was already warned about
+				None => continue,
+			};
+			let full_name = format!("{}.{}", schema, name);
+			let ref_name = format!("{}.{}", fk.ref_schema, fk.ref_table);
+			let reason = if fk.cols.len() != 1 {
+				Some("it has more than one column")
+			} else if parent.3.key.iter().ne(fk.ref_cols.iter()) {
+				Some("it doesn't reference the primary key")
+			} else if full_name == ref_name {
+				Some("it references its own table")
+			} else if !table.cols.iter().any(|(col, _)| col.name == fk.cols[0]) {
+				Some("its column isn't mapped")
+			} else if !joined.insert(if full_name < ref_name {
+				(full_name.clone(), ref_name.clone())
+			} else {
+				(ref_name.clone(), full_name.clone())
+			}) {
+				Some("there's already one between the tables")
+			} else {
+				None
+			};
+			if let Some(reason) = reason {
+				warnings.push(format!(
+					"Foreign key `{}` of `{}` isn't given a joinable! as {}",
+					fk.name, full_name, reason
+				));
+				continue;
+			}
+
+			let child = path(schema, name);
+			let parent = path(&fk.ref_schema, &fk.ref_table);
+			let column = format_heck(&fk.cols[0], opt, SnakeCase);
+			joinables.push(quote! { ::diesel::joinable!(#child -> #parent (#column)); });
+		}
+	}
+
+	// allow_tables_to_appear_in_same_query! only takes identifiers so the tables are given unique names in a module
+	let allow_tables = if tables.len() > 1 {
+		let mut seen = HashSet::new();
+		let (paths, aliases): (Vec<_>, Vec<_>) = tables
+			.iter()
+			.map(|(schema, name, ..)| {
+				let schema_name = format_heck(schema, opt, SnakeCase).to_string();
+				let table_name = format_heck(name, opt, SnakeCase).to_string();
+				let alias = format!("{}_{}", schema_name.trim_start_matches("r#"), table_name.trim_start_matches("r#"));
+				let mut unique = alias.clone();
+				let mut i = 1;
+				while !seen.insert(unique.clone()) {
+					unique = format!("{}_{}", alias, i);
+					i += 1;
+				}
+				(path(schema, name), format_ident!("{}", unique))
+			})
+			.unzip();
+		quote! {
+			mod all_tables {
+				#(pub use super::#paths as #aliases;)*
+				::diesel::allow_tables_to_appear_in_same_query!(#(#aliases),*);
+			}
+		}
+	} else {
+		quote! {}
+	};
+
+	quote! {
+		#(#joinables)*
+		#allow_tables
+	}
+}
+
+/// Every table or view which is given a `table!`, with the names of its schema and itself
+fn tables<'a>(
+	db: &'a FullDB,
+	opt: &Opt,
+	warnings: &Warnings,
+) -> Vec<(&'a str, &'a str, &'a PsqlCompositeType, Table<'a>)> {
+	let mut tables = Vec::new();
+	for schema in &db.schemas {
+		for (typ, c) in relations(schema) {
+			if let Some(table) = table(&schema.name, &typ.name, c, db, opt, warnings) {
+				tables.push((schema.name.as_str(), typ.name.as_str(), c, table));
+			}
+		}
+	}
+	tables
+}
+
+/// The feature of diesel the widest `table!` needs, `None` if they all fit in the default of 32 columns
+pub fn column_feature(db: &FullDB, opt: &Opt, warnings: &Warnings) -> Option<&'static str> {
+	let widest = tables(db, opt, warnings).iter().map(|v| v.3.cols.len()).max().unwrap_or(0);
+	if widest > 64 {
+		Some("128-column-tables")
+	} else if widest > 32 {
+		Some("64-column-tables")
+	} else {
+		None
+	}
+}
+
+/// A `recursion_limit` for the crate root if `allow_tables_to_appear_in_same_query!` recurses deeper than the default
+/// allows, it recurses once for each table
+pub fn crate_attrs(db: &FullDB, opt: &Opt, warnings: &Warnings) -> TokenStream {
+	let depth = tables(db, opt, warnings).len() + 32;
+	if depth > 128 {
+		let limit = depth.next_power_of_two().to_string();
+		quote! { #![recursion_limit = #limit] }
+	} else {
+		quote! {}
+	}
+}
+
+/// The columns and key of a table or view, `None` if it can't be given a `table!`
+///
+/// Columns of types diesel doesn't have a sql type for are left out, tables need a primary key of columns which are mapped
+pub fn table<'a>(
+	schema: &str,
+	name: &str,
+	c: &'a PsqlCompositeType,
+	db: &FullDB,
+	opt: &Opt,
+	warnings: &Warnings,
+) -> Option<Table<'a>> {
+	let full_name = format!("{}.{}", schema, name);
+	if schema != "public" && syn::parse_str::<Ident>(schema).is_err() {
+		warnings.push(format!(
+			"`{}` isn't mapped by the diesel backend as its schema's name isn't a rust identifier",
+			full_name
+		));
+		return None;
+	}
+
+	let cols: Vec<_> = c
+		.cols
+		.iter()
+		.filter_map(|col| match column_sql_type(col, db, opt, warnings) {
+			Some(sql_type) => Some((col, sql_type)),
+			None => {
+				warnings.push(format!(
+					"Column `{}` of `{}` has type `{}.{}` which diesel has no sql type for, it's left out",
+					col.name, full_name, col.type_ns_name, col.type_name
+				));
+				None
+			},
+		})
+		.collect();
+
+	if cols.len() > MAX_COLUMNS {
+		warnings.push(format!(
+			"`{}` isn't mapped by the diesel backend as it has more than {} columns",
+			full_name, MAX_COLUMNS
+		));
+		return None;
+	}
+
+	let key: Vec<&str> = if c.kind == RelKind::Table {
+		c.primary_key.iter().map(String::as_str).collect()
+	} else {
+		cols.first().map(|(col, _)| col.name.as_str()).into_iter().collect()
+	};
+	if key.is_empty() {
+		if c.kind == RelKind::Table {
+			warnings.push(format!(
+				"`{}` has no primary key so it isn't mapped by the diesel backend",
+				full_name
+			));
+		} else {
+			warnings.push(format!("`{}` has no columns the diesel backend can map", full_name));
+		}
+		return None;
+	}
+	if let Some(missing) = key.iter().find(|v| !cols.iter().any(|(col, _)| col.name == **v)) {
+		warnings.push(format!(
+			"`{}` isn't mapped by the diesel backend as its primary key column `{}` isn't",
+			full_name, missing
+		));
+		return None;
+	}
+
+	Some(Table { cols, key })
+}
+
+/// The path of a table's module in `schema`
+pub fn table_path(schema: &str, name: &str, opt: &Opt) -> TokenStream {
+	let schema = format_heck(schema, opt, SnakeCase);
+	let name = format_heck(name, opt, SnakeCase);
+	quote! { crate::schema::#schema::#name }
+}
+
+/// The type of a column's field in a table's struct, arrays are `Option<Vec<T>>` when the column can be NULL
+pub fn field_type(col: &Column, opt: &Opt) -> TokenStream {
+	let schema_name = format_heck(&col.type_ns_name, opt, SnakeCase);
+	let type_name = format_heck(element_name(col), opt, CamelCase);
+	let mut field_type = quote! { super::#schema_name::#type_name };
+	if col.num_dimentions > 0 {
+		field_type = quote! { Vec<#field_type> };
+	}
+	if col.not_null {
+		field_type
+	} else {
+		quote! { Option<#field_type> }
+	}
+}
+
+/// The name of the type of a column, or of the elements of an array column
+fn element_name(col: &Column) -> &str {
+	if col.num_dimentions > 0 {
+		col.type_name.strip_prefix('_').unwrap_or(&col.type_name)
+	} else {
+		&col.type_name
+	}
+}
+
+fn column_sql_type(col: &Column, db: &FullDB, opt: &Opt, warnings: &Warnings) -> Option<TokenStream> {
+	let mut sql_type = sql_type(&col.type_ns_name, element_name(col), db, opt)?;
+	if col.num_dimentions > 0 {
+		if col.num_dimentions > 1 {
+			warnings.push(format!(
+				"Column `{}` has {} dimensions but diesel only reads one-dimensional arrays, it's mapped as one",
+				col.name, col.num_dimentions
+			));
+		}
+		sql_type = quote! { ::diesel::sql_types::Array<#sql_type> };
+	}
+	if col.not_null {
+		Some(sql_type)
+	} else {
+		Some(quote! { ::diesel::sql_types::Nullable<#sql_type> })
+	}
+}
+
+/// The diesel sql type of a type, domains use the sql type of their base type
+pub fn sql_type(schema: &str, name: &str, db: &FullDB, opt: &Opt) -> Option<TokenStream> {
+	let typ = db
+		.schemas
+		.iter()
+		.filter(|v| v.name == schema)
+		.flat_map(|v| &v.types)
+		.find(|v| v.name == name)?;
+	match &typ.typ {
+		PsqlTypType::Base(b) => match base_type(b.oid, opt) {
+			Ok((sql_type, _)) => Some(sql_type),
+			Err(_) if b.extension.as_deref() == Some("citext") && b.name == "citext" => {
+				Some(quote! { ::diesel::sql_types::Citext })
+			},
+			Err(_) => None,
+		},
+		PsqlTypType::Enum(_) => Some(enum_sql_type(schema, name, opt)),
+		PsqlTypType::Domain(d) => sql_type(&d.base_ns_name, &d.base_name, db, opt),
+		_ => None,
+	}
+}
+
+/// The path of an enum's sql type in `schema`
+pub fn enum_sql_type(schema: &str, name: &str, opt: &Opt) -> TokenStream {
+	let schema_name = format_heck(schema, opt, SnakeCase);
+	let name_type = type_ident(name, opt);
+	quote! { crate::schema::#schema_name::sql_types::#name_type }
+}
+
+/// The name of an enum's rust type, which its sql type in `schema` shares
+fn type_ident(name: &str, opt: &Opt) -> Ident {
+	let stripped_name = name.replace(|c: char| !(c.is_ascii_alphanumeric() || c == '_'), "_");
+	format_heck(&stripped_name, opt, CamelCase)
+}
+
+/// The diesel sql type and rust type of a type built into postgres
+///
+/// `Err` has the third party dependency which would map it, if there is one
+pub fn base_type(oid: u32, opt: &Opt) -> Result<(TokenStream, TokenStream), Option<&'static str>> {
+	let date_time = |name: &str, chrono: TokenStream, time: TokenStream| {
+		let sql_type = format_ident!("{}", name);
+		if opt.uses_lib(Chrono) {
+			Ok((quote! { ::diesel::sql_types::#sql_type }, chrono))
+		} else if opt.uses_lib(Time) {
+			Ok((quote! { ::diesel::sql_types::#sql_type }, time))
+		} else {
+			Err(Some("chrono or time"))
+		}
+	};
+	let third_party = |used: bool, lib_name: &'static str, sql_type: TokenStream, rust_type: TokenStream| {
+		if used {
+			Ok((sql_type, rust_type))
+		} else {
+			Err(Some(lib_name))
+		}
+	};
+	let simple = |name: &str, rust_type: TokenStream| {
+		let sql_type = format_ident!("{}", name);
+		Ok((quote! { ::diesel::sql_types::#sql_type }, rust_type))
+	};
+
+	match oid {
+		16 => simple("Bool", quote! { std::primitive::bool }),
+		17 => simple("Bytea", quote! { Vec<u8> }),
+		18 => simple("CChar", quote! { u8 }),
+		19 | 25 => simple("Text", quote! { String }),
+		20 => simple("Int8", quote! { i64 }),
+		21 => simple("Int2", quote! { i16 }),
+		23 => simple("Int4", quote! { i32 }),
+		26 => simple("Oid", quote! { u32 }),
+		114 => third_party(
+			opt.uses_lib(SerdeJson),
+			SerdeJson.to_str(),
+			quote! { ::diesel::sql_types::Json },
+			quote! { ::serde_json::Value },
+		),
+		650 => simple("Cidr", quote! { ::ipnetwork::IpNetwork }),
+		700 => simple("Float4", quote! { f32 }),
+		701 => simple("Float8", quote! { f64 }),
+		790 => simple("Money", quote! { ::diesel::data_types::PgMoney }),
+		829 => simple("MacAddr", quote! { [u8; 6] }),
+		869 => simple("Inet", quote! { ::ipnetwork::IpNetwork }),
+		1042 => simple("Bpchar", quote! { String }),
+		1043 => simple("Varchar", quote! { String }),
+		1082 => date_time("Date", quote! { ::chrono::NaiveDate }, quote! { ::time::Date }),
+		1083 => date_time("Time", quote! { ::chrono::NaiveTime }, quote! { ::time::Time }),
+		1114 => date_time(
+			"Timestamp",
+			quote! { ::chrono::NaiveDateTime },
+			quote! { ::time::PrimitiveDateTime },
+		)
+		.or_else(|_| simple("Timestamp", quote! { std::time::SystemTime })),
+		1184 => date_time(
+			"Timestamptz",
+			quote! { ::chrono::DateTime<::chrono::Utc> },
+			quote! { ::time::OffsetDateTime },
+		),
+		1186 => simple("Interval", quote! { ::diesel::data_types::PgInterval }),
+		1700 => third_party(
+			opt.uses_lib(RustDecimal),
+			RustDecimal.to_str(),
+			quote! { ::diesel::sql_types::Numeric },
+			quote! { ::rust_decimal::Decimal },
+		),
+		2950 => third_party(
+			opt.uses_lib(Uuid),
+			Uuid.to_str(),
+			quote! { ::diesel::sql_types::Uuid },
+			quote! { ::uuid::Uuid },
+		),
+		3802 => third_party(
+			opt.uses_lib(SerdeJson),
+			SerdeJson.to_str(),
+			quote! { ::diesel::sql_types::Jsonb },
+			quote! { ::serde_json::Value },
+		),
+		_ => Err(None),
+	}
+}
+
+/// Whether an oid belongs to a type built into postgres, which can't be given a rust type with `--extension-type`
+pub fn is_builtin(oid: u32) -> bool {
+	oid < FIRST_NORMAL_OBJECT_ID
+}
+
+/// `#[sql_name]` for `table!` when the rust name isn't the one in the database
+fn sql_name_attr(ident: &Ident, sql_name: &str) -> TokenStream {
+	if ident.to_string().trim_start_matches("r#") == sql_name {
+		quote! {}
+	} else {
+		quote! { #[sql_name = #sql_name] }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn base(name: &str, oid: u32) -> PsqlType {
+		PsqlType {
+			name: String::from(name),
+			ns: 0,
+			typ: PsqlTypType::Base(PsqlBaseType {
+				oid,
+				name: String::from(name),
+				extension: None,
+			}),
+		}
+	}
+
+	/// A table of (name, type, not null) columns with types from pg_catalog, its primary key, and its foreign keys of
+	/// (columns, table, referenced columns) in `shop`
+	fn table(name: &str, cols: &[(&str, &str, bool)], key: &[&str], fks: &[(&[&str], &str, &[&str])]) -> PsqlType {
+		let strings = |v: &[&str]| v.iter().map(|v| String::from(*v)).collect::<Vec<_>>();
+		let cols = cols
+			.iter()
+			.enumerate()
+			.map(|(i, (name, type_name, not_null))| Column {
+				pos: i as i16 + 1,
+				name: String::from(*name),
+				type_id: 0,
+				type_name: String::from(*type_name),
+				type_ns_name: String::from("pg_catalog"),
+				not_null: *not_null,
+				num_dimentions: 0,
+				has_default: false,
+			})
+			.collect();
+		let foreign_keys = fks
+			.iter()
+			.map(|(cols, ref_table, ref_cols)| ForeignKey {
+				name: format!("{}_{}_fkey", name, cols.join("_")),
+				cols: strings(cols),
+				ref_schema: String::from("shop"),
+				ref_table: String::from(*ref_table),
+				ref_cols: strings(ref_cols),
+			})
+			.collect();
+		PsqlType {
+			name: String::from(name),
+			ns: 1,
+			typ: PsqlTypType::Composite(PsqlCompositeType {
+				oid: 0,
+				kind: RelKind::Table,
+				cols,
+				primary_key: strings(key),
+				foreign_keys,
+			}),
+		}
+	}
+
+	fn schema(id: u32, name: &str, types: Vec<PsqlType>) -> Schema {
+		Schema {
+			id,
+			name: String::from(name),
+			owner_name: String::from("postgres"),
+			types,
+			procs: Vec::new(),
+		}
+	}
+
+	/// Customers with orders of several items, each of which can be shipped, and employees with managers
+	fn shop() -> FullDB {
+		let mut db = FullDB::new(Dialect::Postgres);
+		db.add_schema(schema(0, "pg_catalog", vec![base("int4", 23), base("text", 25)]));
+		db.add_schema(schema(
+			1,
+			"shop",
+			vec![
+				table(
+					"customers",
+					&[("id", "int4", true), ("name", "text", true)],
+					&["id"],
+					&[],
+				),
+				table(
+					"orders",
+					&[("id", "int4", true), ("customer_id", "int4", false)],
+					&["id"],
+					&[(&["customer_id"], "customers", &["id"])],
+				),
+				table(
+					"order_items",
+					&[
+						("order_id", "int4", true),
+						("line", "int4", true),
+						("note", "text", false),
+					],
+					&["order_id", "line"],
+					&[(&["order_id"], "orders", &["id"])],
+				),
+				table(
+					"shipments",
+					&[("id", "int4", true), ("order_id", "int4", true), ("line", "int4", true)],
+					&["id"],
+					&[(&["order_id", "line"], "order_items", &["order_id", "line"])],
+				),
+				table(
+					"employees",
+					&[("id", "int4", true), ("manager_id", "int4", false)],
+					&["id"],
+					&[(&["manager_id"], "employees", &["id"])],
+				),
+			],
+		));
+		db
+	}
+
+	/// The warning about a foreign key of a table in `shop` named by `table`
+	fn not_joinable(fk: &str, table: &str, reason: &str) -> String {
+		format!(
+			"Foreign key `{}_fkey` of `shop.{}` isn't given a joinable! as {}",
+			fk, table, reason
+		)
+	}
+
+	#[test]
+	fn composite_primary_keys() {
+		let db = shop();
+		let warnings = Warnings::default();
+		let content = schema_content(&db.schemas[1], &db, &Opt::default(), &warnings).to_string();
+		let expected = quote! {
+			::diesel::table! {
+				#[doc = " `shop.order_items`"]
+				shop.order_items (order_id, line) {
+					order_id -> ::diesel::sql_types::Int4,
+					line -> ::diesel::sql_types::Int4,
+					note -> ::diesel::sql_types::Nullable<::diesel::sql_types::Text>,
+				}
+			}
+		};
+		assert!(content.contains(&expected.to_string()), "{}", content);
+		assert_eq!(warnings.take(), Vec::<String>::new());
+	}
+
+	#[test]
+	fn joinable_foreign_keys() {
+		let db = shop();
+		let warnings = Warnings::default();
+		let expected = quote! {
+			::diesel::joinable!(shop::orders -> shop::customers (customer_id));
+			::diesel::joinable!(shop::order_items -> shop::orders (order_id));
+			mod all_tables {
+				pub use super::shop::customers as shop_customers;
+				pub use super::shop::orders as shop_orders;
+				pub use super::shop::order_items as shop_order_items;
+				pub use super::shop::shipments as shop_shipments;
+				pub use super::shop::employees as shop_employees;
+				::diesel::allow_tables_to_appear_in_same_query!(
+					shop_customers,
+					shop_orders,
+					shop_order_items,
+					shop_shipments,
+					shop_employees
+				);
+			}
+		};
+		assert_eq!(joins(&db, &Opt::default(), &warnings).to_string(), expected.to_string());
+		assert_eq!(
+			warnings.take(),
+			[
+				not_joinable("shipments_order_id_line", "shipments", "it has more than one column"),
+				not_joinable("employees_manager_id", "employees", "it references its own table"),
+			]
+		);
+	}
+
+	#[test]
+	fn one_joinable_between_two_tables() {
+		let mut db = shop();
+		db.schemas[1].types = vec![
+			table(
+				"customers",
+				&[("id", "int4", true), ("name", "text", true)],
+				&["id"],
+				&[],
+			),
+			table(
+				"transfers",
+				&[("id", "int4", true), ("from_id", "int4", true), ("to_id", "int4", true)],
+				&["id"],
+				&[
+					(&["from_id"], "customers", &["id"]),
+					(&["to_id"], "customers", &["id"]),
+					(&["id"], "customers", &["name"]),
+				],
+			),
+		];
+		let warnings = Warnings::default();
+		let joinables = joins(&db, &Opt::default(), &warnings).to_string();
+		assert!(joinables
+			.starts_with(&quote! { ::diesel::joinable!(shop::transfers -> shop::customers (from_id)); }.to_string()));
+		assert_eq!(joinables.matches("joinable").count(), 1);
+		assert_eq!(
+			warnings.take(),
+			[
+				not_joinable("transfers_to_id", "transfers", "there's already one between the tables"),
+				not_joinable("transfers_id", "transfers", "it doesn't reference the primary key"),
+			]
+		);
+	}
+
+	#[test]
+	fn tables_need_a_mapped_primary_key() {
+		let mut db = shop();
+		db.schemas[1].types = vec![
+			table("log", &[("line", "text", true)], &[], &[]),
+			table("points", &[("p", "point", true), ("id", "int4", true)], &["p"], &[]),
+		];
+		let warnings = Warnings::default();
+		assert!(schema_content(&db.schemas[1], &db, &Opt::default(), &warnings).is_empty());
+		assert_eq!(
+			warnings.take(),
+			[
+				"`shop.log` has no primary key so it isn't mapped by the diesel backend",
+				"Column `p` of `shop.points` has type `pg_catalog.point` which diesel has no sql type for, it's \
+				 left out",
+				"`shop.points` isn't mapped by the diesel backend as its primary key column `p` isn't",
+			]
+		);
+	}
+
+	#[test]
+	fn public_tables_have_no_schema() {
+		let mut db = shop();
+		db.schemas[1].name = String::from("public");
+		db.schemas[1].types = vec![table("Customers", &[("Id", "int4", true)], &["Id"], &[])];
+		let opt = Opt {
+			rust_case: true,
+			..Opt::default()
+		};
+		let expected = quote! {
+			::diesel::table! {
+				#[doc = " `public.Customers`"]
+				#[sql_name = "Customers"]
+				customers (id) {
+					#[sql_name = "Id"]
+					id -> ::diesel::sql_types::Int4,
+				}
+			}
+		};
+		let content = schema_content(&db.schemas[1], &db, &opt, &Warnings::default());
+		assert_eq!(content.to_string(), expected.to_string());
+	}
+}
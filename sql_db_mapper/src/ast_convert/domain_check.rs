@@ -0,0 +1,784 @@
+//! Translating the CHECK constraints of domains into rust
+//!
+//! Postgres stores constraints already parsed so they're read back in its normalised form (i.e. `BETWEEN` becomes two
+//! comparisons and `IN` becomes `= ANY (ARRAY[...])`), only that form is handled. Anything else is left to the database
+use super::super::{Opt, ThirdParty};
+use proc_macro2::{Literal, TokenStream};
+use quote::{quote, ToTokens as _};
+
+/// How the value of a domain's base type is checked in rust
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaseKind {
+	Int,
+	Float,
+	Text,
+}
+
+impl BaseKind {
+	/// The kind of a built in base type, `None` if its checks can't be translated
+	pub fn from_oid(oid: u32) -> Option<BaseKind> {
+		match oid {
+			20 | 21 | 23 | 26 => Some(BaseKind::Int),
+			700 | 701 => Some(BaseKind::Float),
+			19 | 25 | 1043 => Some(BaseKind::Text),
+			_ => None,
+		}
+	}
+}
+
+/// The condition a value named `value` has to meet to pass the check, or why it couldn't be translated
+pub fn check_to_rust(expr: &str, kind: BaseKind, opt: &Opt) -> Result<TokenStream, String> {
+	match translate(&parse(expr)?, kind, opt)? {
+		Typed::Bool(cond) => Ok(cond),
+		_ => Err(String::from("not a condition")),
+	}
+}
+
+/// The value of a DEFAULT which is a constant of the base type
+pub fn default_to_rust(expr: &str, kind: BaseKind, opt: &Opt) -> Option<TokenStream> {
+	let expr = parse(expr).ok()?;
+	if !is_constant(&expr) {
+		return None;
+	}
+	match (kind, translate(&expr, kind, opt).ok()?) {
+		(BaseKind::Int, Typed::IntLit(v)) => Some(Literal::i64_unsuffixed(v).into_token_stream()),
+		(BaseKind::Float, Typed::IntLit(v)) => float_lit(v as f64),
+		(BaseKind::Float, Typed::FloatLit(v)) => float_lit(v),
+		(BaseKind::Text, Typed::StrLit(v)) => Some(quote! { String::from(#v) }),
+		_ => None,
+	}
+}
+
+/// Strip the parentheses postgres wraps around the whole expression
+pub fn display_expr(expr: &str) -> &str {
+	let inner = match expr.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+		Some(v) => v,
+		None => return expr,
+	};
+	// make sure the outer parentheses are a pair and not i.e. `(a) AND (b)`
+	let mut depth = 0;
+	let mut in_str = false;
+	for c in inner.chars() {
+		match c {
+			'\'' => in_str = !in_str,
+			'(' if !in_str => depth += 1,
+			')' if !in_str => {
+				if depth == 0 {
+					return expr;
+				}
+				depth -= 1;
+			},
+			_ => (),
+		}
+	}
+	inner
+}
+
+fn is_constant(expr: &Expr) -> bool {
+	match expr {
+		Expr::Number(_) | Expr::Str(_) => true,
+		Expr::Cast(e, _) => is_constant(e),
+		_ => false,
+	}
+}
+
+fn float_lit(v: f64) -> Option<TokenStream> {
+	if v.is_finite() {
+		Some(Literal::f64_unsuffixed(v).into_token_stream())
+	} else {
+		None
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+	Word(String),
+	Number(String),
+	Str(String),
+	Op(String),
+	Open,
+	Close,
+	OpenBracket,
+	CloseBracket,
+	Comma,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+	let chars: Vec<char> = s.chars().collect();
+	let mut ret = Vec::new();
+	let mut i = 0;
+	let take_while = |i: &mut usize, f: &dyn Fn(char) -> bool| -> String {
+		let start = *i;
+		while *i < chars.len() && f(chars[*i]) {
+			*i += 1;
+		}
+		chars[start..*i].iter().collect()
+	};
+	while i < chars.len() {
+		let c = chars[i];
+		if c.is_whitespace() {
+			i += 1;
+		} else if c.is_ascii_alphabetic() || c == '_' {
+			ret.push(Token::Word(take_while(&mut i, &|c| c.is_ascii_alphanumeric() || c == '_')));
+		} else if c.is_ascii_digit() {
+			let mut num = take_while(&mut i, &|c| c.is_ascii_digit() || c == '.');
+			if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+				num.push('e');
+				i += 1;
+				if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+					num.push(chars[i]);
+					i += 1;
+				}
+				num += &take_while(&mut i, &|c| c.is_ascii_digit());
+			}
+			ret.push(Token::Number(num));
+		} else if c == '\'' {
+			let mut text = String::new();
+			i += 1;
+			loop {
+				match chars.get(i) {
+					Some('\'') if chars.get(i + 1) == Some(&'\'') => {
+						text.push('\'');
+						i += 2;
+					},
+					Some('\'') => {
+						i += 1;
+						break;
+					},
+					Some(c) => {
+						text.push(*c);
+						i += 1;
+					},
+					None => return Err(String::from("unterminated string")),
+				}
+			}
+			ret.push(Token::Str(text));
+		} else {
+			i += 1;
+			ret.push(match c {
+				'(' => Token::Open,
+				')' => Token::Close,
+				'[' => Token::OpenBracket,
+				']' => Token::CloseBracket,
+				',' => Token::Comma,
+				'<' | '>' | '=' | '!' | '~' | '*' | ':' => {
+					i -= 1;
+					Token::Op(take_while(&mut i, &|c| "<>=!~*:".contains(c)))
+				},
+				_ => return Err(format!("unsupported character `{}`", c)),
+			});
+		}
+	}
+	Ok(ret)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+	Value,
+	Bool(bool),
+	Number(String),
+	Str(String),
+	Array(Vec<Expr>),
+	Cast(Box<Expr>, String),
+	Call(String, Vec<Expr>),
+	Not(Box<Expr>),
+	And(Box<Expr>, Box<Expr>),
+	Or(Box<Expr>, Box<Expr>),
+	Op(String, Box<Expr>, Box<Expr>),
+	/// `lhs op ANY (array)` or `lhs op ALL (array)`
+	Any {
+		op: String,
+		all: bool,
+		lhs: Box<Expr>,
+		rhs: Box<Expr>,
+	},
+	/// `IS NULL` or `IS NOT NULL` (true)
+	IsNull(Box<Expr>, bool),
+}
+
+fn parse(s: &str) -> Result<Expr, String> {
+	let mut parser = Parser {
+		tokens: tokenize(s)?,
+		pos: 0,
+	};
+	let expr = parser.or()?;
+	if parser.pos == parser.tokens.len() {
+		Ok(expr)
+	} else {
+		Err(String::from("unsupported expression"))
+	}
+}
+
+struct Parser {
+	tokens: Vec<Token>,
+	pos: usize,
+}
+
+impl Parser {
+	fn peek(&self) -> Option<&Token> {
+		self.tokens.get(self.pos)
+	}
+
+	fn next(&mut self) -> Option<Token> {
+		let ret = self.tokens.get(self.pos).cloned();
+		self.pos += 1;
+		ret
+	}
+
+	fn peek_keyword(&self, keyword: &str) -> bool {
+		matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+	}
+
+	fn eat_keyword(&mut self, keyword: &str) -> bool {
+		let ret = self.peek_keyword(keyword);
+		if ret {
+			self.pos += 1;
+		}
+		ret
+	}
+
+	fn expect(&mut self, token: Token) -> Result<(), String> {
+		if self.next() == Some(token) {
+			Ok(())
+		} else {
+			Err(String::from("unsupported expression"))
+		}
+	}
+
+	fn or(&mut self) -> Result<Expr, String> {
+		let mut ret = self.and()?;
+		while self.eat_keyword("OR") {
+			ret = Expr::Or(Box::new(ret), Box::new(self.and()?));
+		}
+		Ok(ret)
+	}
+
+	fn and(&mut self) -> Result<Expr, String> {
+		let mut ret = self.not()?;
+		while self.eat_keyword("AND") {
+			ret = Expr::And(Box::new(ret), Box::new(self.not()?));
+		}
+		Ok(ret)
+	}
+
+	fn not(&mut self) -> Result<Expr, String> {
+		if self.eat_keyword("NOT") {
+			Ok(Expr::Not(Box::new(self.not()?)))
+		} else {
+			self.comparison()
+		}
+	}
+
+	fn comparison(&mut self) -> Result<Expr, String> {
+		let lhs = self.postfix()?;
+		if self.eat_keyword("IS") {
+			let not_null = self.eat_keyword("NOT");
+			return if self.eat_keyword("NULL") {
+				Ok(Expr::IsNull(Box::new(lhs), not_null))
+			} else {
+				Err(String::from("unsupported IS expression"))
+			};
+		}
+		let op = match self.peek() {
+			Some(Token::Op(op)) => op.clone(),
+			_ => return Ok(lhs),
+		};
+		self.pos += 1;
+		let all = self.peek_keyword("ALL");
+		if all || self.peek_keyword("ANY") {
+			self.pos += 1;
+			self.expect(Token::Open)?;
+			let rhs = self.or()?;
+			self.expect(Token::Close)?;
+			Ok(Expr::Any {
+				op,
+				all,
+				lhs: Box::new(lhs),
+				rhs: Box::new(rhs),
+			})
+		} else {
+			Ok(Expr::Op(op, Box::new(lhs), Box::new(self.postfix()?)))
+		}
+	}
+
+	fn postfix(&mut self) -> Result<Expr, String> {
+		let mut ret = self.primary()?;
+		while self.peek() == Some(&Token::Op(String::from("::"))) {
+			self.pos += 1;
+			ret = Expr::Cast(Box::new(ret), self.type_name()?);
+		}
+		Ok(ret)
+	}
+
+	/// A type name such as `integer`, `character varying(20)`, or `text[]`
+	fn type_name(&mut self) -> Result<String, String> {
+		let mut words = Vec::new();
+		while let Some(Token::Word(w)) = self.peek() {
+			if ["AND", "OR", "IS", "NOT"].iter().any(|k| w.eq_ignore_ascii_case(k)) {
+				break;
+			}
+			words.push(w.clone());
+			self.pos += 1;
+		}
+		if words.is_empty() {
+			return Err(String::from("unsupported cast"));
+		}
+		// type modifiers don't change the rust type
+		if self.peek() == Some(&Token::Open) {
+			self.pos += 1;
+			while matches!(self.peek(), Some(Token::Number(_)) | Some(Token::Comma)) {
+				self.pos += 1;
+			}
+			self.expect(Token::Close)?;
+		}
+		let mut ret = words.join(" ");
+		if self.peek() == Some(&Token::OpenBracket) {
+			self.pos += 1;
+			self.expect(Token::CloseBracket)?;
+			ret += "[]";
+		}
+		Ok(ret)
+	}
+
+	fn primary(&mut self) -> Result<Expr, String> {
+		match self.next() {
+			Some(Token::Open) => {
+				let ret = self.or()?;
+				self.expect(Token::Close)?;
+				Ok(ret)
+			},
+			Some(Token::Number(n)) => Ok(Expr::Number(n)),
+			Some(Token::Str(s)) => Ok(Expr::Str(s)),
+			Some(Token::Word(w)) if w.eq_ignore_ascii_case("VALUE") => Ok(Expr::Value),
+			Some(Token::Word(w)) if w.eq_ignore_ascii_case("true") => Ok(Expr::Bool(true)),
+			Some(Token::Word(w)) if w.eq_ignore_ascii_case("false") => Ok(Expr::Bool(false)),
+			Some(Token::Word(w)) if w.eq_ignore_ascii_case("ARRAY") => {
+				self.expect(Token::OpenBracket)?;
+				let items = self.list(Token::CloseBracket)?;
+				Ok(Expr::Array(items))
+			},
+			Some(Token::Word(w)) if self.peek() == Some(&Token::Open) => {
+				self.pos += 1;
+				let args = self.list(Token::Close)?;
+				Ok(Expr::Call(w.to_lowercase(), args))
+			},
+			Some(t) => Err(format!("unsupported expression at {:?}", t)),
+			None => Err(String::from("unexpected end of expression")),
+		}
+	}
+
+	fn list(&mut self, end: Token) -> Result<Vec<Expr>, String> {
+		let mut ret = Vec::new();
+		if self.peek() == Some(&end) {
+			self.pos += 1;
+			return Ok(ret);
+		}
+		loop {
+			ret.push(self.or()?);
+			match self.next() {
+				Some(Token::Comma) => (),
+				Some(t) if t == end => return Ok(ret),
+				_ => return Err(String::from("unsupported list")),
+			}
+		}
+	}
+}
+
+/// A translated expression and its rust type, constants are kept as values so they can take the type they're compared to
+enum Typed {
+	Bool(TokenStream),
+	/// An `i64`
+	Int(TokenStream),
+	/// An `f64`
+	Float(TokenStream),
+	/// A `&str`
+	Text(TokenStream),
+	IntLit(i64),
+	FloatLit(f64),
+	StrLit(String),
+}
+
+const TEXT_TYPES: &[&str] = &["text", "character varying", "varchar", "name"];
+const INT_TYPES: &[&str] = &["smallint", "integer", "bigint", "oid", "int2", "int4", "int8"];
+const FLOAT_TYPES: &[&str] = &["real", "double precision", "float4", "float8"];
+
+fn translate(expr: &Expr, kind: BaseKind, opt: &Opt) -> Result<Typed, String> {
+	use Typed::*;
+	Ok(match expr {
+		Expr::Value => match kind {
+			BaseKind::Int => Int(quote! { i64::from(value) }),
+			BaseKind::Float => Float(quote! { f64::from(value) }),
+			BaseKind::Text => Text(quote! { value.as_str() }),
+		},
+		Expr::Bool(b) => Bool(quote! { #b }),
+		Expr::Number(n) => match n.parse::<i64>() {
+			Ok(v) => IntLit(v),
+			Err(_) => FloatLit(n.parse().map_err(|_| format!("invalid number {}", n))?),
+		},
+		Expr::Str(s) => StrLit(s.clone()),
+		Expr::Cast(e, typ) => cast(translate(e, kind, opt)?, typ)?,
+		Expr::Call(name, args) => match (name.as_str(), args.as_slice()) {
+			("length" | "char_length" | "character_length", [arg]) => match translate(arg, kind, opt)? {
+				Text(t) => Int(quote! { (#t.chars().count() as i64) }),
+				_ => return Err(format!("{} of a value that isn't text", name)),
+			},
+			("octet_length", [arg]) => match translate(arg, kind, opt)? {
+				Text(t) => Int(quote! { (#t.len() as i64) }),
+				_ => return Err(format!("{} of a value that isn't text", name)),
+			},
+			("lower", [arg]) => match translate(arg, kind, opt)? {
+				Text(t) => Text(quote! { #t.to_lowercase().as_str() }),
+				_ => return Err(format!("{} of a value that isn't text", name)),
+			},
+			("upper", [arg]) => match translate(arg, kind, opt)? {
+				Text(t) => Text(quote! { #t.to_uppercase().as_str() }),
+				_ => return Err(format!("{} of a value that isn't text", name)),
+			},
+			("abs", [arg]) => match translate(arg, kind, opt)? {
+				Int(t) => Int(quote! { #t.abs() }),
+				Float(t) => Float(quote! { #t.abs() }),
+				_ => return Err(format!("{} of a value that isn't a number", name)),
+			},
+			_ => return Err(format!("unsupported function {}", name)),
+		},
+		Expr::Not(e) => {
+			let e = as_bool(translate(e, kind, opt)?)?;
+			Bool(quote! { !#e })
+		},
+		Expr::And(a, b) => {
+			let (a, b) = (as_bool(translate(a, kind, opt)?)?, as_bool(translate(b, kind, opt)?)?);
+			Bool(quote! { (#a && #b) })
+		},
+		Expr::Or(a, b) => {
+			let (a, b) = (as_bool(translate(a, kind, opt)?)?, as_bool(translate(b, kind, opt)?)?);
+			Bool(quote! { (#a || #b) })
+		},
+		Expr::Op(op, lhs, rhs) => {
+			let (lhs, rhs) = (translate(lhs, kind, opt)?, translate(rhs, kind, opt)?);
+			match op.as_str() {
+				"~" | "~*" | "!~" | "!~*" => regex_match(op, lhs, rhs, opt)?,
+				_ => compare(op, lhs, rhs)?,
+			}
+		},
+		Expr::Any { op, all, lhs, rhs } => {
+			let items = match &**rhs {
+				Expr::Array(items) => items.clone(),
+				Expr::Cast(array, typ) => match (&**array, typ.strip_suffix("[]")) {
+					(Expr::Array(items), Some(typ)) => items
+						.iter()
+						.map(|v| Expr::Cast(Box::new(v.clone()), String::from(typ)))
+						.collect(),
+					_ => return Err(String::from("ANY of something other than an array")),
+				},
+				_ => return Err(String::from("ANY of something other than an array")),
+			};
+			let mut conds = Vec::new();
+			for item in &items {
+				let cond = compare(op, translate(lhs, kind, opt)?, translate(item, kind, opt)?)?;
+				conds.push(as_bool(cond)?);
+			}
+			match (conds.is_empty(), all) {
+				(true, _) => Bool(quote! { #all }),
+				(false, true) => Bool(quote! { (#(#conds)&&*) }),
+				(false, false) => Bool(quote! { (#(#conds)||*) }),
+			}
+		},
+		// the value of a domain is never null in rust
+		Expr::IsNull(e, not_null) => match translate(e, kind, opt)? {
+			Int(_) | Float(_) | Text(_) => Bool(quote! { #not_null }),
+			_ => return Err(String::from("IS NULL of a constant")),
+		},
+		Expr::Array(_) => return Err(String::from("unsupported array")),
+	})
+}
+
+fn as_bool(typed: Typed) -> Result<TokenStream, String> {
+	match typed {
+		Typed::Bool(v) => Ok(v),
+		_ => Err(String::from("not a condition")),
+	}
+}
+
+fn cast(value: Typed, typ: &str) -> Result<Typed, String> {
+	use Typed::*;
+	let unsupported = || format!("unsupported cast to {}", typ);
+	Ok(if TEXT_TYPES.contains(&typ) {
+		match value {
+			Text(t) => Text(t),
+			StrLit(s) => StrLit(s),
+			Int(t) => Text(quote! { #t.to_string().as_str() }),
+			IntLit(v) => StrLit(v.to_string()),
+			_ => return Err(unsupported()),
+		}
+	} else if INT_TYPES.contains(&typ) {
+		match value {
+			Int(t) => Int(t),
+			IntLit(v) => IntLit(v),
+			StrLit(s) => IntLit(s.trim().parse().map_err(|_| unsupported())?),
+			_ => return Err(unsupported()),
+		}
+	} else if FLOAT_TYPES.contains(&typ) {
+		match value {
+			Float(t) => Float(t),
+			Int(t) => Float(quote! { (#t as f64) }),
+			FloatLit(v) => FloatLit(v),
+			IntLit(v) => FloatLit(v as f64),
+			StrLit(s) => FloatLit(s.trim().parse().map_err(|_| unsupported())?),
+			_ => return Err(unsupported()),
+		}
+	} else {
+		return Err(unsupported());
+	})
+}
+
+fn compare(op: &str, lhs: Typed, rhs: Typed) -> Result<Typed, String> {
+	use Typed::*;
+	let op_tokens = match op {
+		"=" => quote! { == },
+		"<>" | "!=" => quote! { != },
+		"<" => quote! { < },
+		"<=" => quote! { <= },
+		">" => quote! { > },
+		">=" => quote! { >= },
+		_ => return Err(format!("unsupported operator {}", op)),
+	};
+	if (matches!(lhs, Text(_)) || matches!(rhs, Text(_))) && !matches!(op, "=" | "<>" | "!=") {
+		return Err(String::from("ordering text depends on the collation"));
+	}
+	let int = |v: i64| Literal::i64_unsuffixed(v).into_token_stream();
+	let float = |v: f64| float_lit(v).ok_or_else(|| String::from("unsupported float constant"));
+	let (lhs, rhs) = match (lhs, rhs) {
+		(Int(l), Int(r)) => (l, r),
+		(Int(l), IntLit(r)) => (l, int(r)),
+		(IntLit(l), Int(r)) => (int(l), r),
+		(Float(l), Float(r)) => (l, r),
+		(Float(l), FloatLit(r)) => (l, float(r)?),
+		(Float(l), IntLit(r)) => (l, float(r as f64)?),
+		(FloatLit(l), Float(r)) => (float(l)?, r),
+		(IntLit(l), Float(r)) => (float(l as f64)?, r),
+		(Text(l), Text(r)) => (l, r),
+		(Text(l), StrLit(r)) => (l, quote! { #r }),
+		(StrLit(l), Text(r)) => (quote! { #l }, r),
+		_ => return Err(String::from("unsupported comparison")),
+	};
+	Ok(Bool(quote! { (#lhs #op_tokens #rhs) }))
+}
+
+fn regex_match(op: &str, lhs: Typed, rhs: Typed, opt: &Opt) -> Result<Typed, String> {
+	let (text, pattern) = match (lhs, rhs) {
+		(Typed::Text(text), Typed::StrLit(pattern)) => (text, pattern),
+		_ => return Err(String::from("unsupported regex match")),
+	};
+	if !opt.uses_lib(ThirdParty::Regex) {
+		return Err(String::from("enable the regex dependency to check regex matches"));
+	}
+	let pattern = if op.ends_with('*') {
+		format!("(?i){}", pattern)
+	} else {
+		pattern
+	};
+	// postgres regexes are mostly compatible, the ones that don't compile are left to the database
+	if regex::Regex::new(&pattern).is_err() {
+		return Err(format!("pattern `{}` isn't supported by the regex crate", pattern));
+	}
+	let is_match = quote! {
+		{
+			static RE: std::sync::OnceLock<crate::orm::regex::Regex> = std::sync::OnceLock::new();
+			crate::orm::domain::regex_is_match(&RE, #pattern, #text)
+		}
+	};
+	Ok(Typed::Bool(if op.starts_with('!') {
+		quote! { !#is_match }
+	} else {
+		is_match
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{sql_tree::*, Warnings};
+
+	fn check(expr: &str, kind: BaseKind) -> Result<String, String> {
+		check_to_rust(expr, kind, &Opt::default()).map(|v| v.to_string())
+	}
+
+	fn with_regex() -> Opt {
+		Opt {
+			third_party: vec![ThirdParty::Regex],
+			..Opt::default()
+		}
+	}
+
+	#[test]
+	fn compares_with_a_cast_constant() {
+		assert_eq!(
+			check("(VALUE >= (0)::double precision)", BaseKind::Float),
+			Ok(quote! { (f64::from(value) >= 0.0) }.to_string())
+		);
+		assert_eq!(
+			check("((VALUE > 0) AND (VALUE <= '100'::integer))", BaseKind::Int),
+			Ok(quote! { ((i64::from(value) > 0) && (i64::from(value) <= 100)) }.to_string())
+		);
+	}
+
+	#[test]
+	fn length_of_text() {
+		assert_eq!(
+			check("(length((VALUE)::text) > 0)", BaseKind::Text),
+			Ok(quote! { ((value.as_str().chars().count() as i64) > 0) }.to_string())
+		);
+		assert_eq!(
+			check("(octet_length(lower(VALUE)) < 10)", BaseKind::Text),
+			Ok(quote! { ((value.as_str().to_lowercase().as_str().len() as i64) < 10) }.to_string())
+		);
+	}
+
+	#[test]
+	fn regex_match() {
+		let cond = check_to_rust("(VALUE ~ '^[A-Z]{3}$'::text)", BaseKind::Text, &with_regex()).unwrap();
+		let cond = cond.to_string();
+		assert!(cond.contains("regex_is_match"), "{}", cond);
+		assert!(cond.contains(r#""^[A-Z]{3}$""#), "{}", cond);
+		assert!(!cond.starts_with('!'), "{}", cond);
+
+		let cond = check_to_rust("(VALUE !~* 'abc'::text)", BaseKind::Text, &with_regex()).unwrap();
+		let cond = cond.to_string();
+		assert!(cond.starts_with('!'), "{}", cond);
+		assert!(cond.contains(r#""(?i)abc""#), "{}", cond);
+	}
+
+	#[test]
+	fn regex_needs_the_regex_dependency() {
+		assert_eq!(
+			check("(VALUE ~ '^[A-Z]{3}$'::text)", BaseKind::Text),
+			Err(String::from("enable the regex dependency to check regex matches"))
+		);
+		assert_eq!(
+			check_to_rust("(VALUE ~ '(?<=a)b'::text)", BaseKind::Text, &with_regex()).map(|v| v.to_string()),
+			Err(String::from("pattern `(?<=a)b` isn't supported by the regex crate"))
+		);
+	}
+
+	#[test]
+	fn in_list() {
+		assert_eq!(
+			check("(VALUE = ANY (ARRAY['a'::text, 'b'::text]))", BaseKind::Text),
+			Ok(quote! { ((value.as_str() == "a") || (value.as_str() == "b")) }.to_string())
+		);
+		// the form of a varchar domain
+		assert_eq!(
+			check(
+				"((VALUE)::text = ANY ((ARRAY['a'::character varying, 'b'::character varying])::text[]))",
+				BaseKind::Text
+			),
+			Ok(quote! { ((value.as_str() == "a") || (value.as_str() == "b")) }.to_string())
+		);
+		assert_eq!(
+			check("(VALUE <> ALL (ARRAY[1, 2]))", BaseKind::Int),
+			Ok(quote! { ((i64::from(value) != 1) && (i64::from(value) != 2)) }.to_string())
+		);
+	}
+
+	#[test]
+	fn casts() {
+		assert_eq!(
+			check("(((VALUE)::text)::integer > 0)", BaseKind::Text),
+			Err(String::from("unsupported cast to integer"))
+		);
+		assert_eq!(
+			check("((VALUE)::double precision < 1.5)", BaseKind::Int),
+			Ok(quote! { ((i64::from(value) as f64) < 1.5) }.to_string())
+		);
+		assert_eq!(
+			check("((VALUE)::text <> '0'::text)", BaseKind::Int),
+			Ok(quote! { (i64::from(value).to_string().as_str() != "0") }.to_string())
+		);
+		assert_eq!(
+			check("((VALUE)::numeric > 0)", BaseKind::Int),
+			Err(String::from("unsupported cast to numeric"))
+		);
+	}
+
+	#[test]
+	fn unsupported_expressions() {
+		assert_eq!(
+			check("(is_valid(VALUE) = true)", BaseKind::Int),
+			Err(String::from("unsupported function is_valid"))
+		);
+		assert_eq!(
+			check("(VALUE > 'a'::text)", BaseKind::Text),
+			Err(String::from("ordering text depends on the collation"))
+		);
+		assert_eq!(
+			check("(VALUE + 1)", BaseKind::Int),
+			Err(String::from("unsupported character `+`"))
+		);
+		assert_eq!(check("(VALUE)", BaseKind::Int), Err(String::from("not a condition")));
+	}
+
+	#[test]
+	fn unsupported_checks_are_only_checked_by_the_database() {
+		let domain = PsqlDomain {
+			oid: 0,
+			base_oid: 23,
+			base_name: String::from("int4"),
+			base_ns_name: String::from("pg_catalog"),
+			not_null: false,
+			default: None,
+			checks: vec![
+				DomainCheck {
+					name: String::from("is_even"),
+					expr: String::from("((VALUE % 2) = 0)"),
+				},
+				DomainCheck {
+					name: String::from("positive"),
+					expr: String::from("(VALUE > 0)"),
+				},
+			],
+		};
+		let warnings = Warnings::default();
+		let tokens = super::super::sql_types::domain_to_ast(
+			&domain,
+			"public",
+			"even",
+			"even",
+			&FullDB::new(Dialect::Postgres),
+			&Opt::default(),
+			&warnings,
+		)
+		.to_string();
+
+		assert_eq!(
+			warnings.take(),
+			["Check `is_even` of domain `public.even` is only checked by the database: unsupported character `%`"]
+		);
+		assert!(
+			tokens.contains("CHECK `is_even`: `(VALUE % 2) = 0` (only checked by the database)"),
+			"{}",
+			tokens
+		);
+		assert!(tokens.contains("CHECK `positive`: `VALUE > 0`\""), "{}", tokens);
+		assert!(
+			tokens.contains(&quote! { (i64::from(value) > 0) }.to_string()),
+			"{}",
+			tokens
+		);
+		assert!(!tokens.contains("\"is_even\""), "{}", tokens);
+	}
+
+	#[test]
+	fn constant_defaults() {
+		let default = |expr, kind| default_to_rust(expr, kind, &Opt::default()).map(|v| v.to_string());
+		assert_eq!(default("42", BaseKind::Int), Some(String::from("42")));
+		assert_eq!(
+			default("'0.5'::double precision", BaseKind::Float),
+			Some(String::from("0.5"))
+		);
+		assert_eq!(
+			default("'none'::text", BaseKind::Text),
+			Some(quote! { String::from("none") }.to_string())
+		);
+		assert_eq!(default("nextval('seq'::regclass)", BaseKind::Int), None);
+	}
+}
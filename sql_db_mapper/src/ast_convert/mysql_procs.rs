@@ -0,0 +1,217 @@
+//! Functions for generating the mysql backend, the rust types of MySQL's types, the conversions of enums and rows, and the
+//! functions calling stored routines
+use super::{
+	super::{mysql::VOID_TYPE, sql_tree::*, Opt, ThirdParty::*, Warnings},
+	format_heck, sql_procs,
+	Case::*,
+};
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+
+/// The rust type of one of `mysql::MYSQL_TYPES`, by its name as unsigned integers share oids with signed ones
+///
+/// Dates, times, decimals, and json are read with mysql_common's third party features, times fall back to a `Duration` and
+/// decimals and json to the text MySQL sends them as
+pub fn base_type(b: &PsqlBaseType, opt: &Opt, warnings: &Warnings) -> Option<TokenStream> {
+	let date_time = |chrono: TokenStream, time: TokenStream| {
+		if opt.uses_lib(Chrono) {
+			Some(chrono)
+		} else if opt.uses_lib(Time) {
+			Some(time)
+		} else {
+			warnings.push(format!(
+				"Enable {} or {} dependency to provide mapping for MySQL type `{}`",
+				Chrono.to_str(),
+				Time.to_str(),
+				b.name
+			));
+			None
+		}
+	};
+	Some(match b.name.as_str() {
+		"boolean" => quote! { std::primitive::bool },
+		"tinyint" => quote! { i8 },
+		"tinyint_unsigned" => quote! { u8 },
+		"smallint" => quote! { i16 },
+		"smallint_unsigned" | "year" => quote! { u16 },
+		"mediumint" | "int" => quote! { i32 },
+		"mediumint_unsigned" | "int_unsigned" => quote! { u32 },
+		"bigint" => quote! { i64 },
+		"bigint_unsigned" => quote! { u64 },
+		"float" => quote! { f32 },
+		"double" => quote! { f64 },
+		"decimal" if opt.uses_lib(RustDecimal) => quote! { ::mysql_common::rust_decimal::Decimal },
+		"decimal" | "char" | "varchar" | "tinytext" | "text" | "mediumtext" | "longtext" | "set" => quote! { String },
+		"bit" | "binary" | "varbinary" | "tinyblob" | "blob" | "mediumblob" | "longblob" => quote! { Vec<u8> },
+		"json" if opt.uses_lib(SerdeJson) => quote! { ::mysql_common::serde_json::Value },
+		"json" => quote! { String },
+		"date" => date_time(
+			quote! { ::mysql_common::chrono::NaiveDate },
+			quote! { ::mysql_common::time::Date },
+		)?,
+		"datetime" | "timestamp" => date_time(
+			quote! { ::mysql_common::chrono::NaiveDateTime },
+			quote! { ::mysql_common::time::PrimitiveDateTime },
+		)?,
+		"time" if opt.uses_lib(Chrono) => quote! { ::mysql_common::chrono::NaiveTime },
+		"time" if opt.uses_lib(Time) => quote! { ::mysql_common::time::Time },
+		"time" => quote! { std::time::Duration },
+		name => {
+			warnings.push(format!("No Rust type for MySQL type `{}`", name));
+			return None;
+		},
+	})
+}
+
+/// mysql_common's conversions of an enum, which go through its label
+pub fn enum_impls(name_type: &Ident) -> TokenStream {
+	quote! {
+		impl From<#name_type> for ::mysql_common::Value {
+			fn from(value: #name_type) -> Self {
+				::mysql_common::Value::Bytes(value.as_str().as_bytes().to_vec())
+			}
+		}
+
+		impl std::convert::TryFrom<::mysql_common::Value> for #name_type {
+			type Error = ::mysql_common::FromValueError;
+
+			fn try_from(value: ::mysql_common::Value) -> Result<Self, Self::Error> {
+				let label = match &value {
+					::mysql_common::Value::Bytes(bytes) => std::str::from_utf8(bytes).ok().and_then(|v| v.parse().ok()),
+					_ => None,
+				};
+				label.ok_or(::mysql_common::FromValueError(value))
+			}
+		}
+
+		impl ::mysql_common::value::convert::FromValue for #name_type {
+			type Intermediate = Self;
+		}
+	}
+}
+
+/// `FromRow` for the struct of a table or view, which reads each column by name so it works with any query selecting them
+pub fn from_row(c: &PsqlCompositeType, name_type: &Ident, opt: &Opt) -> TokenStream {
+	let fields: Vec<_> = c.cols.iter().map(|v| format_heck(&v.name, opt, SnakeCase)).collect();
+	let columns = c.cols.iter().map(|v| &v.name);
+	quote! {
+		impl ::mysql_common::row::convert::FromRow for #name_type {
+			fn from_row_opt(row: ::mysql_common::Row) -> Result<Self, ::mysql_common::FromRowError> {
+				match (#(row.get_opt(#columns),)*) {
+					(#(Some(Ok(#fields)),)*) => Ok(#name_type { #(#fields),* }),
+					_ => Err(::mysql_common::FromRowError(row)),
+				}
+			}
+		}
+	}
+}
+
+/// Takes a stored function or procedure and turns it into a function over a mysql (`is_sync`) or mysql_async connection
+///
+/// Functions are called with `SELECT` and procedures with `CALL`, which drops any result sets they return
+///
+/// ```ignore
+/// const ADD3_SQL: &str = "SELECT `people`.`add3`(?)";
+/// /// Calls `people.add3`
+/// pub fn add3<C: ::mysql::prelude::Queryable>(
+/// 	conn: &mut C,
+/// 	a: &super::people::int,
+/// ) -> ::mysql::Result<Option<super::people::int>> {
+/// 	/* implementation */
+/// }
+///
+/// const ARCHIVE_SQL: &str = "CALL `people`.`archive`(?)";
+/// /// Calls the procedure `people.archive`
+/// pub async fn archive<C: ::mysql_async::prelude::Queryable>(conn: &mut C, id: &super::people::int) -> ::mysql_async::Result<()>;
+/// ```
+pub fn proc_to_rust(procs: &[SqlProc], db: &FullDB, opt: &Opt, is_sync: bool) -> TokenStream {
+	sql_procs::mapped_procs(procs, opt)
+		.into_iter()
+		.map(|mapped| {
+			let proc = mapped.proc;
+			let fn_name = &mapped.overload_name;
+			let sql_name = format!("{}_SQL", fn_name.to_string().trim_start_matches("r#"));
+			let sql_name = format_heck(&sql_name, opt, ShoutySnake);
+			let is_procedure = is_procedure(proc, db);
+			let params = vec!["?"; proc.inputs.0.len()].join(", ");
+			let (sql, doc) = if is_procedure {
+				(
+					format!(
+						"CALL {}.{}({})",
+						quote_ident(&proc.ns_name),
+						quote_ident(&proc.name),
+						params
+					),
+					format!("Calls the procedure `{}.{}`", proc.ns_name, proc.name),
+				)
+			} else {
+				(
+					format!(
+						"SELECT {}.{}({})",
+						quote_ident(&proc.ns_name),
+						quote_ident(&proc.name),
+						params
+					),
+					format!("Calls `{}.{}`", proc.ns_name, proc.name),
+				)
+			};
+
+			let names = proc.inputs.0.iter().map(|tan| format_heck(&tan.name, opt, SnakeCase));
+			let fn_params = proc.inputs.as_function_params(opt);
+			let query_params = if proc.inputs.0.is_empty() {
+				quote! { ::mysql_common::params::Params::Empty }
+			} else {
+				quote! { ::mysql_common::params::Params::Positional(vec![#(::mysql_common::Value::from(#names)),*]) }
+			};
+			let (opt_async, opt_await, krate) = if is_sync {
+				(quote! {}, quote! {}, quote! { ::mysql })
+			} else {
+				(quote! { async }, quote! { .await }, quote! { ::mysql_async })
+			};
+
+			let ret_type = proc.outputs.to_tokens(opt);
+			let (ret, body) = if is_procedure {
+				(
+					quote! { () },
+					quote! { conn.exec_drop(#sql_name, #query_params)#opt_await },
+				)
+			} else {
+				// a function always returns one row, a NULL is read as None
+				(
+					quote! { Option<#ret_type> },
+					quote! {
+						let row: Option<Option<#ret_type>> = conn.exec_first(#sql_name, #query_params)#opt_await?;
+						Ok(row.flatten())
+					},
+				)
+			};
+
+			quote! {
+				const #sql_name: &str = #sql;
+				#[doc = #doc]
+				pub #opt_async fn #fn_name<C: #krate::prelude::Queryable>(
+					conn: &mut C,
+					#fn_params
+				) -> #krate::Result<#ret> {
+					#body
+				}
+			}
+		})
+		.collect()
+}
+
+/// A name quoted for MySQL
+fn quote_ident(name: &str) -> String {
+	format!("`{}`", name.replace('`', "``"))
+}
+
+/// Whether it's a procedure, which return `void` rather than one of the schema's types
+fn is_procedure(proc: &SqlProc, db: &FullDB) -> bool {
+	proc.outputs.name == VOID_TYPE
+		&& db
+			.schemas
+			.iter()
+			.filter(|v| v.name == proc.outputs.schema)
+			.flat_map(|v| &v.types)
+			.any(|v| v.name == VOID_TYPE && matches!(v.typ, PsqlTypType::Other(2278)))
+}
@@ -0,0 +1,260 @@
+//! Functions for generating the rusqlite backend, the rust types of SQLite's types, `from_row`, and the `crud` module
+use super::{
+	super::{sql_tree::*, Opt, ThirdParty::*, Warnings},
+	format_heck, sql_copy, sql_types,
+	Case::*,
+};
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+
+/// Whether the schema has any tables or views to generate crud functions for
+pub fn has_relations(schema: &Schema) -> bool {
+	relations(schema).next().is_some()
+}
+
+fn relations(schema: &Schema) -> impl Iterator<Item = (&PsqlType, &PsqlCompositeType)> {
+	schema.types.iter().filter_map(|typ| match &typ.typ {
+		PsqlTypType::Composite(c) if c.kind.is_relation() => Some((typ, c)),
+		_ => None,
+	})
+}
+
+/// The rust type of one of `sqlite::SQLITE_TYPES`, by the oid it was given
+///
+/// Dates and json are read with rusqlite's third party features and otherwise fall back to the text SQLite stores them as
+pub fn base_type(b: &PsqlBaseType, opt: &Opt, warnings: &Warnings) -> Option<TokenStream> {
+	let date_time = |chrono: TokenStream, time: TokenStream| {
+		if opt.uses_lib(Chrono) {
+			chrono
+		} else if opt.uses_lib(Time) {
+			time
+		} else {
+			quote! { String }
+		}
+	};
+	Some(match b.oid {
+		16 => quote! { std::primitive::bool },
+		17 => quote! { Vec<u8> },
+		20 => quote! { i64 },
+		25 => quote! { String },
+		114 if opt.uses_lib(SerdeJson) => quote! { ::serde_json::Value },
+		114 => quote! { String },
+		701 | 1700 => quote! { f64 },
+		1082 => date_time(quote! { ::chrono::NaiveDate }, quote! { ::time::Date }),
+		1083 => date_time(quote! { ::chrono::NaiveTime }, quote! { ::time::Time }),
+		1114 => date_time(quote! { ::chrono::NaiveDateTime }, quote! { ::time::PrimitiveDateTime }),
+		2276 => quote! { ::rusqlite::types::Value },
+		oid => {
+			warnings.push(format!("No Rust type for SQLite type `{}` with oid : {}", b.name, oid));
+			return None;
+		},
+	})
+}
+
+/// `from_row` of the struct of a table or view, which reads each column by name so it works with any query selecting them
+pub fn from_row(c: &PsqlCompositeType, name_type: &Ident, opt: &Opt) -> TokenStream {
+	let fields = c.cols.iter().map(|v| format_heck(&v.name, opt, SnakeCase));
+	let columns = c.cols.iter().map(|v| &v.name);
+	quote! {
+		impl #name_type {
+			/// Read a row by the names of its columns
+			pub fn from_row(row: &::rusqlite::Row<'_>) -> ::rusqlite::Result<Self> {
+				Ok(#name_type {
+					#(#fields: row.get(#columns)?),*
+				})
+			}
+		}
+	}
+}
+
+/// The `New{Table}` struct holding the columns of a table that don't have a default, the rowid is one of those with a default
+pub fn new_struct(
+	c: &PsqlCompositeType,
+	schema: &str,
+	sql_name: &str,
+	name: &str,
+	opt: &Opt,
+	warnings: &Warnings,
+) -> TokenStream {
+	let cols = match sql_copy::insertable_columns(c) {
+		Some(v) => v,
+		None => return quote! {},
+	};
+	let name_type = sql_copy::new_struct_name(name, opt);
+	let struct_body = cols.into_iter().map(|v| sql_types::column_to_field(v, opt, warnings));
+	let derives = sql_types::get_derives(schema, sql_name, &["Debug", "Clone"], opt, warnings);
+
+	quote! {
+		/// A row to insert with `insert`, columns which have defaults are left out
+		#derives
+		pub struct #name_type {
+			#(#struct_body),*
+		}
+	}
+}
+
+/// Builds the contents of a schema's module in `crud`, a module for each table or view
+///
+/// ```ignore
+/// pub mod people {
+/// 	pub type Row = crate::types::main::people;
+/// 	pub fn select_all(conn: &::rusqlite::Connection) -> ::rusqlite::Result<Vec<Row>>;
+/// 	// tables with a primary key
+/// 	pub fn get(conn: &::rusqlite::Connection, id: &crate::types::main::integer) -> ::rusqlite::Result<Option<Row>>;
+/// 	pub fn update(conn: &::rusqlite::Connection, row: &Row) -> ::rusqlite::Result<usize>;
+/// 	pub fn delete(conn: &::rusqlite::Connection, id: &crate::types::main::integer) -> ::rusqlite::Result<usize>;
+/// 	// tables with columns that don't have a default
+/// 	pub type NewRow = crate::types::main::Newpeople;
+/// 	pub fn insert(conn: &::rusqlite::Connection, row: &NewRow) -> ::rusqlite::Result<i64>;
+/// }
+/// ```
+pub fn crud_content(schema: &Schema, opt: &Opt) -> TokenStream {
+	let tables = relations(schema).map(|(typ, c)| table_to_rust(&schema.name, &typ.name, c, opt));
+	quote! {
+		use super::*;
+		#(#tables)*
+	}
+}
+
+/// A name quoted for SQLite
+fn quote_ident(name: &str) -> String {
+	format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn table_to_rust(schema: &str, name: &str, c: &PsqlCompositeType, opt: &Opt) -> TokenStream {
+	let stripped_name = name.replace(|c: char| !(c.is_ascii_alphanumeric() || c == '_'), "_");
+	let module_name = format_heck(&stripped_name, opt, SnakeCase);
+	let schema_name = format_heck(schema, opt, SnakeCase);
+	let row_type = format_heck(&stripped_name, opt, CamelCase);
+	let table = format!("{}.{}", quote_ident(schema), quote_ident(name));
+	let doc = format!("Functions over `{}.{}`", schema, name);
+
+	let all_cols: Vec<_> = c.cols.iter().map(|v| quote_ident(&v.name)).collect();
+	let select_sql = format!("SELECT {} FROM {}", all_cols.join(", "), table);
+
+	let key: Vec<_> = c
+		.primary_key
+		.iter()
+		.filter_map(|key| c.cols.iter().find(|v| v.name == *key))
+		.collect();
+	let by_key = if c.kind == RelKind::Table && !key.is_empty() {
+		let key_names: Vec<_> = key.iter().map(|v| format_heck(&v.name, opt, SnakeCase)).collect();
+		let key_params = key.iter().zip(&key_names).map(|(v, param)| {
+			let type_schema = format_heck(&v.type_ns_name, opt, SnakeCase);
+			let type_name = format_heck(&v.type_name, opt, CamelCase);
+			if v.not_null {
+				quote! { #param: &crate::types::#type_schema::#type_name }
+			} else {
+				quote! { #param: &Option<crate::types::#type_schema::#type_name> }
+			}
+		});
+		let key_params = quote! { #(#key_params),* };
+		let where_clause = key
+			.iter()
+			.enumerate()
+			.map(|(i, v)| format!("{} = ?{}", quote_ident(&v.name), i + 1))
+			.collect::<Vec<_>>()
+			.join(" AND ");
+		let get_sql = format!("{} WHERE {}", select_sql, where_clause);
+		let delete_sql = format!("DELETE FROM {} WHERE {}", table, where_clause);
+
+		// every column besides the key is set, the key is numbered after them
+		let others: Vec<_> = c.cols.iter().filter(|v| !c.primary_key.contains(&v.name)).collect();
+		let update = if others.is_empty() {
+			quote! {}
+		} else {
+			let set = others
+				.iter()
+				.enumerate()
+				.map(|(i, v)| format!("{} = ?{}", quote_ident(&v.name), i + 1))
+				.collect::<Vec<_>>()
+				.join(", ");
+			let update_where = key
+				.iter()
+				.enumerate()
+				.map(|(i, v)| format!("{} = ?{}", quote_ident(&v.name), others.len() + i + 1))
+				.collect::<Vec<_>>()
+				.join(" AND ");
+			let update_sql = format!("UPDATE {} SET {} WHERE {}", table, set, update_where);
+			let update_fields = others
+				.iter()
+				.chain(key.iter())
+				.map(|v| format_heck(&v.name, opt, SnakeCase));
+			quote! {
+				const UPDATE_SQL: &str = #update_sql;
+				/// Set every other column of the row with the same primary key, returns the number of rows changed
+				pub fn update(conn: &::rusqlite::Connection, row: &Row) -> ::rusqlite::Result<usize> {
+					conn.prepare_cached(UPDATE_SQL)?.execute(::rusqlite::params![#(row.#update_fields),*])
+				}
+			}
+		};
+
+		quote! {
+			const GET_SQL: &str = #get_sql;
+			/// Read the row with the primary key
+			pub fn get(conn: &::rusqlite::Connection, #key_params) -> ::rusqlite::Result<Option<Row>> {
+				use ::rusqlite::OptionalExtension as _;
+				conn.prepare_cached(GET_SQL)?
+					.query_row(::rusqlite::params![#(#key_names),*], Row::from_row)
+					.optional()
+			}
+
+			#update
+
+			const DELETE_SQL: &str = #delete_sql;
+			/// Delete the row with the primary key, returns the number of rows deleted
+			pub fn delete(conn: &::rusqlite::Connection, #key_params) -> ::rusqlite::Result<usize> {
+				conn.prepare_cached(DELETE_SQL)?.execute(::rusqlite::params![#(#key_names),*])
+			}
+		}
+	} else {
+		quote! {}
+	};
+
+	let insert = match sql_copy::insertable_columns(c) {
+		Some(cols) => {
+			let new_type = sql_copy::new_struct_name(&stripped_name, opt);
+			let insert_sql = format!(
+				"INSERT INTO {} ({}) VALUES ({})",
+				table,
+				cols.iter().map(|v| quote_ident(&v.name)).collect::<Vec<_>>().join(", "),
+				(1..=cols.len())
+					.map(|i| format!("?{}", i))
+					.collect::<Vec<_>>()
+					.join(", "),
+			);
+			let fields = cols.iter().map(|v| format_heck(&v.name, opt, SnakeCase));
+			quote! {
+				pub type NewRow = crate::types::#schema_name::#new_type;
+
+				const INSERT_SQL: &str = #insert_sql;
+				/// Insert the row, returns its rowid (which is meaningless for a `WITHOUT ROWID` table)
+				pub fn insert(conn: &::rusqlite::Connection, row: &NewRow) -> ::rusqlite::Result<i64> {
+					conn.prepare_cached(INSERT_SQL)?.execute(::rusqlite::params![#(row.#fields),*])?;
+					Ok(conn.last_insert_rowid())
+				}
+			}
+		},
+		None => quote! {},
+	};
+
+	quote! {
+		#[doc = #doc]
+		pub mod #module_name {
+			use super::*;
+
+			pub type Row = crate::types::#schema_name::#row_type;
+
+			const SELECT_SQL: &str = #select_sql;
+			/// Read every row
+			pub fn select_all(conn: &::rusqlite::Connection) -> ::rusqlite::Result<Vec<Row>> {
+				let mut stmt = conn.prepare_cached(SELECT_SQL)?;
+				let rows = stmt.query_map([], Row::from_row)?;
+				rows.collect()
+			}
+
+			#by_key
+			#insert
+		}
+	}
+}
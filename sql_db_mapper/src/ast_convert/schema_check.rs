@@ -0,0 +1,122 @@
+//! Functions for generating the fingerprint of the mapped tables and functions used to check a database at runtime
+use super::super::{sql_tree::*, Opt, Tuples};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Builds the contents of the `schema_check` module
+///
+/// ```ignore
+/// pub const FINGERPRINT: SchemaFingerprint = SchemaFingerprint {
+/// 	tables: &[TableFingerprint { schema: "people", name: "people", columns: &[/* ... */] }],
+/// 	functions: &[FunctionFingerprint { schema: "people", name: "add3", /* ... */ }],
+/// };
+/// #[cfg(feature = "sync")]
+/// pub fn verify_schema(client: &mut SyncClient) -> Result<SchemaReport, SqlError>;
+/// #[cfg(feature = "async")]
+/// pub async fn verify_schema_async(client: &AsyncClient) -> Result<SchemaReport, SqlError>;
+/// ```
+pub fn schema_check_content(db: &FullDB, opt: &Opt) -> TokenStream {
+	let tables = db.schemas.iter().flat_map(|schema| {
+		schema.types.iter().filter_map(move |typ| match &typ.typ {
+			PsqlTypType::Composite(c) => Some(table_fingerprint(&schema.name, &typ.name, c)),
+			_ => None,
+		})
+	});
+	let functions = db.schemas.iter().flat_map(|schema| {
+		schema
+			.procs
+			.iter()
+			.flat_map(move |procs| mapped_procs(procs, opt))
+			.filter(|proc| !(proc.outputs.schema == "pg_catalog" && proc.outputs.name == "record"))
+			.map(move |proc| function_fingerprint(schema, proc))
+	});
+
+	quote! {
+		use super::orm::{
+			schema_check::*,
+			SqlError,
+			SyncClient,
+			AsyncClient,
+		};
+
+		/// The tables and functions this mapping was generated from
+		pub const FINGERPRINT: SchemaFingerprint = SchemaFingerprint {
+			tables: &[#(#tables),*],
+			functions: &[#(#functions),*],
+		};
+
+		/// Check that the database matches the one this mapping was generated from
+		#[cfg(feature = "sync")]
+		pub fn verify_schema(client: &mut SyncClient) -> Result<SchemaReport, SqlError> {
+			FINGERPRINT.verify(client)
+		}
+
+		/// Check that the database matches the one this mapping was generated from
+		#[cfg(feature = "async")]
+		pub async fn verify_schema_async(client: &AsyncClient) -> Result<SchemaReport, SqlError> {
+			FINGERPRINT.verify_async(client).await
+		}
+	}
+}
+
+fn type_ref(schema: &str, name: &str) -> TokenStream {
+	quote! { TypeRef { schema: #schema, name: #name } }
+}
+
+fn table_fingerprint(schema: &str, name: &str, c: &PsqlCompositeType) -> TokenStream {
+	let columns = c.cols.iter().map(|col| {
+		let col_name = &col.name;
+		let typ = type_ref(&col.type_ns_name, &col.type_name);
+		let not_null = col.not_null;
+		let dimensions = col.num_dimentions;
+		quote! {
+			ColumnFingerprint { name: #col_name, typ: #typ, not_null: #not_null, dimensions: #dimensions }
+		}
+	});
+	quote! {
+		TableFingerprint { schema: #schema, name: #name, columns: &[#(#columns),*] }
+	}
+}
+
+/// The procs which have a function generated for them, mirrors the choices made in `sql_procs::proc_to_rust`
+fn mapped_procs<'a>(procs: &'a [SqlProc], opt: &Opt) -> &'a [SqlProc] {
+	match opt.use_tuples {
+		Tuples::ForOverloads | Tuples::ForAll => procs,
+		Tuples::NoOverloads if procs.len() == 1 => procs,
+		Tuples::NoOverloads => &[],
+		Tuples::OldestOverload => oldest_overload(procs).map(std::slice::from_ref).unwrap_or(&[]),
+	}
+}
+
+fn function_fingerprint(schema: &Schema, proc: &SqlProc) -> TokenStream {
+	let schema_name = &proc.ns_name;
+	let name = &proc.name;
+	let args = proc.inputs.0.iter().map(|v| type_ref(&v.typ.schema, &v.typ.name));
+	let returns_set = proc.returns_set;
+	// anonymous return types are generated with this name, their columns are fingerprinted as the generated struct reads them
+	let anonymous = if proc.outputs.schema == proc.ns_name && proc.outputs.name == format!("{}Return", proc.name) {
+		schema.types.iter().find_map(|v| match &v.typ {
+			PsqlTypType::SimpleComposite(cols) if v.name == proc.outputs.name => Some(cols),
+			_ => None,
+		})
+	} else {
+		None
+	};
+	let (returns, columns) = match anonymous {
+		Some(cols) => (
+			type_ref("pg_catalog", "record"),
+			cols.0.iter().map(|v| type_ref(&v.typ.schema, &v.typ.name)).collect(),
+		),
+		None => (type_ref(&proc.outputs.schema, &proc.outputs.name), Vec::new()),
+	};
+	quote! {
+		FunctionFingerprint {
+			schema: #schema_name,
+			name: #name,
+			args: &[#(#args),*],
+			returns: #returns,
+			returns_set: #returns_set,
+			returns_columns: &[#(#columns),*],
+		}
+	}
+}
@@ -0,0 +1,179 @@
+//! Functions for generating the binary `COPY` functions of tables and views
+use super::{
+	super::{sql_tree::*, Opt, Warnings},
+	format_heck, sql_types, CamelCase, SnakeCase,
+};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+/// Whether the schema has any tables or views to generate copy functions for
+pub fn has_relations(schema: &Schema) -> bool {
+	relations(schema).next().is_some()
+}
+
+fn relations(schema: &Schema) -> impl Iterator<Item = (&PsqlType, &PsqlCompositeType)> {
+	schema.types.iter().filter_map(|typ| match &typ.typ {
+		PsqlTypType::Composite(c) if c.kind.is_relation() => Some((typ, c)),
+		_ => None,
+	})
+}
+
+/// The columns written by `copy_in`, `None` if rows can't be copied into it
+pub fn insertable_columns(c: &PsqlCompositeType) -> Option<Vec<&Column>> {
+	let cols: Vec<_> = c.cols.iter().filter(|v| !v.has_default).collect();
+	if c.kind == RelKind::Table && !cols.is_empty() {
+		Some(cols)
+	} else {
+		None
+	}
+}
+
+pub fn new_struct_name(name: &str, opt: &Opt) -> proc_macro2::Ident {
+	format_ident!("New{}", format_heck(name, opt, CamelCase))
+}
+
+/// The `New{Table}` struct holding the columns of a table that don't have a default
+pub fn new_struct(
+	c: &PsqlCompositeType,
+	schema: &str,
+	sql_name: &str,
+	name: &str,
+	opt: &Opt,
+	warnings: &Warnings,
+) -> TokenStream {
+	let cols = match insertable_columns(c) {
+		Some(v) => v,
+		None => return quote! {},
+	};
+	let name_type = new_struct_name(name, opt);
+	let struct_body = cols.into_iter().map(|v| sql_types::column_to_field(v, opt, warnings));
+	let derives = sql_types::get_derives(schema, sql_name, &["Debug", "Clone"], opt, warnings);
+
+	quote! {
+		/// A row to insert with `copy_in`, columns which have defaults are left out
+		#derives
+		pub struct #name_type {
+			#(#struct_body),*
+		}
+	}
+}
+
+/// Builds the contents of a schema's module in `copy`, a module for each table or view
+///
+/// ```ignore
+/// pub mod people {
+/// 	impl CopyOut for crate::types::people::People { /* ... */ }
+/// 	impl CopyIn for crate::types::people::NewPeople { /* ... */ }
+/// 	#[cfg(feature = "sync")]
+/// 	pub fn copy_in(client: &mut SyncClient, rows: impl IntoIterator<Item = NewPeople>) -> Result<u64, SqlError>;
+/// 	#[cfg(feature = "sync")]
+/// 	pub fn copy_out(client: &mut SyncClient) -> Result<impl Iterator<Item = Result<People, SqlError>> + '_, SqlError>;
+/// 	#[cfg(feature = "async")]
+/// 	pub async fn copy_in_async(client: &AsyncClient, rows: impl IntoIterator<Item = NewPeople>) -> Result<u64, SqlError>;
+/// 	#[cfg(feature = "async")]
+/// 	pub async fn copy_out_async(client: &AsyncClient) -> Result<impl Stream<Item = Result<People, SqlError>>, SqlError>;
+/// }
+/// ```
+pub fn copy_content(schema: &Schema, opt: &Opt) -> TokenStream {
+	let tables = relations(schema).map(|(typ, c)| table_to_rust(&schema.name, &typ.name, c, opt));
+	quote! {
+		use super::*;
+		#(#tables)*
+	}
+}
+
+fn copy_table(schema: &str, name: &str, cols: &[&Column]) -> TokenStream {
+	let col_names = cols.iter().map(|v| &v.name);
+	let type_oids = cols.iter().map(|v| v.type_id);
+	quote! {
+		CopyTable {
+			schema: #schema,
+			name: #name,
+			columns: &[#(#col_names),*],
+			type_oids: &[#(#type_oids),*],
+		}
+	}
+}
+
+fn table_to_rust(schema: &str, name: &str, c: &PsqlCompositeType, opt: &Opt) -> TokenStream {
+	let stripped_name = name.replace(|c: char| !(c.is_ascii_alphanumeric() || c == '_'), "_");
+	let module_name = format_heck(&stripped_name, opt, SnakeCase);
+	let schema_name = format_heck(schema, opt, SnakeCase);
+	let row_type = format_heck(&stripped_name, opt, CamelCase);
+
+	let all_cols: Vec<_> = c.cols.iter().collect();
+	let out_table = copy_table(schema, name, &all_cols);
+	let out_fields = all_cols.iter().enumerate().map(|(i, v)| {
+		let field_name = format_heck(&v.name, opt, SnakeCase);
+		quote! { #field_name: row.try_get(#i)? }
+	});
+	let doc = format!("Binary `COPY` of `{}.{}`", schema, name);
+
+	let copy_in = match insertable_columns(c) {
+		Some(cols) => {
+			let new_type = new_struct_name(&stripped_name, opt);
+			let in_table = copy_table(schema, name, &cols);
+			let in_fields = cols.iter().map(|v| {
+				let field_name = format_heck(&v.name, opt, SnakeCase);
+				quote! { &self.#field_name }
+			});
+			quote! {
+				pub type NewRow = crate::types::#schema_name::#new_type;
+
+				impl CopyIn for NewRow {
+					const TABLE: CopyTable = #in_table;
+
+					fn values(&self) -> Vec<&(dyn ToSql + Sync)> {
+						vec![#(#in_fields),*]
+					}
+				}
+
+				/// Insert every row with `COPY ... FROM STDIN (FORMAT binary)`, returns the number of rows inserted
+				#[cfg(feature = "sync")]
+				pub fn copy_in(client: &mut SyncClient, rows: impl IntoIterator<Item = NewRow>) -> Result<u64, SqlError> {
+					crate::orm::copy::copy_in(client, rows)
+				}
+
+				/// Insert every row with `COPY ... FROM STDIN (FORMAT binary)`, returns the number of rows inserted
+				#[cfg(feature = "async")]
+				pub async fn copy_in_async(client: &AsyncClient, rows: impl IntoIterator<Item = NewRow>) -> Result<u64, SqlError> {
+					crate::orm::copy::copy_in_async(client, rows).await
+				}
+			}
+		},
+		None => quote! {},
+	};
+
+	quote! {
+		#[doc = #doc]
+		pub mod #module_name {
+			use super::*;
+
+			pub type Row = crate::types::#schema_name::#row_type;
+
+			impl CopyOut for Row {
+				const TABLE: CopyTable = #out_table;
+
+				fn from_copy_row(row: &BinaryCopyOutRow) -> Result<Self, SqlError> {
+					Ok(Self {
+						#(#out_fields),*
+					})
+				}
+			}
+
+			/// Read every row with `COPY ... TO STDOUT (FORMAT binary)`
+			#[cfg(feature = "sync")]
+			pub fn copy_out(client: &mut SyncClient) -> Result<impl Iterator<Item = Result<Row, SqlError>> + '_, SqlError> {
+				crate::orm::copy::copy_out(client)
+			}
+
+			/// Read every row with `COPY ... TO STDOUT (FORMAT binary)`
+			#[cfg(feature = "async")]
+			pub async fn copy_out_async(client: &AsyncClient) -> Result<impl Stream<Item = Result<Row, SqlError>>, SqlError> {
+				crate::orm::copy::copy_out_async(client).await
+			}
+
+			#copy_in
+		}
+	}
+}
@@ -0,0 +1,520 @@
+//! Functions for generating the per schema traits and their mock implementations
+use super::{
+	super::{sql_tree::*, Opt, Warnings},
+	format_heck, sql_copy, sql_procs, sql_query, CamelCase, SnakeCase,
+};
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use std::collections::HashSet;
+
+/// A method of a schema's trait
+struct Method {
+	name: Ident,
+	doc: String,
+	/// The parameters after the receiver
+	params: TokenStream,
+	/// The type in the `Ok` of the return value
+	ret: TokenStream,
+	sync_body: TokenStream,
+	async_body: TokenStream,
+	/// The owned arguments recorded by the mock and the expression making them from the parameters
+	mock_args: TokenStream,
+	mock_values: TokenStream,
+}
+
+/// Whether the schema's trait would have any methods
+pub fn has_methods(schema: &Schema, opt: &Opt, warnings: &Warnings) -> bool {
+	!methods(schema, opt, warnings).is_empty()
+}
+
+fn methods(schema: &Schema, opt: &Opt, warnings: &Warnings) -> Vec<Method> {
+	let schema_name = format_heck(&schema.name, opt, SnakeCase);
+	let mut ret = Vec::new();
+
+	for proc in schema.procs.iter().flat_map(|v| sql_procs::mapped_procs(v, opt)) {
+		let fn_name = &proc.fn_name;
+		let names: Vec<_> = proc
+			.proc
+			.inputs
+			.0
+			.iter()
+			.map(|tan| format_heck(&tan.name, opt, SnakeCase))
+			.collect();
+		let types: Vec<_> = proc.proc.inputs.0.iter().map(|tan| tan.typ.to_tokens(opt)).collect();
+		let (sync_body, async_body) = if proc.is_tuple {
+			// the trait behind overloads is private and its boxed futures are too strict about lifetimes to be awaited in
+			// an async_trait method so they run the query themselves
+			let sql = sql_procs::call_string(proc.proc);
+			let sync_body = sql_procs::proc_body(proc.proc, quote! { #sql }, opt, true);
+			let async_body = sql_procs::proc_body(proc.proc, quote! { #sql }, opt, false);
+			(
+				quote! {
+					let client = self;
+					#sync_body
+				},
+				quote! {
+					let client = self;
+					#async_body
+				},
+			)
+		} else {
+			(
+				quote! { crate::sync_fns::#schema_name::#fn_name(self, #(#names),*) },
+				quote! { crate::async_fns::#schema_name::#fn_name(self, #(#names),*).await },
+			)
+		};
+		ret.push(Method {
+			name: proc.overload_name,
+			doc: format!("Calls `{}.{}`", proc.proc.ns_name, proc.proc.name),
+			params: quote! { #(#names: &#types),* },
+			ret: sql_procs::return_type(proc.proc, opt),
+			sync_body,
+			async_body,
+			mock_args: quote! { (#(#types,)*) },
+			mock_values: quote! { (#(#names.clone(),)*) },
+		});
+	}
+
+	for (typ, c) in sql_query::relations(schema) {
+		let stripped_name = typ.name.replace(|c: char| !(c.is_ascii_alphanumeric() || c == '_'), "_");
+		let module_name = format_heck(&stripped_name, opt, SnakeCase);
+		let row_type = format_heck(&stripped_name, opt, CamelCase);
+		let row_type = quote! { crate::types::#schema_name::#row_type };
+
+		if opt.query_builder {
+			ret.push(Method {
+				name: format_heck(&format!("select_{}", stripped_name), opt, SnakeCase),
+				doc: format!("Runs a select on `{}.{}`", schema.name, typ.name),
+				params: quote! { query: &crate::orm::query::Query<#row_type> },
+				ret: quote! { Vec<#row_type> },
+				sync_body: quote! { query.fetch(self) },
+				async_body: quote! { query.fetch_async(self).await },
+				mock_args: quote! { QueryCall },
+				mock_values: quote! { QueryCall::new(query) },
+			});
+		}
+
+		if opt.copy && sql_copy::insertable_columns(c).is_some() {
+			let new_type = sql_copy::new_struct_name(&stripped_name, opt);
+			let new_type = quote! { crate::types::#schema_name::#new_type };
+			ret.push(Method {
+				name: format_heck(&format!("insert_{}", stripped_name), opt, SnakeCase),
+				doc: format!(
+					"Inserts rows into `{}.{}` with binary `COPY`, returns the number of rows inserted",
+					schema.name, typ.name
+				),
+				params: quote! { rows: Vec<#new_type> },
+				ret: quote! { u64 },
+				sync_body: quote! { crate::copy::#schema_name::#module_name::copy_in(self, rows) },
+				async_body: quote! { crate::copy::#schema_name::#module_name::copy_in_async(self, rows).await },
+				mock_args: quote! { Vec<#new_type> },
+				mock_values: quote! { rows },
+			});
+		}
+	}
+
+	let mut seen = HashSet::new();
+	ret.retain(|v| {
+		let is_new = seen.insert(v.name.to_string());
+		if !is_new {
+			warnings.push(format!(
+				"Skipped mocking {} in schema {}, another method has the same name",
+				v.name, schema.name
+			));
+		}
+		is_new
+	});
+	ret
+}
+
+/// Builds the contents of a schema's module in `mock`
+///
+/// ```ignore
+/// pub mod people {
+/// 	#[cfg(feature = "sync")]
+/// 	pub trait PeopleFns {
+/// 		fn new_person(&mut self, name: &Text, birthday: &Date) -> Result<Option<Void>, SqlError>;
+/// 		fn select_people(&mut self, query: &Query<People>) -> Result<Vec<People>, SqlError>;
+/// 		fn insert_people(&mut self, rows: Vec<NewPeople>) -> Result<u64, SqlError>;
+/// 	}
+/// 	#[cfg(feature = "sync")]
+/// 	impl PeopleFns for SyncClient { /* calls the functions in sync_fns, query, and copy */ }
+/// 	// PeopleFnsAsync is the same with async methods taking &self
+/// 	#[cfg(feature = "async")]
+/// 	impl PeopleFnsAsync for AsyncClient { /* ... */ }
+///
+/// 	pub struct MockPeopleFns {
+/// 		pub new_person: MockFn<(Text, Date), Option<Void>>,
+/// 		pub select_people: MockFn<QueryCall, Vec<People>>,
+/// 		pub insert_people: MockFn<Vec<NewPeople>, u64>,
+/// 	}
+/// 	impl PeopleFns for MockPeopleFns { /* ... */ }
+/// 	impl PeopleFnsAsync for MockPeopleFns { /* ... */ }
+/// }
+/// ```
+pub fn mock_content(schema: &Schema, opt: &Opt, warnings: &Warnings) -> TokenStream {
+	let methods = methods(schema, opt, warnings);
+	let type_name = format_heck(&schema.name, opt, CamelCase);
+	let trait_name = format_ident!("{}Fns", type_name);
+	let async_trait_name = format_ident!("{}FnsAsync", type_name);
+	let mock_name = format_ident!("Mock{}Fns", type_name);
+
+	let trait_doc = format!("The functions of schema `{}`", schema.name);
+	let mock_doc = format!(
+		"A mock of [`{}`] and [`{}`], set the return values of each method through its field",
+		trait_name, async_trait_name
+	);
+
+	let sync_sigs = methods.iter().map(|m| {
+		let Method { name, doc, params, ret, .. } = m;
+		quote! {
+			#[doc = #doc]
+			fn #name(&mut self, #params) -> Result<#ret, SqlError>;
+		}
+	});
+	let async_sigs = methods.iter().map(|m| {
+		let Method { name, doc, params, ret, .. } = m;
+		quote! {
+			#[doc = #doc]
+			async fn #name(&self, #params) -> Result<#ret, SqlError>;
+		}
+	});
+	let sync_impls = methods.iter().map(|m| {
+		let Method {
+			name,
+			params,
+			ret,
+			sync_body,
+			..
+		} = m;
+		quote! {
+			fn #name(&mut self, #params) -> Result<#ret, SqlError> {
+				#sync_body
+			}
+		}
+	});
+	let async_impls = methods.iter().map(|m| {
+		let Method {
+			name,
+			params,
+			ret,
+			async_body,
+			..
+		} = m;
+		quote! {
+			async fn #name(&self, #params) -> Result<#ret, SqlError> {
+				#async_body
+			}
+		}
+	});
+	let mock_fields = methods.iter().map(|m| {
+		let Method {
+			name, ret, mock_args, ..
+		} = m;
+		quote! { pub #name: MockFn<#mock_args, #ret> }
+	});
+	let mock_inits = methods.iter().map(|m| {
+		let name = &m.name;
+		let full_name = format!("{}.{}", schema.name, name);
+		quote! { #name: MockFn::new(#full_name) }
+	});
+	let mock_sync_impls = methods.iter().map(|m| {
+		let Method {
+			name,
+			params,
+			ret,
+			mock_values,
+			..
+		} = m;
+		quote! {
+			fn #name(&mut self, #params) -> Result<#ret, SqlError> {
+				Ok(self.#name.call(#mock_values))
+			}
+		}
+	});
+	let mock_async_impls = methods.iter().map(|m| {
+		let Method {
+			name,
+			params,
+			ret,
+			mock_values,
+			..
+		} = m;
+		quote! {
+			async fn #name(&self, #params) -> Result<#ret, SqlError> {
+				Ok(self.#name.call(#mock_values))
+			}
+		}
+	});
+
+	quote! {
+		use super::*;
+
+		#[doc = #trait_doc]
+		#[cfg(feature = "sync")]
+		pub trait #trait_name {
+			#(#sync_sigs)*
+		}
+
+		#[cfg(feature = "sync")]
+		impl #trait_name for SyncClient {
+			#(#sync_impls)*
+		}
+
+		#[doc = #trait_doc]
+		#[cfg(feature = "async")]
+		#[async_trait]
+		pub trait #async_trait_name {
+			#(#async_sigs)*
+		}
+
+		#[cfg(feature = "async")]
+		#[async_trait]
+		impl #async_trait_name for AsyncClient {
+			#(#async_impls)*
+		}
+
+		#[doc = #mock_doc]
+		#[derive(Debug)]
+		pub struct #mock_name {
+			#(#mock_fields),*
+		}
+
+		impl #mock_name {
+			pub fn new() -> Self {
+				#mock_name {
+					#(#mock_inits),*
+				}
+			}
+		}
+
+		impl Default for #mock_name {
+			fn default() -> Self {
+				Self::new()
+			}
+		}
+
+		#[cfg(feature = "sync")]
+		impl #trait_name for #mock_name {
+			#(#mock_sync_impls)*
+		}
+
+		#[cfg(feature = "async")]
+		#[async_trait]
+		impl #async_trait_name for #mock_name {
+			#(#mock_async_impls)*
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const MOCK_DOC: &str =
+		"A mock of [`PeopleFns`] and [`PeopleFnsAsync`], set the return values of each method through its field";
+
+	fn proc(name: &str, inputs: &[(&str, &str)], output: &str, returns_set: bool) -> SqlProc {
+		SqlProc {
+			ns: 0,
+			ns_name: String::from("people"),
+			oid: 0,
+			name: String::from(name),
+			returns_set,
+			num_args: inputs.len() as i16,
+			inputs: NamesAndTypes(
+				inputs
+					.iter()
+					.map(|(name, typ)| TypeAndName {
+						typ: FullType {
+							schema: String::from("pg_catalog"),
+							name: String::from(*typ),
+						},
+						name: String::from(*name),
+					})
+					.collect(),
+			),
+			outputs: FullType {
+				schema: String::from("pg_catalog"),
+				name: String::from(output),
+			},
+		}
+	}
+
+	/// A table of one `text` column
+	fn table(name: &str) -> PsqlType {
+		PsqlType {
+			name: String::from(name),
+			ns: 0,
+			typ: PsqlTypType::Composite(PsqlCompositeType {
+				oid: 0,
+				kind: RelKind::Table,
+				cols: vec![Column {
+					pos: 1,
+					name: String::from("name"),
+					type_id: 25,
+					type_name: String::from("text"),
+					type_ns_name: String::from("pg_catalog"),
+					not_null: true,
+					num_dimentions: 0,
+					has_default: false,
+				}],
+				primary_key: Vec::new(),
+				foreign_keys: Vec::new(),
+			}),
+		}
+	}
+
+	fn schema(types: Vec<PsqlType>, procs: Vec<Vec<SqlProc>>) -> Schema {
+		Schema {
+			id: 0,
+			name: String::from("people"),
+			owner_name: String::from("postgres"),
+			types,
+			procs,
+		}
+	}
+
+	/// An item printed the same way as those of [`mock`]
+	fn item(tokens: TokenStream) -> String {
+		let item: syn::Item = syn::parse2(tokens).unwrap();
+		quote! { #item }.to_string()
+	}
+
+	/// The mock struct of the schema's module and its implementation of the sync trait
+	fn mock(schema: &Schema, opt: &Opt, warnings: &Warnings) -> (String, String) {
+		let file: syn::File = syn::parse2(mock_content(schema, opt, warnings)).unwrap();
+		let mut mock = None;
+		let mut mock_impl = None;
+		for item in file.items {
+			match item {
+				syn::Item::Struct(v) if v.ident == "MockPeopleFns" => mock = Some(quote! { #v }.to_string()),
+				syn::Item::Impl(v) => {
+					let (self_ty, trait_) = (&v.self_ty, v.trait_.as_ref().map(|v| &v.1));
+					if quote! { #self_ty }.to_string() == "MockPeopleFns"
+						&& quote! { #trait_ }.to_string() == "PeopleFns"
+					{
+						mock_impl = Some(quote! { #v }.to_string());
+					}
+				},
+				_ => (),
+			}
+		}
+		(mock.unwrap(), mock_impl.unwrap())
+	}
+
+	#[test]
+	fn mock_records_the_arguments_of_each_method() {
+		let opt = Opt {
+			rust_case: true,
+			query_builder: true,
+			..Opt::default()
+		};
+		let warnings = Warnings::default();
+		let schema = schema(
+			vec![table("person")],
+			vec![vec![proc(
+				"add_person",
+				&[("name", "text"), ("age", "int4")],
+				"int4",
+				false,
+			)]],
+		);
+		let (mock, mock_impl) = mock(&schema, &opt, &warnings);
+		let expected = quote! {
+			#[doc = #MOCK_DOC]
+			#[derive(Debug)]
+			pub struct MockPeopleFns {
+				pub add_person: MockFn<
+					(crate::types::pg_catalog::Text, crate::types::pg_catalog::Int4,),
+					Option<crate::types::pg_catalog::Int4>
+				>,
+				pub select_person: MockFn<QueryCall, Vec<crate::types::people::Person>>
+			}
+		};
+		assert_eq!(mock, item(expected));
+		let expected = quote! {
+			#[cfg(feature = "sync")]
+			impl PeopleFns for MockPeopleFns {
+				fn add_person(
+					&mut self,
+					name: &crate::types::pg_catalog::Text,
+					age: &crate::types::pg_catalog::Int4
+				) -> Result<Option<crate::types::pg_catalog::Int4>, SqlError> {
+					Ok(self.add_person.call((name.clone(), age.clone(),)))
+				}
+				fn select_person(
+					&mut self,
+					query: &crate::orm::query::Query<crate::types::people::Person>
+				) -> Result<Vec<crate::types::people::Person>, SqlError> {
+					Ok(self.select_person.call(QueryCall::new(query)))
+				}
+			}
+		};
+		assert_eq!(mock_impl, item(expected));
+		assert_eq!(warnings.take(), Vec::<String>::new());
+	}
+
+	#[test]
+	fn overloads_are_numbered() {
+		let opt = Opt {
+			rust_case: true,
+			..Opt::default()
+		};
+		let schema = schema(
+			Vec::new(),
+			vec![vec![
+				proc("find", &[("id", "int4")], "text", true),
+				proc("find", &[("name", "text")], "text", true),
+			]],
+		);
+		let (mock, _) = mock(&schema, &opt, &Warnings::default());
+		let expected = quote! {
+			#[doc = #MOCK_DOC]
+			#[derive(Debug)]
+			pub struct MockPeopleFns {
+				pub find_0: MockFn<(crate::types::pg_catalog::Int4,), Vec<crate::types::pg_catalog::Text>>,
+				pub find_1: MockFn<(crate::types::pg_catalog::Text,), Vec<crate::types::pg_catalog::Text>>
+			}
+		};
+		assert_eq!(mock, item(expected));
+	}
+
+	#[test]
+	fn clashing_methods_are_skipped_with_a_warning() {
+		let opt = Opt {
+			rust_case: true,
+			query_builder: true,
+			..Opt::default()
+		};
+		let warnings = Warnings::default();
+		let schema = schema(
+			vec![table("person")],
+			vec![vec![proc("select_person", &[], "int4", false)]],
+		);
+		let (mock, _) = mock(&schema, &opt, &warnings);
+		let expected = quote! {
+			#[doc = #MOCK_DOC]
+			#[derive(Debug)]
+			pub struct MockPeopleFns {
+				pub select_person: MockFn<(), Option<crate::types::pg_catalog::Int4>>
+			}
+		};
+		assert_eq!(mock, item(expected));
+		assert_eq!(
+			warnings.take(),
+			["Skipped mocking select_person in schema people, another method has the same name"]
+		);
+	}
+
+	#[test]
+	fn tables_alone_have_no_methods() {
+		let schema = schema(vec![table("person")], Vec::new());
+		assert!(!has_methods(&schema, &Opt::default(), &Warnings::default()));
+		let opt = Opt {
+			copy: true,
+			..Opt::default()
+		};
+		assert!(has_methods(&schema, &opt, &Warnings::default()));
+	}
+}
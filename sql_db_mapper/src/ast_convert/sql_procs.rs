@@ -1,11 +1,11 @@
 //! Functions for generating rust functions
 use super::{
-	super::{sql_tree::*, Opt, Tuples},
+	super::{sql_tree::*, Opt, Tuples, Warnings},
 	format_heck,
 	Case::*,
 };
-use proc_macro2::TokenStream;
-use quote::quote;
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
 
 /// Takes a SQL procedure and turns it into a rust function
 ///
@@ -23,7 +23,7 @@ use quote::quote;
 /// }
 /// // fn can be sync as well
 /// pub async fn my_function(
-/// 	// client is & is async and &mut if sync (mirrors client's methods between tokio-postgres and postgres)
+/// 	// client is &mut Client if sync and &C for any C: AsyncConnection if async (which then returns Result<_, C::Error>)
 /// 	client: &mut Client,
 /// 	param0: &super::pg_catalog::varchar,
 /// 	param1: &super::pg_catalog::varchar,
@@ -55,41 +55,93 @@ use quote::quote;
 /// 	//impls for other input params
 /// }
 /// ```
-pub fn proc_to_rust(proc: &[SqlProc], opt: &Opt, is_sync: bool) -> TokenStream {
-	if proc.len() == 0 {
-		if opt.debug {
-			println!("Error; retrieved an empty Vec of SqlProcs")
-		};
+pub fn proc_to_rust(proc: &[SqlProc], opt: &Opt, warnings: &Warnings, is_sync: bool) -> TokenStream {
+	if proc.is_empty() {
+		warnings.push(String::from("Error; retrieved an empty Vec of SqlProcs"));
 		return quote! {};
 	}
 
 	match opt.use_tuples {
 		Tuples::ForOverloads => {
 			if proc.len() == 1 {
-				single_proc_to_rust(&proc[0], &proc[0].name, false, opt, is_sync)
+				single_proc_to_rust(&proc[0], &proc[0].name, false, opt, warnings, is_sync)
 			} else {
-				to_many_fns(proc, opt, is_sync)
+				to_many_fns(proc, opt, warnings, is_sync)
 			}
 		},
-		Tuples::ForAll => to_many_fns(proc, opt, is_sync),
+		Tuples::ForAll => to_many_fns(proc, opt, warnings, is_sync),
 		Tuples::NoOverloads => {
 			if proc.len() == 1 {
-				single_proc_to_rust(&proc[0], &proc[0].name, false, opt, is_sync)
+				single_proc_to_rust(&proc[0], &proc[0].name, false, opt, warnings, is_sync)
 			} else {
-				if opt.debug {
-					println!("Overloaded Proc: '{}' not mapped", proc[0].name)
-				};
+				warnings.push(format!("Overloaded Proc: '{}' not mapped", proc[0].name));
 				quote! {}
 			}
 		},
-		Tuples::OldestOverload => single_proc_to_rust(&proc[0], &proc[0].name, false, opt, is_sync),
+		Tuples::OldestOverload => match oldest_overload(proc) {
+			Some(oldest) => single_proc_to_rust(oldest, &oldest.name, false, opt, warnings, is_sync),
+			None => quote! {},
+		},
+	}
+}
+
+/// A procedure as it's mapped in `sync_fns` and `async_fns`
+pub struct MappedProc<'a> {
+	pub proc: &'a SqlProc,
+	/// The name of the generated function
+	pub fn_name: Ident,
+	/// A unique name for this overload, the same as `fn_name` if there's only one
+	pub overload_name: Ident,
+	/// Whether the function takes its client and parameters as a tuple
+	pub is_tuple: bool,
+}
+
+/// Gets each procedure which `proc_to_rust` makes a function or overload for, in the same order
+pub fn mapped_procs<'a>(procs: &'a [SqlProc], opt: &Opt) -> Vec<MappedProc<'a>> {
+	if procs.is_empty() {
+		return Vec::new();
+	}
+	let mapped: Vec<_> = match opt.use_tuples {
+		Tuples::ForOverloads | Tuples::NoOverloads if procs.len() == 1 => vec![(0, &procs[0], false)],
+		Tuples::ForOverloads | Tuples::ForAll => procs.iter().enumerate().map(|(i, p)| (i, p, true)).collect(),
+		Tuples::NoOverloads => Vec::new(),
+		Tuples::OldestOverload => oldest_overload(procs).map(|p| (0, p, false)).into_iter().collect(),
+	};
+	let fn_name = format_heck(&procs[0].name, opt, SnakeCase);
+	mapped
+		.into_iter()
+		.filter(|(_, p, _)| !returns_record(p))
+		.map(|(i, proc, is_tuple)| MappedProc {
+			proc,
+			fn_name: fn_name.clone(),
+			overload_name: if procs.len() == 1 || !is_tuple {
+				fn_name.clone()
+			} else {
+				format_ident!("{}_{}", fn_name, i)
+			},
+			is_tuple,
+		})
+		.collect()
+}
+
+fn returns_record(proc: &SqlProc) -> bool {
+	proc.outputs.schema == "pg_catalog" && proc.outputs.name == "record"
+}
+
+/// The type in the `Ok` of a procedure's function, a `Vec` if it returns a set and an `Option` otherwise
+pub fn return_type(proc: &SqlProc, opt: &Opt) -> TokenStream {
+	let typ = proc.outputs.to_tokens(opt);
+	if proc.returns_set {
+		quote! { Vec<#typ> }
+	} else {
+		quote! { Option<#typ> }
 	}
 }
 
 /// Turns an overloaded SQL function to a rough equicvalent in rust
-fn to_many_fns(procs: &[SqlProc], opt: &Opt, is_sync: bool) -> TokenStream {
+fn to_many_fns(procs: &[SqlProc], opt: &Opt, warnings: &Warnings, is_sync: bool) -> TokenStream {
 	let name_type = format_heck(&procs[0].name, opt, SnakeCase);
-	let doc_comments = to_overload_doc(&procs, opt);
+	let doc_comments = to_overload_doc(procs, opt);
 	let fn_docs = quote! {
 		/// This is an overloaded SQL function, it takes one tuple parameter.
 		///
@@ -128,7 +180,7 @@ fn to_many_fns(procs: &[SqlProc], opt: &Opt, is_sync: bool) -> TokenStream {
 		)
 	};
 
-	let trait_impls = procs.iter().enumerate().map(|(i, p)| to_trait_impl(i, p, opt, is_sync));
+	let trait_impls = procs.iter().enumerate().map(|(i, p)| to_trait_impl(i, p, opt, warnings, is_sync));
 
 	quote! {
 		#fn_code
@@ -145,10 +197,10 @@ fn to_many_fns(procs: &[SqlProc], opt: &Opt, is_sync: bool) -> TokenStream {
 }
 
 /// For overloaded functions get the function implementation
-fn to_trait_impl(index: usize, proc: &SqlProc, opt: &Opt, is_sync: bool) -> TokenStream {
+fn to_trait_impl(index: usize, proc: &SqlProc, opt: &Opt, warnings: &Warnings, is_sync: bool) -> TokenStream {
 	//build SQL string to call proc
 	let new_name = format!("{}{}", proc.name, index);
-	single_proc_to_rust(proc, &new_name, true, opt, is_sync)
+	single_proc_to_rust(proc, &new_name, true, opt, warnings, is_sync)
 }
 /// gets the type of the input to one variant for an overloaded function
 fn to_tuple_type(types: &[TypeAndName], opt: &Opt, is_sync: bool) -> TokenStream {
@@ -160,7 +212,7 @@ fn to_tuple_type(types: &[TypeAndName], opt: &Opt, is_sync: bool) -> TokenStream
 	if is_sync {
 		quote! { (&'a mut Client, #(#tuple_middle),* ) }
 	} else {
-		quote! { (&'a Client, #(#tuple_middle),* ) }
+		quote! { (&'a C, #(#tuple_middle),* ) }
 	}
 }
 
@@ -195,7 +247,14 @@ fn to_overload_doc(procs: &[SqlProc], opt: &Opt) -> TokenStream {
 }
 
 
-fn single_proc_to_rust(proc: &SqlProc, name: &str, is_overide: bool, opt: &Opt, is_sync: bool) -> TokenStream {
+fn single_proc_to_rust(
+	proc: &SqlProc,
+	name: &str,
+	is_overide: bool,
+	opt: &Opt,
+	warnings: &Warnings,
+	is_sync: bool,
+) -> TokenStream {
 	let name_type = format_heck(name, opt, SnakeCase);
 
 	//build SQL string to call proc
@@ -204,69 +263,41 @@ fn single_proc_to_rust(proc: &SqlProc, name: &str, is_overide: bool, opt: &Opt,
 	let call_string = make_call_string(&proc.ns_name, &proc.name, proc.num_args as usize);
 	let call_string = quote! { const #call_string_name : &str = #call_string; };
 
-	//get the output type name
-	let ret_type_name = if proc.outputs.schema == "pg_catalog" && proc.outputs.name == "record" {
-		if opt.debug {
-			println!(
-				"Cannot make wrapper for procedure {} which returns pg_catalog::record",
-				name
-			)
-		};
+	if returns_record(proc) {
+		warnings.push(format!(
+			"Cannot make wrapper for procedure {} which returns pg_catalog::record",
+			name
+		));
 		return quote! {};
-	} else {
-		let typ = proc.outputs.to_tokens(opt);
-		quote! { #typ }
-	};
+	}
 	//get the return type properly wrapped in a Vec or Option
-	let new_ret_type_name = if proc.returns_set {
-		quote! { Vec<#ret_type_name> }
-	} else {
-		quote! { Option<#ret_type_name> }
-	};
+	let new_ret_type_name = return_type(proc, opt);
 
 	let func_params = proc.inputs.as_function_params(opt);
-	let query_params = as_query_params(&proc.inputs.0, opt);
 
-	let (opt_async, opt_await, is_async_trait, client_type) = if is_sync {
-		(quote! {}, quote! {}, quote! {}, quote! { &mut Client })
+	// async functions run on any AsyncConnection, which returns its own error type
+	let (opt_async, is_async_trait, generics, client_type, error_type) = if is_sync {
+		(quote! {}, quote! {}, quote! {}, quote! { &mut Client }, quote! { SqlError })
 	} else {
 		(
 			quote! { async },
-			quote! { .await },
 			quote! { #[async_trait] },
-			quote! { &Client },
+			quote! { C: AsyncConnection },
+			quote! { &C },
+			quote! { C::Error },
 		)
 	};
 
 	//the body of the function
-	let body = if proc.returns_set {
-		quote! {
-			let stmt = client.prepare(#call_string_name)#opt_await?;
-			client
-				.query(&stmt, &[#query_params])#opt_await?
-				.iter()
-				.map(#ret_type_name::from_row)
-				.collect()
-		}
-	} else {
-		quote! {
-			let stmt = client.prepare(#call_string_name)#opt_await?;
-			Ok(client
-				.query_opt(&stmt, &[#query_params])#opt_await?
-				.as_ref()
-				.map(#ret_type_name::from_row)
-				.transpose()?
-			)
-		}
-	};
+	let body = proc_body(proc, quote! { #call_string_name }, opt, is_sync);
 	//the wrappings on the body
 	let func_text = if is_overide {
 		let tuple_type = to_tuple_type(&proc.inputs.0, opt, is_sync);
 		let tuple_pattern = to_tuple_pattern(&proc.inputs.0, opt);
 		quote! {
 			#is_async_trait
-			impl<'a> OverloadTrait for #tuple_type {
-				type Output = Result<#new_ret_type_name, SqlError>;
+			impl<'a, #generics> OverloadTrait for #tuple_type {
+				type Output = Result<#new_ret_type_name, #error_type>;
 				#opt_async fn tmp(self)-> Self::Output {
 					let #tuple_pattern = self;
 					#body
@@ -275,10 +306,10 @@ fn single_proc_to_rust(proc: &SqlProc, name: &str, is_overide: bool, opt: &Opt,
 		}
 	} else {
 		quote! {
-			pub #opt_async fn #name_type(
+			pub #opt_async fn #name_type<#generics>(
 				client : #client_type,
 				#func_params
-			) -> Result<#new_ret_type_name, SqlError> {
+			) -> Result<#new_ret_type_name, #error_type> {
 				#body
 			}
 		}
@@ -289,14 +320,57 @@ fn single_proc_to_rust(proc: &SqlProc, name: &str, is_overide: bool, opt: &Opt,
 	}
 }
 
+/// The body of a procedure's function, runs `sql` on `client` with the procedure's parameters
+pub fn proc_body(proc: &SqlProc, sql: TokenStream, opt: &Opt, is_sync: bool) -> TokenStream {
+	let ret_type_name = proc.outputs.to_tokens(opt);
+	let query_params = as_query_params(&proc.inputs.0, opt);
 
-fn make_call_string(namespace: &str, function: &str, len: usize) -> String {
-	let mut ret = format!(r#"SELECT * FROM "{}"."{}"("#, namespace, function);
-	for i in 1..len {
-		ret += &format!("${},", i);
+	// AsyncConnection prepares the statement itself, a pooled connection may have it cached
+	match (is_sync, proc.returns_set) {
+		(true, true) => quote! {
+			let stmt = client.prepare(#sql)?;
+			client
+				.query(&stmt, &[#query_params])?
+				.iter()
+				.map(#ret_type_name::from_row)
+				.collect()
+		},
+		(true, false) => quote! {
+			let stmt = client.prepare(#sql)?;
+			Ok(client
+				.query_opt(&stmt, &[#query_params])?
+				.as_ref()
+				.map(#ret_type_name::from_row)
+				.transpose()?
+			)
+		},
+		(false, true) => quote! {
+			let rows = client.query(#sql, &[#query_params]).await?;
+			Ok(rows
+				.iter()
+				.map(#ret_type_name::from_row)
+				.collect::<Result<_, SqlError>>()?
+			)
+		},
+		(false, false) => quote! {
+			Ok(client
+				.query_opt(#sql, &[#query_params]).await?
+				.as_ref()
+				.map(#ret_type_name::from_row)
+				.transpose()?
+			)
+		},
 	}
-	ret += &format!("${})", len);
-	ret
+}
+
+/// The SQL sent to call a procedure
+pub fn call_string(proc: &SqlProc) -> String {
+	make_call_string(&proc.ns_name, &proc.name, proc.num_args as usize)
+}
+
+fn make_call_string(namespace: &str, function: &str, len: usize) -> String {
+	let params: Vec<_> = (1..=len).map(|i| format!("${}", i)).collect();
+	format!(r#"SELECT * FROM "{}"."{}"({})"#, namespace, function, params.join(","))
 }
 
 fn as_query_params(inputs: &[TypeAndName], opt: &Opt) -> TokenStream {
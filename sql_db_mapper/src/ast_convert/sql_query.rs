@@ -0,0 +1,92 @@
+//! Functions for generating the typed select builders of tables and views
+use super::{
+	super::{sql_tree::*, Opt},
+	format_heck, CamelCase, SnakeCase,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Whether the schema has any tables or views to generate a builder for
+pub fn has_relations(schema: &Schema) -> bool {
+	relations(schema).next().is_some()
+}
+
+pub fn relations(schema: &Schema) -> impl Iterator<Item = (&PsqlType, &PsqlCompositeType)> {
+	schema.types.iter().filter_map(|typ| match &typ.typ {
+		PsqlTypType::Composite(c) if c.kind.is_relation() => Some((typ, c)),
+		_ => None,
+	})
+}
+
+/// Builds the contents of a schema's module in `query`, a module for each table or view
+///
+/// ```ignore
+/// pub mod people {
+/// 	pub type Row = crate::types::people::People;
+/// 	pub struct Column;
+/// 	impl Column {
+/// 		pub const Name: TypedColumn<Row, crate::types::pg_catalog::Varchar> = TypedColumn::new("name");
+/// 		pub const Birthday: TypedColumn<Row, crate::types::pg_catalog::Date> = TypedColumn::new("birthday");
+/// 	}
+/// 	pub const TABLE: Table = Table { schema: "people", name: "people", columns: &["name", "birthday"] };
+/// 	pub fn query() -> Query<Row>;
+/// }
+/// ```
+pub fn query_content(schema: &Schema, opt: &Opt) -> TokenStream {
+	let tables = relations(schema).map(|(typ, c)| table_to_rust(&schema.name, &typ.name, c, opt));
+	quote! {
+		use super::*;
+		#(#tables)*
+	}
+}
+
+fn table_to_rust(schema: &str, name: &str, c: &PsqlCompositeType, opt: &Opt) -> TokenStream {
+	let stripped_name = name.replace(|c: char| !(c.is_ascii_alphanumeric() || c == '_'), "_");
+	let module_name = format_heck(&stripped_name, opt, SnakeCase);
+	let schema_name = format_heck(schema, opt, SnakeCase);
+	let row_type = format_heck(&stripped_name, opt, CamelCase);
+
+	let columns = c.cols.iter().map(|v| {
+		let col_name = &v.name;
+		let const_name = format_heck(&v.name, opt, CamelCase);
+		let type_schema = format_heck(&v.type_ns_name, opt, SnakeCase);
+		let type_name = format_heck(&v.type_name, opt, CamelCase);
+		let mut value_type = quote! { crate::types::#type_schema::#type_name };
+		for _ in 0..v.num_dimentions {
+			value_type = quote! { Vec<#value_type> };
+		}
+		quote! {
+			pub const #const_name: TypedColumn<Row, #value_type> = TypedColumn::new(#col_name);
+		}
+	});
+	let col_names = c.cols.iter().map(|v| &v.name);
+	let doc = format!("Typed selects on `{}.{}`", schema, name);
+
+	quote! {
+		#[doc = #doc]
+		pub mod #module_name {
+			use super::*;
+
+			/// The type each row is read into
+			pub type Row = crate::types::#schema_name::#row_type;
+
+			/// The columns of the table, used to build filters and orderings
+			pub struct Column;
+			#[allow(non_upper_case_globals)]
+			impl Column {
+				#(#columns)*
+			}
+
+			pub const TABLE: Table = Table {
+				schema: #schema,
+				name: #name,
+				columns: &[#(#col_names),*],
+			};
+
+			/// Start a select of every row
+			pub fn query() -> Query<Row> {
+				Query::new(&TABLE)
+			}
+		}
+	}
+}
@@ -1,27 +1,46 @@
 //! Functions for generating rust types
 use super::{
-	super::{sql_tree::*, Opt},
-	format_heck,
+	super::{sql_tree::*, Opt, Warnings},
+	diesel_schema,
+	domain_check::{self, BaseKind},
+	format_heck, mysql_procs, rusqlite_crud, sql_copy,
 	Case::*,
 };
-use crate::ThirdParty;
+use crate::{Backend, ThirdParty};
 use ThirdParty::*;
 
-use proc_macro2::TokenStream;
-use quote::quote;
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote, ToTokens as _};
+use syn::parse::Parser as _;
 
 
 /// Takes a SQL type and renders its Rust equivalent
 ///
 /// All the generated typs include derives for Debug, Clone, FromSql, ToSql, and TryFromRow (which allows straight conversion from the postgres and tokio-postgres Row struct into the type)
 ///
+/// With the sqlx backend those are replaced by `sqlx::Type` and, for composite types, `sqlx::FromRow`. Domains implement
+/// `sqlx::Type` themselves so they're accepted as either the domain or its base type
+///
+/// With the diesel backend enums and domains implement diesel's `FromSql` and `ToSql` for their sql type, tables and views
+/// derive `Queryable` and `Selectable` with a `New{Table}` deriving `Insertable`, and other composite types aren't mapped
+///
+/// With the rusqlite backend, which maps SQLite databases, there are only base types and the structs of tables and views. The
+/// structs have a `from_row` function and tables have a `New{Table}` for inserts
+///
+/// With the mysql backend there are base types, an enum for each `ENUM(...)` column, and the structs of tables and views.
+/// They implement mysql_common's `FromValue` or `FromRow` and enums convert into a `Value`
+///
 /// ```ignore
-/// //an postgres enum type
+/// //an postgres enum type, variants are in the database's sort order
 /// #[derive(Debug, Clone, TryFromRow, ToSql, FromSql)]
+/// #[derive(Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 /// pub enum MySqlEnum {
 /// 	Variant1,
 /// 	Variant2,
 /// }
+/// impl MySqlEnum { pub const ALL: &'static [MySqlEnum]; pub fn as_str(&self) -> &'static str; }
+/// impl Display for MySqlEnum { /* ... */ }
+/// impl FromStr for MySqlEnum { /* ... */ }
 ///
 /// // a composite type; the type of a table, view, or anonymous record returned by a procedure
 /// #[derive(Debug, Clone, TryFromRow, ToSql, FromSql)]
@@ -36,77 +55,520 @@ use quote::quote;
 /// pub type bytea = Vec<u8>;
 /// pub type int8 = i64;
 ///
-/// // a domain type, a simple wrapper on another type
+/// // a domain type, a wrapper on another type which checks the domain's constraints in `TryFrom`
 /// #[derive(Debug, Clone, TryFromRow, ToSql, FromSql)]
 /// pub struct MyNewType(pub pg_catalog::varchar);
+/// impl TryFrom<pg_catalog::varchar> for MyNewType { /* ... */ }
 ///
 /// // other types can't be converted at the current moment (if the program is called with the debug flag it will print when it comes across something it skips)
 ///
 /// ```
-pub fn type_to_rust(typ: &PsqlType, opt: &Opt) -> TokenStream {
+pub fn type_to_rust(typ: &PsqlType, schema: &str, db: &FullDB, opt: &Opt, warnings: &Warnings) -> TokenStream {
 	use PsqlTypType::*;
 	let stripped_name = typ.name.clone().replace(|c: char| !(c.is_ascii_alphanumeric() || c=='_'), "_");
 	match &typ.typ {
-		Enum(e) => enum_to_ast(e, &stripped_name, opt),
-		Composite(c) => composite_to_ast(c, &stripped_name, opt),
-		Base(b) => base_to_ast(b, opt),
-		Domain(d) => domain_to_ast(d, &stripped_name, opt),
+		// SQLite doesn't have any of these
+		Enum(_) | Domain(_) | SimpleComposite(_) if opt.backend == Backend::Rusqlite => {
+			warnings.push(format!("`{}.{}` isn't mapped by the rusqlite backend", schema, typ.name));
+			quote! {}
+		},
+		// MySQL doesn't have these
+		Domain(_) | SimpleComposite(_) if opt.backend == Backend::Mysql => {
+			warnings.push(format!("`{}.{}` isn't mapped by the mysql backend", schema, typ.name));
+			quote! {}
+		},
+		Enum(e) => enum_to_ast(e, schema, &typ.name, &stripped_name, opt, warnings),
+		Composite(c) if opt.backend == Backend::Diesel => {
+			diesel_models(c, schema, &typ.name, &stripped_name, db, opt, warnings)
+		},
+		Composite(c) => composite_to_ast(c, schema, &typ.name, &stripped_name, opt, warnings),
+		Base(b) => base_to_ast(b, opt, warnings),
+		Domain(d) => domain_to_ast(d, schema, &typ.name, &stripped_name, db, opt, warnings),
 		Other(oid) => {
 			if *oid == 2278 {
 				let name_type = format_heck(&typ.name, opt, CamelCase);
 				quote! { pub type #name_type = (); }
 			} else {
-				if opt.debug {
-					println!("Couldn't convert type: {}, {}", typ.name, oid)
-				};
+				warnings.push(format!("Couldn't convert type: {}, {}", typ.name, oid));
 				quote! {}
 			}
 		},
-		SimpleComposite(c) => simple_composite_to_ast(c, &typ.name, opt),
+		// functions aren't mapped by the diesel backend so neither are the records they return
+		SimpleComposite(_) if opt.backend == Backend::Diesel => quote! {},
+		SimpleComposite(c) => simple_composite_to_ast(c, schema, &typ.name, opt, warnings),
 	}
 }
 
 /// creates the syn node for an enum
-pub fn enum_to_ast(e: &PsqlEnumType, name: &str, opt: &Opt) -> TokenStream {
+///
+/// The labels are in the database's sort order so the derived `Ord` matches it. Display, FromStr, serde, and the postgres
+/// derives all use the label rather than the name of the variant
+pub fn enum_to_ast(
+	e: &PsqlEnumType,
+	schema: &str,
+	sql_name: &str,
+	name: &str,
+	opt: &Opt,
+	warnings: &Warnings,
+) -> TokenStream {
 	let name_type = format_heck(name, opt, CamelCase);
+	let full_name = format!("{}.{}", schema, sql_name);
+	let variants: Vec<_> = e.labels.iter().map(|v| format_heck(v, opt, CamelCase)).collect();
+	let labels = &e.labels;
+	let doc = format!(" The enum `{}`", full_name);
+
+	let unknown = format_ident!("Unknown");
+	if opt.enum_unknown_variant && variants.contains(&unknown) {
+		warnings.push(format!(
+			"Enum `{}` has a label named Unknown so it's generated without a catch-all variant",
+			full_name
+		));
+	}
+	if !opt.enum_unknown_variant || variants.contains(&unknown) {
+		let (derives, type_attr, label_attrs): (_, _, Vec<_>) = match opt.backend {
+			Backend::Postgres => (
+				get_derives(schema, sql_name, &ENUM_DERIVES, opt, warnings),
+				quote! { #[postgres(name = #sql_name)] },
+				labels.iter().map(|v| quote! { #[postgres(name = #v)] }).collect(),
+			),
+			Backend::Sqlx => {
+				let type_name = sqlx_type_name(schema, sql_name);
+				(
+					get_derives(schema, sql_name, &SQLX_ENUM_DERIVES, opt, warnings),
+					quote! { #[sqlx(type_name = #type_name)] },
+					labels.iter().map(|v| quote! { #[sqlx(rename = #v)] }).collect(),
+				)
+			},
+			Backend::Diesel => {
+				let sql_type = diesel_schema::enum_sql_type(schema, sql_name, opt);
+				(
+					get_derives(schema, sql_name, &DIESEL_ENUM_DERIVES, opt, warnings),
+					quote! { #[diesel(sql_type = #sql_type)] },
+					labels.iter().map(|_| quote! {}).collect(),
+				)
+			},
+			Backend::Mysql => (
+				get_derives(schema, sql_name, &MYSQL_ENUM_DERIVES, opt, warnings),
+				quote! {},
+				labels.iter().map(|_| quote! {}).collect(),
+			),
+			Backend::Rusqlite => unreachable!("SQLite doesn't have enums"),
+		};
+		let sql_impls = match opt.backend {
+			Backend::Diesel => diesel_enum_impls(&name_type, schema, sql_name, opt),
+			Backend::Mysql => mysql_procs::enum_impls(&name_type),
+			_ => quote! {},
+		};
+		let renames = labels.iter().map(|v| serde_attr(quote! { serde(rename = #v) }, opt));
+		return quote! {
+			#[doc = #doc]
+			#derives
+			#type_attr
+			pub enum #name_type {
+				#(
+					#label_attrs
+					#renames
+					#variants
+				),*
+			}
 
-	//the enum definition itself
-	let enum_body = e.labels.iter().map(|v| format_heck(v, opt, CamelCase));
-	let derives = get_derives();
+			impl #name_type {
+				/// Every value in the database's sort order
+				pub const ALL: &'static [#name_type] = &[#(#name_type::#variants),*];
 
+				/// The label of the value in the database
+				pub fn as_str(&self) -> &'static str {
+					match self {
+						#(#name_type::#variants => #labels),*
+					}
+				}
+			}
+
+			impl std::fmt::Display for #name_type {
+				fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+					f.write_str(self.as_str())
+				}
+			}
+
+			impl std::str::FromStr for #name_type {
+				type Err = crate::orm::UnknownLabel;
+
+				fn from_str(s: &str) -> Result<Self, Self::Err> {
+					match s {
+						#(#labels => Ok(#name_type::#variants),)*
+						_ => Err(crate::orm::UnknownLabel::new(#full_name, s)),
+					}
+				}
+			}
+
+			#sql_impls
+		};
+	}
+
+	let (derives, type_attr) = match opt.backend {
+		Backend::Postgres => (get_derives(schema, sql_name, &UNKNOWN_ENUM_DERIVES, opt, warnings), quote! {}),
+		Backend::Sqlx => (get_derives(schema, sql_name, &SQLX_UNKNOWN_ENUM_DERIVES, opt, warnings), quote! {}),
+		Backend::Diesel => {
+			let sql_type = diesel_schema::enum_sql_type(schema, sql_name, opt);
+			(
+				get_derives(schema, sql_name, &DIESEL_UNKNOWN_ENUM_DERIVES, opt, warnings),
+				quote! { #[diesel(sql_type = #sql_type)] },
+			)
+		},
+		Backend::Mysql => (get_derives(schema, sql_name, &MYSQL_UNKNOWN_ENUM_DERIVES, opt, warnings), quote! {}),
+		Backend::Rusqlite => unreachable!("SQLite doesn't have enums"),
+	};
+	let sql_impls = unknown_enum_sql_impls(&name_type, schema, sql_name, opt);
+	let serde_from = serde_attr(quote! { serde(from = "String", into = "String") }, opt);
 	quote! {
+		#[doc = #doc]
+		///
+		/// Labels added to the database after the mapping was generated are read as `Unknown`, which sorts after every other value
 		#derives
+		#type_attr
+		#serde_from
+		#[non_exhaustive]
 		pub enum #name_type {
-			#(#enum_body),*
+			#(#variants,)*
+			Unknown(String),
+		}
+
+		impl #name_type {
+			/// Every known value in the database's sort order
+			pub const ALL: &'static [#name_type] = &[#(#name_type::#variants),*];
+
+			/// The label of the value in the database
+			pub fn as_str(&self) -> &str {
+				match self {
+					#(#name_type::#variants => #labels,)*
+					#name_type::Unknown(label) => label.as_str(),
+				}
+			}
+		}
+
+		impl From<&str> for #name_type {
+			fn from(s: &str) -> Self {
+				match s {
+					#(#labels => #name_type::#variants,)*
+					_ => #name_type::Unknown(String::from(s)),
+				}
+			}
+		}
+
+		impl From<String> for #name_type {
+			fn from(s: String) -> Self {
+				Self::from(s.as_str())
+			}
+		}
+
+		impl From<#name_type> for String {
+			fn from(value: #name_type) -> Self {
+				match value {
+					#name_type::Unknown(label) => label,
+					value => String::from(value.as_str()),
+				}
+			}
+		}
+
+		impl std::fmt::Display for #name_type {
+			fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+				f.write_str(self.as_str())
+			}
+		}
+
+		impl std::str::FromStr for #name_type {
+			type Err = std::convert::Infallible;
+
+			fn from_str(s: &str) -> Result<Self, Self::Err> {
+				Ok(Self::from(s))
+			}
+		}
+
+		#sql_impls
+	}
+}
+
+/// The conversions of an enum with an `Unknown` variant, which go through its label
+fn unknown_enum_sql_impls(name_type: &Ident, schema: &str, sql_name: &str, opt: &Opt) -> TokenStream {
+	match opt.backend {
+		Backend::Postgres => quote! {
+			impl<'a> FromSql<'a> for #name_type {
+				fn from_sql(_: &crate::orm::enums::Type, raw: &'a [u8]) -> Result<Self, crate::orm::enums::BoxError> {
+					Ok(Self::from(crate::orm::enums::label_from_sql(raw)?))
+				}
+
+				fn accepts(ty: &crate::orm::enums::Type) -> bool {
+					crate::orm::enums::accepts(ty, #sql_name)
+				}
+			}
+
+			impl ToSql for #name_type {
+				fn to_sql(
+					&self,
+					_: &crate::orm::enums::Type,
+					out: &mut crate::orm::enums::BytesMut,
+				) -> Result<crate::orm::enums::IsNull, crate::orm::enums::BoxError> {
+					Ok(crate::orm::enums::label_to_sql(self.as_str(), out))
+				}
+
+				fn accepts(ty: &crate::orm::enums::Type) -> bool {
+					crate::orm::enums::accepts(ty, #sql_name)
+				}
+
+				crate::orm::enums::to_sql_checked!();
+			}
+		},
+		Backend::Sqlx => {
+			let type_name = sqlx_type_name(schema, sql_name);
+			quote! {
+				impl ::sqlx::Type<::sqlx::Postgres> for #name_type {
+					fn type_info() -> ::sqlx::postgres::PgTypeInfo {
+						::sqlx::postgres::PgTypeInfo::with_name(#type_name)
+					}
+				}
+
+				impl ::sqlx::postgres::PgHasArrayType for #name_type {
+					fn array_type_info() -> ::sqlx::postgres::PgTypeInfo {
+						::sqlx::postgres::PgTypeInfo::array_of(#type_name)
+					}
+				}
+
+				impl<'r> ::sqlx::Decode<'r, ::sqlx::Postgres> for #name_type {
+					fn decode(value: ::sqlx::postgres::PgValueRef<'r>) -> Result<Self, ::sqlx::error::BoxDynError> {
+						Ok(Self::from(<&str as ::sqlx::Decode<::sqlx::Postgres>>::decode(value)?))
+					}
+				}
+
+				impl ::sqlx::Encode<'_, ::sqlx::Postgres> for #name_type {
+					fn encode_by_ref(
+						&self,
+						buf: &mut ::sqlx::postgres::PgArgumentBuffer,
+					) -> Result<::sqlx::encode::IsNull, ::sqlx::error::BoxDynError> {
+						<&str as ::sqlx::Encode<::sqlx::Postgres>>::encode(self.as_str(), buf)
+					}
+				}
+			}
+		},
+		Backend::Diesel => diesel_enum_impls(name_type, schema, sql_name, opt),
+		Backend::Mysql => mysql_procs::enum_impls(name_type),
+		Backend::Rusqlite => unreachable!("SQLite doesn't have enums"),
+	}
+}
+
+/// diesel's conversions of an enum, which go through its label
+fn diesel_enum_impls(name_type: &Ident, schema: &str, sql_name: &str, opt: &Opt) -> TokenStream {
+	let sql_type = diesel_schema::enum_sql_type(schema, sql_name, opt);
+	quote! {
+		impl ::diesel::deserialize::FromSql<#sql_type, ::diesel::pg::Pg> for #name_type {
+			fn from_sql(bytes: ::diesel::pg::PgValue<'_>) -> ::diesel::deserialize::Result<Self> {
+				Ok(std::str::from_utf8(bytes.as_bytes())?.parse()?)
+			}
+		}
+
+		impl ::diesel::serialize::ToSql<#sql_type, ::diesel::pg::Pg> for #name_type {
+			fn to_sql<'b>(
+				&'b self,
+				out: &mut ::diesel::serialize::Output<'b, '_, ::diesel::pg::Pg>,
+			) -> ::diesel::serialize::Result {
+				std::io::Write::write_all(out, self.as_str().as_bytes())?;
+				Ok(::diesel::serialize::IsNull::No)
+			}
 		}
 	}
 }
 
 /// creates the syn node for a struct
-pub fn composite_to_ast(c: &PsqlCompositeType, name: &str, opt: &Opt) -> TokenStream {
+pub fn composite_to_ast(
+	c: &PsqlCompositeType,
+	schema: &str,
+	sql_name: &str,
+	name: &str,
+	opt: &Opt,
+	warnings: &Warnings,
+) -> TokenStream {
 	let name_type = format_heck(name, opt, CamelCase);
 
-	let struct_body = c.cols.iter().map(|v| {
-		let field_name = format_heck(&v.name, opt, SnakeCase);
-		let schema_name = format_heck(&v.type_ns_name, opt, SnakeCase);
-		let type_name = format_heck(&v.type_name, opt, CamelCase);
-		let mut field_type = if v.not_null {
-			quote! { super::#schema_name::#type_name }
-		} else {
-			quote! { Option<super::#schema_name::#type_name> }
-		};
-		for _ in 0..v.num_dimentions {
-			field_type = quote! { Vec<#field_type> };
-		}
-		quote! { pub #field_name : #field_type }
-	});
-	let derives = get_derives();
+	let struct_body = c.cols.iter().map(|v| column_to_field(v, opt, warnings));
+	let (derives, type_attr, from_row) = match opt.backend {
+		Backend::Postgres => (get_derives(schema, sql_name, &COMMON_DERIVES, opt, warnings), quote! {}, quote! {}),
+		Backend::Diesel => unreachable!("diesel_models makes the structs of the diesel backend"),
+		Backend::Rusqlite => (
+			get_derives(schema, sql_name, &["Debug", "Clone"], opt, warnings),
+			quote! {},
+			rusqlite_crud::from_row(c, &name_type, opt),
+		),
+		Backend::Mysql => (
+			get_derives(schema, sql_name, &["Debug", "Clone"], opt, warnings),
+			quote! {},
+			mysql_procs::from_row(c, &name_type, opt),
+		),
+		Backend::Sqlx => {
+			let type_name = sqlx_type_name(schema, sql_name);
+			let type_attr = quote! { #[sqlx(type_name = #type_name)] };
+			let fields: Vec<_> = c.cols.iter().map(|v| (format_heck(&v.name, opt, SnakeCase), &v.name)).collect();
+			// the derive of sqlx::Type doesn't allow renaming fields so FromRow is implemented by hand when they're renamed
+			if fields.iter().any(|(field, column)| needs_rename(field, column)) {
+				let (fields, columns): (Vec<_>, Vec<_>) = fields.into_iter().unzip();
+				let from_row = quote! {
+					impl<'r> ::sqlx::FromRow<'r, ::sqlx::postgres::PgRow> for #name_type {
+						fn from_row(row: &'r ::sqlx::postgres::PgRow) -> Result<Self, ::sqlx::Error> {
+							use ::sqlx::Row as _;
+							Ok(#name_type {
+								#(#fields: row.try_get(#columns)?),*
+							})
+						}
+					}
+				};
+				(get_derives(schema, sql_name, &SQLX_TYPE_DERIVES, opt, warnings), type_attr, from_row)
+			} else {
+				(get_derives(schema, sql_name, &SQLX_COMPOSITE_DERIVES, opt, warnings), type_attr, quote! {})
+			}
+		},
+	};
+	let new_struct = if opt.postgres_only(opt.copy) {
+		sql_copy::new_struct(c, schema, sql_name, name, opt, warnings)
+	} else if opt.backend == Backend::Rusqlite {
+		rusqlite_crud::new_struct(c, schema, sql_name, name, opt, warnings)
+	} else {
+		quote! {}
+	};
 
 	quote! {
 		#derives
+		#type_attr
 		pub struct #name_type {
 			#(#struct_body),*
 		}
+		#from_row
+		#new_struct
+	}
+}
+
+/// creates the structs of a table or view for the diesel backend, the struct of its rows and a `New{Table}` to insert
+///
+/// ```ignore
+/// #[derive(Debug, Clone, ::diesel::Queryable, ::diesel::Selectable)]
+/// #[diesel(table_name = crate::schema::things::inventory)]
+/// #[diesel(check_for_backend(::diesel::pg::Pg))]
+/// pub struct Inventory {
+/// 	pub id: super::pg_catalog::Int4,
+/// 	pub item_type: Option<super::things::ItemType>,
+/// }
+/// // columns with a default are an Option so None uses the default
+/// #[derive(Debug, Clone, ::diesel::Insertable)]
+/// #[diesel(table_name = crate::schema::things::inventory)]
+/// pub struct NewInventory {
+/// 	pub id: Option<super::pg_catalog::Int4>,
+/// 	pub item_type: Option<super::things::ItemType>,
+/// }
+/// ```
+pub fn diesel_models(
+	c: &PsqlCompositeType,
+	schema: &str,
+	sql_name: &str,
+	name: &str,
+	db: &FullDB,
+	opt: &Opt,
+	warnings: &Warnings,
+) -> TokenStream {
+	if !c.kind.is_relation() {
+		warnings.push(format!(
+			"Composite type `{}.{}` isn't mapped by the diesel backend",
+			schema, sql_name
+		));
+		return quote! {};
+	}
+	let table = match diesel_schema::table(schema, sql_name, c, db, opt, warnings) {
+		Some(table) => table,
+		None => return quote! {},
+	};
+	let name_type = format_heck(name, opt, CamelCase);
+	let table_path = diesel_schema::table_path(schema, sql_name, opt);
+	let (field_names, field_types): (Vec<_>, Vec<_>) = table
+		.cols
+		.iter()
+		.map(|(col, _)| (format_heck(&col.name, opt, SnakeCase), diesel_schema::field_type(col, opt)))
+		.unzip();
+	let derives = get_derives(schema, sql_name, &DIESEL_TABLE_DERIVES, opt, warnings);
+
+	let new_struct = if c.kind == RelKind::Table {
+		let new_name = sql_copy::new_struct_name(name, opt);
+		let new_types = table.cols.iter().zip(&field_types).map(|((col, _), field_type)| {
+			if col.has_default && col.not_null {
+				quote! { Option<#field_type> }
+			} else {
+				quote! { #field_type }
+			}
+		});
+		let derives = get_derives(schema, sql_name, &DIESEL_INSERT_DERIVES, opt, warnings);
+		quote! {
+			/// A row to insert, columns which have a default are `Option` so `None` uses it
+			#derives
+			#[diesel(table_name = #table_path)]
+			pub struct #new_name {
+				#(pub #field_names : #new_types),*
+			}
+		}
+	} else {
+		quote! {}
+	};
+
+	quote! {
+		#derives
+		#[diesel(table_name = #table_path)]
+		#[diesel(check_for_backend(::diesel::pg::Pg))]
+		pub struct #name_type {
+			#(pub #field_names : #field_types),*
+		}
+		#new_struct
+	}
+}
+
+/// The field for a column in the struct of a table, view, or composite type
+pub fn column_to_field(v: &Column, opt: &Opt, warnings: &Warnings) -> TokenStream {
+	let field_name = format_heck(&v.name, opt, SnakeCase);
+	let schema_name = format_heck(&v.type_ns_name, opt, SnakeCase);
+	let type_name = format_heck(&v.type_name, opt, CamelCase);
+	let mut field_type = if v.not_null {
+		quote! { super::#schema_name::#type_name }
+	} else {
+		quote! { Option<super::#schema_name::#type_name> }
+	};
+	let mut dimensions = v.num_dimentions;
+	if opt.backend == Backend::Sqlx && dimensions > 1 {
+		warnings.push(format!(
+			"Column `{}` has {} dimensions but sqlx only reads one-dimensional arrays, it's mapped as one",
+			v.name, dimensions
+		));
+		dimensions = 1;
+	}
+	for _ in 0..dimensions {
+		field_type = quote! { Vec<#field_type> };
+	}
+	quote! { pub #field_name : #field_type }
+}
+
+/// Whether sqlx's `FromRow` would look the field up by a different name than its column's, it ignores the `r#` of raw
+/// identifiers
+fn needs_rename(field_name: &Ident, sql_name: &str) -> bool {
+	field_name.to_string().trim_start_matches("r#") != sql_name
+}
+
+/// The name sqlx looks a type up by, which is how postgres displays it with the default `search_path`
+fn sqlx_type_name(schema: &str, name: &str) -> String {
+	let quote_ident = |ident: &str| {
+		let is_plain = ident.starts_with(|c: char| c.is_ascii_lowercase() || c == '_')
+			&& ident.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+		if is_plain {
+			String::from(ident)
+		} else {
+			format!("\"{}\"", ident.replace('"', "\"\""))
+		}
+	};
+	if schema == "public" || schema == "pg_catalog" {
+		quote_ident(name)
+	} else {
+		format!("{}.{}", quote_ident(schema), quote_ident(name))
 	}
 }
 
@@ -123,91 +585,410 @@ macro_rules! my_try {
 }
 
 /// creates the syn node for a base type (typedef)
-pub fn base_to_ast(b: &PsqlBaseType, opt: &Opt) -> TokenStream {
+pub fn base_to_ast(b: &PsqlBaseType, opt: &Opt, warnings: &Warnings) -> TokenStream {
 	let third_party = |lib_name: ThirdParty, tokens: TokenStream| -> Option<TokenStream> {
 		if opt.uses_lib(lib_name) {
 			Some(tokens)
 		} else {
-			if opt.debug {
-				println!(
-					"Enable {} dependency to provide mapping for postgres type `{}` with oid : {}",
-					lib_name.to_str(),
-					b.name,
-					b.oid
-				);
-			}
+			warnings.push(format!(
+				"Enable {} dependency to provide mapping for postgres type `{}` with oid : {}",
+				lib_name.to_str(),
+				b.name,
+				b.oid
+			));
+			None
+		}
+	};
+	// chrono is preferred if both are enabled, timestamps fall back to SystemTime if neither is
+	let date_time = |chrono: TokenStream, time: TokenStream, fallback: Option<TokenStream>| -> Option<TokenStream> {
+		if opt.uses_lib(Chrono) {
+			Some(chrono)
+		} else if opt.uses_lib(Time) {
+			Some(time)
+		} else if fallback.is_some() {
+			fallback
+		} else {
+			warnings.push(format!(
+				"Enable {} or {} dependency to provide mapping for postgres type `{}` with oid : {}",
+				Chrono.to_str(),
+				Time.to_str(),
+				b.name,
+				b.oid
+			));
 			None
 		}
 	};
 	let name_type = format_heck(&b.name, opt, CamelCase);
 
-	let oid_type = match b.oid {
-		16 => quote! { std::primitive::bool },
-		17 => quote! { Vec<u8> },
-		18 => quote! { i8 },
-		19 | 25 | 1042 | 1043 => quote! { String },
-		20 => quote! { i64 },
-		21 => quote! { i16 },
-		23 => quote! { i32 },
-		26 => quote! { u32 },
-		114 | 3802 => my_try!(third_party(SerdeJson, quote! { serde_json::Value })),
-		700 => quote! { f32 },
-		701 => quote! { f64 },
-		869 => quote! { std::net::IpAddr },
-		1082 => my_try!(third_party(Chrono, quote! { chrono::NaiveDate })),
-		1083 => my_try!(third_party(Chrono, quote! { chrono::NaiveTime })),
-		1114 => {
-			if opt.uses_lib(Chrono) {
-				quote! { chrono::NaiveDateTime }
-			} else {
-				quote! { std::time::SystemTime }
-			}
-		},
-		1184 => {
-			if opt.uses_lib(Chrono) {
-				quote! { chrono::DateTime<chrono::Utc> }
-			} else {
-				quote! { std::time::SystemTime }
-			}
+	// sqlx has its own types for some of these and reexports the third party crates it supports, the geometric types
+	// need sqlx 0.8.3 which is the version the generated Cargo.toml asks for
+	let sqlx_type = if opt.backend == Backend::Sqlx {
+		match b.oid {
+			26 => Some(quote! { ::sqlx::postgres::types::Oid }),
+			114 | 3802 => Some(my_try!(third_party(SerdeJson, quote! { ::sqlx::types::JsonValue }))),
+			600 => Some(quote! { ::sqlx::postgres::types::PgPoint }),
+			601 => Some(quote! { ::sqlx::postgres::types::PgLSeg }),
+			602 => Some(quote! { ::sqlx::postgres::types::PgPath }),
+			603 => Some(quote! { ::sqlx::postgres::types::PgBox }),
+			604 => Some(quote! { ::sqlx::postgres::types::PgPolygon }),
+			628 => Some(quote! { ::sqlx::postgres::types::PgLine }),
+			650 => Some(quote! { ::sqlx::types::ipnetwork::IpNetwork }),
+			718 => Some(quote! { ::sqlx::postgres::types::PgCircle }),
+			790 => Some(quote! { ::sqlx::postgres::types::PgMoney }),
+			829 => Some(my_try!(third_party(Eui48, quote! { ::sqlx::types::mac_address::MacAddress }))),
+			1082 => Some(my_try!(date_time(
+				quote! { ::sqlx::types::chrono::NaiveDate },
+				quote! { ::sqlx::types::time::Date },
+				None
+			))),
+			1083 => Some(my_try!(date_time(
+				quote! { ::sqlx::types::chrono::NaiveTime },
+				quote! { ::sqlx::types::time::Time },
+				None
+			))),
+			1114 => Some(my_try!(date_time(
+				quote! { ::sqlx::types::chrono::NaiveDateTime },
+				quote! { ::sqlx::types::time::PrimitiveDateTime },
+				None
+			))),
+			1184 => Some(my_try!(date_time(
+				quote! { ::sqlx::types::chrono::DateTime<::sqlx::types::chrono::Utc> },
+				quote! { ::sqlx::types::time::OffsetDateTime },
+				None
+			))),
+			1186 => Some(quote! { ::sqlx::postgres::types::PgInterval }),
+			1560 | 1562 => Some(my_try!(third_party(BitVec, quote! { ::sqlx::types::BitVec }))),
+			1700 => Some(my_try!(third_party(RustDecimal, quote! { ::sqlx::types::Decimal }))),
+			2950 => Some(my_try!(third_party(Uuid, quote! { ::sqlx::types::Uuid }))),
+			_ => None,
+		}
+	} else {
+		None
+	};
+
+	// diesel's sql types only accept some of the rust types of the other backends, types which aren't built in are mapped
+	// like any other extension type
+	let sqlx_type = match diesel_schema::base_type(b.oid, opt) {
+		_ if opt.backend != Backend::Diesel => sqlx_type,
+		Ok((_, rust_type)) => Some(rust_type),
+		Err(_) if !diesel_schema::is_builtin(b.oid) => None,
+		Err(Some(lib_name)) => {
+			warnings.push(format!(
+				"Enable {} dependency to provide mapping for postgres type `{}` with oid : {}",
+				lib_name, b.name, b.oid
+			));
+			return quote! {};
 		},
-		1700 => my_try!(third_party(RustDecimal, quote! { rust_decimal::Decimal })),
-		2278 => quote! { () },
-		2950 => my_try!(third_party(Uuid, quote! { uuid::Uuid })),
-		oid => {
-			if opt.debug {
-				println!("No Rust type for postgres type `{}` with oid : {}", b.name, oid);
-			}
+		Err(None) => {
+			warnings.push(format!(
+				"The diesel backend has no mapping for postgres type `{}` with oid : {}",
+				b.name, b.oid
+			));
 			return quote! {};
 		},
 	};
 
+	// SQLite's and MySQL's types are given oids of postgres types but only some of them, and not always the same rust type
+	let sqlx_type = match opt.backend {
+		Backend::Rusqlite => Some(my_try!(rusqlite_crud::base_type(b, opt, warnings))),
+		Backend::Mysql => Some(my_try!(mysql_procs::base_type(b, opt, warnings))),
+		_ => sqlx_type,
+	};
+
+	// types are given by their full path as the name of the typedef may be the same as the crate (i.e. `pub type uuid = uuid::Uuid`)
+	let oid_type = match sqlx_type {
+		Some(sqlx_type) => sqlx_type,
+		None => match b.oid {
+			16 => quote! { std::primitive::bool },
+			17 => quote! { Vec<u8> },
+			18 => quote! { i8 },
+			19 | 25 | 1042 | 1043 => quote! { String },
+			20 => quote! { i64 },
+			21 => quote! { i16 },
+			23 => quote! { i32 },
+			26 => quote! { u32 },
+			114 | 3802 => my_try!(third_party(SerdeJson, quote! { crate::orm::serde_json::Value })),
+			600 => my_try!(third_party(GeoTypes, quote! { crate::orm::geo_types::Point<f64> })),
+			602 => my_try!(third_party(GeoTypes, quote! { crate::orm::geo_types::LineString<f64> })),
+			603 => my_try!(third_party(GeoTypes, quote! { crate::orm::geo_types::Rect<f64> })),
+			650 => quote! { crate::orm::Cidr },
+			700 => quote! { f32 },
+			701 => quote! { f64 },
+			790 => quote! { crate::orm::PgMoney },
+			829 => my_try!(third_party(Eui48, quote! { crate::orm::eui48::MacAddress })),
+			869 => quote! { std::net::IpAddr },
+			1082 => my_try!(date_time(quote! { crate::orm::chrono::NaiveDate }, quote! { crate::orm::time::Date }, None)),
+			1083 => my_try!(date_time(quote! { crate::orm::chrono::NaiveTime }, quote! { crate::orm::time::Time }, None)),
+			1114 => my_try!(date_time(
+				quote! { crate::orm::chrono::NaiveDateTime },
+				quote! { crate::orm::time::PrimitiveDateTime },
+				Some(quote! { std::time::SystemTime })
+			)),
+			1184 => my_try!(date_time(
+				quote! { crate::orm::chrono::DateTime<crate::orm::chrono::Utc> },
+				quote! { crate::orm::time::OffsetDateTime },
+				Some(quote! { std::time::SystemTime })
+			)),
+			1186 => quote! { crate::orm::PgInterval },
+			1560 | 1562 => my_try!(third_party(BitVec, quote! { crate::orm::bit_vec::BitVec })),
+			1700 => my_try!(third_party(RustDecimal, quote! { crate::orm::rust_decimal::Decimal })),
+			2278 => quote! { () },
+			2950 => my_try!(third_party(Uuid, quote! { crate::orm::uuid::Uuid })),
+			oid => match opt.extension_type(&b.name, b.extension.as_deref()) {
+				Some(rust_type) => match syn::parse_str::<syn::Type>(rust_type) {
+					Ok(rust_type) => rust_type.into_token_stream(),
+					Err(_) => {
+						warnings.push(format!(
+							"`{}` given for postgres type `{}` is not a rust type",
+							rust_type, b.name
+						));
+						return quote! {};
+					},
+				},
+				None => {
+					match &b.extension {
+						Some(extension) => warnings.push(format!(
+							"No Rust type for postgres type `{}` from extension `{}`, provide one with --extension-type",
+							b.name, extension
+						)),
+						None => warnings.push(format!(
+							"No Rust type for postgres type `{}` with oid : {}",
+							b.name, oid
+						)),
+					}
+					return quote! {};
+				},
+			},
+		},
+	};
+
 	quote! { pub type #name_type = #oid_type; }
 }
 
 /// creates the syn node for a domain (newtype)
-pub fn domain_to_ast(b: &PsqlDomain, name: &str, opt: &Opt) -> TokenStream {
+///
+/// CHECK constraints that can be translated are checked by `TryFrom<Base>`, which is also used when deserializing. The rest are
+/// only checked by the database and listed in the doc comment
+pub fn domain_to_ast(
+	d: &PsqlDomain,
+	schema: &str,
+	sql_name: &str,
+	name: &str,
+	db: &FullDB,
+	opt: &Opt,
+	warnings: &Warnings,
+) -> TokenStream {
 	let name_type = format_heck(name, opt, CamelCase);
-	let schema_name = format_heck(&b.base_ns_name, opt, SnakeCase);
-	let type_name = format_heck(&b.base_name, opt, CamelCase);
-	let derives = get_derives();
+	let schema_name = format_heck(&d.base_ns_name, opt, SnakeCase);
+	let type_name = format_heck(&d.base_name, opt, CamelCase);
+	let base_type = quote! { super::#schema_name::#type_name };
+	let base_type_str = format!("super::{}::{}", schema_name, type_name);
+	let diesel_sql_type = if opt.backend == Backend::Diesel {
+		match diesel_schema::sql_type(&d.base_ns_name, &d.base_name, db, opt) {
+			Some(sql_type) => sql_type,
+			None => {
+				warnings.push(format!(
+					"Domain `{}.{}` isn't mapped as diesel has no sql type for `{}.{}`",
+					schema, sql_name, d.base_ns_name, d.base_name
+				));
+				return quote! {};
+			},
+		}
+	} else {
+		quote! {}
+	};
+	let derives = match opt.backend {
+		Backend::Postgres => get_derives(schema, sql_name, &COMMON_DERIVES, opt, warnings),
+		Backend::Sqlx => get_derives(schema, sql_name, &SQLX_DOMAIN_DERIVES, opt, warnings),
+		Backend::Rusqlite => unreachable!("SQLite doesn't have domains"),
+		Backend::Mysql => unreachable!("MySQL doesn't have domains"),
+		Backend::Diesel => {
+			let derives = get_derives(schema, sql_name, &DIESEL_DOMAIN_DERIVES, opt, warnings);
+			quote! {
+				#derives
+				#[diesel(sql_type = #diesel_sql_type)]
+			}
+		},
+	};
+	let serde_try_from = serde_attr(quote! { serde(try_from = #base_type_str) }, opt);
+	let vis = if opt.private_domain_fields { quote! {} } else { quote! { pub } };
+	let full_name = format!("{}.{}", schema, sql_name);
+	let kind = BaseKind::from_oid(d.base_oid);
+
+	let mut doc = vec![format!(" Domain `{}` over `{}.{}`", full_name, d.base_ns_name, d.base_name)];
+	if d.not_null || d.default.is_some() || !d.checks.is_empty() {
+		doc.push(String::new());
+	}
+	if d.not_null {
+		doc.push(String::from(" * NOT NULL, columns of this domain are never `Option`"));
+	}
+	if let Some(default) = &d.default {
+		doc.push(format!(" * DEFAULT `{}`", default));
+	}
+	let mut checks = Vec::new();
+	for check in &d.checks {
+		let expr = domain_check::display_expr(&check.expr);
+		let cond = match kind {
+			Some(kind) => domain_check::check_to_rust(&check.expr, kind, opt),
+			None => Err(String::from("the base type isn't a number or text")),
+		};
+		match cond {
+			Ok(cond) => {
+				doc.push(format!(" * CHECK `{}`: `{}`", check.name, expr));
+				let constraint = &check.name;
+				checks.push(quote! {
+					if !#cond {
+						return Err(crate::orm::DomainError::new(#full_name, #constraint, #expr));
+					}
+				});
+			},
+			Err(reason) => {
+				warnings.push(format!(
+					"Check `{}` of domain `{}` is only checked by the database: {}",
+					check.name, full_name, reason
+				));
+				doc.push(format!(" * CHECK `{}`: `{}` (only checked by the database)", check.name, expr));
+			},
+		}
+	}
+
+	let default = d
+		.default
+		.as_deref()
+		.zip(kind)
+		.and_then(|(default, kind)| domain_check::default_to_rust(default, kind, opt))
+		.map(|default| {
+			// the DEFAULT is checked like any other value, postgres only checks it once it's used
+			let msg = format!("the DEFAULT of domain `{}` fails its CHECK constraints", full_name);
+			quote! {
+				/// The domain's DEFAULT, panics if it fails the domain's checks
+				impl Default for #name_type {
+					fn default() -> Self {
+						<Self as std::convert::TryFrom<#base_type>>::try_from(#default).expect(#msg)
+					}
+				}
+			}
+		});
+
+	let sql_impls = match opt.backend {
+		Backend::Postgres => quote! {},
+		Backend::Sqlx => sqlx_domain_type(&name_type, &base_type, &sqlx_type_name(schema, sql_name)),
+		Backend::Diesel => diesel_domain_impls(&name_type, &base_type, &diesel_sql_type),
+		Backend::Rusqlite => unreachable!("SQLite doesn't have domains"),
+		Backend::Mysql => unreachable!("MySQL doesn't have domains"),
+	};
 
 	quote! {
+		#(#[doc = #doc])*
 		#derives
-		pub struct #name_type(pub super::#schema_name::#type_name);
+		#serde_try_from
+		pub struct #name_type(#vis #base_type);
+
+		impl std::convert::TryFrom<#base_type> for #name_type {
+			type Error = crate::orm::DomainError;
+
+			fn try_from(value: #base_type) -> Result<Self, Self::Error> {
+				#(#checks)*
+				Ok(#name_type(value))
+			}
+		}
+
+		impl std::ops::Deref for #name_type {
+			type Target = #base_type;
+
+			fn deref(&self) -> &Self::Target {
+				&self.0
+			}
+		}
+
+		impl #name_type {
+			/// The value as the domain's base type
+			pub fn into_inner(self) -> #base_type {
+				self.0
+			}
+		}
+
+		#default
+		#sql_impls
+	}
+}
+
+/// `sqlx::Type` for a domain, postgres reports the domain in composite types and arrays but the base type in query results
+fn sqlx_domain_type(name_type: &Ident, base_type: &TokenStream, type_name: &str) -> TokenStream {
+	quote! {
+		impl ::sqlx::Type<::sqlx::Postgres> for #name_type {
+			fn type_info() -> ::sqlx::postgres::PgTypeInfo {
+				::sqlx::postgres::PgTypeInfo::with_name(#type_name)
+			}
+
+			fn compatible(ty: &::sqlx::postgres::PgTypeInfo) -> bool {
+				*ty == Self::type_info() || <#base_type as ::sqlx::Type<::sqlx::Postgres>>::compatible(ty)
+			}
+		}
+
+		impl ::sqlx::postgres::PgHasArrayType for #name_type {
+			fn array_type_info() -> ::sqlx::postgres::PgTypeInfo {
+				::sqlx::postgres::PgTypeInfo::array_of(#type_name)
+			}
+
+			fn array_compatible(ty: &::sqlx::postgres::PgTypeInfo) -> bool {
+				*ty == Self::array_type_info() || <#base_type as ::sqlx::postgres::PgHasArrayType>::array_compatible(ty)
+			}
+		}
+	}
+}
+
+/// diesel's conversions of a domain, which are those of its base type
+fn diesel_domain_impls(name_type: &Ident, base_type: &TokenStream, sql_type: &TokenStream) -> TokenStream {
+	quote! {
+		impl ::diesel::deserialize::FromSql<#sql_type, ::diesel::pg::Pg> for #name_type {
+			fn from_sql(bytes: ::diesel::pg::PgValue<'_>) -> ::diesel::deserialize::Result<Self> {
+				<#base_type as ::diesel::deserialize::FromSql<#sql_type, ::diesel::pg::Pg>>::from_sql(bytes).map(#name_type)
+			}
+		}
+
+		impl ::diesel::serialize::ToSql<#sql_type, ::diesel::pg::Pg> for #name_type {
+			fn to_sql<'b>(
+				&'b self,
+				out: &mut ::diesel::serialize::Output<'b, '_, ::diesel::pg::Pg>,
+			) -> ::diesel::serialize::Result {
+				<#base_type as ::diesel::serialize::ToSql<#sql_type, ::diesel::pg::Pg>>::to_sql(&self.0, out)
+			}
+		}
 	}
 }
 
 /// creates the syn node for a struct for the anon return type of a function
-pub fn simple_composite_to_ast(c: &NamesAndTypes, name: &str, opt: &Opt) -> TokenStream {
+pub fn simple_composite_to_ast(
+	c: &NamesAndTypes,
+	schema: &str,
+	name: &str,
+	opt: &Opt,
+	warnings: &Warnings,
+) -> TokenStream {
 	let struct_name = format_heck(name, opt, CamelCase);
 	let struct_body = c.0.iter().map(|tan| -> TokenStream {
 		let field_name = format_heck(&tan.name, opt, SnakeCase);
 		let type_name = tan.typ.to_tokens(opt);
+		let rename = if opt.backend == Backend::Sqlx && needs_rename(&field_name, &tan.name) {
+			let sql_name = &tan.name;
+			quote! { #[sqlx(rename = #sql_name)] }
+		} else {
+			quote! {}
+		};
 		quote! {
+			#rename
 			pub #field_name : #type_name
 		}
 	});
-	let derives = get_derives();
+	// these aren't types in the database so they can only be read from rows
+	let derives = match opt.backend {
+		Backend::Postgres => get_derives(schema, name, &COMMON_DERIVES, opt, warnings),
+		Backend::Sqlx => get_derives(schema, name, &SQLX_ROW_DERIVES, opt, warnings),
+		Backend::Diesel => unreachable!("the diesel backend doesn't map functions"),
+		Backend::Rusqlite => unreachable!("SQLite doesn't have functions"),
+		Backend::Mysql => unreachable!("MySQL functions return a single value"),
+	};
 
 	quote! {
 		#derives
@@ -217,10 +998,285 @@ pub fn simple_composite_to_ast(c: &NamesAndTypes, name: &str, opt: &Opt) -> Toke
 	}
 }
 
+/// Derived by every generated type besides serde's, the `SQLX_`, `DIESEL_`, and `MYSQL_` ones are used instead with those
+/// backends
+const COMMON_DERIVES: [&str; 5] = ["Debug", "Clone", "TryFromRow", "ToSql", "FromSql"];
+const ENUM_DERIVES: [&str; 11] = [
+	"Debug", "Clone", "TryFromRow", "ToSql", "FromSql", "Copy", "PartialEq", "Eq", "Hash", "PartialOrd", "Ord",
+];
+const UNKNOWN_ENUM_DERIVES: [&str; 8] = ["Debug", "Clone", "TryFromRow", "PartialEq", "Eq", "Hash", "PartialOrd", "Ord"];
+const SQLX_COMPOSITE_DERIVES: [&str; 4] = ["Debug", "Clone", "::sqlx::FromRow", "::sqlx::Type"];
+const SQLX_TYPE_DERIVES: [&str; 3] = ["Debug", "Clone", "::sqlx::Type"];
+const SQLX_ROW_DERIVES: [&str; 3] = ["Debug", "Clone", "::sqlx::FromRow"];
+const SQLX_DOMAIN_DERIVES: [&str; 4] = ["Debug", "Clone", "::sqlx::Encode", "::sqlx::Decode"];
+const SQLX_UNKNOWN_ENUM_DERIVES: [&str; 7] = ["Debug", "Clone", "PartialEq", "Eq", "Hash", "PartialOrd", "Ord"];
+const SQLX_ENUM_DERIVES: [&str; 9] = [
+	"Debug", "Clone", "::sqlx::Type", "Copy", "PartialEq", "Eq", "Hash", "PartialOrd", "Ord",
+];
+const MYSQL_UNKNOWN_ENUM_DERIVES: [&str; 7] = ["Debug", "Clone", "PartialEq", "Eq", "Hash", "PartialOrd", "Ord"];
+const MYSQL_ENUM_DERIVES: [&str; 8] = ["Debug", "Clone", "Copy", "PartialEq", "Eq", "Hash", "PartialOrd", "Ord"];
+const DIESEL_TABLE_DERIVES: [&str; 4] = ["Debug", "Clone", "::diesel::Queryable", "::diesel::Selectable"];
+const DIESEL_INSERT_DERIVES: [&str; 3] = ["Debug", "Clone", "::diesel::Insertable"];
+const DIESEL_DOMAIN_DERIVES: [&str; 4] = ["Debug", "Clone", "::diesel::AsExpression", "::diesel::FromSqlRow"];
+const DIESEL_UNKNOWN_ENUM_DERIVES: [&str; 9] = [
+	"Debug", "Clone", "::diesel::AsExpression", "::diesel::FromSqlRow", "PartialEq", "Eq", "Hash", "PartialOrd", "Ord",
+];
+const DIESEL_ENUM_DERIVES: [&str; 10] = [
+	"Debug", "Clone", "::diesel::AsExpression", "::diesel::FromSqlRow", "Copy", "PartialEq", "Eq", "Hash", "PartialOrd", "Ord",
+];
+
+/// The derives and attributes of a generated type
+///
+/// `derived` are the traits the type needs, the traits and attributes given in the options for the type are added to them
+pub fn get_derives(schema: &str, sql_name: &str, derived: &[&str], opt: &Opt, warnings: &Warnings) -> TokenStream {
+	let serde = serde_attr(quote! { derive(Serialize, Deserialize) }, opt);
+	let derived_paths: Vec<syn::Path> = derived.iter().filter_map(|v| syn::parse_str(v).ok()).collect();
+	let dropped = |what: String| {
+		warnings.push(format!(
+			"{} on `{}.{}` was left out as the {} backend doesn't use serde",
+			what,
+			schema,
+			sql_name,
+			opt.backend.to_str()
+		))
+	};
+
+	// the options are checked to be valid paths and attributes when they're parsed
+	let mut custom: Vec<syn::Path> = Vec::new();
+	for derive in opt.custom_derives(schema, sql_name) {
+		let path: syn::Path = match syn::parse_str(derive) {
+			Ok(v) => v,
+			Err(_) => continue,
+		};
+		// compared by name so `serde::Serialize` is the same as the `Serialize` derived along with `Deserialize`
+		let name = last_segment(&path);
+		if name == "Serialize" || name == "Deserialize" {
+			if opt.backend != Backend::Postgres {
+				dropped(format!("`--derive {}`", derive));
+			}
+		} else if !derived_paths.iter().chain(&custom).any(|v| last_segment(v) == name) {
+			custom.push(path);
+		}
+	}
+	let custom = if custom.is_empty() {
+		quote! {}
+	} else {
+		quote! { #[derive(#(#custom),*)] }
+	};
+	let attributes: Vec<_> = opt
+		.custom_attributes(schema, sql_name)
+		.filter_map(|v| {
+			let attr = syn::Attribute::parse_outer.parse_str(v).ok()?.pop()?;
+			if !attr.path().is_ident("serde") {
+				Some(attr.into_token_stream())
+			} else if opt.backend == Backend::Postgres {
+				Some(serde_attr(attr.meta.into_token_stream(), opt))
+			} else {
+				dropped(format!("`--attribute {}`", v));
+				None
+			}
+		})
+		.collect();
 
-fn get_derives() -> TokenStream {
 	quote! {
-		#[derive(Serialize, Deserialize)]
-		#[derive(Debug, Clone, TryFromRow, ToSql, FromSql)]
+		#serde
+		#[derive(#(#derived_paths),*)]
+		#custom
+		#(#attributes)*
+	}
+}
+
+/// The name of a derive without the path to it
+fn last_segment(path: &syn::Path) -> String {
+	path.segments.last().map(|v| v.ident.to_string()).unwrap_or_default()
+}
+
+/// A serde derive or attribute, behind the `serde` feature if `serde_feature` is set
+///
+/// Only the postgres backend keeps them. The types sqlx and diesel use for intervals, money, ltree, and geometry don't
+/// implement serde's traits, and the crates generated for rusqlite and mysql don't depend on serde
+pub fn serde_attr(meta: TokenStream, opt: &Opt) -> TokenStream {
+	if opt.backend != Backend::Postgres {
+		quote! {}
+	} else if opt.serde_feature {
+		quote! { #[cfg_attr(feature = "serde", #meta)] }
+	} else {
+		quote! { #[#meta] }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The derives of `public.t` with `--derive` and `--attribute` given for every type
+	fn derives(backend: Backend, derives: &str, attributes: &[&str]) -> (String, Vec<String>) {
+		let opt = Opt {
+			backend,
+			derives: vec![derives.parse().unwrap()],
+			attributes: attributes.iter().map(|v| v.parse().unwrap()).collect(),
+			..Opt::default()
+		};
+		let warnings = Warnings::default();
+		let tokens = get_derives("public", "t", &["Debug", "Clone"], &opt, &warnings);
+		(tokens.to_string(), warnings.take())
+	}
+
+	#[test]
+	fn derives_are_deduplicated_by_name() {
+		let (tokens, warnings) = derives(
+			Backend::Postgres,
+			"serde::Serialize, std::fmt::Debug, PartialEq, Deserialize, PartialEq",
+			&["#[serde(rename_all = \"camelCase\")]"],
+		);
+		let expected = quote! {
+			#[derive(Serialize, Deserialize)]
+			#[derive(Debug, Clone)]
+			#[derive(PartialEq)]
+			#[serde(rename_all = "camelCase")]
+		};
+		assert_eq!(tokens, expected.to_string());
+		assert_eq!(warnings, Vec::<String>::new());
+	}
+
+	#[test]
+	fn serde_is_left_out_with_a_warning() {
+		for backend in [Backend::Sqlx, Backend::Diesel, Backend::Rusqlite, Backend::Mysql] {
+			let (tokens, warnings) = derives(
+				backend,
+				"Hash, serde::Serialize",
+				&["#[serde(rename_all = \"camelCase\")]", "#[allow(dead_code)]"],
+			);
+			let expected = quote! {
+				#[derive(Debug, Clone)]
+				#[derive(Hash)]
+				#[allow(dead_code)]
+			};
+			assert_eq!(tokens, expected.to_string());
+			let left_out = |what: &str| {
+				format!(
+					"{} on `public.t` was left out as the {} backend doesn't use serde",
+					what,
+					backend.to_str()
+				)
+			};
+			assert_eq!(
+				warnings,
+				[
+					left_out("`--derive serde::Serialize`"),
+					left_out("`--attribute #[serde(rename_all = \"camelCase\")]`"),
+				]
+			);
+		}
+	}
+
+	#[test]
+	fn domain_default_is_checked() {
+		let domain = PsqlDomain {
+			oid: 0,
+			base_oid: 23,
+			base_name: String::from("int4"),
+			base_ns_name: String::from("pg_catalog"),
+			not_null: false,
+			default: Some(String::from("0")),
+			checks: vec![DomainCheck {
+				name: String::from("positive_check"),
+				expr: String::from("(VALUE > 0)"),
+			}],
+		};
+		let opt = Opt {
+			rust_case: true,
+			..Opt::default()
+		};
+		let warnings = Warnings::default();
+		let db = FullDB::new(Dialect::Postgres);
+		let tokens = domain_to_ast(&domain, "public", "positive", "positive", &db, &opt, &warnings);
+		let file: syn::File = syn::parse2(tokens).unwrap();
+		let default = file
+			.items
+			.iter()
+			.find_map(|v| match v {
+				syn::Item::Impl(v) if v.trait_.as_ref().is_some_and(|v| v.1.is_ident("Default")) => Some(quote! { #v }),
+				_ => None,
+			})
+			.unwrap();
+		let expected = quote! {
+			#[doc = r" The domain's DEFAULT, panics if it fails the domain's checks"]
+			impl Default for Positive {
+				fn default() -> Self {
+					<Self as std::convert::TryFrom<super::pg_catalog::Int4>>::try_from(0)
+						.expect("the DEFAULT of domain `public.positive` fails its CHECK constraints")
+				}
+			}
+		};
+		let expected: syn::Item = syn::parse2(expected).unwrap();
+		assert_eq!(default.to_string(), quote! { #expected }.to_string());
+		assert_eq!(warnings.take(), Vec::<String>::new());
+	}
+
+	/// The alias `base_to_ast` makes for a type named `name` which isn't built into postgres, with `--extension-type`
+	/// given `extension_types`
+	fn extension_alias(
+		name: &str,
+		extension: Option<&str>,
+		backend: Backend,
+		extension_types: &[&str],
+	) -> (String, Vec<String>) {
+		let b = PsqlBaseType {
+			oid: 16500,
+			name: String::from(name),
+			extension: extension.map(String::from),
+		};
+		let opt = Opt {
+			backend,
+			rust_case: true,
+			extension_types: extension_types.iter().map(|v| v.parse().unwrap()).collect(),
+			..Opt::default()
+		};
+		let warnings = Warnings::default();
+		let tokens = base_to_ast(&b, &opt, &warnings);
+		(tokens.to_string(), warnings.take())
+	}
+
+	#[test]
+	fn extension_types_need_their_extension() {
+		let (tokens, warnings) = extension_alias("citext", Some("citext"), Backend::Postgres, &[]);
+		assert_eq!(tokens, quote! { pub type Citext = String; }.to_string());
+		assert_eq!(warnings, Vec::<String>::new());
+
+		let (tokens, warnings) = extension_alias("citext", None, Backend::Postgres, &[]);
+		assert_eq!(tokens, "");
+		assert_eq!(warnings, ["No Rust type for postgres type `citext` with oid : 16500"]);
+
+		let (tokens, warnings) = extension_alias("citext", Some("my_types"), Backend::Postgres, &[]);
+		assert_eq!(tokens, "");
+		assert_eq!(
+			warnings,
+			["No Rust type for postgres type `citext` from extension `my_types`, provide one with --extension-type"]
+		);
+	}
+
+	#[test]
+	fn extension_types_depend_on_the_backend() {
+		let (tokens, _) = extension_alias("hstore", Some("hstore"), Backend::Sqlx, &[]);
+		assert_eq!(
+			tokens,
+			quote! { pub type Hstore = ::sqlx::postgres::types::PgHstore; }.to_string()
+		);
+		let (tokens, _) = extension_alias("vector", Some("vector"), Backend::Sqlx, &[]);
+		assert_eq!(tokens, "");
+		let (tokens, _) = extension_alias("vector", Some("vector"), Backend::Postgres, &[]);
+		assert_eq!(tokens, quote! { pub type Vector = crate::orm::Vector; }.to_string());
+	}
+
+	#[test]
+	fn extension_type_option_takes_precedence() {
+		let extension_types = ["citext=my_crate::Unused", "citext=my_crate::CaseInsensitive"];
+		let expected = quote! { pub type Citext = my_crate::CaseInsensitive; }.to_string();
+		for extension in [Some("citext"), None] {
+			let (tokens, warnings) = extension_alias("citext", extension, Backend::Postgres, &extension_types);
+			assert_eq!(tokens, expected);
+			assert_eq!(warnings, Vec::<String>::new());
+		}
 	}
 }
@@ -0,0 +1,94 @@
+//! Functions for generating the functions of the sqlx backend
+use super::{
+	super::{sql_tree::*, Opt, Tuples, Warnings},
+	format_heck, sql_procs,
+	Case::*,
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Takes the overloads of a SQL procedure and turns each one that's mapped into an async function over a
+/// `sqlx::PgExecutor`
+///
+/// Procedures returning a table, view, or composite type are read with `query_as` and the rest with `query_scalar`
+///
+/// ```ignore
+/// const ADD3_SQL: &str = "SELECT * FROM \"people\".\"add3\"($1)";
+/// pub async fn add3<'e, E: ::sqlx::PgExecutor<'e>>(
+/// 	executor: E,
+/// 	a: &super::pg_catalog::Int4,
+/// // Vec<T> and fetch_all if the procedure returns a set
+/// ) -> Result<Option<super::pg_catalog::Int4>, ::sqlx::Error> {
+/// 	::sqlx::query_scalar::<_, super::pg_catalog::Int4>(ADD3_SQL)
+/// 		.bind(a)
+/// 		.fetch_optional(executor)
+/// 		.await
+/// }
+///
+/// // there's no tuple trick for overloads, each one gets a function with its index after the name
+/// pub async fn overloaded_0<'e, E: ::sqlx::PgExecutor<'e>>(executor: E, a: &super::pg_catalog::Int4) -> /* ... */;
+/// pub async fn overloaded_1<'e, E: ::sqlx::PgExecutor<'e>>(executor: E, a: &super::pg_catalog::Text) -> /* ... */;
+/// ```
+pub fn proc_to_rust(procs: &[SqlProc], db: &FullDB, opt: &Opt, warnings: &Warnings) -> TokenStream {
+	if procs.len() > 1 && opt.use_tuples == Tuples::NoOverloads {
+		warnings.push(format!("Overloaded Proc: '{}' not mapped", procs[0].name));
+	}
+	for proc in procs.iter().filter(|v| v.outputs.schema == "pg_catalog" && v.outputs.name == "record") {
+		warnings.push(format!(
+			"Cannot make wrapper for procedure {} which returns pg_catalog::record",
+			proc.name
+		));
+	}
+
+	sql_procs::mapped_procs(procs, opt)
+		.into_iter()
+		.map(|mapped| {
+			let proc = mapped.proc;
+			let fn_name = &mapped.overload_name;
+			let sql_name = format!("{}_SQL", fn_name.to_string().trim_start_matches("r#"));
+			let sql_name = format_heck(&sql_name, opt, ShoutySnake);
+			let sql = sql_procs::call_string(proc);
+			let doc = format!("Calls `{}.{}`", proc.ns_name, proc.name);
+
+			let names: Vec<_> = proc.inputs.0.iter().map(|tan| format_heck(&tan.name, opt, SnakeCase)).collect();
+			let params = proc.inputs.as_function_params(opt);
+			let ret_type = proc.outputs.to_tokens(opt);
+			let ret = sql_procs::return_type(proc, opt);
+
+			let query = if is_row(&proc.outputs, db) {
+				quote! { ::sqlx::query_as::<_, #ret_type> }
+			} else {
+				quote! { ::sqlx::query_scalar::<_, #ret_type> }
+			};
+			let fetch = if proc.returns_set {
+				quote! { fetch_all }
+			} else {
+				quote! { fetch_optional }
+			};
+
+			quote! {
+				const #sql_name: &str = #sql;
+				#[doc = #doc]
+				pub async fn #fn_name<'e, E: ::sqlx::PgExecutor<'e>>(
+					executor: E,
+					#params
+				) -> Result<#ret, ::sqlx::Error> {
+					#query(#sql_name)
+						#(.bind(#names))*
+						.#fetch(executor)
+						.await
+				}
+			}
+		})
+		.collect()
+}
+
+/// Whether the type is read from a whole row, rather than a single column
+fn is_row(typ: &FullType, db: &FullDB) -> bool {
+	db.schemas
+		.iter()
+		.filter(|v| v.name == typ.schema)
+		.flat_map(|v| &v.types)
+		.find(|v| v.name == typ.name)
+		.is_some_and(|v| matches!(v.typ, PsqlTypType::Composite(_) | PsqlTypType::SimpleComposite(_)))
+}
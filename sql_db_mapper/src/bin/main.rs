@@ -1,11 +1,84 @@
-use sql_db_mapper::Opt;
+use postgres::{Client, NoTls};
+use sql_db_mapper::{
+	connection::{Introspect, MyClient},
+	diff::diff,
+	ephemeral::EphemeralPostgres,
+	migrate::migrate,
+	watch::watch,
+	Command, Error, Opt, Warnings,
+};
+use std::time::Duration;
 use structopt::StructOpt;
 
 fn main() {
 	let opt = Opt::from_args();
 
-	let mut client = opt.get_client();
-	let full_db = client.get_all(opt.no_functions);
+	if let Err(e) = run(&opt) {
+		eprintln!("{}", e);
+		std::process::exit(1);
+	}
+}
+
+fn run(opt: &Opt) -> Result<(), Error> {
+	match &opt.cmd {
+		None => generate(opt, &mut *opt.get_introspector()?)?,
+		Some(Command::Snapshot { file }) => {
+			let mut client = opt.get_introspector()?;
+			let full_db = client.introspect(opt.no_functions)?;
+			print_warnings(opt, &client.take_warnings());
+
+			full_db.save_snapshot(file)?;
+		},
+		Some(Command::Diff { old, new }) => {
+			let warnings = Warnings::default();
+			let old = old.load_with_warnings(opt.no_functions, &warnings)?;
+			let new = new.load_with_warnings(opt.no_functions, &warnings)?;
+			print_warnings(opt, &warnings.take());
+
+			print!("{}", diff(&old, &new, opt.use_tuples));
+		},
+		Some(Command::Migrate { dir, ephemeral }) => {
+			// the server is stopped when this is dropped, after the mapping is generated
+			let server = if *ephemeral { Some(EphemeralPostgres::start()?) } else { None };
+			let conn = match &server {
+				Some(server) => server.conn(),
+				None => opt.conn.clone().ok_or(Error::NoSource)?,
+			};
+			let mut client = Client::connect(&conn, NoTls)?;
+			let applied = migrate(&mut client, dir)?;
+			if applied.is_empty() {
+				eprintln!("no migrations to apply");
+			}
+			for migration in &applied {
+				eprintln!("applied {}", migration.name);
+			}
+
+			generate(opt, &mut MyClient::new(client)?)?;
+		},
+		Some(Command::Watch {
+			install_trigger,
+			debounce,
+		}) => watch(opt, *install_trigger, Duration::from_millis(*debounce))?,
+	}
+	Ok(())
+}
+
+/// Read the database and write its mapping to the output
+fn generate(opt: &Opt, client: &mut dyn Introspect) -> Result<(), Error> {
+	let full_db = client.introspect(opt.no_functions)?;
+	let warnings = Warnings::default();
+	for warning in client.take_warnings() {
+		warnings.push(warning);
+	}
+
+	full_db.make_output(opt, warnings)
+}
 
-	full_db.make_output(&opt);
+/// Anything that couldn't be read is only shown in debug mode, the same as with generating a mapping
+fn print_warnings(opt: &Opt, warnings: &[String]) {
+	if opt.debug {
+		for warning in warnings {
+			eprintln!("{}", warning);
+		}
+	}
 }
@@ -0,0 +1,300 @@
+//! Combines the generated Cargo.toml with a template from the user or the Cargo.toml already in the output directory
+//!
+//! Both are merged the same way: keys are set over the other file's, tables are merged key by key, and dependencies keep
+//! the keys only one side sets with their features combined. A dependency with `workspace = true` loses its `version` and
+//! other sources as those come from the workspace
+use super::Error;
+use std::path::Path;
+use toml_edit::{DocumentMut, InlineTable, Item, TableLike, TomlError, Value};
+
+/// The tables whose entries are dependencies
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// The keys of a dependency saying where it comes from, which can't be used along with `workspace = true`
+const SOURCE_KEYS: [&str; 7] = ["version", "path", "git", "branch", "tag", "rev", "registry"];
+
+/// Set the contents of the template file at `template_path` over the generated Cargo.toml, the name given with
+/// `--package-name` takes precedence over the template's
+///
+/// Returns [`Error::CargoToml`] if the template isn't valid TOML
+pub(crate) fn apply_template(
+	generated: &str,
+	template_path: &Path,
+	package_name: Option<&str>,
+) -> Result<String, Error> {
+	let in_file = |e: Error| Error::InFile(template_path.to_path_buf(), Box::new(e));
+	let template = std::fs::read_to_string(template_path).map_err(|e| in_file(Error::Io(e)))?;
+	let template: DocumentMut = template
+		.parse()
+		.map_err(|e: TomlError| in_file(Error::CargoToml(e.to_string())))?;
+	let mut cargo_toml: DocumentMut = generated
+		.parse()
+		.map_err(|e: TomlError| Error::CargoToml(e.to_string()))?;
+
+	merge(cargo_toml.as_table_mut(), template.as_table());
+	if let Some(name) = package_name {
+		cargo_toml["package"]["name"] = toml_edit::value(name);
+	}
+	Ok(cargo_toml.to_string())
+}
+
+/// Set the generated dependencies, features, and other tables in an existing Cargo.toml. Its `[package]` is kept if it
+/// has one and everything the generated Cargo.toml doesn't set is left as it was, including comments and formatting
+pub(crate) fn merge_into_existing(existing: &str, generated: &str) -> Result<String, TomlError> {
+	let mut cargo_toml: DocumentMut = existing.parse()?;
+	let mut generated: DocumentMut = generated.parse()?;
+	if cargo_toml.contains_key("package") {
+		generated.remove("package");
+	}
+	merge(cargo_toml.as_table_mut(), generated.as_table());
+	Ok(cargo_toml.to_string())
+}
+
+/// Set each key of `over` in `base`, merging the tables both have rather than replacing them
+fn merge(base: &mut dyn TableLike, over: &dyn TableLike) {
+	for (key, item) in over.iter() {
+		let base_item = match base.get_mut(key) {
+			Some(v) => v,
+			None => {
+				insert_formatted(base, over, key, item);
+				continue;
+			},
+		};
+		match (base_item.as_table_like_mut(), item.as_table_like()) {
+			(Some(base_table), Some(table)) if DEPENDENCY_TABLES.contains(&key) => {
+				merge_dependencies(base_table, table)
+			},
+			(Some(base_table), Some(table)) => merge(base_table, table),
+			_ => *base_item = item.clone(),
+		}
+	}
+}
+
+fn merge_dependencies(base: &mut dyn TableLike, over: &dyn TableLike) {
+	for (name, dependency) in over.iter() {
+		match base.get_mut(name) {
+			Some(base_dependency) => merge_dependency(base_dependency, dependency),
+			None => insert_formatted(base, over, name, dependency),
+		}
+	}
+}
+
+/// Insert a key of `over` into `base` along with the comments before it
+fn insert_formatted(base: &mut dyn TableLike, over: &dyn TableLike, key: &str, item: &Item) {
+	match over.key(key) {
+		Some(key) => {
+			base.entry_format(key).or_insert(item.clone());
+		},
+		None => {
+			base.insert(key, item.clone());
+		},
+	}
+}
+
+/// Merge a dependency given as either a version or a table into another
+fn merge_dependency(base: &mut Item, over: &Item) {
+	if let Some(version) = base.as_value().filter(|v| v.is_str()).cloned() {
+		if !over.is_table_like() {
+			*base = over.clone();
+			return;
+		}
+		let mut table = InlineTable::new();
+		table.insert("version", version);
+		*base = Item::Value(Value::InlineTable(table));
+	}
+	let base_table = match base.as_table_like_mut() {
+		Some(v) => v,
+		None => {
+			*base = over.clone();
+			return;
+		},
+	};
+
+	match over.as_table_like() {
+		Some(over) => {
+			for (key, item) in over.iter() {
+				match (
+					key,
+					base_table.get_mut(key).and_then(Item::as_array_mut),
+					item.as_array(),
+				) {
+					("features", Some(base_features), Some(features)) => {
+						for feature in features {
+							if !base_features.iter().any(|v| v.as_str() == feature.as_str()) {
+								base_features.push(feature.clone());
+							}
+						}
+						base_features.fmt();
+					},
+					_ => {
+						base_table.insert(key, item.clone());
+					},
+				}
+			}
+		},
+		None => {
+			base_table.insert("version", over.clone());
+		},
+	}
+	remove_workspace_sources(base_table);
+	// the spacing around the values taken from either side is made consistent
+	base_table.fmt();
+}
+
+/// Remove the version and other sources of a dependency inherited from the workspace
+fn remove_workspace_sources(dependency: &mut dyn TableLike) {
+	if dependency.get("workspace").and_then(Item::as_bool) == Some(true) {
+		for key in SOURCE_KEYS {
+			dependency.remove(key);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn merged(existing: &str, generated: &str) -> String {
+		merge_into_existing(existing, generated).unwrap()
+	}
+
+	#[test]
+	fn version_string_becomes_inline_table() {
+		assert_eq!(
+			merged(
+				"[dependencies]\nserde = \"1.0.100\"\n",
+				"[dependencies]\nserde = { features = [\"derive\"] }\n"
+			),
+			"[dependencies]\nserde = { version = \"1.0.100\", features = [\"derive\"] }\n"
+		);
+		// a version over a version replaces it
+		assert_eq!(
+			merged("[dependencies]\nserde = \"1.0.100\"\n", "[dependencies]\nserde = \"1.0\"\n"),
+			"[dependencies]\nserde = \"1.0\"\n"
+		);
+		// as does a version over a table
+		assert_eq!(
+			merged(
+				"[dependencies]\nserde = { version = \"1.0.100\", features = [\"rc\"] }\n",
+				"[dependencies]\nserde = \"1.0\"\n"
+			),
+			"[dependencies]\nserde = { version = \"1.0\", features = [\"rc\"] }\n"
+		);
+	}
+
+	#[test]
+	fn features_are_unioned() {
+		assert_eq!(
+			merged(
+				"[dependencies]\nuuid = { version = \"1\", features = [\"v4\", \"serde\"], optional = true }\n",
+				"[dependencies]\nuuid = { version = \"1.0\", features = [\"serde\", \"v7\"] }\n"
+			),
+			"[dependencies]\nuuid = { version = \"1.0\", features = [\"v4\", \"serde\", \"v7\"], optional = true }\n"
+		);
+		// features of the package itself are keys set over the existing ones rather than unioned
+		assert_eq!(
+			merged(
+				"[features]\ndefault = [\"a\"]\nmine = []\n",
+				"[features]\ndefault = [\"b\"]\n"
+			),
+			"[features]\ndefault = [\"b\"]\nmine = []\n"
+		);
+	}
+
+	#[test]
+	fn workspace_dependencies_lose_their_sources() {
+		let sources = "version = \"1\", path = \"../core\", git = \"https://example.com/core\", registry = \"mine\"";
+		assert_eq!(
+			merged(
+				"[dependencies]\ncore = { workspace = true }\n",
+				&format!("[dependencies]\ncore = {{ {}, features = [\"a\"] }}\n", sources)
+			),
+			"[dependencies]\ncore = { workspace = true, features = [\"a\"] }\n"
+		);
+		assert_eq!(
+			merged(
+				&format!("[build-dependencies]\ncore = {{ {} }}\n", sources),
+				"[build-dependencies]\ncore = { workspace = true }\n"
+			),
+			"[build-dependencies]\ncore = { workspace = true }\n"
+		);
+	}
+
+	#[test]
+	fn existing_package_is_kept() {
+		let generated = "[package]\nname = \"generated\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1.0\"\n";
+		assert_eq!(
+			merged("[package]\nname = \"mine\"\nversion = \"2.0.0\"\n", generated),
+			"[package]\nname = \"mine\"\nversion = \"2.0.0\"\n\n[dependencies]\nserde = \"1.0\"\n"
+		);
+		assert_eq!(merged("", generated), generated);
+	}
+
+	#[test]
+	fn comments_are_kept() {
+		let existing = "# my crate
+[package]
+name = \"mine\" # not the generated name
+
+[dependencies]
+# pinned for a bug
+serde = { version = \"=1.0.100\", features = [\"rc\"] } # see the issue
+";
+		let generated = "[dependencies]
+serde = { version = \"1.0\", features = [\"derive\"] }
+# only for the async functions
+async-trait = { version = \"0.1\", optional = true }
+";
+		assert_eq!(
+			merged(existing, generated),
+			"# my crate
+[package]
+name = \"mine\" # not the generated name
+
+[dependencies]
+# pinned for a bug
+serde = { version = \"1.0\", features = [\"rc\", \"derive\"] } # see the issue
+# only for the async functions
+async-trait = { version = \"0.1\", optional = true }
+"
+		);
+	}
+
+	#[test]
+	fn template_is_set_over_the_generated_cargo_toml() {
+		let path = std::env::temp_dir().join(format!("sql_db_mapper-template-{}.toml", std::process::id()));
+		let template = "[package]
+name = \"template\"
+edition = \"2021\"
+
+[dependencies]
+serde = { workspace = true }
+";
+		std::fs::write(&path, template).unwrap();
+		let generated = "[package]
+name = \"generated\"
+edition = \"2018\"
+
+[dependencies]
+serde = { version = \"1.0\", features = [\"derive\"] }
+";
+		let with_template = apply_template(generated, &path, None).unwrap();
+		let with_name = apply_template(generated, &path, Some("named")).unwrap();
+		std::fs::write(&path, "[package").unwrap();
+		let invalid = apply_template(generated, &path, None);
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(
+			with_template,
+			"[package]
+name = \"template\"
+edition = \"2021\"
+
+[dependencies]
+serde = { features = [\"derive\"], workspace = true }
+"
+		);
+		assert!(with_name.starts_with("[package]\nname = \"named\"\n"), "{}", with_name);
+		assert!(matches!(invalid, Err(Error::InFile(v, e)) if v == path && matches!(*e, Error::CargoToml(_))));
+	}
+}
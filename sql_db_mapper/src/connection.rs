@@ -1,7 +1,53 @@
-use super::{pg_select_types::*, sql_tree::*};
+#[cfg(feature = "mysql")]
+use super::mysql::MysqlClient;
+#[cfg(feature = "sqlite")]
+use super::sqlite::SqliteClient;
+use super::{ddl::DdlReader, pg_select_types::*, sql_tree::*, Error};
 use postgres::{Client, Statement};
 use sql_db_mapper_core::*;
 
+/// Something that reads the structure of a database, implemented for PostgreSQL, SQLite, MySQL, and SQL files
+pub trait Introspect {
+	/// Read the whole database, stored functions are skipped if `no_functions` is set
+	fn introspect(&mut self, no_functions: bool) -> Result<FullDB, Error>;
+
+	/// Anything that couldn't be read by the last `introspect`, only SQL files leave anything out
+	fn take_warnings(&mut self) -> Vec<String> {
+		Vec::new()
+	}
+}
+
+impl Introspect for MyClient {
+	fn introspect(&mut self, no_functions: bool) -> Result<FullDB, Error> {
+		self.get_all(no_functions)
+	}
+}
+
+#[cfg(feature = "sqlite")]
+impl Introspect for SqliteClient {
+	/// SQLite doesn't have stored functions so `no_functions` makes no difference
+	fn introspect(&mut self, _no_functions: bool) -> Result<FullDB, Error> {
+		Ok(self.get_all()?)
+	}
+}
+
+#[cfg(feature = "mysql")]
+impl Introspect for MysqlClient {
+	fn introspect(&mut self, no_functions: bool) -> Result<FullDB, Error> {
+		Ok(self.get_all(no_functions)?)
+	}
+}
+
+impl Introspect for DdlReader {
+	fn introspect(&mut self, no_functions: bool) -> Result<FullDB, Error> {
+		self.get_all(no_functions)
+	}
+
+	fn take_warnings(&mut self) -> Vec<String> {
+		std::mem::take(&mut self.warnings)
+	}
+}
+
 
 const RUST_KEYWORDS: [&str; 58] = [
 	"as",
@@ -64,38 +110,69 @@ const RUST_KEYWORDS: [&str; 58] = [
 	"yield",
 ];
 
+/// The names arguments are mapped with, from `proargnames` which is empty when none of them are named. Unnamed arguments and
+/// those named after rust keywords are called `input_{i}`
+pub(crate) fn arg_names_or_default(arg_names: Vec<String>, num_args: usize) -> Vec<String> {
+	let arg_names = if num_args != arg_names.len() {
+		let mut tmp: Vec<String> = Vec::new();
+		for i in 0..num_args {
+			tmp.push(format!("input_{}", i));
+		}
+		tmp
+	} else {
+		arg_names
+	};
+	arg_names
+		.into_iter()
+		.enumerate()
+		.map(|(i, v)| {
+			if v.is_empty() || RUST_KEYWORDS.iter().any(|&keyword| keyword == v) {
+				format!("input_{}", i)
+			} else {
+				v
+			}
+		})
+		.collect()
+}
+
 pub struct MyClient {
 	client: Client,
 	schemas_stmt: Statement,
 	types_stmt: Statement,
 	enum_stmt: Statement,
 	columns_stmt: Statement,
+	primary_key_stmt: Statement,
+	foreign_keys_stmt: Statement,
 	domain_base_stmt: Statement,
+	domain_checks_stmt: Statement,
 	proc_names_stmt: Statement,
 	procs_stmt: Statement,
 	type_name_stmt: Statement,
 }
 //
 impl MyClient {
-	pub fn new(mut client: Client) -> MyClient {
-		MyClient {
-			schemas_stmt: client.prepare(GET_SCHEMAS).unwrap(),
-			types_stmt: client.prepare(GET_TYPES).unwrap(),
-			enum_stmt: client.prepare(GET_ENUM).unwrap(),
-			columns_stmt: client.prepare(GET_COLUMNS).unwrap(),
-			domain_base_stmt: client.prepare(GET_DOMAIN_BASE).unwrap(),
-			proc_names_stmt: client.prepare(GET_PROC_NAMES).unwrap(),
-			procs_stmt: client.prepare(GET_PROCS).unwrap(),
-			type_name_stmt: client.prepare(GET_TYPE_NAME).unwrap(),
+	pub fn new(mut client: Client) -> Result<MyClient, SqlError> {
+		Ok(MyClient {
+			schemas_stmt: client.prepare(GET_SCHEMAS)?,
+			types_stmt: client.prepare(GET_TYPES)?,
+			enum_stmt: client.prepare(GET_ENUM)?,
+			columns_stmt: client.prepare(GET_COLUMNS)?,
+			primary_key_stmt: client.prepare(GET_PRIMARY_KEY)?,
+			foreign_keys_stmt: client.prepare(GET_FOREIGN_KEYS)?,
+			domain_base_stmt: client.prepare(GET_DOMAIN_BASE)?,
+			domain_checks_stmt: client.prepare(GET_DOMAIN_CHECKS)?,
+			proc_names_stmt: client.prepare(GET_PROC_NAMES)?,
+			procs_stmt: client.prepare(GET_PROCS)?,
+			type_name_stmt: client.prepare(GET_TYPE_NAME)?,
 			client,
-		}
+		})
 	}
 
-	pub fn get_all(&mut self, no_functions: bool) -> FullDB {
-		let mut full_db = FullDB { schemas: Vec::new() };
+	pub fn get_all(&mut self, no_functions: bool) -> Result<FullDB, Error> {
+		let mut full_db = FullDB::new(Dialect::Postgres);
 
 		// gets all the schemas in the current db
-		let schemas = self.get_schemas().unwrap();
+		let schemas = self.get_schemas()?;
 		let schemas: Vec<_> = schemas
 			.into_iter()
 			.map(|v| Schema {
@@ -109,11 +186,11 @@ impl MyClient {
 
 		for mut schema in schemas {
 			//get all types and tables
-			let types = self.get_psql_types(schema.id);
+			let types = self.get_psql_types(schema.id)?;
 			schema.append_types(types);
 			//get all stored procedures/functions (if required)
 			if !no_functions {
-				let (procs, types2) = self.get_procedures(schema.id);
+				let (procs, types2) = self.get_procedures(schema.id)?;
 				schema.append_procs(procs);
 				schema.append_types(types2);
 			}
@@ -121,35 +198,43 @@ impl MyClient {
 			//add everything to the schema object
 			full_db.add_schema(schema);
 		}
-		full_db
-	}
-
-	pub fn get_procedures(&mut self, schema_id: SchemaId) -> (Vec<Vec<SqlProc>>, Vec<PsqlType>) {
-		let names = self.get_proc_names(schema_id).unwrap();
-
-		let (procs, types): (Vec<_>, Vec<_>) = names
-			.into_iter()
-			.map(|v| self.get_procs_by_name(v.name, schema_id))
-			.unzip();
-
-		(procs, types.concat())
+		Ok(full_db)
 	}
 
-	fn get_procs_by_name(&mut self, proc_name: String, schema_id: SchemaId) -> (Vec<SqlProc>, Vec<PsqlType>) {
-		let full_procs = self.get_procs(schema_id, proc_name).unwrap();
+	pub fn get_procedures(&mut self, schema_id: SchemaId) -> Result<(Vec<Vec<SqlProc>>, Vec<PsqlType>), Error> {
+		let names = self.get_proc_names(schema_id)?;
 
 		let mut procs = Vec::new();
 		let mut types = Vec::new();
-
-		for (p, t) in full_procs.into_iter().map(|v| self.get_proc_by_id(v)) {
+		for name in names {
+			let (p, t) = self.get_procs_by_name(name.name, schema_id)?;
 			procs.push(p);
 			types.extend(t);
 		}
 
-		(procs, types)
+		Ok((procs, types))
 	}
 
-	fn get_proc_by_id(&mut self, v: GetProcs) -> (SqlProc, Option<PsqlType>) {
+	fn get_procs_by_name(
+		&mut self,
+		proc_name: String,
+		schema_id: SchemaId,
+	) -> Result<(Vec<SqlProc>, Vec<PsqlType>), Error> {
+		let full_procs = self.get_procs(schema_id, proc_name)?;
+
+		let mut overloads = Vec::new();
+		for v in full_procs {
+			overloads.push(self.get_proc_by_id(v)?);
+		}
+		// ordered the same as `sort_overloads` along with the types of the tables they return
+		overloads.sort_by(|a, b| a.0.input_types().cmp(&b.0.input_types()));
+		let (procs, types): (Vec<_>, Vec<_>) = overloads.into_iter().unzip();
+		let types = types.into_iter().flatten().collect();
+
+		Ok((procs, types))
+	}
+
+	fn get_proc_by_id(&mut self, v: GetProcs) -> Result<(SqlProc, Option<PsqlType>), Error> {
 		let (all_arg_types, arg_modes): (Vec<u32>, Vec<i8>) = if let Some(all_arg_types) = v.all_arg_types {
 			if let Some(arg_modes) = v.arg_modes {
 				(all_arg_types, arg_modes)
@@ -163,16 +248,12 @@ impl MyClient {
 			let len = inputs.len();
 			(inputs, vec![b'i' as i8; len])
 		};
-		let arg_names = v.arg_names;
-		let arg_names = match arg_names {
-			Some(a_n) => a_n,
-			None => Vec::new(),
-		};
-		let (inputs, outputs) = self.get_proc_output_type(&all_arg_types, &arg_modes, arg_names);
+		let arg_names = v.arg_names.unwrap_or_default();
+		let (inputs, outputs) = self.get_proc_output_type(&all_arg_types, &arg_modes, arg_names)?;
 
 		let new_outputs = if outputs.0.is_empty() {
 			let ret_type_id = v.ret_type_id;
-			let type_name = self.get_type_name(ret_type_id).unwrap().unwrap();
+			let type_name = self.get_type_name(ret_type_id)?.ok_or(Error::MissingType(ret_type_id))?;
 
 			FullType {
 				schema: type_name.ns_name,
@@ -195,7 +276,7 @@ impl MyClient {
 			})
 		};
 
-		(
+		Ok((
 			SqlProc {
 				ns: v.ns_oid,
 				ns_name: v.ns_name,
@@ -207,7 +288,7 @@ impl MyClient {
 				outputs: new_outputs,
 			},
 			anon_ret_type,
-		)
+		))
 	}
 
 	fn get_proc_output_type(
@@ -215,28 +296,9 @@ impl MyClient {
 		all_arg_types: &[u32],
 		arg_modes: &[i8],
 		arg_names: Vec<String>,
-	) -> (NamesAndTypes, NamesAndTypes) {
+	) -> Result<(NamesAndTypes, NamesAndTypes), Error> {
 		assert_eq!(all_arg_types.len(), arg_modes.len());
-		let arg_names = if all_arg_types.len() != arg_names.len() {
-			let mut tmp: Vec<String> = Vec::new();
-			for i in 0..all_arg_types.len() {
-				tmp.push(format!("input_{}", i));
-			}
-			tmp
-		} else {
-			arg_names
-		};
-		let arg_names: Vec<_> = arg_names
-			.into_iter()
-			.enumerate()
-			.map(|(i, v)| {
-				if v.is_empty() || RUST_KEYWORDS.iter().any(|&keyword| keyword == v) {
-					format!("input_{}", i)
-				} else {
-					v
-				}
-			})
-			.collect();
+		let arg_names = arg_names_or_default(arg_names, all_arg_types.len());
 		let mut inputs: Vec<TypeAndName> = Vec::new();
 		let mut outputs: Vec<TypeAndName> = Vec::new();
 
@@ -245,7 +307,7 @@ impl MyClient {
 			let typ_mode = arg_modes[i];
 			let arg_name = arg_names[i].clone();
 
-			let type_name = self.get_type_name(typ_oid).unwrap().unwrap();
+			let type_name = self.get_type_name(typ_oid)?.ok_or(Error::MissingType(typ_oid))?;
 
 			match typ_mode as u8 {
 				b'i' => inputs.push(TypeAndName {
@@ -265,18 +327,18 @@ impl MyClient {
 				_ => (), //panic!("Only input params and table outputs supported")
 			}
 		}
-		(NamesAndTypes(inputs), NamesAndTypes(outputs))
+		Ok((NamesAndTypes(inputs), NamesAndTypes(outputs)))
 	}
 
-	pub fn get_psql_types(&mut self, schema_id: SchemaId) -> Vec<PsqlType> {
+	pub fn get_psql_types(&mut self, schema_id: SchemaId) -> Result<Vec<PsqlType>, Error> {
 		let ns_oid = schema_id;
 		// let stmt = self.prepare_cached(GET_TYPES);
-		let types = self.get_types(ns_oid).unwrap();
+		let types = self.get_types(ns_oid)?;
 
 		types
 			.into_iter()
 			.map(|v| {
-				PsqlType {
+				Ok(PsqlType {
 					name: v.name.clone(),
 					ns: schema_id,
 					// len : v.len,
@@ -286,17 +348,21 @@ impl MyClient {
 						match v.typ as u8 {
 							b'e' => Enum(PsqlEnumType {
 								oid: v.oid,
-								labels: self.get_enum_labels(v.oid),
+								labels: self.get_enum_labels(v.oid)?,
 							}),
 							b'c' => Composite(PsqlCompositeType {
 								oid: v.oid,
-								cols: self.get_psql_columns(v.rel_id),
+								kind: RelKind::from_relkind(v.rel_kind),
+								cols: self.get_psql_columns(v.rel_id)?,
+								primary_key: self.get_primary_key(v.rel_id)?.into_iter().map(|v| v.name).collect(),
+								foreign_keys: self.get_psql_foreign_keys(v.rel_id)?,
 							}),
 							b'b' => Base(PsqlBaseType {
 								oid: v.oid,
 								name: v.name,
+								extension: v.extension,
 							}),
-							b'd' => Domain(self.get_psql_domain(v.oid)),
+							b'd' => Domain(self.get_psql_domain(v.oid)?),
 							_ => {
 								// println!("typ:{}, name:{}, oid:{}", tmp as u8 as char, v.get::<_, String>(1), v.get::<_, u32>(0));
 								Other(v.oid)
@@ -305,25 +371,36 @@ impl MyClient {
 					},
 					// relid : v.rel_id,
 					// align : v.align,
-				}
+				})
 			})
 			.collect()
 	}
 
-	fn get_psql_domain(&mut self, oid: u32) -> PsqlDomain {
-		let domain_base = self.get_domain_base(oid).unwrap().unwrap();
+	fn get_psql_domain(&mut self, oid: u32) -> Result<PsqlDomain, Error> {
+		let domain_base = self.get_domain_base(oid)?.ok_or(Error::MissingType(oid))?;
+		let checks = self
+			.get_domain_checks(oid)?
+			.into_iter()
+			.map(|v| DomainCheck {
+				name: v.name,
+				expr: v.expr,
+			})
+			.collect();
 
-		PsqlDomain {
+		Ok(PsqlDomain {
 			oid,
 			base_oid: domain_base.oid,
 			base_ns_name: domain_base.ns_name,
 			base_name: domain_base.typ_name,
-		}
+			not_null: domain_base.not_null,
+			default: domain_base.default,
+			checks,
+		})
 	}
 
-	pub fn get_psql_columns(&mut self, rel_id: u32) -> Vec<Column> {
-		self.get_columns(rel_id)
-			.unwrap()
+	pub fn get_psql_columns(&mut self, rel_id: u32) -> Result<Vec<Column>, SqlError> {
+		Ok(self
+			.get_columns(rel_id)?
 			.into_iter()
 			.map(|v| Column {
 				pos: v.attnum,
@@ -333,12 +410,27 @@ impl MyClient {
 				type_ns_name: v.nspname,
 				not_null: v.not_null,
 				num_dimentions: v.num_dimentions,
+				has_default: v.has_default,
 			})
-			.collect()
+			.collect())
 	}
 
-	fn get_enum_labels(&mut self, type_id: u32) -> Vec<String> {
-		self.get_enum(type_id).unwrap().into_iter().map(|v| v.label).collect()
+	fn get_psql_foreign_keys(&mut self, rel_id: u32) -> Result<Vec<ForeignKey>, SqlError> {
+		Ok(self
+			.get_foreign_keys(rel_id)?
+			.into_iter()
+			.map(|v| ForeignKey {
+				name: v.name,
+				cols: v.cols,
+				ref_schema: v.ref_schema,
+				ref_table: v.ref_table,
+				ref_cols: v.ref_cols,
+			})
+			.collect())
+	}
+
+	fn get_enum_labels(&mut self, type_id: u32) -> Result<Vec<String>, SqlError> {
+		Ok(self.get_enum(type_id)?.into_iter().map(|v| v.label).collect())
 	}
 }
 /// Wrappers on SQL select statements
@@ -375,6 +467,22 @@ impl MyClient {
 			.collect()
 	}
 
+	fn get_primary_key(&mut self, class_id: u32) -> Result<Vec<GetPrimaryKey>, SqlError> {
+		self.client
+			.query(&self.primary_key_stmt, &[&class_id])?
+			.iter()
+			.map(TryFromRow::from_row)
+			.collect()
+	}
+
+	fn get_foreign_keys(&mut self, class_id: u32) -> Result<Vec<GetForeignKeys>, SqlError> {
+		self.client
+			.query(&self.foreign_keys_stmt, &[&class_id])?
+			.iter()
+			.map(TryFromRow::from_row)
+			.collect()
+	}
+
 	fn get_domain_base(&mut self, type_id: u32) -> Result<Option<GetDomainBase>, SqlError> {
 		self.client
 			.query_opt(&self.domain_base_stmt, &[&type_id])?
@@ -383,6 +491,14 @@ impl MyClient {
 			.transpose()
 	}
 
+	fn get_domain_checks(&mut self, type_id: u32) -> Result<Vec<GetDomainChecks>, SqlError> {
+		self.client
+			.query(&self.domain_checks_stmt, &[&type_id])?
+			.iter()
+			.map(TryFromRow::from_row)
+			.collect()
+	}
+
 	fn get_proc_names(&mut self, ns_id: u32) -> Result<Vec<GetProcNames>, SqlError> {
 		self.client
 			.query(&self.proc_names_stmt, &[&ns_id])?
@@ -0,0 +1,2661 @@
+//! Reads the structure of a PostgreSQL database from the SQL files that create it, without connecting to a database
+//!
+//! The statements of each file are applied in order to an empty database to build the same [`FullDB`] that
+//! [`MyClient`](super::connection::MyClient) would read after running them. Schemas, tables, views, types, domains, and
+//! functions are read along with the `ALTER`s and `DROP`s of them and `SET search_path`. Statements that can't change the
+//! mapping (i.e. `GRANT`, `CREATE INDEX`, or `INSERT`) are skipped and anything else that can't be read is left out with a
+//! warning.
+//!
+//! Oids are given out in the order things are made so they won't match a real database. pg_catalog only has its base and
+//! pseudo types (not the row types of the system catalogs) and information_schema and pg_toast aren't included. Domain
+//! defaults and checks are written in postgres's normalised form, including the casts it adds for the common base types
+//! (i.e. `(VALUE >= (0)::double precision)`), but unusual expressions may be printed differently
+use super::{connection::arg_names_or_default, sql_tree::*, Error};
+use sqlparser::{
+	ast::{
+		AlterColumnOperation, AlterTableOperation, ArrayElemTypeDef, ColumnDef, ColumnOption, CreateTable, DataType,
+		Expr, FunctionArg, FunctionArgExpr, FunctionArguments, FunctionDesc, Ident, ObjectName, ObjectType,
+		OneOrManyWithParens, Query, SchemaName, SelectItem, SetExpr, Statement, TableConstraint, TableFactor,
+		TimezoneInfo, UnaryOperator, UserDefinedTypeRepresentation, Value,
+	},
+	dialect::PostgreSqlDialect,
+	keywords::Keyword,
+	parser::{Parser, ParserError},
+	tokenizer::{Token, TokenWithSpan, Tokenizer},
+};
+use std::{
+	collections::HashMap,
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+/// The types of pg_catalog which are read from a database (those with array types, and void) as (name, oid, array oid,
+/// typtype). Types with a typtype other than b are pseudo, range, and multirange types
+pub const PG_TYPES: [(&str, u32, u32, u8); 79] = [
+	("bool", 16, 1000, b'b'),
+	("bytea", 17, 1001, b'b'),
+	("char", 18, 1002, b'b'),
+	("name", 19, 1003, b'b'),
+	("int8", 20, 1016, b'b'),
+	("int2", 21, 1005, b'b'),
+	("int2vector", 22, 1006, b'b'),
+	("int4", 23, 1007, b'b'),
+	("regproc", 24, 1008, b'b'),
+	("text", 25, 1009, b'b'),
+	("oid", 26, 1028, b'b'),
+	("tid", 27, 1010, b'b'),
+	("xid", 28, 1011, b'b'),
+	("cid", 29, 1012, b'b'),
+	("oidvector", 30, 1013, b'b'),
+	("json", 114, 199, b'b'),
+	("xml", 142, 143, b'b'),
+	("point", 600, 1017, b'b'),
+	("lseg", 601, 1018, b'b'),
+	("path", 602, 1019, b'b'),
+	("box", 603, 1020, b'b'),
+	("polygon", 604, 1027, b'b'),
+	("line", 628, 629, b'b'),
+	("cidr", 650, 651, b'b'),
+	("float4", 700, 1021, b'b'),
+	("float8", 701, 1022, b'b'),
+	("circle", 718, 719, b'b'),
+	("macaddr8", 774, 775, b'b'),
+	("money", 790, 791, b'b'),
+	("macaddr", 829, 1040, b'b'),
+	("inet", 869, 1041, b'b'),
+	("aclitem", 1033, 1034, b'b'),
+	("bpchar", 1042, 1014, b'b'),
+	("varchar", 1043, 1015, b'b'),
+	("date", 1082, 1182, b'b'),
+	("time", 1083, 1183, b'b'),
+	("timestamp", 1114, 1115, b'b'),
+	("timestamptz", 1184, 1185, b'b'),
+	("interval", 1186, 1187, b'b'),
+	("timetz", 1266, 1270, b'b'),
+	("bit", 1560, 1561, b'b'),
+	("varbit", 1562, 1563, b'b'),
+	("numeric", 1700, 1231, b'b'),
+	("refcursor", 1790, 2201, b'b'),
+	("regprocedure", 2202, 2207, b'b'),
+	("regoper", 2203, 2208, b'b'),
+	("regoperator", 2204, 2209, b'b'),
+	("regclass", 2205, 2210, b'b'),
+	("regtype", 2206, 2211, b'b'),
+	("record", 2249, 2287, b'p'),
+	("cstring", 2275, 1263, b'p'),
+	("void", 2278, 0, b'p'),
+	("uuid", 2950, 2951, b'b'),
+	("txid_snapshot", 2970, 2949, b'b'),
+	("pg_lsn", 3220, 3221, b'b'),
+	("tsvector", 3614, 3643, b'b'),
+	("tsquery", 3615, 3645, b'b'),
+	("gtsvector", 3642, 3644, b'b'),
+	("regconfig", 3734, 3735, b'b'),
+	("regdictionary", 3769, 3770, b'b'),
+	("jsonb", 3802, 3807, b'b'),
+	("int4range", 3904, 3905, b'r'),
+	("numrange", 3906, 3907, b'r'),
+	("tsrange", 3908, 3909, b'r'),
+	("tstzrange", 3910, 3911, b'r'),
+	("daterange", 3912, 3913, b'r'),
+	("int8range", 3926, 3927, b'r'),
+	("jsonpath", 4072, 4073, b'b'),
+	("regnamespace", 4089, 4090, b'b'),
+	("regrole", 4096, 4097, b'b'),
+	("regcollation", 4191, 4192, b'b'),
+	("int4multirange", 4451, 6150, b'm'),
+	("nummultirange", 4532, 6151, b'm'),
+	("tsmultirange", 4533, 6152, b'm'),
+	("tstzmultirange", 4534, 6153, b'm'),
+	("datemultirange", 4535, 6155, b'm'),
+	("int8multirange", 4536, 6157, b'm'),
+	("pg_snapshot", 5038, 5039, b'b'),
+	("xid8", 5069, 271, b'b'),
+];
+
+/// Pseudo types functions can take or return which aren't in [`PG_TYPES`] as they have no array types
+const PG_PSEUDO_TYPES: [(&str, u32); 9] = [
+	("any", 2276),
+	("anyarray", 2277),
+	("anyelement", 2283),
+	("anyenum", 3500),
+	("anynonarray", 2776),
+	("anyrange", 3831),
+	("event_trigger", 3838),
+	("internal", 2281),
+	("trigger", 2279),
+];
+
+/// Names which postgres's grammar turns into an integer type with a sequence for its default, when they aren't quoted
+const SERIAL_TYPES: [(&str, &str); 6] = [
+	("bigserial", "int8"),
+	("serial", "int4"),
+	("serial2", "int2"),
+	("serial4", "int4"),
+	("serial8", "int8"),
+	("smallserial", "int2"),
+];
+
+/// Statements which can't change the mapping, given by their leading keywords
+const SKIPPED_STATEMENTS: [&str; 42] = [
+	"ALTER DEFAULT PRIVILEGES",
+	"ALTER INDEX",
+	"ALTER ROLE",
+	"ALTER SEQUENCE",
+	"ALTER STATISTICS",
+	"ALTER USER",
+	"ANALYZE",
+	"BEGIN",
+	"CALL",
+	"CHECKPOINT",
+	"CLUSTER",
+	"COMMENT",
+	"COMMIT",
+	"CREATE EVENT TRIGGER",
+	"CREATE INDEX",
+	"CREATE POLICY",
+	"CREATE ROLE",
+	"CREATE SEQUENCE",
+	"CREATE STATISTICS",
+	"CREATE TRIGGER",
+	"CREATE UNIQUE INDEX",
+	"CREATE USER",
+	"DELETE",
+	"DROP INDEX",
+	"DROP POLICY",
+	"DROP ROLE",
+	"DROP SEQUENCE",
+	"DROP TRIGGER",
+	"DROP USER",
+	"END",
+	"GRANT",
+	"INSERT",
+	"LOCK",
+	"REFRESH MATERIALIZED VIEW",
+	"REINDEX",
+	"RELEASE",
+	"REVOKE",
+	"ROLLBACK",
+	"SAVEPOINT",
+	"SELECT",
+	"START TRANSACTION",
+	"UPDATE",
+];
+
+/// The first oid postgres gives to objects made after initdb
+const FIRST_NORMAL_OBJECT_ID: u32 = 16384;
+const PG_CATALOG_OID: u32 = 11;
+const PUBLIC_OID: u32 = 2200;
+/// The owner of schemas made without `AUTHORIZATION`
+const DEFAULT_OWNER: &str = "postgres";
+/// The longest name postgres keeps, one less than NAMEDATALEN
+const MAX_NAME_LEN: usize = 63;
+
+/// Reads a set of SQL files as if they were run in order against an empty database
+pub struct DdlReader {
+	files: Vec<PathBuf>,
+	pub(crate) warnings: Vec<String>,
+}
+
+impl DdlReader {
+	pub fn new(files: Vec<PathBuf>) -> DdlReader {
+		DdlReader {
+			files,
+			warnings: Vec::new(),
+		}
+	}
+
+	/// Read a `.sql` file or the `.sql` files in a directory ordered by name, i.e. numbered migrations
+	pub fn open(path: &Path) -> io::Result<DdlReader> {
+		Ok(DdlReader::new(sql_files(path)?))
+	}
+
+	/// Apply every file, statements which can't be read are left out and listed in `warnings`
+	pub fn get_all(&mut self, no_functions: bool) -> Result<FullDB, Error> {
+		let mut db = DdlDb::new();
+		for file in &self.files {
+			let sql = fs::read_to_string(file).map_err(|e| Error::InFile(file.clone(), Box::new(Error::Io(e))))?;
+			db.apply(&sql, &file.display().to_string())
+				.map_err(|e| Error::InFile(file.clone(), Box::new(e)))?;
+		}
+		let (full_db, warnings) = db.finish(no_functions);
+		self.warnings = warnings;
+		Ok(full_db)
+	}
+}
+
+/// Whether a connection string is the path of a `.sql` file or a directory, which are read with [`DdlReader`]
+pub fn is_ddl_path(conn: &str) -> bool {
+	let path = Path::new(conn);
+	path.is_dir() || (path.is_file() && is_sql_file(path))
+}
+
+/// The `.sql` files in a directory sorted by name, or just `path` if it's a file
+pub fn sql_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+	if !path.is_dir() {
+		return Ok(vec![path.to_path_buf()]);
+	}
+	let mut files = Vec::new();
+	for entry in fs::read_dir(path)? {
+		let path = entry?.path();
+		if path.is_file() && is_sql_file(&path) {
+			files.push(path);
+		}
+	}
+	files.sort();
+	Ok(files)
+}
+
+fn is_sql_file(path: &Path) -> bool {
+	path.extension().is_some_and(|v| v.eq_ignore_ascii_case("sql"))
+}
+
+struct DdlDb {
+	schemas: Vec<DdlSchema>,
+	search_path: Vec<String>,
+	next_oid: u32,
+	warnings: Vec<String>,
+}
+
+struct DdlSchema {
+	oid: u32,
+	name: String,
+	owner: String,
+	/// In the order they were made, which is the order of their oids
+	types: Vec<DdlType>,
+	procs: Vec<DdlProc>,
+}
+
+struct DdlType {
+	oid: u32,
+	/// 0 if it has no array type
+	array_oid: u32,
+	name: String,
+	kind: DdlTypeKind,
+}
+
+enum DdlTypeKind {
+	/// Copied to the tree as is, the built in types and those of extensions
+	Fixed(PsqlTypType),
+	Enum(Vec<String>),
+	Relation(DdlRelation),
+	Domain(DdlDomain),
+}
+
+struct DdlDomain {
+	base_oid: u32,
+	not_null: bool,
+	default: Option<String>,
+	/// Ordered by name
+	checks: Vec<DomainCheck>,
+}
+
+/// A table, view, or composite type. Constraints refer to columns by position as postgres does so renaming a column doesn't
+/// change them
+struct DdlRelation {
+	kind: RelKind,
+	/// The names of the types are filled in at the end so they follow renames
+	cols: Vec<Column>,
+	/// Positions of dropped columns aren't reused
+	next_attnum: i16,
+	/// The constraint name and positions of the columns
+	primary_key: Option<(String, Vec<i16>)>,
+	foreign_keys: Vec<DdlForeignKey>,
+}
+
+struct DdlForeignKey {
+	name: String,
+	cols: Vec<i16>,
+	ref_oid: u32,
+	ref_cols: Vec<i16>,
+}
+
+struct DdlProc {
+	oid: u32,
+	name: String,
+	returns_set: bool,
+	num_args: i16,
+	/// The types of the IN, INOUT, and VARIADIC arguments, which tell overloads apart
+	signature: Vec<u32>,
+	inputs: Vec<(String, u32)>,
+	/// `None` for `RETURNS TABLE`, whose columns are `table`
+	output: Option<u32>,
+	table: Vec<(String, u32)>,
+}
+
+/// The table a column or constraint is added to
+#[derive(Clone, Copy)]
+struct TableId<'a> {
+	schema: usize,
+	oid: u32,
+	name: &'a str,
+}
+
+/// A type as it's used by a column or argument
+#[derive(Clone, Copy)]
+struct TypeRef {
+	oid: u32,
+	dims: i32,
+	/// A NOT NULL domain
+	not_null: bool,
+	serial: bool,
+}
+
+impl DdlDb {
+	fn new() -> DdlDb {
+		let types = PG_TYPES
+			.iter()
+			.map(|&(name, oid, array_oid, typtype)| DdlType {
+				oid,
+				array_oid,
+				name: String::from(name),
+				kind: DdlTypeKind::Fixed(if typtype == b'b' {
+					PsqlTypType::Base(PsqlBaseType {
+						oid,
+						name: String::from(name),
+						extension: None,
+					})
+				} else {
+					PsqlTypType::Other(oid)
+				}),
+			})
+			.collect();
+		DdlDb {
+			schemas: vec![
+				DdlSchema {
+					oid: PG_CATALOG_OID,
+					name: String::from("pg_catalog"),
+					owner: String::from(DEFAULT_OWNER),
+					types,
+					procs: Vec::new(),
+				},
+				DdlSchema {
+					oid: PUBLIC_OID,
+					name: String::from("public"),
+					owner: String::from("pg_database_owner"),
+					types: Vec::new(),
+					procs: Vec::new(),
+				},
+			],
+			search_path: vec![String::from("public")],
+			next_oid: FIRST_NORMAL_OBJECT_ID,
+			warnings: Vec::new(),
+		}
+	}
+
+	fn new_oid(&mut self) -> u32 {
+		self.next_oid += 1;
+		self.next_oid - 1
+	}
+
+	/// Apply every statement of a file, those which can't be read are added to the warnings prefixed with `file:line`
+	fn apply(&mut self, sql: &str, file: &str) -> Result<(), Error> {
+		let tokens = Tokenizer::new(&PostgreSqlDialect {}, sql)
+			.tokenize_with_location()
+			.map_err(|e| Error::Ddl(e.to_string()))?;
+		for tokens in split_statements(tokens) {
+			let line = tokens[0].span.start.line;
+			if let Err(e) = self.apply_statement(tokens) {
+				self.warnings.push(format!("{}:{}: {}", file, line, e));
+			}
+		}
+		Ok(())
+	}
+
+	fn apply_statement(&mut self, tokens: Vec<TokenWithSpan>) -> Result<(), String> {
+		let words = leading_words(&tokens);
+		let words: Vec<_> = words.iter().map(String::as_str).collect();
+		let owner_change = tokens
+			.windows(2)
+			.any(|v| is_word(&v[0].token, "OWNER") && is_word(&v[1].token, "TO"));
+		let dialect = PostgreSqlDialect {};
+		let mut parser = Parser::new(&dialect).with_tokens_with_locations(tokens);
+		let parser = &mut parser;
+		if SKIPPED_STATEMENTS.iter().any(|v| starts_with_words(&words, v)) {
+			return Ok(());
+		}
+		match words.as_slice() {
+			// owners aren't part of the mapping
+			["ALTER", kind, ..] if owner_change && *kind != "TABLE" => Ok(()),
+			["ALTER", "TYPE", ..] => self.alter_type(parser),
+			["CREATE", "DOMAIN", ..] => self.create_domain(parser),
+			["CREATE", "FUNCTION", ..]
+			| ["CREATE", "PROCEDURE", ..]
+			| ["CREATE", "OR", "REPLACE", "FUNCTION"]
+			| ["CREATE", "OR", "REPLACE", "PROCEDURE"] => self.create_function(parser),
+			["DROP", "DOMAIN", ..] => self.drop_manually(parser, ObjectKind::Domain),
+			["DROP", "MATERIALIZED", "VIEW", ..] => self.drop_manually(parser, ObjectKind::MaterializedView),
+			["CREATE", "TYPE", ..] if is_range_type(parser) => self.create_range_type(parser),
+			_ => {
+				let statement = parser.parse_statement().map_err(parse_error)?;
+				expect_end(parser)?;
+				self.apply_parsed(statement, &words)
+			},
+		}
+	}
+
+	fn apply_parsed(&mut self, statement: Statement, words: &[&str]) -> Result<(), String> {
+		match statement {
+			Statement::CreateSchema {
+				schema_name,
+				if_not_exists,
+			} => {
+				let (name, owner) = match schema_name {
+					SchemaName::Simple(name) => (object_name(&name)?.1, None),
+					SchemaName::UnnamedAuthorization(owner) => (ident(&owner), Some(ident(&owner))),
+					SchemaName::NamedAuthorization(name, owner) => (object_name(&name)?.1, Some(ident(&owner))),
+				};
+				self.create_schema(name, owner, if_not_exists)
+			},
+			Statement::CreateTable(table) => self.create_table(&table),
+			Statement::CreateType { name, representation } => self.create_type(&name, representation),
+			Statement::CreateView {
+				or_replace,
+				materialized,
+				name,
+				columns,
+				query,
+				if_not_exists,
+				..
+			} => {
+				let kind = if materialized {
+					RelKind::MaterializedView
+				} else {
+					RelKind::View
+				};
+				let names = columns.iter().map(|v| ident(&v.name)).collect();
+				self.create_view(&name, kind, names, &query, or_replace, if_not_exists)
+			},
+			Statement::AlterTable {
+				name,
+				if_exists,
+				operations,
+				..
+			} => self.alter_table(&name, if_exists, operations),
+			Statement::Drop {
+				object_type,
+				if_exists,
+				names,
+				..
+			} => {
+				let kind = match object_type {
+					ObjectType::Schema => ObjectKind::Schema,
+					ObjectType::Table => ObjectKind::Table,
+					ObjectType::View => ObjectKind::View,
+					ObjectType::Type => ObjectKind::Type,
+					ObjectType::Index | ObjectType::Role | ObjectType::Sequence => return Ok(()),
+					_ => return Err(format!("`DROP {}` isn't supported", object_type)),
+				};
+				for name in &names {
+					self.drop(name, kind, if_exists)?;
+				}
+				Ok(())
+			},
+			Statement::DropFunction {
+				if_exists, func_desc, ..
+			} => {
+				for desc in &func_desc {
+					self.drop_function(desc, if_exists)?;
+				}
+				Ok(())
+			},
+			Statement::DropProcedure {
+				if_exists, proc_desc, ..
+			} => {
+				for desc in &proc_desc {
+					self.drop_function(desc, if_exists)?;
+				}
+				Ok(())
+			},
+			Statement::CreateExtension {
+				name,
+				if_not_exists,
+				schema,
+				..
+			} => self.create_extension(&ident(&name), schema.as_ref().map(ident), if_not_exists),
+			Statement::SetVariable {
+				variables: OneOrManyWithParens::One(variable),
+				value,
+				..
+			} => {
+				if object_name(&variable)?.1 == "search_path" {
+					self.search_path = value.iter().filter_map(search_path_entry).collect();
+				}
+				Ok(())
+			},
+			Statement::SetVariable { .. } | Statement::SetTimeZone { .. } | Statement::SetNames { .. } => Ok(()),
+			_ => Err(format!(
+				"`{}` statements aren't supported",
+				words.iter().take(2).cloned().collect::<Vec<_>>().join(" ")
+			)),
+		}
+	}
+
+	fn create_schema(&mut self, name: String, owner: Option<String>, if_not_exists: bool) -> Result<(), String> {
+		if self.schema_index(&name).is_some() {
+			return if if_not_exists {
+				Ok(())
+			} else {
+				Err(format!("schema `{}` already exists", name))
+			};
+		}
+		let oid = self.new_oid();
+		self.schemas.push(DdlSchema {
+			oid,
+			name,
+			owner: owner.unwrap_or_else(|| String::from(DEFAULT_OWNER)),
+			types: Vec::new(),
+			procs: Vec::new(),
+		});
+		Ok(())
+	}
+
+	fn create_table(&mut self, table: &CreateTable) -> Result<(), String> {
+		if table.query.is_some() || table.like.is_some() || table.clone.is_some() {
+			return Err(String::from(
+				"tables made with `AS`, `LIKE`, or `CLONE` aren't supported",
+			));
+		}
+		let (schema, name) = self.creation_schema(&table.name)?;
+		if self.schemas[schema].types.iter().any(|v| v.name == name) {
+			return if table.if_not_exists {
+				Ok(())
+			} else {
+				Err(format!("type `{}` already exists", name))
+			};
+		}
+		let oid = self.new_oid();
+		let array_oid = self.new_oid();
+		let mut relation = DdlRelation::new(RelKind::Table);
+		let id = TableId {
+			schema,
+			oid,
+			name: &name,
+		};
+		let mut constraints = Vec::new();
+		for def in &table.columns {
+			constraints.append(&mut self.add_column(&mut relation, def)?);
+		}
+		constraints.extend(table.constraints.iter().cloned());
+		// the primary key is made first so the foreign keys can reference it, i.e. from the same table
+		constraints.sort_by_key(|v| !matches!(v, TableConstraint::PrimaryKey { .. }));
+		for constraint in &constraints {
+			self.add_constraint(id, &mut relation, constraint)?;
+		}
+		self.schemas[schema].types.push(DdlType {
+			oid,
+			array_oid,
+			name,
+			kind: DdlTypeKind::Relation(relation),
+		});
+		Ok(())
+	}
+
+	/// Add a column to a table that's being made or altered, returning its PRIMARY KEY and FOREIGN KEY constraints to be
+	/// added with those of the table
+	fn add_column(&self, relation: &mut DdlRelation, def: &ColumnDef) -> Result<Vec<TableConstraint>, String> {
+		let name = ident(&def.name);
+		if relation.cols.iter().any(|v| v.name == name) {
+			return Err(format!("column `{}` specified more than once", name));
+		}
+		let typ = self.resolve_type(&def.data_type)?;
+		let pos = relation.next_attnum;
+		let mut col = new_column(pos, name, typ);
+		let mut constraints = Vec::new();
+		for option in &def.options {
+			match &option.option {
+				ColumnOption::NotNull => col.not_null = true,
+				ColumnOption::Default(_) | ColumnOption::Generated { .. } | ColumnOption::Identity(_) => {
+					col.has_default = true;
+					// identity columns are always NOT NULL, generated columns are only NOT NULL when declared
+					if let ColumnOption::Generated {
+						generation_expr: None, ..
+					}
+					| ColumnOption::Identity(_) = option.option
+					{
+						col.not_null = true;
+					}
+				},
+				ColumnOption::Unique { is_primary: true, .. } => constraints.push(TableConstraint::PrimaryKey {
+					name: option.name.clone(),
+					index_name: None,
+					index_type: None,
+					columns: vec![def.name.clone()],
+					index_options: Vec::new(),
+					characteristics: None,
+				}),
+				ColumnOption::ForeignKey {
+					foreign_table,
+					referred_columns,
+					..
+				} => constraints.push(TableConstraint::ForeignKey {
+					name: option.name.clone(),
+					columns: vec![def.name.clone()],
+					foreign_table: foreign_table.clone(),
+					referred_columns: referred_columns.clone(),
+					on_delete: None,
+					on_update: None,
+					characteristics: None,
+				}),
+				_ => (),
+			}
+		}
+		relation.cols.push(col);
+		relation.next_attnum += 1;
+		Ok(constraints)
+	}
+
+	/// Add a PRIMARY KEY or FOREIGN KEY to a table that's being made or altered, other constraints aren't mapped
+	fn add_constraint(
+		&self,
+		table: TableId,
+		relation: &mut DdlRelation,
+		constraint: &TableConstraint,
+	) -> Result<(), String> {
+		match constraint {
+			TableConstraint::PrimaryKey { name, columns, .. } => {
+				if relation.primary_key.is_some() {
+					return Err(format!(
+						"multiple primary keys for table `{}` are not allowed",
+						table.name
+					));
+				}
+				let cols = relation.positions(columns)?;
+				for col in relation.cols.iter_mut().filter(|v| cols.contains(&v.pos)) {
+					col.not_null = true;
+				}
+				let name = match name {
+					Some(name) => ident(name),
+					None => make_object_name(table.name, None, "pkey"),
+				};
+				relation.primary_key = Some((name, cols));
+			},
+			TableConstraint::ForeignKey {
+				name,
+				columns,
+				foreign_table,
+				referred_columns,
+				..
+			} => {
+				let cols = relation.positions(columns)?;
+				// a table can reference itself, including while it's being made
+				let is_self = match object_name(foreign_table)? {
+					(Some(schema), name) => self.schema_index(&schema) == Some(table.schema) && name == table.name,
+					(None, name) => {
+						name == table.name && self.find_type_index(foreign_table).is_none_or(|v| v.0 == table.schema)
+					},
+				};
+				let (ref_oid, ref_relation) = if is_self {
+					(table.oid, &*relation)
+				} else {
+					self.find_relation(foreign_table)?
+				};
+				let ref_cols = if referred_columns.is_empty() {
+					match &ref_relation.primary_key {
+						Some((_, cols)) => cols.clone(),
+						None => {
+							return Err(format!(
+								"there is no primary key for referenced table `{}`",
+								foreign_table
+							))
+						},
+					}
+				} else {
+					ref_relation.positions(referred_columns)?
+				};
+				if cols.len() != ref_cols.len() {
+					return Err(String::from(
+						"number of referencing and referenced columns for foreign key disagree",
+					));
+				}
+				let name = match name {
+					Some(name) => ident(name),
+					None => {
+						let col_names = relation.names(&cols).join("_");
+						choose_name(table.name, Some(&col_names), "fkey", |v| relation.has_constraint(v))
+					},
+				};
+				relation.foreign_keys.push(DdlForeignKey {
+					name,
+					cols,
+					ref_oid,
+					ref_cols,
+				});
+			},
+			TableConstraint::Unique { .. } | TableConstraint::Check { .. } => (),
+			_ => return Err(format!("constraint `{}` isn't supported", constraint)),
+		}
+		Ok(())
+	}
+
+	fn create_type(&mut self, name: &ObjectName, representation: UserDefinedTypeRepresentation) -> Result<(), String> {
+		let (schema, name) = self.creation_schema(name)?;
+		if self.schemas[schema].types.iter().any(|v| v.name == name) {
+			return Err(format!("type `{}` already exists", name));
+		}
+		let oid = self.new_oid();
+		let array_oid = self.new_oid();
+		let kind = match representation {
+			UserDefinedTypeRepresentation::Enum { labels } => {
+				DdlTypeKind::Enum(labels.into_iter().map(|v| v.value).collect())
+			},
+			UserDefinedTypeRepresentation::Composite { attributes } => {
+				let mut relation = DdlRelation::new(RelKind::Type);
+				for attribute in &attributes {
+					let typ = self.resolve_type(&attribute.data_type)?;
+					if typ.serial {
+						return Err(String::from("the serial types can only be used by tables"));
+					}
+					let col = new_column(relation.next_attnum, ident(&attribute.name), typ);
+					relation.cols.push(col);
+					relation.next_attnum += 1;
+				}
+				DdlTypeKind::Relation(relation)
+			},
+		};
+		self.schemas[schema].types.push(DdlType {
+			oid,
+			array_oid,
+			name,
+			kind,
+		});
+		Ok(())
+	}
+
+	/// `CREATE TYPE name AS RANGE (...)`, added with its multirange type as neither are mapped
+	fn create_range_type(&mut self, parser: &mut Parser) -> Result<(), String> {
+		parser
+			.expect_keywords(&[Keyword::CREATE, Keyword::TYPE])
+			.map_err(parse_error)?;
+		let name = parser.parse_object_name(false).map_err(parse_error)?;
+		let (schema, name) = self.creation_schema(&name)?;
+		if self.schemas[schema].types.iter().any(|v| v.name == name) {
+			return Err(format!("type `{}` already exists", name));
+		}
+		let multirange = match name.find("range") {
+			Some(i) => format!("{}multirange{}", &name[..i], &name[i + 5..]),
+			None => format!("{}_multirange", name),
+		};
+		for name in [name, multirange] {
+			let oid = self.new_oid();
+			let array_oid = self.new_oid();
+			self.schemas[schema].types.push(DdlType {
+				oid,
+				array_oid,
+				name,
+				kind: DdlTypeKind::Fixed(PsqlTypType::Other(oid)),
+			});
+		}
+		Ok(())
+	}
+
+	/// `CREATE DOMAIN`, which sqlparser doesn't parse
+	fn create_domain(&mut self, parser: &mut Parser) -> Result<(), String> {
+		parser.expect_keyword(Keyword::CREATE).map_err(parse_error)?;
+		parse_word(parser, "DOMAIN")?;
+		let name = parser.parse_object_name(false).map_err(parse_error)?;
+		let _ = parser.parse_keyword(Keyword::AS);
+		let data_type = parser.parse_data_type().map_err(parse_error)?;
+		let (schema, name) = self.creation_schema(&name)?;
+		if self.schemas[schema].types.iter().any(|v| v.name == name) {
+			return Err(format!("type `{}` already exists", name));
+		}
+		let base = self.resolve_type(&data_type)?;
+		if base.serial {
+			return Err(String::from("the serial types can only be used by tables"));
+		}
+		let mut domain = DdlDomain {
+			base_oid: base.oid,
+			not_null: false,
+			default: None,
+			checks: Vec::new(),
+		};
+		// the casts postgres adds depend on the type, arrays get none
+		let base_name = match self.type_by_oid(base.oid) {
+			Some(typ) if base.dims == 0 => typ.name.clone(),
+			_ => String::new(),
+		};
+		let base_name = base_name.as_str();
+		let mut checks = Vec::new();
+		while parser.peek_token().token != Token::EOF {
+			if parser.parse_keyword(Keyword::DEFAULT) {
+				domain.default =
+					Some(ExprCasts::default(base_name).pg_expr(&parser.parse_expr().map_err(parse_error)?));
+				continue;
+			}
+			if parser.parse_keyword(Keyword::COLLATE) {
+				parser.parse_object_name(false).map_err(parse_error)?;
+				continue;
+			}
+			let constraint_name = if parser.parse_keyword(Keyword::CONSTRAINT) {
+				Some(ident(&parser.parse_identifier(false).map_err(parse_error)?))
+			} else {
+				None
+			};
+			if parser.parse_keywords(&[Keyword::NOT, Keyword::NULL]) {
+				domain.not_null = true;
+			} else if parser.parse_keyword(Keyword::NULL) {
+				domain.not_null = false;
+			} else if parser.parse_keyword(Keyword::CHECK) {
+				parser.expect_token(&Token::LParen).map_err(parse_error)?;
+				let expr = ExprCasts::check(base_name).pg_expr(&parser.parse_expr().map_err(parse_error)?);
+				parser.expect_token(&Token::RParen).map_err(parse_error)?;
+				checks.push((constraint_name, expr));
+			} else {
+				return Err(parse_error(
+					parser
+						.expected::<()>("a domain constraint", parser.peek_token())
+						.unwrap_err(),
+				));
+			}
+		}
+		// postgres names the checks after the domain, in the order they're given
+		for (check_name, expr) in checks {
+			let check_name = check_name.unwrap_or_else(|| {
+				choose_name(&name, None, "check", |v| {
+					domain.checks.iter().any(|check| check.name == v)
+				})
+			});
+			domain.checks.push(DomainCheck { name: check_name, expr });
+		}
+		domain.checks.sort_by(|a, b| a.name.cmp(&b.name));
+		let oid = self.new_oid();
+		let array_oid = self.new_oid();
+		self.schemas[schema].types.push(DdlType {
+			oid,
+			array_oid,
+			name,
+			kind: DdlTypeKind::Domain(domain),
+		});
+		Ok(())
+	}
+
+	fn create_view(
+		&mut self,
+		name: &ObjectName,
+		kind: RelKind,
+		names: Vec<String>,
+		query: &Query,
+		or_replace: bool,
+		if_not_exists: bool,
+	) -> Result<(), String> {
+		let (schema, view_name) = self.creation_schema(name)?;
+		let mut cols = self.query_columns(query)?;
+		if names.len() > cols.len() {
+			return Err(String::from("the view has more column names than columns"));
+		}
+		for (col, name) in cols.iter_mut().zip(names) {
+			col.0 = name;
+		}
+		let mut relation = DdlRelation::new(kind);
+		for (name, typ) in cols {
+			if relation.cols.iter().any(|v| v.name == name) {
+				return Err(format!("column `{}` specified more than once", name));
+			}
+			// postgres doesn't keep the dimensions or nullability of a view's columns
+			let col = new_column(
+				relation.next_attnum,
+				name,
+				TypeRef {
+					dims: 0,
+					not_null: false,
+					..typ
+				},
+			);
+			relation.cols.push(col);
+			relation.next_attnum += 1;
+		}
+		match self.schemas[schema].types.iter_mut().find(|v| v.name == view_name) {
+			Some(existing) if or_replace && matches!(&existing.kind, DdlTypeKind::Relation(v) if v.kind == kind) => {
+				existing.kind = DdlTypeKind::Relation(relation);
+				Ok(())
+			},
+			Some(_) if if_not_exists => Ok(()),
+			Some(_) => Err(format!("relation `{}` already exists", view_name)),
+			None => {
+				let oid = self.new_oid();
+				let array_oid = self.new_oid();
+				self.schemas[schema].types.push(DdlType {
+					oid,
+					array_oid,
+					name: view_name,
+					kind: DdlTypeKind::Relation(relation),
+				});
+				Ok(())
+			},
+		}
+	}
+
+	/// The names and types of the columns a query returns, only simple selects of columns and casts can be read
+	fn query_columns(&self, query: &Query) -> Result<Vec<(String, TypeRef)>, String> {
+		if query.with.is_some() {
+			return Err(String::from("views with `WITH` aren't supported"));
+		}
+		let mut body = &*query.body;
+		let select = loop {
+			match body {
+				SetExpr::Select(select) => break select,
+				SetExpr::Query(query) => return self.query_columns(query),
+				// the columns of a UNION are those of its first query
+				SetExpr::SetOperation { left, .. } => body = left,
+				_ => return Err(format!("the columns of `{}` aren't known", body)),
+			}
+		};
+		// the relations in the FROM by the name they're referred to with, and their columns
+		let mut from: Vec<(String, Vec<(String, TypeRef)>)> = Vec::new();
+		let factors = select
+			.from
+			.iter()
+			.flat_map(|v| std::iter::once(&v.relation).chain(v.joins.iter().map(|v| &v.relation)));
+		for factor in factors {
+			match factor {
+				TableFactor::Table {
+					name,
+					alias,
+					args: None,
+					..
+				} => {
+					let (_, relation) = self.find_relation(name)?;
+					let cols = relation
+						.cols
+						.iter()
+						.map(|v| (v.name.clone(), self.column_type(v)))
+						.collect();
+					let alias = match alias {
+						Some(alias) => ident(&alias.name),
+						None => object_name(name)?.1,
+					};
+					from.push((alias, cols));
+				},
+				TableFactor::Derived {
+					subquery,
+					alias: Some(alias),
+					..
+				} => from.push((ident(&alias.name), self.query_columns(subquery)?)),
+				_ => return Err(format!("the columns of `{}` aren't known", factor)),
+			}
+		}
+		let mut cols = Vec::new();
+		for item in &select.projection {
+			match item {
+				SelectItem::Wildcard(_) => cols.extend(from.iter().flat_map(|v| v.1.iter().cloned())),
+				SelectItem::QualifiedWildcard(name, _) => {
+					let name = object_name(name)?.1;
+					match from.iter().find(|v| v.0 == name) {
+						Some((_, relation)) => cols.extend(relation.iter().cloned()),
+						None => return Err(format!("missing FROM-clause entry for table `{}`", name)),
+					}
+				},
+				SelectItem::UnnamedExpr(expr) => cols.push((expr_column_name(expr), self.expr_type(expr, &from)?)),
+				SelectItem::ExprWithAlias { expr, alias } => cols.push((ident(alias), self.expr_type(expr, &from)?)),
+			}
+		}
+		Ok(cols)
+	}
+
+	fn expr_type(&self, expr: &Expr, from: &[(String, Vec<(String, TypeRef)>)]) -> Result<TypeRef, String> {
+		let find = |relation: Option<&str>, name: &str| {
+			from.iter()
+				.filter(|v| relation.is_none_or(|relation| v.0 == relation))
+				.find_map(|v| v.1.iter().find(|v| v.0 == name))
+				.map(|v| v.1)
+				.ok_or_else(|| format!("column `{}` does not exist", name))
+		};
+		match expr {
+			Expr::Identifier(name) => find(None, &ident(name)),
+			Expr::CompoundIdentifier(names) => match names.as_slice() {
+				[.., relation, name] => find(Some(&ident(relation)), &ident(name)),
+				_ => Err(format!("the type of `{}` isn't known", expr)),
+			},
+			Expr::Nested(expr) => self.expr_type(expr, from),
+			Expr::Cast { data_type, .. } => self.resolve_type(data_type),
+			Expr::Value(Value::Number(v, _)) => self.catalog_type(if v.parse::<i32>().is_ok() {
+				"int4"
+			} else if v.parse::<i64>().is_ok() {
+				"int8"
+			} else {
+				"numeric"
+			}),
+			Expr::Value(Value::SingleQuotedString(_)) => self.catalog_type("text"),
+			Expr::Value(Value::Boolean(_)) => self.catalog_type("bool"),
+			Expr::Function(function) if object_name(&function.name)?.1 == "count" => self.catalog_type("int8"),
+			_ => Err(format!("the type of `{}` isn't known", expr)),
+		}
+	}
+
+	fn alter_table(
+		&mut self,
+		name: &ObjectName,
+		if_exists: bool,
+		operations: Vec<AlterTableOperation>,
+	) -> Result<(), String> {
+		let (schema, table) = match self.find_type_index(name) {
+			Some(v) => v,
+			None if if_exists => return Ok(()),
+			None => return Err(format!("relation `{}` does not exist", object_name(name)?.1)),
+		};
+		let oid = self.schemas[schema].types[table].oid;
+		let mut table_name = self.schemas[schema].types[table].name.clone();
+		let mut relation = match &self.schemas[schema].types[table].kind {
+			DdlTypeKind::Relation(v) if v.kind.is_relation() => v.clone(),
+			_ => return Err(format!("`{}` is not a table", table_name)),
+		};
+		// applied to a copy so nothing changes if one of the operations fails
+		for operation in operations {
+			match operation {
+				AlterTableOperation::AddColumn {
+					column_def,
+					if_not_exists,
+					..
+				} => {
+					if if_not_exists && relation.cols.iter().any(|v| v.name == ident(&column_def.name)) {
+						continue;
+					}
+					let id = TableId {
+						schema,
+						oid,
+						name: &table_name,
+					};
+					for constraint in self.add_column(&mut relation, &column_def)? {
+						self.add_constraint(id, &mut relation, &constraint)?;
+					}
+				},
+				AlterTableOperation::AddConstraint(constraint) => {
+					let id = TableId {
+						schema,
+						oid,
+						name: &table_name,
+					};
+					self.add_constraint(id, &mut relation, &constraint)?
+				},
+				AlterTableOperation::DropColumn {
+					column_name, if_exists, ..
+				} => {
+					let pos = match relation.position(&column_name) {
+						Ok(pos) => pos,
+						Err(_) if if_exists => continue,
+						Err(e) => return Err(e),
+					};
+					relation.cols.retain(|v| v.pos != pos);
+					if relation.primary_key.as_ref().is_some_and(|v| v.1.contains(&pos)) {
+						relation.primary_key = None;
+					}
+					relation.foreign_keys.retain(|v| !v.cols.contains(&pos));
+				},
+				AlterTableOperation::DropConstraint { name, .. } => {
+					let name = ident(&name);
+					// UNIQUE and CHECK constraints aren't kept so any other name is ignored
+					if relation.primary_key.as_ref().is_some_and(|v| v.0 == name) {
+						relation.primary_key = None;
+					}
+					relation.foreign_keys.retain(|v| v.name != name);
+				},
+				AlterTableOperation::DropPrimaryKey => relation.primary_key = None,
+				AlterTableOperation::RenameColumn {
+					old_column_name,
+					new_column_name,
+				} => {
+					let pos = relation.position(&old_column_name)?;
+					let new_name = ident(&new_column_name);
+					if relation.cols.iter().any(|v| v.name == new_name) {
+						return Err(format!("column `{}` already exists", new_name));
+					}
+					relation.cols.iter_mut().find(|v| v.pos == pos).unwrap().name = new_name;
+				},
+				AlterTableOperation::RenameConstraint { old_name, new_name } => {
+					let (old_name, new_name) = (ident(&old_name), ident(&new_name));
+					match &mut relation.primary_key {
+						Some((name, _)) if *name == old_name => *name = new_name,
+						_ => {
+							if let Some(fk) = relation.foreign_keys.iter_mut().find(|v| v.name == old_name) {
+								fk.name = new_name;
+							}
+						},
+					}
+				},
+				AlterTableOperation::RenameTable { table_name: new_name } => {
+					let new_name = object_name(&new_name)?.1;
+					if self.schemas[schema].types.iter().any(|v| v.name == new_name) {
+						return Err(format!("relation `{}` already exists", new_name));
+					}
+					table_name = new_name;
+				},
+				AlterTableOperation::AlterColumn { column_name, op } => {
+					let pos = relation.position(&column_name)?;
+					let typ = match &op {
+						AlterColumnOperation::SetDataType { data_type, .. } => Some(self.resolve_type(data_type)?),
+						_ => None,
+					};
+					let col = relation.cols.iter_mut().find(|v| v.pos == pos).unwrap();
+					match op {
+						AlterColumnOperation::SetNotNull => col.not_null = true,
+						AlterColumnOperation::DropNotNull => col.not_null = self.domain_not_null(col.type_id),
+						AlterColumnOperation::SetDefault { .. } | AlterColumnOperation::AddGenerated { .. } => {
+							col.has_default = true
+						},
+						AlterColumnOperation::DropDefault => col.has_default = false,
+						AlterColumnOperation::SetDataType { .. } => {
+							let typ = typ.unwrap();
+							if typ.serial {
+								return Err(String::from("the serial types can only be used when adding a column"));
+							}
+							col.type_id = typ.oid;
+							col.num_dimentions = typ.dims;
+							col.not_null |= typ.not_null;
+						},
+					}
+				},
+				AlterTableOperation::OwnerTo { .. }
+				| AlterTableOperation::EnableRowLevelSecurity
+				| AlterTableOperation::DisableRowLevelSecurity
+				| AlterTableOperation::EnableTrigger { .. }
+				| AlterTableOperation::DisableTrigger { .. }
+				| AlterTableOperation::EnableAlwaysTrigger { .. }
+				| AlterTableOperation::EnableReplicaTrigger { .. }
+				| AlterTableOperation::EnableRule { .. }
+				| AlterTableOperation::DisableRule { .. }
+				| AlterTableOperation::EnableAlwaysRule { .. }
+				| AlterTableOperation::EnableReplicaRule { .. } => (),
+				operation => return Err(format!("`{}` isn't supported", operation)),
+			}
+		}
+		let typ = &mut self.schemas[schema].types[table];
+		typ.name = table_name;
+		typ.kind = DdlTypeKind::Relation(relation);
+		Ok(())
+	}
+
+	/// `ALTER TYPE`, which sqlparser doesn't parse
+	fn alter_type(&mut self, parser: &mut Parser) -> Result<(), String> {
+		parser
+			.expect_keywords(&[Keyword::ALTER, Keyword::TYPE])
+			.map_err(parse_error)?;
+		let name = parser.parse_object_name(false).map_err(parse_error)?;
+		let (schema, index) = self
+			.find_type_index(&name)
+			.ok_or_else(|| format!("type `{}` does not exist", name))?;
+		if parser.parse_keywords(&[Keyword::RENAME, Keyword::TO]) {
+			let new_name = ident(&parser.parse_identifier(false).map_err(parse_error)?);
+			expect_end(parser)?;
+			if self.schemas[schema].types.iter().any(|v| v.name == new_name) {
+				return Err(format!("type `{}` already exists", new_name));
+			}
+			self.schemas[schema].types[index].name = new_name;
+			return Ok(());
+		}
+		let is_add = parser.parse_keyword(Keyword::ADD);
+		let is_rename = !is_add && parser.parse_keyword(Keyword::RENAME);
+		if (is_add || is_rename) && parser.parse_keyword(Keyword::VALUE) {
+			let labels = match &mut self.schemas[schema].types[index].kind {
+				DdlTypeKind::Enum(labels) => labels,
+				_ => return Err(format!("`{}` is not an enum", name)),
+			};
+			if is_rename {
+				let old = parser.parse_literal_string().map_err(parse_error)?;
+				parser.expect_keyword(Keyword::TO).map_err(parse_error)?;
+				let new = parser.parse_literal_string().map_err(parse_error)?;
+				expect_end(parser)?;
+				let label = labels
+					.iter_mut()
+					.find(|v| **v == old)
+					.ok_or_else(|| format!("`{}` is not an existing enum label", old))?;
+				*label = new;
+				return Ok(());
+			}
+			let if_not_exists = parser.parse_keywords(&[Keyword::IF, Keyword::NOT, Keyword::EXISTS]);
+			let label = parser.parse_literal_string().map_err(parse_error)?;
+			let pos = if parser.parse_keyword(Keyword::BEFORE) {
+				Some(0)
+			} else if parser.parse_keyword(Keyword::AFTER) {
+				Some(1)
+			} else {
+				None
+			};
+			let pos = match pos {
+				Some(offset) => {
+					let neighbor = parser.parse_literal_string().map_err(parse_error)?;
+					let i = labels
+						.iter()
+						.position(|v| *v == neighbor)
+						.ok_or_else(|| format!("`{}` is not an existing enum label", neighbor))?;
+					i + offset
+				},
+				None => labels.len(),
+			};
+			expect_end(parser)?;
+			if labels.contains(&label) {
+				return if if_not_exists {
+					Ok(())
+				} else {
+					Err(format!("enum label `{}` already exists", label))
+				};
+			}
+			labels.insert(pos, label);
+			return Ok(());
+		}
+		Err(String::from(
+			"only `RENAME TO`, `ADD VALUE`, and `RENAME VALUE` of `ALTER TYPE` are supported",
+		))
+	}
+
+	fn create_extension(&mut self, name: &str, schema: Option<String>, if_not_exists: bool) -> Result<(), String> {
+		let schema = match schema {
+			Some(schema) => self
+				.schema_index(&schema)
+				.ok_or_else(|| format!("schema `{}` does not exist", schema))?,
+			None => self.first_search_path_schema()?,
+		};
+		let exists = self.schemas.iter().flat_map(|v| &v.types).any(
+			|v| matches!(&v.kind, DdlTypeKind::Fixed(PsqlTypType::Base(base)) if base.extension.as_deref() == Some(name)),
+		);
+		if exists {
+			return if if_not_exists {
+				Ok(())
+			} else {
+				Err(format!("extension `{}` already exists", name))
+			};
+		}
+		// only the types of extensions which are mapped are known, other extensions are assumed to have no types
+		for &(extension, typ, _) in super::BUILTIN_EXTENSION_TYPES {
+			if extension == name {
+				let oid = self.new_oid();
+				let array_oid = self.new_oid();
+				self.schemas[schema].types.push(DdlType {
+					oid,
+					array_oid,
+					name: String::from(typ),
+					kind: DdlTypeKind::Fixed(PsqlTypType::Base(PsqlBaseType {
+						oid,
+						name: String::from(typ),
+						extension: Some(String::from(extension)),
+					})),
+				});
+			}
+		}
+		Ok(())
+	}
+
+	/// `CREATE FUNCTION` and `CREATE PROCEDURE`, sqlparser doesn't parse all of their arguments so only what comes before the
+	/// body is parsed here
+	fn create_function(&mut self, parser: &mut Parser) -> Result<(), String> {
+		let function = parse_function_header(parser).map_err(parse_error)?;
+		let (schema, name) = self.creation_schema(&function.name)?;
+		if matches!(self.schemas[schema].name.as_str(), "pg_catalog" | "information_schema") {
+			return Err(format!("functions can't be made in `{}`", self.schemas[schema].name));
+		}
+
+		let mut names = Vec::new();
+		let mut types = Vec::new();
+		for arg in &function.args {
+			names.push(arg.name.as_ref().map(ident).unwrap_or_default());
+			types.push(self.resolve_proc_type(&arg.data_type)?);
+		}
+		let mut table = Vec::new();
+		for (name, data_type) in &function.table {
+			names.push(ident(name));
+			table.push(self.resolve_proc_type(data_type)?);
+		}
+		// postgres only keeps names when at least one argument is named
+		if names.iter().all(String::is_empty) {
+			names.clear();
+		}
+		let names = arg_names_or_default(names, function.args.len() + table.len());
+
+		let mut inputs = Vec::new();
+		let mut signature = Vec::new();
+		let mut outputs = Vec::new();
+		for (i, arg) in function.args.iter().enumerate() {
+			if arg.mode != ArgMode::Out {
+				signature.push(types[i]);
+			}
+			match arg.mode {
+				ArgMode::In => inputs.push((names[i].clone(), types[i])),
+				ArgMode::Out | ArgMode::InOut => outputs.push(types[i]),
+				ArgMode::Variadic => (),
+			}
+		}
+		let record = self.catalog_type("record")?.oid;
+		let output = if !table.is_empty() {
+			None
+		} else if let Some(returns) = &function.returns {
+			Some(self.resolve_proc_type(returns)?)
+		} else if function.is_procedure {
+			Some(if outputs.is_empty() {
+				self.catalog_type("void")?.oid
+			} else {
+				record
+			})
+		} else {
+			match outputs.as_slice() {
+				[] => return Err(String::from("function result type must be specified")),
+				[output] => Some(*output),
+				_ => Some(record),
+			}
+		};
+		let table = names[function.args.len()..].iter().cloned().zip(table).collect();
+
+		let existing = self.schemas[schema]
+			.procs
+			.iter()
+			.position(|v| v.name == name && v.signature == signature);
+		let oid = match existing {
+			Some(i) if function.or_replace => self.schemas[schema].procs[i].oid,
+			Some(_) => return Err(format!("function `{}` already exists with same argument types", name)),
+			None => self.new_oid(),
+		};
+		let proc = DdlProc {
+			oid,
+			name,
+			returns_set: function.returns_set || output.is_none(),
+			num_args: signature.len() as i16,
+			signature,
+			inputs,
+			output,
+			table,
+		};
+		match existing {
+			Some(i) => self.schemas[schema].procs[i] = proc,
+			None => self.schemas[schema].procs.push(proc),
+		}
+		Ok(())
+	}
+
+	fn drop_function(&mut self, desc: &FunctionDesc, if_exists: bool) -> Result<(), String> {
+		let (schema, name) = match object_name(&desc.name)? {
+			(Some(schema), name) => match self.schema_index(&schema) {
+				Some(schema) => (Some(schema), name),
+				None if if_exists => return Ok(()),
+				None => return Err(format!("schema `{}` does not exist", schema)),
+			},
+			(None, name) => (None, name),
+		};
+		let signature = match &desc.args {
+			Some(args) => {
+				let mut signature = Vec::new();
+				for arg in args {
+					if arg.mode != Some(sqlparser::ast::ArgMode::Out) {
+						signature.push(self.resolve_proc_type(&arg.data_type)?);
+					}
+				}
+				Some(signature)
+			},
+			None => None,
+		};
+		let schemas: Vec<_> = match schema {
+			Some(schema) => vec![schema],
+			None => self.search_path.iter().filter_map(|v| self.schema_index(v)).collect(),
+		};
+		for schema in schemas {
+			let procs = &mut self.schemas[schema].procs;
+			let matching: Vec<_> = procs
+				.iter()
+				.enumerate()
+				.filter(|(_, v)| v.name == name && signature.as_ref().is_none_or(|s| *s == v.signature))
+				.map(|(i, _)| i)
+				.collect();
+			match matching.as_slice() {
+				[] => (),
+				[i] => {
+					procs.remove(*i);
+					return Ok(());
+				},
+				_ => return Err(format!("function name `{}` is not unique", name)),
+			}
+		}
+		if if_exists {
+			Ok(())
+		} else {
+			Err(format!("function `{}` does not exist", name))
+		}
+	}
+
+	fn drop(&mut self, name: &ObjectName, kind: ObjectKind, if_exists: bool) -> Result<(), String> {
+		if kind == ObjectKind::Schema {
+			let name = object_name(name)?.1;
+			return match self.schema_index(&name) {
+				Some(i) => {
+					self.schemas.remove(i);
+					Ok(())
+				},
+				None if if_exists => Ok(()),
+				None => Err(format!("schema `{}` does not exist", name)),
+			};
+		}
+		let (schema, index) = match self.find_type_index(name) {
+			Some(v) => v,
+			None if if_exists => return Ok(()),
+			None => return Err(format!("`{}` does not exist", object_name(name)?.1)),
+		};
+		let matches = match &self.schemas[schema].types[index].kind {
+			DdlTypeKind::Relation(relation) => match kind {
+				ObjectKind::Table => relation.kind == RelKind::Table,
+				ObjectKind::View => relation.kind == RelKind::View,
+				ObjectKind::MaterializedView => relation.kind == RelKind::MaterializedView,
+				_ => kind == ObjectKind::Type && relation.kind == RelKind::Type,
+			},
+			DdlTypeKind::Domain(_) => kind == ObjectKind::Domain,
+			_ => kind == ObjectKind::Type,
+		};
+		if !matches {
+			return Err(format!("`{}` is not a {}", object_name(name)?.1, kind.to_str()));
+		}
+		// anything using it is dropped when the tree is built
+		self.schemas[schema].types.remove(index);
+		Ok(())
+	}
+
+	/// `DROP DOMAIN` and `DROP MATERIALIZED VIEW`, which sqlparser doesn't parse
+	fn drop_manually(&mut self, parser: &mut Parser, kind: ObjectKind) -> Result<(), String> {
+		parser.expect_keyword(Keyword::DROP).map_err(parse_error)?;
+		match kind {
+			ObjectKind::Domain => parse_word(parser, "DOMAIN")?,
+			_ => parser
+				.expect_keywords(&[Keyword::MATERIALIZED, Keyword::VIEW])
+				.map_err(parse_error)?,
+		}
+		let if_exists = parser.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+		let names = parser
+			.parse_comma_separated(|p| p.parse_object_name(false))
+			.map_err(parse_error)?;
+		let _ = parser.parse_one_of_keywords(&[Keyword::CASCADE, Keyword::RESTRICT]);
+		expect_end(parser)?;
+		for name in &names {
+			self.drop(name, kind, if_exists)?;
+		}
+		Ok(())
+	}
+
+	fn schema_index(&self, name: &str) -> Option<usize> {
+		self.schemas.iter().position(|v| v.name == name)
+	}
+
+	fn first_search_path_schema(&self) -> Result<usize, String> {
+		self.search_path
+			.iter()
+			.find_map(|v| self.schema_index(v))
+			.ok_or_else(|| String::from("no schema has been selected to create in"))
+	}
+
+	/// The schema something is made in and its name
+	fn creation_schema(&self, name: &ObjectName) -> Result<(usize, String), String> {
+		match object_name(name)? {
+			(Some(schema), name) => self
+				.schema_index(&schema)
+				.map(|v| (v, name))
+				.ok_or_else(|| format!("schema `{}` does not exist", schema)),
+			(None, name) => Ok((self.first_search_path_schema()?, name)),
+		}
+	}
+
+	/// The schema and index of a type or relation, unqualified names are looked up in pg_catalog then the search path
+	fn find_type_index(&self, name: &ObjectName) -> Option<(usize, usize)> {
+		let (schema, name) = object_name(name).ok()?;
+		let schemas: Vec<_> = match schema {
+			Some(schema) => vec![self.schema_index(&schema)?],
+			None => std::iter::once("pg_catalog")
+				.chain(self.search_path.iter().map(String::as_str))
+				.filter_map(|v| self.schema_index(v))
+				.collect(),
+		};
+		schemas.into_iter().find_map(|schema| {
+			self.schemas[schema]
+				.types
+				.iter()
+				.position(|v| v.name == name)
+				.map(|i| (schema, i))
+		})
+	}
+
+	fn find_relation(&self, name: &ObjectName) -> Result<(u32, &DdlRelation), String> {
+		let (schema, index) = self
+			.find_type_index(name)
+			.ok_or_else(|| format!("relation `{}` does not exist", name))?;
+		let typ = &self.schemas[schema].types[index];
+		match &typ.kind {
+			DdlTypeKind::Relation(relation) if relation.kind.is_relation() => Ok((typ.oid, relation)),
+			_ => Err(format!("`{}` is not a table or view", name)),
+		}
+	}
+
+	fn type_by_oid(&self, oid: u32) -> Option<&DdlType> {
+		self.schemas.iter().flat_map(|v| &v.types).find(|v| v.oid == oid)
+	}
+
+	fn domain_not_null(&self, oid: u32) -> bool {
+		matches!(self.type_by_oid(oid), Some(DdlType { kind: DdlTypeKind::Domain(domain), .. }) if domain.not_null)
+	}
+
+	/// The type of a column of a relation, as it's used by a view selecting it
+	fn column_type(&self, col: &Column) -> TypeRef {
+		TypeRef {
+			oid: col.type_id,
+			dims: col.num_dimentions,
+			not_null: col.not_null,
+			serial: false,
+		}
+	}
+
+	fn catalog_type(&self, name: &str) -> Result<TypeRef, String> {
+		self.schemas
+			.iter()
+			.find(|v| v.oid == PG_CATALOG_OID)
+			.and_then(|v| v.types.iter().find(|v| v.name == name))
+			.map(|v| TypeRef {
+				oid: v.oid,
+				dims: 0,
+				not_null: false,
+				serial: false,
+			})
+			.ok_or_else(|| format!("type `{}` does not exist", name))
+	}
+
+	fn resolve_type(&self, data_type: &DataType) -> Result<TypeRef, String> {
+		match data_type {
+			DataType::Array(ArrayElemTypeDef::SquareBracket(inner, _)) => {
+				let mut typ = self.resolve_type(inner)?;
+				if typ.serial {
+					return Err(String::from("arrays of the serial types aren't allowed"));
+				}
+				// multidimensional arrays are the same type with more dimensions
+				if typ.dims == 0 {
+					let element = self.type_by_oid(typ.oid).filter(|v| v.array_oid != 0);
+					typ.oid = element
+						.map(|v| v.array_oid)
+						.ok_or_else(|| format!("type `{}` has no array type", inner))?;
+					typ.not_null = false;
+				}
+				typ.dims += 1;
+				Ok(typ)
+			},
+			DataType::Custom(name, _) => {
+				if let [part] = name.0.as_slice() {
+					let serial = SERIAL_TYPES
+						.iter()
+						.find(|v| part.quote_style.is_none() && part.value.eq_ignore_ascii_case(v.0));
+					if let Some((_, typ)) = serial {
+						return Ok(TypeRef {
+							serial: true,
+							not_null: true,
+							..self.catalog_type(typ)?
+						});
+					}
+				}
+				let (schema, index) = self
+					.find_type_index(name)
+					.ok_or_else(|| format!("type `{}` does not exist", name))?;
+				let typ = &self.schemas[schema].types[index];
+				Ok(TypeRef {
+					oid: typ.oid,
+					dims: 0,
+					not_null: matches!(&typ.kind, DdlTypeKind::Domain(domain) if domain.not_null),
+					serial: false,
+				})
+			},
+			_ => match builtin_type_name(data_type) {
+				Some(name) => self.catalog_type(name),
+				None => Err(format!("type `{}` isn't supported", data_type)),
+			},
+		}
+	}
+
+	/// The type of an argument or return value of a function, which can be a pseudo type
+	fn resolve_proc_type(&self, data_type: &DataType) -> Result<u32, String> {
+		let pseudo = match data_type {
+			DataType::Trigger => Some(String::from("trigger")),
+			DataType::Custom(name, _) if self.find_type_index(name).is_none() => match object_name(name)? {
+				(None, name) => Some(name),
+				(Some(schema), name) if schema == "pg_catalog" => Some(name),
+				_ => None,
+			},
+			_ => None,
+		};
+		if let Some(&(_, oid)) = pseudo.and_then(|name| PG_PSEUDO_TYPES.iter().find(|v| v.0 == name)) {
+			return Ok(oid);
+		}
+		let typ = self.resolve_type(data_type)?;
+		if typ.serial {
+			return Err(String::from("the serial types can only be used by tables"));
+		}
+		Ok(typ.oid)
+	}
+
+	/// Build the tree from everything that's been applied, along with the warnings
+	fn finish(self, no_functions: bool) -> (FullDB, Vec<String>) {
+		// the schema and name of every type by oid, including array types and pseudo types
+		let mut names = HashMap::new();
+		for schema in &self.schemas {
+			for typ in &schema.types {
+				names.insert(typ.oid, (schema.name.clone(), typ.name.clone()));
+				if typ.array_oid != 0 {
+					names.insert(typ.array_oid, (schema.name.clone(), format!("_{}", typ.name)));
+				}
+			}
+		}
+		for &(name, oid) in &PG_PSEUDO_TYPES {
+			names.insert(oid, (String::from("pg_catalog"), String::from(name)));
+		}
+		let relations: HashMap<_, _> = self
+			.schemas
+			.iter()
+			.flat_map(|schema| schema.types.iter().map(move |v| (schema, v)))
+			.filter_map(|(schema, v)| match &v.kind {
+				DdlTypeKind::Relation(relation) => Some((v.oid, (schema.name.as_str(), v.name.as_str(), relation))),
+				_ => None,
+			})
+			.collect();
+
+		let mut full_db = FullDB::new(Dialect::Postgres);
+		for schema in &self.schemas {
+			let mut types = Vec::new();
+			for typ in &schema.types {
+				let typ_typ = match &typ.kind {
+					DdlTypeKind::Fixed(v) => v.clone(),
+					DdlTypeKind::Enum(labels) => PsqlTypType::Enum(PsqlEnumType {
+						oid: typ.oid,
+						labels: labels.clone(),
+					}),
+					DdlTypeKind::Domain(domain) => {
+						// dropping the base type drops the domain
+						let (base_ns_name, base_name) = match names.get(&domain.base_oid) {
+							Some(v) => v.clone(),
+							None => continue,
+						};
+						PsqlTypType::Domain(PsqlDomain {
+							oid: typ.oid,
+							base_oid: domain.base_oid,
+							base_name,
+							base_ns_name,
+							not_null: domain.not_null,
+							default: domain.default.clone(),
+							checks: domain.checks.clone(),
+						})
+					},
+					DdlTypeKind::Relation(relation) => {
+						PsqlTypType::Composite(relation.to_composite(typ.oid, &names, &relations))
+					},
+				};
+				types.push(PsqlType {
+					name: typ.name.clone(),
+					ns: schema.oid,
+					typ: typ_typ,
+				});
+			}
+
+			let mut procs: Vec<Vec<SqlProc>> = Vec::new();
+			let skip_procs =
+				no_functions || matches!(schema.name.as_str(), "pg_catalog" | "information_schema" | "public");
+			for proc in schema.procs.iter().filter(|_| !skip_procs) {
+				let full_type = |oid: &u32| {
+					names.get(oid).map(|(schema, name)| FullType {
+						schema: schema.clone(),
+						name: name.clone(),
+					})
+				};
+				let names_and_types = |args: &[(String, u32)]| {
+					args.iter()
+						.map(|(name, oid)| {
+							full_type(oid).map(|typ| TypeAndName {
+								typ,
+								name: name.clone(),
+							})
+						})
+						.collect::<Option<Vec<_>>>()
+				};
+				// dropping a type drops the functions using it
+				let (inputs, table) = match (names_and_types(&proc.inputs), names_and_types(&proc.table)) {
+					(Some(inputs), Some(table)) if proc.signature.iter().all(|v| names.contains_key(v)) => {
+						(inputs, table)
+					},
+					_ => continue,
+				};
+				let outputs = match &proc.output {
+					Some(oid) => match full_type(oid) {
+						Some(v) => v,
+						None => continue,
+					},
+					None => {
+						types.push(PsqlType {
+							name: format!("{}Return", proc.name),
+							ns: schema.oid,
+							typ: PsqlTypType::SimpleComposite(NamesAndTypes(table)),
+						});
+						FullType {
+							schema: schema.name.clone(),
+							name: format!("{}Return", proc.name),
+						}
+					},
+				};
+				let proc = SqlProc {
+					ns: schema.oid,
+					ns_name: schema.name.clone(),
+					oid: proc.oid,
+					name: proc.name.clone(),
+					returns_set: proc.returns_set,
+					num_args: proc.num_args,
+					inputs: NamesAndTypes(inputs),
+					outputs,
+				};
+				match procs.iter_mut().find(|v| v[0].name == proc.name) {
+					Some(overloads) => overloads.push(proc),
+					None => procs.push(vec![proc]),
+				}
+			}
+			// types and functions are ordered by name, then the return types are added in the order of the functions
+			procs.sort_by(|a, b| a[0].name.cmp(&b[0].name));
+			let (mut return_types, mut types): (Vec<_>, Vec<_>) = types
+				.into_iter()
+				.partition(|v| matches!(v.typ, PsqlTypType::SimpleComposite(_)));
+			types.sort_by(|a, b| a.name.cmp(&b.name));
+			return_types.sort_by_key(|v| procs.iter().position(|p| format!("{}Return", p[0].name) == v.name));
+			types.append(&mut return_types);
+
+			for overloads in &mut procs {
+				sort_overloads(overloads);
+			}
+			full_db.add_schema(Schema {
+				id: schema.oid,
+				name: schema.name.clone(),
+				owner_name: schema.owner.clone(),
+				types,
+				procs,
+			});
+		}
+		full_db.schemas.sort_by(|a, b| a.name.cmp(&b.name));
+		(full_db, self.warnings)
+	}
+}
+
+impl DdlRelation {
+	fn new(kind: RelKind) -> DdlRelation {
+		DdlRelation {
+			kind,
+			cols: Vec::new(),
+			next_attnum: 1,
+			primary_key: None,
+			foreign_keys: Vec::new(),
+		}
+	}
+
+	fn position(&self, name: &Ident) -> Result<i16, String> {
+		let name = ident(name);
+		self.cols
+			.iter()
+			.find(|v| v.name == name)
+			.map(|v| v.pos)
+			.ok_or_else(|| format!("column `{}` does not exist", name))
+	}
+
+	fn positions(&self, names: &[Ident]) -> Result<Vec<i16>, String> {
+		names.iter().map(|v| self.position(v)).collect()
+	}
+
+	fn names(&self, positions: &[i16]) -> Vec<String> {
+		positions
+			.iter()
+			.filter_map(|pos| self.cols.iter().find(|v| v.pos == *pos))
+			.map(|v| v.name.clone())
+			.collect()
+	}
+
+	fn has_constraint(&self, name: &str) -> bool {
+		self.primary_key.as_ref().is_some_and(|v| v.0 == name) || self.foreign_keys.iter().any(|v| v.name == name)
+	}
+
+	/// The composite type of the tree, columns whose type was dropped and foreign keys to dropped tables are left out
+	fn to_composite(
+		&self,
+		oid: u32,
+		names: &HashMap<u32, (String, String)>,
+		relations: &HashMap<u32, (&str, &str, &DdlRelation)>,
+	) -> PsqlCompositeType {
+		let cols: Vec<_> = self
+			.cols
+			.iter()
+			.filter_map(|col| {
+				let (type_ns_name, type_name) = names.get(&col.type_id)?.clone();
+				Some(Column {
+					type_name,
+					type_ns_name,
+					..col.clone()
+				})
+			})
+			.collect();
+		let has_all = |relation: &DdlRelation, positions: &[i16]| relation.names(positions).len() == positions.len();
+		let primary_key = match &self.primary_key {
+			Some((_, positions)) if has_all(self, positions) => self.names(positions),
+			_ => Vec::new(),
+		};
+		let mut foreign_keys: Vec<_> = self
+			.foreign_keys
+			.iter()
+			.filter_map(|fk| {
+				let &(ref_schema, ref_table, ref_relation) = relations.get(&fk.ref_oid)?;
+				if !has_all(self, &fk.cols) || !has_all(ref_relation, &fk.ref_cols) {
+					return None;
+				}
+				Some(ForeignKey {
+					name: fk.name.clone(),
+					cols: self.names(&fk.cols),
+					ref_schema: String::from(ref_schema),
+					ref_table: String::from(ref_table),
+					ref_cols: ref_relation.names(&fk.ref_cols),
+				})
+			})
+			.collect();
+		foreign_keys.sort_by(|a, b| a.name.cmp(&b.name));
+		PsqlCompositeType {
+			oid,
+			kind: self.kind,
+			cols,
+			primary_key,
+			foreign_keys,
+		}
+	}
+}
+
+impl Clone for DdlRelation {
+	fn clone(&self) -> DdlRelation {
+		DdlRelation {
+			kind: self.kind,
+			cols: self.cols.clone(),
+			next_attnum: self.next_attnum,
+			primary_key: self.primary_key.clone(),
+			foreign_keys: self
+				.foreign_keys
+				.iter()
+				.map(|v| DdlForeignKey {
+					name: v.name.clone(),
+					cols: v.cols.clone(),
+					ref_oid: v.ref_oid,
+					ref_cols: v.ref_cols.clone(),
+				})
+				.collect(),
+		}
+	}
+}
+
+/// The kinds of objects `DROP` is handled for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ObjectKind {
+	Schema,
+	Table,
+	View,
+	MaterializedView,
+	Type,
+	Domain,
+}
+impl ObjectKind {
+	fn to_str(self) -> &'static str {
+		match self {
+			ObjectKind::Schema => "schema",
+			ObjectKind::Table => "table",
+			ObjectKind::View => "view",
+			ObjectKind::MaterializedView => "materialized view",
+			ObjectKind::Type => "type",
+			ObjectKind::Domain => "domain",
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArgMode {
+	In,
+	Out,
+	InOut,
+	Variadic,
+}
+
+struct FunctionArgDef {
+	mode: ArgMode,
+	name: Option<Ident>,
+	data_type: DataType,
+}
+
+/// What comes before the body of a `CREATE FUNCTION` or `CREATE PROCEDURE`
+struct FunctionHeader {
+	or_replace: bool,
+	is_procedure: bool,
+	name: ObjectName,
+	args: Vec<FunctionArgDef>,
+	returns: Option<DataType>,
+	returns_set: bool,
+	/// The columns of `RETURNS TABLE`
+	table: Vec<(Ident, DataType)>,
+}
+
+fn parse_function_header(parser: &mut Parser) -> Result<FunctionHeader, ParserError> {
+	parser.expect_keyword(Keyword::CREATE)?;
+	let or_replace = parser.parse_keywords(&[Keyword::OR, Keyword::REPLACE]);
+	let is_procedure = parser.parse_keyword(Keyword::PROCEDURE);
+	if !is_procedure {
+		parser.expect_keyword(Keyword::FUNCTION)?;
+	}
+	let name = parser.parse_object_name(false)?;
+	parser.expect_token(&Token::LParen)?;
+	let mut args = Vec::new();
+	if !parser.consume_token(&Token::RParen) {
+		loop {
+			args.push(parse_function_arg(parser)?);
+			if parser.consume_token(&Token::RParen) {
+				break;
+			}
+			parser.expect_token(&Token::Comma)?;
+		}
+	}
+	let mut header = FunctionHeader {
+		or_replace,
+		is_procedure,
+		name,
+		args,
+		returns: None,
+		returns_set: false,
+		table: Vec::new(),
+	};
+	if parser.parse_keyword(Keyword::RETURNS) {
+		if parser.parse_keyword(Keyword::TABLE) {
+			parser.expect_token(&Token::LParen)?;
+			header.table = parser.parse_comma_separated(|p| Ok((p.parse_identifier(false)?, p.parse_data_type()?)))?;
+			parser.expect_token(&Token::RParen)?;
+		} else {
+			header.returns_set = is_word(&parser.peek_token().token, "SETOF");
+			if header.returns_set {
+				parser.next_token();
+			}
+			header.returns = Some(parser.parse_data_type()?);
+		}
+	}
+	// the rest of the statement is the language, body, and attributes which don't change the mapping
+	Ok(header)
+}
+
+fn parse_function_arg(parser: &mut Parser) -> Result<FunctionArgDef, ParserError> {
+	let mode = match parser.peek_token().token {
+		Token::Word(w) if w.quote_style.is_none() => match w.value.to_uppercase().as_str() {
+			"IN" => Some(ArgMode::In),
+			"OUT" => Some(ArgMode::Out),
+			"INOUT" => Some(ArgMode::InOut),
+			"VARIADIC" => Some(ArgMode::Variadic),
+			_ => None,
+		},
+		_ => None,
+	};
+	if mode.is_some() {
+		parser.next_token();
+	}
+	// the name is optional, there isn't one if the type is followed by the end of the argument
+	let unnamed = parser.maybe_parse(|p| {
+		let data_type = p.parse_data_type()?;
+		match p.peek_token().token {
+			Token::Comma | Token::RParen | Token::Eq => Ok(data_type),
+			Token::Word(w) if w.keyword == Keyword::DEFAULT => Ok(data_type),
+			_ => p.expected("the end of the argument", p.peek_token()),
+		}
+	})?;
+	let (name, data_type) = match unnamed {
+		Some(data_type) => (None, data_type),
+		None => (Some(parser.parse_identifier(false)?), parser.parse_data_type()?),
+	};
+	if parser.parse_keyword(Keyword::DEFAULT) || parser.consume_token(&Token::Eq) {
+		parser.parse_expr()?;
+	}
+	Ok(FunctionArgDef {
+		mode: mode.unwrap_or(ArgMode::In),
+		name,
+		data_type,
+	})
+}
+
+/// Split the tokens of a file into statements, without the whitespace and comments before them
+fn split_statements(tokens: Vec<TokenWithSpan>) -> Vec<Vec<TokenWithSpan>> {
+	let mut statements = Vec::new();
+	let mut current = Vec::new();
+	for token in tokens {
+		match token.token {
+			Token::SemiColon => statements.push(std::mem::take(&mut current)),
+			Token::Whitespace(_) if current.is_empty() => (),
+			_ => current.push(token),
+		}
+	}
+	statements.push(current);
+	statements.retain(|v| !v.is_empty());
+	statements
+}
+
+/// The first few unquoted words of a statement in upper case, which tell what kind of statement it is
+fn leading_words(tokens: &[TokenWithSpan]) -> Vec<String> {
+	tokens
+		.iter()
+		.filter(|v| !matches!(v.token, Token::Whitespace(_)))
+		.take(4)
+		.map_while(|v| match &v.token {
+			Token::Word(w) if w.quote_style.is_none() => Some(w.value.to_uppercase()),
+			_ => None,
+		})
+		.collect()
+}
+
+fn starts_with_words(words: &[&str], prefix: &str) -> bool {
+	let prefix: Vec<_> = prefix.split(' ').collect();
+	words.len() >= prefix.len() && words[..prefix.len()] == prefix[..]
+}
+
+fn is_word(token: &Token, word: &str) -> bool {
+	matches!(token, Token::Word(w) if w.quote_style.is_none() && w.value.eq_ignore_ascii_case(word))
+}
+
+/// Expect a word sqlparser doesn't have a keyword for
+fn parse_word(parser: &mut Parser, word: &str) -> Result<(), String> {
+	if is_word(&parser.peek_token().token, word) {
+		parser.next_token();
+		Ok(())
+	} else {
+		Err(parse_error(
+			parser.expected::<()>(word, parser.peek_token()).unwrap_err(),
+		))
+	}
+}
+
+/// Whether a `CREATE TYPE` is of a range type, which sqlparser reads as an empty composite type
+fn is_range_type(parser: &Parser) -> bool {
+	let mut i = 0;
+	loop {
+		match parser.peek_nth_token(i).token {
+			Token::EOF => return false,
+			Token::Word(w) if w.keyword == Keyword::AS => return is_word(&parser.peek_nth_token(i + 1).token, "RANGE"),
+			_ => i += 1,
+		}
+	}
+}
+
+fn expect_end(parser: &mut Parser) -> Result<(), String> {
+	match parser.peek_token().token {
+		Token::EOF => Ok(()),
+		token => Err(format!("unexpected `{}` after the end of the statement", token)),
+	}
+}
+
+fn parse_error(e: ParserError) -> String {
+	format!("couldn't parse the statement: {}", e)
+}
+
+/// A name as postgres keeps it, folded to lower case unless it's quoted
+fn ident(ident: &Ident) -> String {
+	match ident.quote_style {
+		Some('"') => ident.value.clone(),
+		_ => ident.value.to_lowercase(),
+	}
+}
+
+/// The schema (if it's qualified) and name of an object, a database before the schema is ignored
+fn object_name(name: &ObjectName) -> Result<(Option<String>, String), String> {
+	match name.0.as_slice() {
+		[object] => Ok((None, ident(object))),
+		[schema, object] | [_, schema, object] => Ok((Some(ident(schema)), ident(object))),
+		_ => Err(format!("improper qualified name `{}`", name)),
+	}
+}
+
+fn search_path_entry(expr: &Expr) -> Option<String> {
+	match expr {
+		Expr::Identifier(v) => Some(ident(v)),
+		Expr::Value(Value::SingleQuotedString(v)) => Some(v.clone()),
+		_ => None,
+	}
+}
+
+/// The name postgres gives a column of a query without an alias
+fn expr_column_name(expr: &Expr) -> String {
+	match expr {
+		Expr::Identifier(v) => ident(v),
+		Expr::CompoundIdentifier(v) => v.last().map(ident).unwrap_or_default(),
+		Expr::Nested(expr) | Expr::Cast { expr, .. } => expr_column_name(expr),
+		Expr::Function(function) => function.name.0.last().map(ident).unwrap_or_default(),
+		_ => String::from("?column?"),
+	}
+}
+
+fn new_column(pos: i16, name: String, typ: TypeRef) -> Column {
+	Column {
+		pos,
+		name,
+		type_id: typ.oid,
+		type_name: String::new(),
+		type_ns_name: String::new(),
+		not_null: typ.not_null,
+		num_dimentions: typ.dims,
+		has_default: typ.serial,
+	}
+}
+
+/// The name of a built in type sqlparser has its own variant for
+fn builtin_type_name(data_type: &DataType) -> Option<&'static str> {
+	let with_time_zone = |tz: &TimezoneInfo| matches!(tz, TimezoneInfo::WithTimeZone | TimezoneInfo::Tz);
+	Some(match data_type {
+		DataType::Bool | DataType::Boolean => "bool",
+		DataType::SmallInt(_) | DataType::Int2(_) => "int2",
+		DataType::Int(_) | DataType::Integer(_) | DataType::Int4(_) => "int4",
+		DataType::BigInt(_) | DataType::Int8(_) => "int8",
+		DataType::Real | DataType::Float4 => "float4",
+		DataType::Float(Some(precision)) if *precision <= 24 => "float4",
+		DataType::Float(_) | DataType::Float8 | DataType::Double | DataType::DoublePrecision => "float8",
+		DataType::Numeric(_) | DataType::Decimal(_) | DataType::Dec(_) => "numeric",
+		DataType::Text => "text",
+		DataType::Varchar(_) | DataType::CharacterVarying(_) | DataType::CharVarying(_) => "varchar",
+		DataType::Char(_) | DataType::Character(_) => "bpchar",
+		DataType::Bytea => "bytea",
+		DataType::Uuid => "uuid",
+		DataType::JSON => "json",
+		DataType::JSONB => "jsonb",
+		DataType::Date => "date",
+		DataType::Time(_, tz) if with_time_zone(tz) => "timetz",
+		DataType::Time(..) => "time",
+		DataType::Timestamp(_, tz) if with_time_zone(tz) => "timestamptz",
+		DataType::Timestamp(..) => "timestamp",
+		DataType::Interval => "interval",
+		DataType::Bit(_) => "bit",
+		DataType::BitVarying(_) => "varbit",
+		DataType::Regclass => "regclass",
+		_ => return None,
+	})
+}
+
+/// Postgres's name for a constraint, the names are shortened to fit in [`MAX_NAME_LEN`] with the longer one shortened first
+fn make_object_name(name1: &str, name2: Option<&str>, label: &str) -> String {
+	let mut name1_len = name1.chars().count();
+	let mut name2_len = name2.map_or(0, |v| v.chars().count());
+	let overhead = label.len() + 1 + if name2.is_some() { 1 } else { 0 };
+	while name1_len + name2_len + overhead > MAX_NAME_LEN {
+		if name1_len > name2_len {
+			name1_len -= 1;
+		} else {
+			name2_len -= 1;
+		}
+	}
+	let mut name: String = name1.chars().take(name1_len).collect();
+	if let Some(name2) = name2 {
+		name.push('_');
+		name.extend(name2.chars().take(name2_len));
+	}
+	name + "_" + label
+}
+
+/// [`make_object_name`] with a number after the label if the name is taken, i.e. `color_check1`
+fn choose_name(name1: &str, name2: Option<&str>, label: &str, is_taken: impl Fn(&str) -> bool) -> String {
+	let mut name = make_object_name(name1, name2, label);
+	let mut i = 0;
+	while is_taken(&name) {
+		i += 1;
+		name = make_object_name(name1, name2, &format!("{}{}", label, i));
+	}
+	name
+}
+
+/// The casts postgres adds when it stores an expression of a domain, which depend on the domain's base type
+#[derive(Debug, Clone, Copy)]
+struct ExprCasts {
+	/// The type number literals are cast to, and whether ones with a decimal point are cast too
+	number: Option<(&'static str, bool)>,
+	/// VALUE is cast to text, for domains over varchar
+	value_to_text: bool,
+	/// The type string literals are written as
+	string: &'static str,
+}
+impl ExprCasts {
+	/// The casts of a CHECK of a domain over `base`
+	fn check(base: &str) -> ExprCasts {
+		ExprCasts {
+			number: match base {
+				"float4" | "float8" => Some(("double precision", true)),
+				"numeric" => Some(("numeric", false)),
+				_ => None,
+			},
+			value_to_text: base == "varchar",
+			string: "text",
+		}
+	}
+
+	/// The casts of the DEFAULT of a domain over `base`, only string literals are written with a type
+	fn default(base: &str) -> ExprCasts {
+		ExprCasts {
+			number: None,
+			value_to_text: false,
+			string: if base == "varchar" { "character varying" } else { "text" },
+		}
+	}
+
+	/// An expression written the way postgres prints the ones it stores (i.e. `BETWEEN` as two comparisons), which is
+	/// the only form the defaults and checks of domains are translated from
+	fn pg_expr(self, expr: &Expr) -> String {
+		match expr {
+			Expr::Identifier(v) if is_value(v) && self.value_to_text => String::from("(VALUE)::text"),
+			Expr::Identifier(v) if is_value(v) => String::from("VALUE"),
+			Expr::Identifier(v) => ident(v),
+			Expr::CompoundIdentifier(v) => v.iter().map(ident).collect::<Vec<_>>().join("."),
+			Expr::Value(Value::SingleQuotedString(v)) => format!("'{}'::{}", v.replace('\'', "''"), self.string),
+			Expr::Value(Value::Number(v, _)) => match self.number {
+				Some((typ, decimals)) if decimals || !v.contains(['.', 'e', 'E']) => format!("({})::{}", v, typ),
+				_ => v.clone(),
+			},
+			Expr::Nested(expr) => self.pg_expr(expr),
+			Expr::BinaryOp { left, op, right } => format!("({} {} {})", self.pg_expr(left), op, self.pg_expr(right)),
+			Expr::UnaryOp {
+				op: UnaryOperator::Not,
+				expr,
+			} => format!("(NOT {})", self.pg_expr(expr)),
+			Expr::Between {
+				expr,
+				negated: false,
+				low,
+				high,
+			} => format!(
+				"(({0} >= {1}) AND ({0} <= {2}))",
+				self.pg_expr(expr),
+				self.pg_expr(low),
+				self.pg_expr(high)
+			),
+			Expr::Between {
+				expr,
+				negated: true,
+				low,
+				high,
+			} => format!(
+				"(({0} < {1}) OR ({0} > {2}))",
+				self.pg_expr(expr),
+				self.pg_expr(low),
+				self.pg_expr(high)
+			),
+			// postgres turns an IN of one value into a comparison
+			Expr::InList { expr, list, negated } if list.len() == 1 => format!(
+				"({} {} {})",
+				self.pg_expr(expr),
+				if *negated { "<>" } else { "=" },
+				self.pg_expr(&list[0])
+			),
+			// the array of an IN on varchar is made of varchars then cast to text[]
+			Expr::InList { expr, list, negated } if self.value_to_text => format!(
+				"({} {} ((ARRAY[{}])::text[]))",
+				self.pg_expr(expr),
+				if *negated { "<> ALL" } else { "= ANY" },
+				list.iter()
+					.map(|v| ExprCasts::default("varchar").pg_expr(v))
+					.collect::<Vec<_>>()
+					.join(", ")
+			),
+			Expr::InList { expr, list, negated } => format!(
+				"({} {} (ARRAY[{}]))",
+				self.pg_expr(expr),
+				if *negated { "<> ALL" } else { "= ANY" },
+				list.iter().map(|v| self.pg_expr(v)).collect::<Vec<_>>().join(", ")
+			),
+			// IS NULL takes any type so VALUE isn't cast
+			Expr::IsNull(expr) => format!("({} IS NULL)", self.uncast_value().pg_expr(expr)),
+			Expr::IsNotNull(expr) => format!("({} IS NOT NULL)", self.uncast_value().pg_expr(expr)),
+			Expr::Like {
+				negated, expr, pattern, ..
+			} => format!(
+				"({} {} {})",
+				self.pg_expr(expr),
+				if *negated { "!~~" } else { "~~" },
+				self.pg_expr(pattern)
+			),
+			Expr::Cast { expr, data_type, .. } => {
+				format!("({})::{}", self.pg_expr(expr), data_type.to_string().to_lowercase())
+			},
+			Expr::Function(function) => match &function.args {
+				FunctionArguments::List(list) if list.clauses.is_empty() && list.duplicate_treatment.is_none() => {
+					let args: Option<Vec<_>> = list
+						.args
+						.iter()
+						.map(|v| match v {
+							FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Some(self.pg_expr(expr)),
+							_ => None,
+						})
+						.collect();
+					match args {
+						Some(args) => format!("{}({})", function.name, args.join(", ")),
+						None => function.to_string(),
+					}
+				},
+				_ => function.to_string(),
+			},
+			_ => expr.to_string(),
+		}
+	}
+
+	fn uncast_value(self) -> ExprCasts {
+		ExprCasts {
+			value_to_text: false,
+			..self
+		}
+	}
+}
+
+fn is_value(ident: &Ident) -> bool {
+	ident.quote_style.is_none() && ident.value.eq_ignore_ascii_case("value")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::connection::MyClient;
+	use postgres::{Client, NoTls};
+	use serde_json::Value as Json;
+
+	fn read(sql: &str) -> (FullDB, Vec<String>) {
+		let mut db = DdlDb::new();
+		db.apply(sql, "test.sql").unwrap();
+		db.finish(false)
+	}
+
+	fn find_type<'a>(full_db: &'a FullDB, schema: &str, name: &str) -> Option<&'a PsqlTypType> {
+		let schema = full_db.schemas.iter().find(|v| v.name == schema)?;
+		schema.types.iter().find(|v| v.name == name).map(|v| &v.typ)
+	}
+
+	fn table<'a>(full_db: &'a FullDB, name: &str) -> &'a PsqlCompositeType {
+		match find_type(full_db, "public", name) {
+			Some(PsqlTypType::Composite(v)) => v,
+			v => panic!("`{}` isn't a table: {:?}", name, v),
+		}
+	}
+
+	fn domain<'a>(full_db: &'a FullDB, name: &str) -> &'a PsqlDomain {
+		match find_type(full_db, "public", name) {
+			Some(PsqlTypType::Domain(v)) => v,
+			v => panic!("`{}` isn't a domain: {:?}", name, v),
+		}
+	}
+
+	fn columns(table: &PsqlCompositeType) -> Vec<(&str, &str, bool)> {
+		table
+			.cols
+			.iter()
+			.map(|v| (v.name.as_str(), v.type_name.as_str(), v.not_null))
+			.collect()
+	}
+
+	#[test]
+	fn alter_table() {
+		let (full_db, warnings) = read(
+			"CREATE TABLE t(a int PRIMARY KEY, b text);
+			ALTER TABLE t ADD COLUMN c bool NOT NULL, DROP COLUMN b;
+			ALTER TABLE t RENAME COLUMN a TO id;
+			ALTER TABLE t ALTER COLUMN c DROP NOT NULL, ALTER COLUMN c TYPE int8;
+			ALTER TABLE t RENAME TO things;",
+		);
+		assert_eq!(warnings, Vec::<String>::new());
+		assert!(find_type(&full_db, "public", "t").is_none());
+		let things = table(&full_db, "things");
+		assert_eq!(columns(things), [("id", "int4", true), ("c", "int8", false)]);
+		assert_eq!(things.cols.iter().map(|v| v.pos).collect::<Vec<_>>(), [1, 3]);
+		assert_eq!(things.primary_key, ["id"]);
+	}
+
+	#[test]
+	fn failed_alter_table_changes_nothing() {
+		let (full_db, warnings) = read(
+			"CREATE TABLE t(a int);
+			ALTER TABLE t ADD COLUMN b text, DROP COLUMN missing;",
+		);
+		assert_eq!(warnings.len(), 1);
+		assert_eq!(columns(table(&full_db, "t")), [("a", "int4", false)]);
+	}
+
+	#[test]
+	fn view_columns() {
+		let (full_db, warnings) = read(
+			"CREATE TABLE t(a int NOT NULL, b text);
+			CREATE VIEW v AS SELECT a, b::varchar AS c, 1 AS d FROM t;
+			CREATE VIEW named(x, y) AS SELECT a, b FROM t;",
+		);
+		assert_eq!(warnings, Vec::<String>::new());
+		let v = table(&full_db, "v");
+		assert_eq!(v.kind, RelKind::View);
+		// views don't keep NOT NULL
+		assert_eq!(
+			columns(v),
+			[("a", "int4", false), ("c", "varchar", false), ("d", "int4", false)]
+		);
+		assert_eq!(
+			columns(table(&full_db, "named")),
+			[("x", "int4", false), ("y", "text", false)]
+		);
+	}
+
+	#[test]
+	fn domains() {
+		let (full_db, warnings) = read(
+			"CREATE DOMAIN positive AS int NOT NULL DEFAULT 1 CHECK (VALUE > 0);
+			CREATE DOMAIN two_checks AS int CHECK (VALUE > 0) CHECK (VALUE < 10);
+			CREATE TABLE t(a positive, b two_checks);",
+		);
+		assert_eq!(warnings, Vec::<String>::new());
+		let positive = domain(&full_db, "positive");
+		assert_eq!(positive.base_name, "int4");
+		assert!(positive.not_null);
+		assert_eq!(positive.default.as_deref(), Some("1"));
+		assert_eq!(
+			positive.checks,
+			[DomainCheck {
+				name: String::from("positive_check"),
+				expr: String::from("(VALUE > 0)"),
+			}]
+		);
+		// the second unnamed check is given the next free name
+		let names: Vec<_> = domain(&full_db, "two_checks").checks.iter().map(|v| v.name.as_str()).collect();
+		assert_eq!(names, ["two_checks_check", "two_checks_check1"]);
+		// columns of a NOT NULL domain are NOT NULL
+		assert_eq!(
+			columns(table(&full_db, "t")),
+			[("a", "positive", true), ("b", "two_checks", false)]
+		);
+	}
+
+	#[test]
+	fn functions() {
+		let (full_db, warnings) = read(
+			"CREATE SCHEMA s;
+			CREATE FUNCTION s.pairs(n int) RETURNS TABLE(a int, b text) LANGUAGE sql AS $$ SELECT 1, '' $$;
+			CREATE FUNCTION s.one(n int, m text DEFAULT '') RETURNS int LANGUAGE sql AS $$ SELECT 1 $$;
+			CREATE FUNCTION s.one(n text) RETURNS SETOF int LANGUAGE sql AS $$ SELECT 1 $$;",
+		);
+		assert_eq!(warnings, Vec::<String>::new());
+		let s = full_db.schemas.iter().find(|v| v.name == "s").unwrap();
+		let procs: Vec<_> = s.procs.iter().map(|v| (v[0].name.as_str(), v.len())).collect();
+		assert_eq!(procs, [("one", 2), ("pairs", 1)]);
+
+		let one = &s.procs[0];
+		assert_eq!(one[0].num_args, 2);
+		assert_eq!(one[0].outputs.name, "int4");
+		assert!(!one[0].returns_set);
+		assert!(one[1].returns_set);
+
+		let pairs = &s.procs[1][0];
+		assert!(pairs.returns_set);
+		assert_eq!(pairs.outputs.name, "pairsReturn");
+		let returns = match find_type(&full_db, "s", "pairsReturn") {
+			Some(PsqlTypType::SimpleComposite(v)) => v,
+			v => panic!("{:?}", v),
+		};
+		let returns: Vec<_> = returns.0.iter().map(|v| (v.name.as_str(), v.typ.name.as_str())).collect();
+		assert_eq!(returns, [("a", "int4"), ("b", "text")]);
+	}
+
+	#[test]
+	fn overloads_are_ordered_by_argument_types() {
+		let (full_db, warnings) = read(ORDER_SQL);
+		assert_eq!(warnings, Vec::<String>::new());
+		assert_eq!(names_in_order(&full_db), order_expected());
+		let f = &full_db.schemas.iter().find(|v| v.name == "Sort_B").unwrap().procs[0];
+		assert_eq!(oldest_overload(f).unwrap().inputs.0[0].typ.name, "text");
+	}
+
+	#[test]
+	fn drop() {
+		let (full_db, warnings) = read(
+			"CREATE SCHEMA s;
+			CREATE TYPE s.e AS ENUM ('a');
+			CREATE TABLE s.t(a int);
+			CREATE TABLE s.kept(a int);
+			CREATE FUNCTION s.f(e s.e) RETURNS int LANGUAGE sql AS $$ SELECT 1 $$;
+			CREATE FUNCTION s.g() RETURNS int LANGUAGE sql AS $$ SELECT 1 $$;
+			DROP TABLE s.t;
+			DROP TABLE IF EXISTS s.missing;
+			DROP TYPE s.e CASCADE;
+			DROP FUNCTION s.g();",
+		);
+		assert_eq!(warnings, Vec::<String>::new());
+		let s = full_db.schemas.iter().find(|v| v.name == "s").unwrap();
+		let types: Vec<_> = s.types.iter().map(|v| v.name.as_str()).collect();
+		assert_eq!(types, ["kept"]);
+		// f is dropped with the type of its argument
+		assert!(s.procs.is_empty());
+
+		let (full_db, _) = read("CREATE SCHEMA s; DROP SCHEMA s;");
+		assert!(full_db.schemas.iter().all(|v| v.name != "s"));
+	}
+
+	#[test]
+	fn constraint_names() {
+		let (full_db, warnings) = read(
+			"CREATE TABLE a(x int, y int, PRIMARY KEY (x, y));
+			CREATE TABLE b(
+				x int, y int, z int REFERENCES b,
+				FOREIGN KEY (x, y) REFERENCES a(x, y),
+				FOREIGN KEY (x, y) REFERENCES a(x, y),
+				CONSTRAINT named FOREIGN KEY (x, y) REFERENCES a(x, y),
+				PRIMARY KEY (z)
+			);
+			ALTER TABLE a DROP CONSTRAINT a_pkey;",
+		);
+		assert_eq!(warnings, Vec::<String>::new());
+		assert!(table(&full_db, "a").primary_key.is_empty());
+		let b = table(&full_db, "b");
+		assert_eq!(b.primary_key, ["z"]);
+		let fks: Vec<_> = b
+			.foreign_keys
+			.iter()
+			.map(|v| (v.name.as_str(), v.ref_table.as_str(), v.ref_cols.join(",")))
+			.collect();
+		assert_eq!(
+			fks,
+			[
+				("b_x_y_fkey", "a", String::from("x,y")),
+				("b_x_y_fkey1", "a", String::from("x,y")),
+				("b_z_fkey", "b", String::from("z")),
+				("named", "a", String::from("x,y")),
+			]
+		);
+	}
+
+	#[test]
+	fn long_constraint_names_are_shortened() {
+		let name = make_object_name(&"t".repeat(40), Some(&"c".repeat(40)), "fkey");
+		assert_eq!(name.len(), MAX_NAME_LEN);
+		assert_eq!(name, format!("{}_{}_fkey", "t".repeat(29), "c".repeat(28)));
+	}
+
+	#[test]
+	fn warnings() {
+		let (full_db, warnings) = read(
+			"CREATE TABLE t(a int);
+			GRANT ALL ON t TO PUBLIC;
+			CREATE TRIGGER tr AFTER INSERT ON t FOR EACH ROW EXECUTE FUNCTION f();
+			CREATE AGGREGATE total(int) (SFUNC = int4pl, STYPE = int);
+			CREATE TABLE t(b int);
+			ALTER TABLE missing ADD COLUMN b int;
+			CREATE TABLE u(a int);",
+		);
+		// skipped statements don't warn and the statements after the ones that failed are still read
+		assert_eq!(warnings.len(), 3, "{:?}", warnings);
+		assert!(warnings[0].starts_with("test.sql:4: "), "{}", warnings[0]);
+		assert_eq!(warnings[1], "test.sql:5: type `t` already exists");
+		assert_eq!(warnings[2], "test.sql:6: relation `missing` does not exist");
+		assert_eq!(columns(table(&full_db, "t")), [("a", "int4", false)]);
+		assert!(find_type(&full_db, "public", "u").is_some());
+	}
+
+	/// Names that sort differently by byte than in most locales, and overloads made out of the order of their types
+	const ORDER_SQL: &str = "CREATE SCHEMA sort_a;
+		CREATE SCHEMA \"Sort_B\";
+		CREATE TABLE sort_a.b(a int);
+		CREATE TABLE sort_a.\"A\"(a int);
+		CREATE FUNCTION \"Sort_B\".f(a text) RETURNS int LANGUAGE sql AS $$ SELECT 1 $$;
+		CREATE FUNCTION \"Sort_B\".f(a bool) RETURNS int LANGUAGE sql AS $$ SELECT 1 $$;
+		CREATE FUNCTION \"Sort_B\".f(a int) RETURNS int LANGUAGE sql AS $$ SELECT 1 $$;";
+
+	fn order_expected() -> Vec<String> {
+		["Sort_B.f(bool)", "Sort_B.f(int4)", "Sort_B.f(text)", "sort_a.A", "sort_a.b"]
+			.map(String::from)
+			.to_vec()
+	}
+
+	/// The types and overloads of the schemas made by `ORDER_SQL` in the order they were read
+	fn names_in_order(full_db: &FullDB) -> Vec<String> {
+		let mut names = Vec::new();
+		for schema in full_db.schemas.iter().filter(|v| v.name.starts_with("sort_") || v.name.starts_with("Sort_")) {
+			for typ in &schema.types {
+				names.push(format!("{}.{}", schema.name, typ.name));
+			}
+			for proc in schema.procs.iter().flatten() {
+				let inputs: Vec<_> = proc.inputs.0.iter().map(|v| v.typ.name.as_str()).collect();
+				names.push(format!("{}.{}({})", schema.name, proc.name, inputs.join(", ")));
+			}
+		}
+		names
+	}
+
+	/// Null the oids, which are given out in a different order than postgres's, and keep the schemas the SQL made
+	fn without_oids(full_db: &FullDB, schemas: &[&str]) -> Json {
+		fn strip(value: &mut Json) {
+			match value {
+				Json::Object(map) => {
+					for (key, value) in map.iter_mut() {
+						match key.as_str() {
+							"id" | "ns" | "oid" | "type_id" | "base_oid" | "Other" => *value = Json::Null,
+							_ => strip(value),
+						}
+					}
+				},
+				Json::Array(values) => values.iter_mut().for_each(strip),
+				_ => (),
+			}
+		}
+		let schemas: Vec<_> = full_db.schemas.iter().filter(|v| schemas.contains(&v.name.as_str())).collect();
+		let mut value = serde_json::to_value(schemas).unwrap();
+		strip(&mut value);
+		value
+	}
+
+	#[test]
+	#[ignore = "replaces the contents of the database given by SQL_DB_MAPPER_TEST_DB"]
+	fn same_as_database() {
+		let path = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../examples/postgres_db.sql"));
+		let conn = std::env::var("SQL_DB_MAPPER_TEST_DB").expect("SQL_DB_MAPPER_TEST_DB isn't set");
+		let mut client = Client::connect(&conn, NoTls).unwrap();
+		client.batch_execute(&fs::read_to_string(path).unwrap()).unwrap();
+		let from_database = MyClient::new(client).unwrap().get_all(false).unwrap();
+
+		let mut reader = DdlReader::open(path).unwrap();
+		let from_file = reader.get_all(false).unwrap();
+		assert_eq!(reader.warnings, Vec::<String>::new());
+
+		// pg_catalog is left out as only its base and pseudo types are read from files
+		let schemas = ["other", "people", "public", "things"];
+		assert_eq!(without_oids(&from_database, &schemas), without_oids(&from_file, &schemas));
+	}
+
+	#[test]
+	#[ignore = "adds schemas to the database given by SQL_DB_MAPPER_TEST_DB"]
+	fn same_order_as_database() {
+		let conn = std::env::var("SQL_DB_MAPPER_TEST_DB").expect("SQL_DB_MAPPER_TEST_DB isn't set");
+		let mut client = Client::connect(&conn, NoTls).unwrap();
+		client
+			.batch_execute("DROP SCHEMA IF EXISTS sort_a, \"Sort_B\" CASCADE")
+			.unwrap();
+		client.batch_execute(ORDER_SQL).unwrap();
+		let from_database = MyClient::new(client).unwrap().get_all(false).unwrap();
+		// the same whatever the collation of the database
+		assert_eq!(names_in_order(&from_database), order_expected());
+	}
+}
@@ -0,0 +1,838 @@
+//! Compare two database trees and report what changed in the generated Rust API
+//!
+//! Items are matched by name (oids differ between databases so they are never compared). Each change is flagged as breaking
+//! if code written against the old mapping could stop compiling or start failing against the new one
+
+use super::{sql_tree::*, Tuples};
+use std::fmt;
+
+/// What happened to an item between the old and new database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+	Added,
+	Removed,
+	Changed,
+}
+
+/// The kind of database object a change refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+	Schema,
+	Table,
+	Enum,
+	EnumLabel,
+	Domain,
+	Type,
+	Column,
+	Function,
+}
+
+/// A single difference between two databases
+#[derive(Debug, Clone)]
+pub struct Change {
+	pub kind: ChangeKind,
+	pub item: ItemKind,
+	/// Dotted path to the item i.e. `schema.table.column`
+	pub path: String,
+	/// Human readable description of the change
+	pub detail: String,
+	/// Whether the change breaks code using the previously generated mapping
+	pub breaking: bool,
+}
+
+/// All the differences found between two databases
+#[derive(Debug, Clone, Default)]
+pub struct DbDiff {
+	pub changes: Vec<Change>,
+}
+
+impl DbDiff {
+	pub fn is_empty(&self) -> bool {
+		self.changes.is_empty()
+	}
+
+	pub fn has_breaking(&self) -> bool {
+		self.changes.iter().any(|v| v.breaking)
+	}
+
+	pub fn breaking(&self) -> impl Iterator<Item = &Change> {
+		self.changes.iter().filter(|v| v.breaking)
+	}
+
+	fn push(&mut self, kind: ChangeKind, item: ItemKind, path: String, detail: String, breaking: bool) {
+		self.changes.push(Change {
+			kind,
+			item,
+			path,
+			detail,
+			breaking,
+		});
+	}
+}
+
+impl fmt::Display for Change {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let sign = match self.kind {
+			ChangeKind::Added => '+',
+			ChangeKind::Removed => '-',
+			ChangeKind::Changed => '~',
+		};
+		let breaking = if self.breaking { " [breaking]" } else { "" };
+		write!(f, "{} {:?} {}: {}{}", sign, self.item, self.path, self.detail, breaking)
+	}
+}
+
+impl fmt::Display for DbDiff {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.is_empty() {
+			return writeln!(f, "No changes");
+		}
+		for change in &self.changes {
+			writeln!(f, "{}", change)?;
+		}
+		let num_breaking = self.breaking().count();
+		writeln!(
+			f,
+			"{} change(s), {} breaking the generated API",
+			self.changes.len(),
+			num_breaking
+		)
+	}
+}
+
+/// Compare two databases
+///
+/// `use_tuples` should be the option used to generate the mapping as it affects which function changes are breaking
+pub fn diff(old: &FullDB, new: &FullDB, use_tuples: Tuples) -> DbDiff {
+	let mut ret = DbDiff::default();
+
+	for old_schema in &old.schemas {
+		match new.schemas.iter().find(|v| v.name == old_schema.name) {
+			Some(new_schema) => diff_schema(&mut ret, old_schema, new_schema, use_tuples),
+			None => ret.push(
+				ChangeKind::Removed,
+				ItemKind::Schema,
+				old_schema.name.clone(),
+				String::from("schema removed"),
+				!(old_schema.no_types() && old_schema.no_procs()),
+			),
+		}
+	}
+	for new_schema in &new.schemas {
+		if !old.schemas.iter().any(|v| v.name == new_schema.name) {
+			ret.push(
+				ChangeKind::Added,
+				ItemKind::Schema,
+				new_schema.name.clone(),
+				String::from("schema added"),
+				false,
+			);
+		}
+	}
+
+	ret
+}
+
+fn diff_schema(ret: &mut DbDiff, old: &Schema, new: &Schema, use_tuples: Tuples) {
+	for old_type in &old.types {
+		let path = format!("{}.{}", old.name, old_type.name);
+		match new.types.iter().find(|v| v.name == old_type.name) {
+			Some(new_type) => diff_type(ret, path, old_type, new_type),
+			None => ret.push(
+				ChangeKind::Removed,
+				type_item_kind(&old_type.typ),
+				path,
+				String::from("type removed"),
+				true,
+			),
+		}
+	}
+	for new_type in &new.types {
+		if !old.types.iter().any(|v| v.name == new_type.name) {
+			ret.push(
+				ChangeKind::Added,
+				type_item_kind(&new_type.typ),
+				format!("{}.{}", new.name, new_type.name),
+				String::from("type added"),
+				false,
+			);
+		}
+	}
+
+	for old_procs in &old.procs {
+		let name = &old_procs[0].name;
+		let path = format!("{}.{}", old.name, name);
+		match new.procs.iter().find(|v| &v[0].name == name) {
+			Some(new_procs) => diff_procs(ret, path, old_procs, new_procs, use_tuples),
+			None => ret.push(
+				ChangeKind::Removed,
+				ItemKind::Function,
+				path,
+				String::from("function removed"),
+				is_mapped(old_procs.len(), use_tuples),
+			),
+		}
+	}
+	for new_procs in &new.procs {
+		let name = &new_procs[0].name;
+		if !old.procs.iter().any(|v| &v[0].name == name) {
+			ret.push(
+				ChangeKind::Added,
+				ItemKind::Function,
+				format!("{}.{}", new.name, name),
+				format!("function added {}", signatures(new_procs)),
+				false,
+			);
+		}
+	}
+}
+
+fn type_item_kind(typ: &PsqlTypType) -> ItemKind {
+	match typ {
+		PsqlTypType::Enum(_) => ItemKind::Enum,
+		PsqlTypType::Composite(_) => ItemKind::Table,
+		PsqlTypType::Domain(_) => ItemKind::Domain,
+		_ => ItemKind::Type,
+	}
+}
+
+fn type_kind_name(typ: &PsqlTypType) -> &'static str {
+	match typ {
+		PsqlTypType::Enum(_) => "enum",
+		PsqlTypType::Composite(_) => "composite",
+		PsqlTypType::Base(_) => "base",
+		PsqlTypType::Domain(_) => "domain",
+		PsqlTypType::Other(_) => "other",
+		PsqlTypType::SimpleComposite(_) => "anonymous record",
+	}
+}
+
+fn diff_type(ret: &mut DbDiff, path: String, old: &PsqlType, new: &PsqlType) {
+	use PsqlTypType::*;
+	match (&old.typ, &new.typ) {
+		(Enum(old_e), Enum(new_e)) => {
+			for label in &old_e.labels {
+				if !new_e.labels.contains(label) {
+					ret.push(
+						ChangeKind::Removed,
+						ItemKind::EnumLabel,
+						format!("{}.{}", path, label),
+						String::from("enum label removed"),
+						true,
+					);
+				}
+			}
+			for label in &new_e.labels {
+				if !old_e.labels.contains(label) {
+					// new variants break exhaustive matches on the enum
+					ret.push(
+						ChangeKind::Added,
+						ItemKind::EnumLabel,
+						format!("{}.{}", path, label),
+						String::from("enum label added"),
+						true,
+					);
+				}
+			}
+		},
+		(Composite(old_c), Composite(new_c)) => diff_columns(ret, &path, &old_c.cols, &new_c.cols),
+		(Domain(old_d), Domain(new_d)) => {
+			if old_d.base_ns_name != new_d.base_ns_name || old_d.base_name != new_d.base_name {
+				ret.push(
+					ChangeKind::Changed,
+					ItemKind::Domain,
+					path.clone(),
+					format!(
+						"base type {}.{} -> {}.{}",
+						old_d.base_ns_name, old_d.base_name, new_d.base_ns_name, new_d.base_name
+					),
+					true,
+				);
+			}
+			diff_checks(ret, &path, &old_d.checks, &new_d.checks);
+		},
+		(SimpleComposite(old_c), SimpleComposite(new_c)) => {
+			diff_columns(ret, &path, &names_to_columns(old_c), &names_to_columns(new_c))
+		},
+		(Base(_), Base(_)) | (Other(_), Other(_)) => (),
+		(old_t, new_t) => ret.push(
+			ChangeKind::Changed,
+			type_item_kind(new_t),
+			path,
+			format!("{} type -> {} type", type_kind_name(old_t), type_kind_name(new_t)),
+			true,
+		),
+	}
+}
+
+/// Checks are matched by name, new or changed checks can reject values the old `TryFrom` accepted
+fn diff_checks(ret: &mut DbDiff, path: &str, old: &[DomainCheck], new: &[DomainCheck]) {
+	for old_check in old {
+		let check_path = format!("{}.{}", path, old_check.name);
+		match new.iter().find(|v| v.name == old_check.name) {
+			Some(new_check) if new_check.expr != old_check.expr => ret.push(
+				ChangeKind::Changed,
+				ItemKind::Domain,
+				check_path,
+				format!("check {} -> {}", old_check.expr, new_check.expr),
+				true,
+			),
+			Some(_) => (),
+			None => ret.push(
+				ChangeKind::Removed,
+				ItemKind::Domain,
+				check_path,
+				format!("check removed {}", old_check.expr),
+				false,
+			),
+		}
+	}
+	for new_check in new {
+		if !old.iter().any(|v| v.name == new_check.name) {
+			ret.push(
+				ChangeKind::Added,
+				ItemKind::Domain,
+				format!("{}.{}", path, new_check.name),
+				format!("check added {}", new_check.expr),
+				true,
+			);
+		}
+	}
+}
+
+/// Anonymous records only have names and types, treat their fields as nullable columns so they can be compared the same way
+fn names_to_columns(nat: &NamesAndTypes) -> Vec<Column> {
+	nat.0
+		.iter()
+		.enumerate()
+		.map(|(i, tan)| Column {
+			pos: i as i16,
+			name: tan.name.clone(),
+			type_id: 0,
+			type_name: tan.typ.name.clone(),
+			type_ns_name: tan.typ.schema.clone(),
+			not_null: false,
+			num_dimentions: 0,
+			has_default: false,
+		})
+		.collect()
+}
+
+fn column_type(col: &Column) -> String {
+	let mut ret = format!("{}.{}", col.type_ns_name, col.type_name);
+	for _ in 0..col.num_dimentions {
+		ret += "[]";
+	}
+	ret
+}
+
+fn diff_columns(ret: &mut DbDiff, path: &str, old: &[Column], new: &[Column]) {
+	for old_col in old {
+		let col_path = format!("{}.{}", path, old_col.name);
+		match new.iter().find(|v| v.name == old_col.name) {
+			Some(new_col) => {
+				let (old_type, new_type) = (column_type(old_col), column_type(new_col));
+				if old_type != new_type {
+					ret.push(
+						ChangeKind::Changed,
+						ItemKind::Column,
+						col_path.clone(),
+						format!("type {} -> {}", old_type, new_type),
+						true,
+					);
+				}
+				if old_col.not_null != new_col.not_null {
+					let nullability = |not_null: bool| if not_null { "NOT NULL" } else { "NULL" };
+					ret.push(
+						ChangeKind::Changed,
+						ItemKind::Column,
+						col_path,
+						format!(
+							"nullability {} -> {}",
+							nullability(old_col.not_null),
+							nullability(new_col.not_null)
+						),
+						true,
+					);
+				}
+			},
+			None => ret.push(
+				ChangeKind::Removed,
+				ItemKind::Column,
+				col_path,
+				format!("column removed ({})", column_type(old_col)),
+				true,
+			),
+		}
+	}
+	for new_col in new {
+		if !old.iter().any(|v| v.name == new_col.name) {
+			// adding a field breaks struct literals and pattern matching on the generated struct
+			ret.push(
+				ChangeKind::Added,
+				ItemKind::Column,
+				format!("{}.{}", path, new_col.name),
+				format!("column added ({})", column_type(new_col)),
+				true,
+			);
+		}
+	}
+}
+
+/// Whether a set of overloads with this length gets any rust function generated for it
+fn is_mapped(num_overloads: usize, use_tuples: Tuples) -> bool {
+	num_overloads == 1 || use_tuples != Tuples::NoOverloads
+}
+
+/// Whether a set of overloads with this length is mapped to a function taking a single tuple
+fn is_tuple_style(num_overloads: usize, use_tuples: Tuples) -> bool {
+	match use_tuples {
+		Tuples::ForOverloads => num_overloads > 1,
+		Tuples::ForAll => true,
+		Tuples::NoOverloads | Tuples::OldestOverload => false,
+	}
+}
+
+fn input_types(proc: &SqlProc) -> Vec<String> {
+	proc.inputs.0.iter().map(|v| format!("{}.{}", v.typ.schema, v.typ.name)).collect()
+}
+
+fn signature(proc: &SqlProc) -> String {
+	let ret = format!("{}.{}", proc.outputs.schema, proc.outputs.name);
+	let ret = if proc.returns_set { format!("SETOF {}", ret) } else { ret };
+	format!("({}) -> {}", input_types(proc).join(", "), ret)
+}
+
+fn signatures(procs: &[SqlProc]) -> String {
+	procs.iter().map(signature).collect::<Vec<_>>().join(" | ")
+}
+
+fn diff_procs(ret: &mut DbDiff, path: String, old: &[SqlProc], new: &[SqlProc], use_tuples: Tuples) {
+	let old_mapped = is_mapped(old.len(), use_tuples);
+	let new_mapped = is_mapped(new.len(), use_tuples);
+	if old_mapped && !new_mapped {
+		ret.push(
+			ChangeKind::Changed,
+			ItemKind::Function,
+			path.clone(),
+			format!("now overloaded and no longer mapped {}", signatures(new)),
+			true,
+		);
+	} else if old_mapped && is_tuple_style(old.len(), use_tuples) != is_tuple_style(new.len(), use_tuples) {
+		let style = |is_tuple: bool| if is_tuple { "tuple-call" } else { "plain" };
+		ret.push(
+			ChangeKind::Changed,
+			ItemKind::Function,
+			path.clone(),
+			format!(
+				"{} overload(s) -> {} overload(s), {} style -> {} style",
+				old.len(),
+				new.len(),
+				style(is_tuple_style(old.len(), use_tuples)),
+				style(is_tuple_style(new.len(), use_tuples))
+			),
+			true,
+		);
+	}
+
+	if use_tuples == Tuples::OldestOverload {
+		// only the oldest overload is ever mapped
+		if let (Some(old), Some(new)) = (oldest_overload(old), oldest_overload(new)) {
+			if signature(old) != signature(new) {
+				ret.push(
+					ChangeKind::Changed,
+					ItemKind::Function,
+					path,
+					format!("{} -> {}", signature(old), signature(new)),
+					true,
+				);
+			}
+		}
+		return;
+	}
+
+	for old_proc in old {
+		let sig_path = format!("{}{}", path, signature(old_proc));
+		match new.iter().find(|v| input_types(v) == input_types(old_proc)) {
+			Some(new_proc) => {
+				if signature(old_proc) != signature(new_proc) {
+					ret.push(
+						ChangeKind::Changed,
+						ItemKind::Function,
+						sig_path,
+						format!("return type changed to {}", signature(new_proc)),
+						old_mapped,
+					);
+				}
+			},
+			None => ret.push(
+				ChangeKind::Removed,
+				ItemKind::Function,
+				sig_path,
+				String::from("overload removed"),
+				old_mapped,
+			),
+		}
+	}
+	for new_proc in new {
+		if !old.iter().any(|v| input_types(v) == input_types(new_proc)) {
+			ret.push(
+				ChangeKind::Added,
+				ItemKind::Function,
+				format!("{}{}", path, signature(new_proc)),
+				String::from("overload added"),
+				false,
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn db(schemas: Vec<Schema>) -> FullDB {
+		FullDB {
+			schemas,
+			dialect: Dialect::Postgres,
+		}
+	}
+
+	fn schema(name: &str, types: Vec<PsqlType>, procs: Vec<Vec<SqlProc>>) -> Schema {
+		Schema {
+			id: 0,
+			name: String::from(name),
+			owner_name: String::from("postgres"),
+			types,
+			procs,
+		}
+	}
+
+	fn typ(name: &str, typ: PsqlTypType) -> PsqlType {
+		PsqlType {
+			name: String::from(name),
+			ns: 0,
+			typ,
+		}
+	}
+
+	/// A table of (name, type, not null) columns with types from pg_catalog
+	fn table(name: &str, cols: &[(&str, &str, bool)]) -> PsqlType {
+		let cols = cols
+			.iter()
+			.enumerate()
+			.map(|(i, (name, type_name, not_null))| Column {
+				pos: i as i16 + 1,
+				name: String::from(*name),
+				type_id: 0,
+				type_name: String::from(*type_name),
+				type_ns_name: String::from("pg_catalog"),
+				not_null: *not_null,
+				num_dimentions: 0,
+				has_default: false,
+			})
+			.collect();
+		typ(
+			name,
+			PsqlTypType::Composite(PsqlCompositeType {
+				oid: 0,
+				kind: RelKind::Table,
+				cols,
+				primary_key: Vec::new(),
+				foreign_keys: Vec::new(),
+			}),
+		)
+	}
+
+	fn enum_type(name: &str, labels: &[&str]) -> PsqlType {
+		typ(
+			name,
+			PsqlTypType::Enum(PsqlEnumType {
+				oid: 0,
+				labels: labels.iter().map(|v| String::from(*v)).collect(),
+			}),
+		)
+	}
+
+	fn domain(name: &str, base_name: &str, checks: &[(&str, &str)]) -> PsqlType {
+		typ(
+			name,
+			PsqlTypType::Domain(PsqlDomain {
+				oid: 0,
+				base_oid: 0,
+				base_name: String::from(base_name),
+				base_ns_name: String::from("pg_catalog"),
+				not_null: false,
+				default: None,
+				checks: checks
+					.iter()
+					.map(|(name, expr)| DomainCheck {
+						name: String::from(*name),
+						expr: String::from(*expr),
+					})
+					.collect(),
+			}),
+		)
+	}
+
+	fn full_type(name: &str) -> FullType {
+		FullType {
+			schema: String::from("pg_catalog"),
+			name: String::from(name),
+		}
+	}
+
+	fn proc(name: &str, inputs: &[&str], output: &str) -> SqlProc {
+		SqlProc {
+			ns: 0,
+			ns_name: String::from("public"),
+			oid: 0,
+			name: String::from(name),
+			returns_set: false,
+			num_args: inputs.len() as i16,
+			inputs: NamesAndTypes(
+				inputs
+					.iter()
+					.map(|v| TypeAndName {
+						typ: full_type(v),
+						name: String::new(),
+					})
+					.collect(),
+			),
+			outputs: full_type(output),
+		}
+	}
+
+	/// The kind, path, and whether it's breaking of each change
+	fn changes(diff: &DbDiff) -> Vec<(ChangeKind, ItemKind, &str, bool)> {
+		diff.changes
+			.iter()
+			.map(|v| (v.kind, v.item, v.path.as_str(), v.breaking))
+			.collect()
+	}
+
+	#[test]
+	fn same_database_has_no_changes() {
+		let old = db(vec![schema(
+			"public",
+			vec![table("person", &[("id", "int4", true)]), enum_type("mood", &["sad", "ok"])],
+			vec![vec![proc("add", &["int4", "int4"], "int4")]],
+		)]);
+		let diff = diff(&old, &old.clone(), Tuples::ForOverloads);
+		assert!(diff.is_empty());
+		assert_eq!(diff.to_string(), "No changes\n");
+	}
+
+	#[test]
+	fn added_schema_and_type_arent_breaking() {
+		let old = db(vec![schema("public", Vec::new(), Vec::new())]);
+		let new = db(vec![
+			schema("public", vec![table("person", &[("id", "int4", true)])], Vec::new()),
+			schema("shop", Vec::new(), Vec::new()),
+		]);
+		let diff = diff(&old, &new, Tuples::ForOverloads);
+		assert_eq!(
+			changes(&diff),
+			[
+				(ChangeKind::Added, ItemKind::Table, "public.person", false),
+				(ChangeKind::Added, ItemKind::Schema, "shop", false),
+			]
+		);
+		assert!(!diff.has_breaking());
+	}
+
+	#[test]
+	fn removed_schema_is_breaking_unless_empty() {
+		let old = db(vec![
+			schema("empty", Vec::new(), Vec::new()),
+			schema("shop", vec![enum_type("mood", &["ok"])], Vec::new()),
+		]);
+		let diff = diff(&old, &db(Vec::new()), Tuples::ForOverloads);
+		assert_eq!(
+			changes(&diff),
+			[
+				(ChangeKind::Removed, ItemKind::Schema, "empty", false),
+				(ChangeKind::Removed, ItemKind::Schema, "shop", true),
+			]
+		);
+	}
+
+	#[test]
+	fn column_changes_are_breaking() {
+		let old = db(vec![schema(
+			"public",
+			vec![table(
+				"person",
+				&[("id", "int4", true), ("name", "text", false), ("age", "int4", false)],
+			)],
+			Vec::new(),
+		)]);
+		let new = db(vec![schema(
+			"public",
+			vec![table(
+				"person",
+				&[("id", "int8", true), ("name", "text", true), ("email", "text", false)],
+			)],
+			Vec::new(),
+		)]);
+		let diff = diff(&old, &new, Tuples::ForOverloads);
+		assert_eq!(
+			changes(&diff),
+			[
+				(ChangeKind::Changed, ItemKind::Column, "public.person.id", true),
+				(ChangeKind::Changed, ItemKind::Column, "public.person.name", true),
+				(ChangeKind::Removed, ItemKind::Column, "public.person.age", true),
+				(ChangeKind::Added, ItemKind::Column, "public.person.email", true),
+			]
+		);
+		assert_eq!(diff.changes[0].detail, "type pg_catalog.int4 -> pg_catalog.int8");
+		assert_eq!(diff.changes[1].detail, "nullability NULL -> NOT NULL");
+	}
+
+	#[test]
+	fn enum_labels_added_and_removed_are_breaking() {
+		let old = db(vec![schema("public", vec![enum_type("mood", &["sad", "ok"])], Vec::new())]);
+		let new = db(vec![schema("public", vec![enum_type("mood", &["ok", "happy"])], Vec::new())]);
+		assert_eq!(
+			changes(&diff(&old, &new, Tuples::ForOverloads)),
+			[
+				(ChangeKind::Removed, ItemKind::EnumLabel, "public.mood.sad", true),
+				(ChangeKind::Added, ItemKind::EnumLabel, "public.mood.happy", true),
+			]
+		);
+	}
+
+	#[test]
+	fn type_kind_change_is_breaking() {
+		let old = db(vec![schema("public", vec![enum_type("mood", &["ok"])], Vec::new())]);
+		let new = db(vec![schema("public", vec![domain("mood", "text", &[])], Vec::new())]);
+		let diff = diff(&old, &new, Tuples::ForOverloads);
+		assert_eq!(changes(&diff), [(ChangeKind::Changed, ItemKind::Domain, "public.mood", true)]);
+		assert_eq!(diff.changes[0].detail, "enum type -> domain type");
+	}
+
+	#[test]
+	fn domain_base_type_change_is_breaking() {
+		let old = db(vec![schema("public", vec![domain("percent", "float8", &[])], Vec::new())]);
+		let new = db(vec![schema("public", vec![domain("percent", "float4", &[])], Vec::new())]);
+		let diff = diff(&old, &new, Tuples::ForOverloads);
+		assert_eq!(changes(&diff), [(ChangeKind::Changed, ItemKind::Domain, "public.percent", true)]);
+		assert_eq!(diff.changes[0].detail, "base type pg_catalog.float8 -> pg_catalog.float4");
+	}
+
+	#[test]
+	fn only_removing_a_domain_check_isnt_breaking() {
+		let old = db(vec![schema(
+			"public",
+			vec![domain(
+				"percent",
+				"float8",
+				&[("low", "(VALUE >= (0)::double precision)"), ("high", "(VALUE <= (100)::double precision)")],
+			)],
+			Vec::new(),
+		)]);
+		let new = db(vec![schema(
+			"public",
+			vec![domain(
+				"percent",
+				"float4",
+				&[("high", "(VALUE <= (1)::double precision)"), ("finite", "(VALUE <> 'NaN'::double precision)")],
+			)],
+			Vec::new(),
+		)]);
+		assert_eq!(
+			changes(&diff(&old, &new, Tuples::ForOverloads)),
+			[
+				(ChangeKind::Changed, ItemKind::Domain, "public.percent", true),
+				(ChangeKind::Removed, ItemKind::Domain, "public.percent.low", false),
+				(ChangeKind::Changed, ItemKind::Domain, "public.percent.high", true),
+				(ChangeKind::Added, ItemKind::Domain, "public.percent.finite", true),
+			]
+		);
+	}
+
+	#[test]
+	fn added_function_isnt_breaking_and_return_type_change_is() {
+		let old = db(vec![schema("public", Vec::new(), vec![vec![proc("add", &["int4"], "int4")]])]);
+		let new = db(vec![schema(
+			"public",
+			Vec::new(),
+			vec![vec![proc("add", &["int4"], "int8")], vec![proc("now", &[], "timestamptz")]],
+		)]);
+		let diff = diff(&old, &new, Tuples::ForOverloads);
+		assert_eq!(
+			changes(&diff),
+			[
+				(ChangeKind::Changed, ItemKind::Function, "public.add(pg_catalog.int4) -> pg_catalog.int4", true),
+				(ChangeKind::Added, ItemKind::Function, "public.now", false),
+			]
+		);
+	}
+
+	#[test]
+	fn becoming_overloaded_depends_on_use_tuples() {
+		let old = db(vec![schema("public", Vec::new(), vec![vec![proc("add", &["int4"], "int4")]])]);
+		let new = db(vec![schema(
+			"public",
+			Vec::new(),
+			vec![vec![proc("add", &["int4"], "int4"), proc("add", &["int8"], "int8")]],
+		)]);
+
+		// the plain function becomes one taking a tuple
+		let overloads = diff(&old, &new, Tuples::ForOverloads);
+		assert_eq!(
+			changes(&overloads),
+			[
+				(ChangeKind::Changed, ItemKind::Function, "public.add", true),
+				(ChangeKind::Added, ItemKind::Function, "public.add(pg_catalog.int8) -> pg_catalog.int8", false),
+			]
+		);
+		assert_eq!(
+			overloads.changes[0].detail,
+			"1 overload(s) -> 2 overload(s), plain style -> tuple-call style"
+		);
+
+		// every function already takes a tuple so only the new overload is reported
+		assert!(!diff(&old, &new, Tuples::ForAll).has_breaking());
+
+		// overloaded functions aren't mapped at all
+		let no_overloads = diff(&old, &new, Tuples::NoOverloads);
+		assert_eq!(
+			changes(&no_overloads),
+			[
+				(ChangeKind::Changed, ItemKind::Function, "public.add", true),
+				(ChangeKind::Added, ItemKind::Function, "public.add(pg_catalog.int8) -> pg_catalog.int8", false),
+			]
+		);
+		assert!(no_overloads.changes[0].detail.starts_with("now overloaded and no longer mapped"));
+
+		// only the oldest overload is mapped and it didn't change
+		assert!(diff(&old, &new, Tuples::OldestOverload).is_empty());
+	}
+
+	#[test]
+	fn removed_unmapped_overloads_arent_breaking() {
+		let old = db(vec![schema(
+			"public",
+			Vec::new(),
+			vec![vec![proc("add", &["int4"], "int4"), proc("add", &["int8"], "int8")]],
+		)]);
+		let new = db(vec![schema("public", Vec::new(), Vec::new())]);
+		assert!(!diff(&old, &new, Tuples::NoOverloads).has_breaking());
+		assert!(diff(&old, &new, Tuples::ForOverloads).has_breaking());
+	}
+
+	#[test]
+	fn summary_counts_breaking_changes() {
+		let old = db(vec![schema("public", vec![enum_type("mood", &["ok"])], Vec::new())]);
+		let new = db(vec![
+			schema("public", vec![enum_type("mood", &["ok", "happy"])], Vec::new()),
+			schema("shop", Vec::new(), Vec::new()),
+		]);
+		assert_eq!(
+			diff(&old, &new, Tuples::ForOverloads).to_string(),
+			"+ EnumLabel public.mood.happy: enum label added [breaking]\n+ Schema shop: schema added\n2 change(s), 1 \
+			 breaking the generated API\n"
+		);
+	}
+}
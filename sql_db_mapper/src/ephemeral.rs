@@ -0,0 +1,169 @@
+//! A throwaway PostgreSQL server so mappings can be generated from migrations without a running database
+//!
+//! The server is made with `initdb` and `pg_ctl` from the PATH in a new temporary directory and listens on a random port
+//! and a socket in that directory. It's stopped and the directory removed when the [`EphemeralPostgres`] is dropped
+use super::Error;
+use std::{
+	fs,
+	net::TcpListener,
+	path::{Path, PathBuf},
+	process::{Command, Output},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The superuser made by initdb, the server trusts every local connection
+const USER: &str = "postgres";
+
+/// A running temporary server, see the [module docs](self)
+#[derive(Debug)]
+pub struct EphemeralPostgres {
+	dir: PathBuf,
+	port: u16,
+}
+
+impl EphemeralPostgres {
+	/// Create the database cluster and start the server, waiting until it accepts connections
+	///
+	/// Returns [`Error::Ephemeral`] with what initdb or pg_ctl wrote if either fails. Neither can be run as root, and as
+	/// the server is connected to through a socket it can't be started on platforms without Unix sockets
+	pub fn start() -> Result<EphemeralPostgres, Error> {
+		if cfg!(not(unix)) {
+			return Err(Error::Ephemeral(String::from(
+				"a temporary server can only be started on platforms with Unix sockets",
+			)));
+		}
+		let nanos = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map_or(0, |v| v.subsec_nanos());
+		let dir = std::env::temp_dir().join(format!("sql_db_mapper-{}-{}", std::process::id(), nanos));
+		fs::create_dir(&dir)?;
+		// stops the server and removes the directory if starting fails
+		let server = EphemeralPostgres {
+			dir,
+			port: free_port()?,
+		};
+
+		run(Command::new("initdb").arg("--pgdata").arg(server.data_dir()).args([
+			"--username",
+			USER,
+			"--auth",
+			"trust",
+			"--encoding",
+			"UTF8",
+			"--no-sync",
+		]))?;
+		run(Command::new("pg_ctl")
+			.arg("--pgdata")
+			.arg(server.data_dir())
+			.arg("--log")
+			.arg(server.dir.join("log"))
+			.arg("-o")
+			.arg(format!(
+				"-p {} -k {} -c listen_addresses=localhost -c fsync=off",
+				server.port,
+				shell_quote(&server.dir.display().to_string())
+			))
+			.args(["--wait", "start"]))
+		.map_err(|e| server.with_log(e))?;
+		Ok(server)
+	}
+
+	/// The string to connect to the server with, through its socket
+	pub fn conn(&self) -> String {
+		// quoted as the temporary directory can have spaces
+		let host = self.dir.display().to_string().replace('\\', r"\\").replace('\'', r"\'");
+		format!("host='{}' port={} user={} dbname=postgres", host, self.port, USER)
+	}
+
+	pub fn port(&self) -> u16 {
+		self.port
+	}
+
+	fn data_dir(&self) -> PathBuf {
+		self.dir.join("data")
+	}
+
+	/// Add the server's log to an error starting it
+	fn with_log(&self, e: Error) -> Error {
+		match (e, fs::read_to_string(self.dir.join("log"))) {
+			(Error::Ephemeral(e), Ok(log)) => Error::Ephemeral(format!("{}\n{}", e, log.trim_end())),
+			(e, _) => e,
+		}
+	}
+}
+
+impl Drop for EphemeralPostgres {
+	/// Stops the server without waiting for clients to disconnect, nothing in it is kept
+	fn drop(&mut self) {
+		if self.data_dir().join("postmaster.pid").exists() {
+			let _ = Command::new("pg_ctl")
+				.arg("--pgdata")
+				.arg(self.data_dir())
+				.args(["--mode", "immediate", "--wait", "stop"])
+				.output();
+		}
+		let _ = fs::remove_dir_all(&self.dir);
+	}
+}
+
+/// A port nothing is listening on, found by letting the OS pick one
+fn free_port() -> Result<u16, Error> {
+	Ok(TcpListener::bind(("127.0.0.1", 0))?.local_addr()?.port())
+}
+
+/// Quote an argument of the options pg_ctl passes to postgres, which go through the shell
+fn shell_quote(arg: &str) -> String {
+	format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Run a program from the PATH, failing with its output if it doesn't succeed
+fn run(command: &mut Command) -> Result<Output, Error> {
+	let program = Path::new(command.get_program()).display().to_string();
+	let output = command.output().map_err(|e| match e.kind() {
+		std::io::ErrorKind::NotFound => Error::Ephemeral(format!("`{}` wasn't found on the PATH", program)),
+		_ => Error::Io(e),
+	})?;
+	if !output.status.success() {
+		return Err(Error::Ephemeral(format!(
+			"{} failed: {}",
+			program,
+			String::from_utf8_lossy(&output.stderr).trim_end()
+		)));
+	}
+	Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use postgres::{Client, NoTls};
+
+	#[test]
+	fn quoting() {
+		assert_eq!(shell_quote("/tmp/dir"), "'/tmp/dir'");
+		assert_eq!(shell_quote("/tmp/my dir's"), r"'/tmp/my dir'\''s'");
+		let server = EphemeralPostgres {
+			dir: PathBuf::from("/tmp/my dir's"),
+			port: 5433,
+		};
+		assert_eq!(
+			server.conn(),
+			r"host='/tmp/my dir\'s' port=5433 user=postgres dbname=postgres"
+		);
+		// there's no server or directory to remove
+		std::mem::forget(server);
+	}
+
+	#[test]
+	#[ignore = "needs initdb and pg_ctl on the PATH and can't be run as root"]
+	fn start_connect_and_drop() {
+		let server = EphemeralPostgres::start().unwrap();
+		let dir = server.dir.clone();
+		let mut client = Client::connect(&server.conn(), NoTls).unwrap();
+		let port: String = client.query_one("SHOW port", &[]).unwrap().get(0);
+		assert_eq!(port, server.port().to_string());
+		drop(client);
+		drop(server);
+		assert!(!dir.exists());
+	}
+}
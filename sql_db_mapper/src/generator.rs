@@ -0,0 +1,589 @@
+//! Library interface for generating mappings without going through the command line options or stdout
+//!
+//! ```no_run
+//! use sql_db_mapper::{Generator, ThirdParty};
+//!
+//! let output = Generator::new()
+//! 	.conn("host=localhost user=postgres")
+//! 	.rust_case(true)
+//! 	.third_party(vec![ThirdParty::Chrono])
+//! 	.generate()
+//! 	.unwrap();
+//! std::fs::write("src/lib.rs", output.lib_rs()).unwrap();
+//! ```
+use super::{
+	connection::{Introspect, MyClient},
+	sql_tree::{Dialect, FullDB},
+	cargo_toml::merge_into_existing,
+	Backend, CustomAttribute, CustomDerive, DbSource, ExistingCargoToml, ExtensionType, Formatter, Opt,
+	ThirdParty, Tuples, TypeTarget,
+};
+use std::{
+	cell::RefCell,
+	collections::BTreeMap,
+	fmt,
+	path::{Path, PathBuf},
+};
+
+/// Errors that can occur while reading a database or writing a mapping
+#[derive(Debug)]
+pub enum Error {
+	/// Querying the database failed
+	Sql(postgres::Error),
+	/// Reading a SQLite database failed
+	#[cfg(feature = "sqlite")]
+	Sqlite(rusqlite::Error),
+	/// Reading a MySQL or MariaDB database failed
+	#[cfg(feature = "mysql")]
+	Mysql(mysql::Error),
+	/// Reading a snapshot or writing output failed
+	Io(std::io::Error),
+	/// A SQL file couldn't be split into statements
+	Ddl(String),
+	/// The migrations directory couldn't be read
+	Migration(String),
+	/// A temporary PostgreSQL server couldn't be started
+	Ephemeral(String),
+	/// Neither a client, connection string, snapshot, nor database was provided
+	NoSource,
+	/// The command writes to an output file or directory but none was given
+	NoOutput,
+	/// A type the database refers to by oid wasn't found, i.e. it was dropped while the database was being read
+	MissingType(u32),
+	/// Generated code is not valid rust, this is a bug in sql_db_mapper
+	Parse(String),
+	/// The formatter failed
+	Format(String),
+	/// The rustfmt program isn't installed
+	NoRustfmt,
+	/// A Cargo.toml template isn't valid TOML
+	CargoToml(String),
+	/// An error in a specific generated file
+	InFile(PathBuf, Box<Error>),
+	/// The backend can't map a database of this kind
+	Dialect(Backend, Dialect),
+	/// Reading the database needs a feature sql_db_mapper was built without, i.e. `sqlite` or `mysql`
+	NoFeature(&'static str),
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			// postgres::Error only says "db error" when the server returned one
+			Error::Sql(e) => match e.as_db_error() {
+				Some(db_error) => write!(f, "database error: {}", db_error),
+				None => write!(f, "database error: {}", e),
+			},
+			#[cfg(feature = "sqlite")]
+			Error::Sqlite(e) => write!(f, "sqlite error: {}", e),
+			#[cfg(feature = "mysql")]
+			Error::Mysql(e) => write!(f, "mysql error: {}", e),
+			Error::Io(e) => write!(f, "io error: {}", e),
+			Error::Ddl(e) => write!(f, "failed to read sql: {}", e),
+			Error::Migration(e) => write!(f, "invalid migrations: {}", e),
+			Error::Ephemeral(e) => write!(f, "failed to start a temporary postgres server: {}", e),
+			Error::NoSource => write!(f, "no database to generate a mapping from"),
+			Error::NoOutput => write!(f, "an output file or directory is needed"),
+			Error::MissingType(oid) => write!(f, "type with oid {} not found while reading the database", oid),
+			Error::Parse(e) => write!(f, "generated invalid rust code: {}", e),
+			Error::Format(e) => write!(f, "failed to format output: {}", e),
+			Error::NoRustfmt => write!(f, "rustfmt isn't installed, use --formatter prettyplease"),
+			Error::CargoToml(e) => write!(f, "invalid Cargo.toml: {}", e),
+			Error::InFile(path, e) => write!(f, "{}: {}", path.display(), e),
+			Error::Dialect(backend, dialect) => write!(
+				f,
+				"the {} backend can't map a {} database",
+				backend.to_str(),
+				dialect.to_str()
+			),
+			Error::NoFeature(feature) => write!(
+				f,
+				"sql_db_mapper was built without the `{}` feature needed to read this database",
+				feature
+			),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Sql(e) => Some(e),
+			#[cfg(feature = "sqlite")]
+			Error::Sqlite(e) => Some(e),
+			#[cfg(feature = "mysql")]
+			Error::Mysql(e) => Some(e),
+			Error::Io(e) => Some(e),
+			Error::NoSource
+			| Error::NoOutput
+			| Error::MissingType(_)
+			| Error::Ddl(_)
+			| Error::Migration(_)
+			| Error::Ephemeral(_)
+			| Error::Parse(_)
+			| Error::Format(_)
+			| Error::NoRustfmt
+			| Error::CargoToml(_)
+			| Error::Dialect(..)
+			| Error::NoFeature(_) => None,
+			Error::InFile(_, e) => Some(e),
+		}
+	}
+}
+
+impl From<postgres::Error> for Error {
+	fn from(e: postgres::Error) -> Error {
+		Error::Sql(e)
+	}
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+	fn from(e: rusqlite::Error) -> Error {
+		Error::Sqlite(e)
+	}
+}
+
+#[cfg(feature = "mysql")]
+impl From<mysql::Error> for Error {
+	fn from(e: mysql::Error) -> Error {
+		Error::Mysql(e)
+	}
+}
+
+impl From<std::io::Error> for Error {
+	fn from(e: std::io::Error) -> Error {
+		Error::Io(e)
+	}
+}
+
+/// Messages about things that couldn't be mapped, collected while generating
+#[derive(Debug, Default)]
+pub struct Warnings(RefCell<Vec<String>>);
+
+impl Warnings {
+	pub fn push(&self, warning: String) {
+		self.0.borrow_mut().push(warning);
+	}
+
+	pub fn take(&self) -> Vec<String> {
+		self.0.replace(Vec::new())
+	}
+}
+
+/// A generated mapping held in memory
+#[derive(Debug, Clone)]
+pub struct GeneratedOutput {
+	/// The contents of each file keyed by its path relative to the crate root (i.e. `src/lib.rs`). Without the `dir` option
+	/// only `src/lib.rs` is generated
+	pub files: BTreeMap<PathBuf, String>,
+	/// The contents of the crate's Cargo.toml
+	pub cargo_toml: String,
+	/// What `write_to` does with a Cargo.toml that's already there
+	pub existing_cargo_toml: ExistingCargoToml,
+	/// Anything that couldn't be mapped
+	pub warnings: Vec<String>,
+}
+
+impl GeneratedOutput {
+	/// The root of the generated crate, which is the whole mapping when not using the `dir` option
+	pub fn lib_rs(&self) -> &str {
+		&self.files[Path::new("src/lib.rs")]
+	}
+
+	/// Write the crate (including Cargo.toml) to the directory `dir`, returning the paths of the files written
+	///
+	/// Files which already have the same contents aren't written so their modification times don't cause rebuilds. The
+	/// files of schemas which are no longer generated under `src/types`, `src/sync_fns`, and `src/async_fns` (i.e.
+	/// `src/types/{schema}.rs` of a dropped schema) are removed, nothing else in `dir` is touched. An existing Cargo.toml
+	/// is replaced, kept, or merged into as `existing_cargo_toml` says
+	pub fn write_to(&self, dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+		let mut written = Vec::new();
+		std::fs::create_dir_all(dir)?;
+		let cargo_toml_path = dir.join("Cargo.toml");
+		if let Some(cargo_toml) = self.cargo_toml_for(&cargo_toml_path)? {
+			if write_if_changed(&cargo_toml_path, &cargo_toml)? {
+				written.push(cargo_toml_path);
+			}
+		}
+		for (path, content) in &self.files {
+			let path = dir.join(path);
+			if let Some(parent) = path.parent() {
+				std::fs::create_dir_all(parent)?;
+			}
+			if write_if_changed(&path, content)? {
+				written.push(path);
+			}
+		}
+		self.remove_stale(dir)?;
+		Ok(written)
+	}
+
+	/// The Cargo.toml to write to `path` given what's there already, `None` if it's left alone
+	///
+	/// Fails with [`std::io::ErrorKind::InvalidData`] if the Cargo.toml to merge into isn't valid TOML
+	fn cargo_toml_for(&self, path: &Path) -> std::io::Result<Option<String>> {
+		let existing = match std::fs::read_to_string(path) {
+			Ok(v) => v,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Some(self.cargo_toml.clone())),
+			Err(e) => return Err(e),
+		};
+		match self.existing_cargo_toml {
+			ExistingCargoToml::Overwrite => Ok(Some(self.cargo_toml.clone())),
+			ExistingCargoToml::Keep => Ok(None),
+			ExistingCargoToml::Merge => merge_into_existing(&existing, &self.cargo_toml).map(Some).map_err(|e| {
+				std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+			}),
+		}
+	}
+
+	/// Remove the files of schemas in `dir` which aren't part of this output
+	fn remove_stale(&self, dir: &Path) -> std::io::Result<()> {
+		for module in SCHEMA_MODULES {
+			self.remove_stale_in(dir, &Path::new("src").join(module))?;
+		}
+		Ok(())
+	}
+
+	/// Remove the `.rs` files under `module_dir` which aren't part of this output, including those of the directories of
+	/// schemas split into a file per item
+	fn remove_stale_in(&self, dir: &Path, module_dir: &Path) -> std::io::Result<()> {
+		let entries = match std::fs::read_dir(dir.join(module_dir)) {
+			Ok(v) => v,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+			Err(e) => return Err(e),
+		};
+		for entry in entries {
+			let entry = entry?;
+			let path = module_dir.join(entry.file_name());
+			if entry.file_type()?.is_dir() {
+				self.remove_stale_in(dir, &path)?;
+			} else if path.extension().is_some_and(|v| v == "rs") && !self.files.contains_key(&path) {
+				remove_if_exists(&dir.join(path))?;
+			}
+		}
+		// only removed if nothing else was put there
+		let _ = std::fs::remove_dir(dir.join(module_dir));
+		Ok(())
+	}
+}
+
+/// The modules with a file per schema in a directory of the same name whose stale files are removed. Only these are
+/// touched so a file someone added next to the generated ones, i.e. `src/query.rs` of their own, is never deleted
+const SCHEMA_MODULES: [&str; 3] = ["types", "sync_fns", "async_fns"];
+
+/// Write `content` to `path` unless it already has exactly that content, returning whether it was written
+pub(crate) fn write_if_changed(path: &Path, content: &str) -> std::io::Result<bool> {
+	match std::fs::read(path) {
+		Ok(old) if old == content.as_bytes() => Ok(false),
+		_ => std::fs::write(path, content).map(|_| true),
+	}
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+	match std::fs::remove_file(path) {
+		Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+		_ => Ok(()),
+	}
+}
+
+/// Where the generator reads the database from
+enum Input {
+	Client(Box<postgres::Client>),
+	Introspector(Box<dyn Introspect>),
+	Source(DbSource),
+	Database(FullDB),
+}
+
+/// Builder for generating a mapping
+///
+/// The options are the same as those of [`Opt`]. A source of the database must be provided with one of [`Generator::client`],
+/// [`Generator::introspector`], [`Generator::conn`], [`Generator::snapshot`], or [`Generator::database`]
+pub struct Generator {
+	opt: Opt,
+	input: Option<Input>,
+}
+
+impl Default for Generator {
+	fn default() -> Generator {
+		Generator::new()
+	}
+}
+
+impl Generator {
+	/// A generator with the default options
+	pub fn new() -> Generator {
+		Generator::from_opt(Opt::default())
+	}
+
+	/// A generator with the given options, if `opt.conn` is set it will be used as the source
+	pub fn from_opt(opt: Opt) -> Generator {
+		let input = opt.conn.clone().map(|v| Input::Source(DbSource::Conn(v)));
+		Generator { opt, input }
+	}
+
+	/// Read the database through an existing connection
+	pub fn client(mut self, client: postgres::Client) -> Generator {
+		self.input = Some(Input::Client(Box::new(client)));
+		self
+	}
+
+	/// Read the database with anything that implements [`Introspect`], i.e. a [`SqliteClient`](crate::sqlite::SqliteClient)
+	pub fn introspector(mut self, introspector: impl Introspect + 'static) -> Generator {
+		self.input = Some(Input::Introspector(Box::new(introspector)));
+		self
+	}
+
+	/// Connect to the database with a connection string, see tokio_postgres::Config for details. A SQLite file is given
+	/// as `sqlite:` followed by its path, a MySQL or MariaDB database by a `mysql://` url, and SQL files by the path of a
+	/// `.sql` file or a directory
+	pub fn conn(mut self, conn: &str) -> Generator {
+		self.opt.conn = Some(String::from(conn));
+		self.input = Some(Input::Source(DbSource::Conn(String::from(conn))));
+		self
+	}
+
+	/// Read the database from a snapshot file
+	pub fn snapshot(mut self, path: impl Into<PathBuf>) -> Generator {
+		self.input = Some(Input::Source(DbSource::Snapshot(path.into())));
+		self
+	}
+
+	/// Use an already read database
+	pub fn database(mut self, db: FullDB) -> Generator {
+		self.input = Some(Input::Database(db));
+		self
+	}
+
+	/// Generate a whole crate directory rather than a single file
+	pub fn dir(mut self, dir: bool) -> Generator {
+		self.opt.dir = dir;
+		self
+	}
+
+	/// Skip formatting the output
+	pub fn ugly(mut self, ugly: bool) -> Generator {
+		self.opt.ugly = ugly;
+		self
+	}
+
+	/// How to format the output
+	pub fn formatter(mut self, formatter: Formatter) -> Generator {
+		self.opt.formatter = formatter;
+		self
+	}
+
+	/// Which crates the generated code uses
+	pub fn backend(mut self, backend: Backend) -> Generator {
+		self.opt.backend = backend;
+		self
+	}
+
+	/// Convert names from the database to rust standard casing
+	pub fn rust_case(mut self, rust_case: bool) -> Generator {
+		self.opt.rust_case = rust_case;
+		self
+	}
+
+	/// String passed to rustfmt --config
+	pub fn rustfmt_config(mut self, config: Option<String>) -> Generator {
+		self.opt.rustfmt_config = config;
+		self
+	}
+
+	/// String passed to rustfmt --config-path
+	pub fn rustfmt_config_path(mut self, config_path: Option<String>) -> Generator {
+		self.opt.rustfmt_config_path = config_path;
+		self
+	}
+
+	/// Only make mappings for tables and views
+	pub fn no_functions(mut self, no_functions: bool) -> Generator {
+		self.opt.no_functions = no_functions;
+		self
+	}
+
+	/// Embed a fingerprint of the mapped tables and functions to check against a database at runtime
+	pub fn schema_check(mut self, schema_check: bool) -> Generator {
+		self.opt.schema_check = schema_check;
+		self
+	}
+
+	/// Generate a typed select builder for every table and view
+	pub fn query_builder(mut self, query_builder: bool) -> Generator {
+		self.opt.query_builder = query_builder;
+		self
+	}
+
+	/// Make the field of domain newtypes private so values can only be made with `TryFrom`
+	pub fn private_domain_fields(mut self, private_domain_fields: bool) -> Generator {
+		self.opt.private_domain_fields = private_domain_fields;
+		self
+	}
+
+	/// Give generated enums a catch-all `Unknown(String)` variant for labels added after the mapping was generated
+	pub fn enum_unknown_variant(mut self, enum_unknown_variant: bool) -> Generator {
+		self.opt.enum_unknown_variant = enum_unknown_variant;
+		self
+	}
+
+	/// Generate functions to bulk insert and export every table with binary COPY
+	pub fn copy(mut self, copy: bool) -> Generator {
+		self.opt.copy = copy;
+		self
+	}
+
+	/// Generate a trait per schema with implementations for the clients and a mock implementation
+	pub fn mock(mut self, mock: bool) -> Generator {
+		self.opt.mock = mock;
+		self
+	}
+
+	/// How to use tuples for overloaded functions
+	pub fn use_tuples(mut self, use_tuples: Tuples) -> Generator {
+		self.opt.use_tuples = use_tuples;
+		self
+	}
+
+	/// Third party crates whose types will be mapped to and from sql types
+	pub fn third_party(mut self, third_party: Vec<ThirdParty>) -> Generator {
+		self.opt.third_party = third_party;
+		self
+	}
+
+	/// Map a type by name to a rust type, see [`Opt::extension_types`]
+	pub fn extension_type(mut self, name: &str, rust_type: &str) -> Generator {
+		self.opt.extension_types.push(ExtensionType::new(name, rust_type));
+		self
+	}
+
+	/// Derive more traits on the generated types matching `target`, i.e. `PartialEq` or `schemars::JsonSchema`
+	pub fn derive(mut self, target: TypeTarget, derives: &[&str]) -> Generator {
+		self.opt.derives.push(CustomDerive {
+			target,
+			derives: derives.iter().map(|v| String::from(*v)).collect(),
+		});
+		self
+	}
+
+	/// Add an attribute to the generated types matching `target`, i.e. `#[serde(rename_all = "camelCase")]`
+	pub fn attribute(mut self, target: TypeTarget, attribute: &str) -> Generator {
+		self.opt.attributes.push(CustomAttribute {
+			target,
+			attribute: String::from(attribute),
+		});
+		self
+	}
+
+	/// Put the serde derives behind a `serde` feature of the generated crate
+	pub fn serde_feature(mut self, serde_feature: bool) -> Generator {
+		self.opt.serde_feature = serde_feature;
+		self
+	}
+
+	/// Write each type to its own file, only with `dir`
+	pub fn file_per_type(mut self, file_per_type: bool) -> Generator {
+		self.opt.file_per_type = file_per_type;
+		self
+	}
+
+	/// Write each function to its own file, only with `dir`
+	pub fn file_per_function(mut self, file_per_function: bool) -> Generator {
+		self.opt.file_per_function = file_per_function;
+		self
+	}
+
+	/// Put the modules of each schema behind a `schema-{name}` feature, only with `dir`
+	pub fn schema_features(mut self, schema_features: bool) -> Generator {
+		self.opt.schema_features = schema_features;
+		self
+	}
+
+	/// The name of the generated package
+	pub fn package_name(mut self, name: &str) -> Generator {
+		self.opt.package_name = Some(String::from(name));
+		self
+	}
+
+	/// A TOML file whose contents are set over the generated Cargo.toml, i.e. to give the package's version or take
+	/// dependencies from a workspace
+	pub fn cargo_template(mut self, path: impl Into<PathBuf>) -> Generator {
+		self.opt.cargo_template = Some(path.into());
+		self
+	}
+
+	/// What `GeneratedOutput::write_to` does with a Cargo.toml already in the directory
+	pub fn existing_cargo_toml(mut self, existing_cargo_toml: ExistingCargoToml) -> Generator {
+		self.opt.existing_cargo_toml = existing_cargo_toml;
+		self
+	}
+
+	/// The options that will be used to generate
+	pub fn opt(&self) -> &Opt {
+		&self.opt
+	}
+
+	/// Read the database (if needed) and generate the mapping
+	pub fn generate(self) -> Result<GeneratedOutput, Error> {
+		let warnings = Warnings::default();
+		let db = match self.input {
+			Some(Input::Client(client)) => MyClient::new(*client)?.get_all(self.opt.no_functions)?,
+			Some(Input::Introspector(mut introspector)) => {
+				let db = introspector.introspect(self.opt.no_functions)?;
+				for warning in introspector.take_warnings() {
+					warnings.push(warning);
+				}
+				db
+			},
+			Some(Input::Source(source)) => source.load_with_warnings(self.opt.no_functions, &warnings)?,
+			Some(Input::Database(db)) => db,
+			None => return Err(Error::NoSource),
+		};
+		db.generate_with_warnings(&self.opt, warnings)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn write_to_only_removes_stale_schema_files() {
+		let dir = std::env::temp_dir().join(format!("sql_db_mapper-write-{}", std::process::id()));
+		let kept = [
+			"src/types/public.rs",
+			"src/types/split/item.rs",
+			"src/query.rs",
+			"src/query/old.rs",
+			"src/mock/old.rs",
+			"src/notes.txt",
+		];
+		let removed = ["src/types/old.rs", "src/types/old/item.rs", "src/sync_fns/old.rs", "src/async_fns/old.rs"];
+		for path in kept.iter().chain(&removed) {
+			let path = dir.join(path);
+			std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+			std::fs::write(path, "").unwrap();
+		}
+		let output = GeneratedOutput {
+			files: ["src/lib.rs", "src/types/public.rs", "src/types/split/item.rs"]
+				.iter()
+				.map(|v| (PathBuf::from(v), String::new()))
+				.collect(),
+			cargo_toml: String::new(),
+			existing_cargo_toml: ExistingCargoToml::Overwrite,
+			warnings: Vec::new(),
+		};
+		output.write_to(&dir).unwrap();
+
+		for path in &kept {
+			assert!(dir.join(path).exists(), "{} was removed", path);
+		}
+		for path in &removed {
+			assert!(!dir.join(path).exists(), "{} wasn't removed", path);
+		}
+		// emptied directories are removed too
+		assert!(!dir.join("src/types/old").exists());
+		assert!(!dir.join("src/sync_fns").exists());
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+}
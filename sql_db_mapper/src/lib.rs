@@ -1,14 +1,32 @@
 #![forbid(unsafe_code)]
+#![allow(clippy::tabs_in_doc_comments)]
 //! Connects to a PostgreSQL database and creates a rust module representing all the schemas complete with mappings for stored functions/procedures
+//!
+//! SQLite databases can be read as well and are mapped with rusqlite, as can MySQL and MariaDB databases which are mapped
+//! with mysql and mysql_async. A PostgreSQL database can also be read from the SQL files that create it without connecting
+//! to one
 
 pub mod ast_convert;
+mod cargo_toml;
 pub mod connection;
+pub mod ddl;
+pub mod diff;
+pub mod ephemeral;
+mod generator;
+pub mod migrate;
+pub mod mysql;
 mod pg_select_types;
-mod sql_tree;
+pub mod sql_tree;
+pub mod sqlite;
+pub mod watch;
+
+pub use generator::{Error, GeneratedOutput, Generator, Warnings};
 
 pub const VERSION: &str = std::env!("CARGO_PKG_VERSION");
 
+use connection::Introspect;
 use postgres::{Client, NoTls};
+use sql_tree::{Dialect, FullDB};
 use std::path::PathBuf;
 use structopt::StructOpt;
 
@@ -24,10 +42,26 @@ pub struct Opt {
 	#[structopt(short, long)]
 	pub debug: bool,
 
-	/// Skip running output through rustfmt
+	/// Skip formatting the output
 	#[structopt(short, long)]
 	pub ugly: bool,
 
+	/// How to format the output. Options:
+	/// rustfmt (the default, run the rustfmt program, respects the rustfmt config options, prettyplease is used with a
+	/// warning if it isn't installed).
+	/// prettyplease (format in process, doesn't require rustfmt to be installed).
+	#[structopt(long, default_value = "rustfmt")]
+	pub formatter: Formatter,
+
+	/// Which crates the generated code uses. Options:
+	/// postgres (the default, postgres and tokio-postgres through sql_db_mapper_core).
+	/// sqlx (sqlx derives and async functions taking a `PgExecutor`, without serde derives, ignores the schema check, query builder, copy, mock, and serde feature options).
+	/// diesel (a `schema` module of `table!` macros and Queryable/Insertable structs, without functions or serde derives, ignores the same options as sqlx).
+	/// rusqlite (for SQLite databases, structs with a `from_row` function and a `crud` module of functions for each table, ignores the same options as sqlx).
+	/// mysql (for MySQL and MariaDB databases, types implementing mysql_common's conversions and functions over mysql and mysql_async connections, ignores the same options as sqlx).
+	#[structopt(long, default_value = "postgres")]
+	pub backend: Backend,
+
 	/// Program will treat output as a directory name rather than a file and generate a whole crate. If output is not provided code is printed as usual
 	#[structopt(long)]
 	pub dir: bool,
@@ -48,6 +82,32 @@ pub struct Opt {
 	#[structopt(long)]
 	pub no_functions: bool,
 
+	/// Embed a fingerprint of the mapped tables and functions and generate `verify_schema` functions to check it against a database at runtime
+	#[structopt(long)]
+	pub schema_check: bool,
+
+	/// Generate a `query` module with a typed select builder for every table and view
+	#[structopt(long)]
+	pub query_builder: bool,
+
+	/// Make the field of domain newtypes private so they can only be made with `TryFrom`, which checks the domain's constraints
+	#[structopt(long)]
+	pub private_domain_fields: bool,
+
+	/// Give generated enums a `#[non_exhaustive]` catch-all `Unknown(String)` variant so labels added to the database later
+	/// can still be read
+	#[structopt(long)]
+	pub enum_unknown_variant: bool,
+
+	/// Generate a `copy` module with functions to bulk insert and export every table with binary COPY
+	#[structopt(long)]
+	pub copy: bool,
+
+	/// Generate a `mock` module with a trait per schema covering its functions (and the selects and inserts of the query
+	/// builder and copy modules), implementations for the sync and async clients, and a mock implementation
+	#[structopt(long)]
+	pub mock: bool,
+
 	/// How to use tuples (used by default for just overloads). Options:
 	/// overloads (the default, use tuples to represent function overloading).
 	/// all (Have all functions take a tuple for consitency).
@@ -57,18 +117,209 @@ pub struct Opt {
 	pub use_tuples: Tuples,
 
 	/// A comma seperated list of third party crates which contain types that will be mapped to and from sql types.
-	/// Valid values are "bit_vec,chrono,eui48,geo_types,rust_decimal,serde_json,time,uuid"
+	/// regex is used to check regex CHECK constraints of domains.
+	/// Valid values are "bit_vec,chrono,eui48,geo_types,regex,rust_decimal,serde_json,time,uuid"
 	#[structopt(long, use_delimiter = true)]
 	pub third_party: Vec<ThirdParty>,
 
-	/// String to connect to database, see tokio_postgres::Config for details.
-	/// If not provided environment variable DATABASE_URL is checked instead
+	/// Map a type by name to a rust type, given as `type_name=path::to::Type`. Can be given multiple times.
+	/// Used for types from extensions which don't have fixed oids, overrides the built in mappings of citext, hstore, ltree, and vector
+	#[structopt(long = "extension-type", number_of_values = 1)]
+	pub extension_types: Vec<ExtensionType>,
+
+	/// Derive more traits on generated types, given as `[target=]Trait,Trait`. The target is a schema or a `schema.type`, without
+	/// one the traits are derived for every type. Can be given multiple times
+	#[structopt(long = "derive", number_of_values = 1)]
+	pub derives: Vec<CustomDerive>,
+
+	/// Add an attribute to generated types, given as `[target=]#[attribute]`. The target is a schema or a `schema.type`, without
+	/// one the attribute is added to every type. Can be given multiple times
+	#[structopt(long = "attribute", number_of_values = 1)]
+	pub attributes: Vec<CustomAttribute>,
+
+	/// Put the serde derives and attributes behind a `serde` feature of the generated crate
+	#[structopt(long)]
+	pub serde_feature: bool,
+
+	/// With --dir write each type (i.e. a table) of a schema to its own file in `src/types/{schema}/`. Each file is a
+	/// module re-exported by the schema's module so a change to one type only recompiles that module
+	#[structopt(long)]
+	pub file_per_type: bool,
+
+	/// With --dir write each function (with its overloads) of a schema to its own file in `src/sync_fns/{schema}/` and
+	/// `src/async_fns/{schema}/`, which are modules the same as with --file-per-type
+	#[structopt(long)]
+	pub file_per_function: bool,
+
+	/// With --dir put the modules of each schema behind a `schema-{name}` feature of the generated crate, all of them are
+	/// default features. Characters of the name other than ASCII letters, digits, `_`, and `-` are replaced with `_`. Not
+	/// supported by the diesel backend
+	#[structopt(long)]
+	pub schema_features: bool,
+
+	/// String to connect to database, see tokio_postgres::Config for details. A SQLite database is given as `sqlite:` followed by
+	/// the path of its file and a MySQL or MariaDB database by a `mysql://` url. The path of a `.sql` file or a directory of
+	/// them reads the database those files would create without connecting to one. If not provided environment variable
+	/// DATABASE_URL is checked instead
 	#[structopt(long, env = "DATABASE_URL")]
-	pub conn: String,
+	pub conn: Option<String>,
+
+	/// Name of the generated package. Defaults to the name of the output file or directory
+	#[structopt(long)]
+	pub package_name: Option<String>,
+
+	/// A TOML file laid out like a Cargo.toml whose contents are set over the generated Cargo.toml, i.e. the `[package]`
+	/// name, version, edition, and authors, more `[dependencies]`, or dependencies with `workspace = true`. The keys of a
+	/// dependency are set over the generated one's and its features added to them
+	#[structopt(long, parse(from_os_str))]
+	pub cargo_template: Option<PathBuf>,
+
+	/// What to do with a Cargo.toml already in the output directory with --dir. Options:
+	/// overwrite (the default, replace it with the generated one).
+	/// keep (leave it as it is).
+	/// merge (set the generated dependencies and features in it, keeping its package and everything else).
+	#[structopt(long, default_value = "overwrite")]
+	pub existing_cargo_toml: ExistingCargoToml,
 
 	/// Output file, stdout if not present
 	#[structopt(parse(from_os_str))]
 	pub output: Option<PathBuf>,
+
+	/// Run another command instead of generating a mapping
+	#[structopt(subcommand)]
+	pub cmd: Option<Command>,
+}
+
+impl Default for Opt {
+	fn default() -> Opt {
+		Opt {
+			debug: false,
+			ugly: false,
+			formatter: Formatter::Rustfmt,
+			backend: Backend::Postgres,
+			dir: false,
+			rust_case: false,
+			rustfmt_config: None,
+			rustfmt_config_path: None,
+			no_functions: false,
+			schema_check: false,
+			query_builder: false,
+			private_domain_fields: false,
+			enum_unknown_variant: false,
+			copy: false,
+			mock: false,
+			use_tuples: Tuples::ForOverloads,
+			third_party: Vec::new(),
+			extension_types: Vec::new(),
+			derives: Vec::new(),
+			attributes: Vec::new(),
+			serde_feature: false,
+			file_per_type: false,
+			file_per_function: false,
+			schema_features: false,
+			conn: None,
+			package_name: None,
+			cargo_template: None,
+			existing_cargo_toml: ExistingCargoToml::Overwrite,
+			output: None,
+			cmd: None,
+		}
+	}
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+	/// Save the database structure read from --conn to a JSON snapshot file which can be used as a source for diff
+	Snapshot {
+		/// File to write the snapshot to
+		#[structopt(parse(from_os_str))]
+		file: PathBuf,
+	},
+	/// Report the changes between two databases and which of them break the generated API.
+	/// Each source is either a path to a snapshot file or a connection string
+	Diff {
+		/// The database the current mapping was generated from
+		old: DbSource,
+		/// The database to compare against
+		new: DbSource,
+	},
+	/// Apply the `.sql` files in a directory which haven't been applied to the PostgreSQL database given by --conn, each in
+	/// a transaction and recorded in a `sql_db_mapper_migrations` table, then generate the mapping as usual.
+	/// Each file name starts with its version, i.e. `0001_create_people.sql`
+	Migrate {
+		/// Directory of the migration files
+		#[structopt(parse(from_os_str))]
+		dir: PathBuf,
+		/// Instead of --conn apply every migration to a new server made with `initdb` and `pg_ctl` from the PATH, which is
+		/// removed once the mapping is generated
+		#[structopt(long)]
+		ephemeral: bool,
+	},
+	/// Generate the mapping to the output then regenerate it whenever the database given by --conn changes, printing what
+	/// changed in the generated API. Changes are noticed by listening for `NOTIFY sql_db_mapper`
+	Watch {
+		/// Install an event trigger which notifies `sql_db_mapper` at the end of every DDL command, requires a superuser
+		#[structopt(long)]
+		install_trigger: bool,
+		/// Milliseconds to wait for more notifications after one arrives before regenerating
+		#[structopt(long, default_value = "500")]
+		debounce: u64,
+	},
+}
+
+/// Somewhere a [`FullDB`] can be read from
+#[derive(Debug, Clone)]
+pub enum DbSource {
+	/// A connection string, see tokio_postgres::Config for details, `sqlite:` followed by the path of a SQLite file, a
+	/// `mysql://` url, or the path of a `.sql` file or a directory of them
+	Conn(String),
+	/// A snapshot file written by the snapshot command
+	Snapshot(PathBuf),
+}
+impl std::str::FromStr for DbSource {
+	type Err = String;
+
+	/// Any string that is the path of an existing file is treated as a snapshot, unless it's a `.sql` file
+	///
+	/// A path that doesn't exist (a `.json` file or anything with a `/` that isn't a connection string) is an error rather
+	/// than a connection string so a mistyped snapshot isn't reported as a failure to connect
+	fn from_str(s: &str) -> Result<DbSource, String> {
+		let path = PathBuf::from(s);
+		if path.is_file() && !ddl::is_ddl_path(s) {
+			Ok(DbSource::Snapshot(path))
+		} else if !path.exists() && looks_like_path(s) {
+			Err(format!("{} doesn't exist", s))
+		} else {
+			Ok(DbSource::Conn(String::from(s)))
+		}
+	}
+}
+
+/// Whether a source is written as a path rather than a connection string, which have `=`, `://`, or `sqlite:`
+fn looks_like_path(s: &str) -> bool {
+	let is_conn = s.contains('=') || s.contains("://") || s.starts_with("sqlite:");
+	!is_conn && (s.ends_with(".json") || s.contains(std::path::MAIN_SEPARATOR) || s.contains('/'))
+}
+impl DbSource {
+	/// Read the whole database
+	pub fn load(&self, no_functions: bool) -> Result<FullDB, Error> {
+		self.load_with_warnings(no_functions, &Warnings::default())
+	}
+
+	/// Read the whole database, adding anything in SQL files that couldn't be read to `warnings`
+	pub fn load_with_warnings(&self, no_functions: bool, warnings: &Warnings) -> Result<FullDB, Error> {
+		match self {
+			DbSource::Conn(conn) => {
+				let mut introspector = introspector(conn)?;
+				let full_db = introspector.introspect(no_functions)?;
+				for warning in introspector.take_warnings() {
+					warnings.push(warning);
+				}
+				Ok(full_db)
+			},
+			DbSource::Snapshot(path) => Ok(FullDB::load_snapshot(path)?),
+		}
+	}
 }
 
 #[derive(Debug, StructOpt, Clone, Copy, PartialEq, Eq)]
@@ -96,7 +347,7 @@ impl std::str::FromStr for Tuples {
 	}
 }
 impl Tuples {
-	fn to_str(&self) -> &'static str {
+	fn to_str(self) -> &'static str {
 		match self {
 			Tuples::ForOverloads => "overloads",
 			Tuples::ForAll => "all",
@@ -105,6 +356,112 @@ impl Tuples {
 		}
 	}
 }
+/// The backend used to format generated code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Formatter {
+	/// Run the rustfmt program
+	Rustfmt,
+	/// Format in process with the prettyplease crate
+	Prettyplease,
+}
+impl std::str::FromStr for Formatter {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Formatter, &'static str> {
+		match s {
+			"rustfmt" => Ok(Formatter::Rustfmt),
+			"prettyplease" => Ok(Formatter::Prettyplease),
+			_ => Err("Invalid formatter, use one of (rustfmt, prettyplease)"),
+		}
+	}
+}
+impl Formatter {
+	fn to_str(self) -> &'static str {
+		match self {
+			Formatter::Rustfmt => "rustfmt",
+			Formatter::Prettyplease => "prettyplease",
+		}
+	}
+}
+/// What to do with a Cargo.toml already in the output directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExistingCargoToml {
+	/// Replace it with the generated one
+	Overwrite,
+	/// Leave it as it is, it's only written if there isn't one
+	Keep,
+	/// Set the generated dependencies and features in it, keeping its comments and formatting
+	Merge,
+}
+impl std::str::FromStr for ExistingCargoToml {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<ExistingCargoToml, &'static str> {
+		match s {
+			"overwrite" => Ok(ExistingCargoToml::Overwrite),
+			"keep" => Ok(ExistingCargoToml::Keep),
+			"merge" => Ok(ExistingCargoToml::Merge),
+			_ => Err("Invalid existing Cargo.toml handling, use one of (overwrite, keep, merge)"),
+		}
+	}
+}
+impl ExistingCargoToml {
+	fn to_str(self) -> &'static str {
+		match self {
+			ExistingCargoToml::Overwrite => "overwrite",
+			ExistingCargoToml::Keep => "keep",
+			ExistingCargoToml::Merge => "merge",
+		}
+	}
+}
+/// The crates generated code is written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+	/// postgres and tokio-postgres, through sql_db_mapper_core
+	Postgres,
+	/// sqlx with its postgres driver
+	Sqlx,
+	/// diesel's `table!` schema and models, functions aren't mapped
+	Diesel,
+	/// rusqlite, the only backend which maps SQLite databases
+	Rusqlite,
+	/// mysql and mysql_async, the only backend which maps MySQL and MariaDB databases
+	Mysql,
+}
+impl std::str::FromStr for Backend {
+	type Err = &'static str;
+
+	fn from_str(s: &str) -> Result<Backend, &'static str> {
+		match s {
+			"postgres" => Ok(Backend::Postgres),
+			"sqlx" => Ok(Backend::Sqlx),
+			"diesel" => Ok(Backend::Diesel),
+			"rusqlite" => Ok(Backend::Rusqlite),
+			"mysql" => Ok(Backend::Mysql),
+			_ => Err("Invalid backend, use one of (postgres, sqlx, diesel, rusqlite, mysql)"),
+		}
+	}
+}
+impl Backend {
+	fn to_str(self) -> &'static str {
+		match self {
+			Backend::Postgres => "postgres",
+			Backend::Sqlx => "sqlx",
+			Backend::Diesel => "diesel",
+			Backend::Rusqlite => "rusqlite",
+			Backend::Mysql => "mysql",
+		}
+	}
+
+	/// The kind of database the backend maps
+	pub fn dialect(self) -> Dialect {
+		match self {
+			Backend::Postgres | Backend::Sqlx | Backend::Diesel => Dialect::Postgres,
+			Backend::Rusqlite => Dialect::Sqlite,
+			Backend::Mysql => Dialect::Mysql,
+		}
+	}
+}
 #[derive(Debug, StructOpt, Clone, Copy, PartialEq, Eq)]
 pub enum ThirdParty {
 	Chrono,
@@ -115,6 +472,7 @@ pub enum ThirdParty {
 	Uuid,
 	BitVec,
 	RustDecimal,
+	Regex,
 }
 impl std::str::FromStr for ThirdParty {
 	type Err = String;
@@ -125,6 +483,7 @@ impl std::str::FromStr for ThirdParty {
 			"chrono" => Ok(ThirdParty::Chrono),
 			"eui48" => Ok(ThirdParty::Eui48),
 			"geo_types" => Ok(ThirdParty::GeoTypes),
+			"regex" => Ok(ThirdParty::Regex),
 			"rust_decimal" => Ok(ThirdParty::RustDecimal),
 			"serde_json" => Ok(ThirdParty::SerdeJson),
 			"time" => Ok(ThirdParty::Time),
@@ -134,12 +493,13 @@ impl std::str::FromStr for ThirdParty {
 	}
 }
 impl ThirdParty {
-	fn to_str(&self) -> &'static str {
+	fn to_str(self) -> &'static str {
 		match self {
 			ThirdParty::BitVec => "with-bit-vec-0_6",
 			ThirdParty::Chrono => "with-chrono-0_4",
 			ThirdParty::Eui48 => "with-eui48-0_4",
 			ThirdParty::GeoTypes => "with-geo-types-0_6",
+			ThirdParty::Regex => "with-regex-1",
 			ThirdParty::RustDecimal => "with-rust_decimal-1",
 			ThirdParty::SerdeJson => "with-serde_json-1",
 			ThirdParty::Uuid => "with-uuid-0_8",
@@ -148,17 +508,173 @@ impl ThirdParty {
 	}
 }
 
+/// A mapping from a SQL type, found by name, to a rust type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionType {
+	/// The name of the type in the database
+	pub name: String,
+	/// Path of the rust type, i.e. `my_crate::Geometry`
+	pub rust_type: String,
+}
+impl std::str::FromStr for ExtensionType {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<ExtensionType, String> {
+		let (name, rust_type) = s
+			.split_once('=')
+			.ok_or_else(|| format!("Invalid extension type `{}`, use type_name=path::to::Type", s))?;
+		let (name, rust_type) = (name.trim(), rust_type.trim());
+		if name.is_empty() || syn::parse_str::<syn::Type>(rust_type).is_err() {
+			return Err(format!("Invalid extension type `{}`, use type_name=path::to::Type", s));
+		}
+		Ok(ExtensionType {
+			name: String::from(name),
+			rust_type: String::from(rust_type),
+		})
+	}
+}
+impl ExtensionType {
+	pub fn new(name: &str, rust_type: &str) -> ExtensionType {
+		ExtensionType {
+			name: String::from(name),
+			rust_type: String::from(rust_type),
+		}
+	}
+}
+
+/// Which generated types a [`CustomDerive`] or [`CustomAttribute`] is added to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeTarget {
+	All,
+	/// Every type in the schema
+	Schema(String),
+	/// A single type, given as schema and type name
+	Type(String, String),
+}
+impl std::str::FromStr for TypeTarget {
+	type Err = std::convert::Infallible;
+
+	fn from_str(s: &str) -> Result<TypeTarget, Self::Err> {
+		Ok(match s.split_once('.') {
+			Some((schema, name)) => TypeTarget::Type(String::from(schema), String::from(name)),
+			None => TypeTarget::Schema(String::from(s)),
+		})
+	}
+}
+impl TypeTarget {
+	fn matches(&self, schema: &str, name: &str) -> bool {
+		match self {
+			TypeTarget::All => true,
+			TypeTarget::Schema(v) => v == schema,
+			TypeTarget::Type(v_schema, v_name) => v_schema == schema && v_name == name,
+		}
+	}
+
+	/// The target as it's given on the command line, including the `=`
+	fn to_prefix(&self) -> String {
+		match self {
+			TypeTarget::All => String::new(),
+			TypeTarget::Schema(schema) => format!("{}=", schema),
+			TypeTarget::Type(schema, name) => format!("{}.{}=", schema, name),
+		}
+	}
+
+	/// Split a target from the front of an option, the target is optional so `value` is returned unchanged if there's no `=`
+	fn split(s: &str) -> (TypeTarget, &str) {
+		match s.split_once('=') {
+			Some((target, value)) if !target.contains(['#', '(', '"']) => {
+				(target.trim().parse().unwrap_or(TypeTarget::All), value.trim())
+			},
+			_ => (TypeTarget::All, s.trim()),
+		}
+	}
+}
+
+/// Traits to derive on some of the generated types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomDerive {
+	pub target: TypeTarget,
+	/// Paths of the derive macros, i.e. `schemars::JsonSchema`
+	pub derives: Vec<String>,
+}
+impl std::str::FromStr for CustomDerive {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<CustomDerive, String> {
+		let (target, derives) = TypeTarget::split(s);
+		let derives: Vec<_> = derives.split(',').map(|v| String::from(v.trim())).collect();
+		if derives.iter().any(|v| syn::parse_str::<syn::Path>(v).is_err()) {
+			return Err(format!("Invalid derive `{}`, use [schema.type=]Trait,Trait", s));
+		}
+		Ok(CustomDerive { target, derives })
+	}
+}
+
+/// An attribute to add to some of the generated types
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomAttribute {
+	pub target: TypeTarget,
+	/// The whole attribute, i.e. `#[serde(rename_all = "camelCase")]`
+	pub attribute: String,
+}
+impl std::str::FromStr for CustomAttribute {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<CustomAttribute, String> {
+		use syn::parse::Parser as _;
+		let (target, attribute) = TypeTarget::split(s);
+		match syn::Attribute::parse_outer.parse_str(attribute) {
+			Ok(v) if v.len() == 1 => Ok(CustomAttribute {
+				target,
+				attribute: String::from(attribute),
+			}),
+			_ => Err(format!("Invalid attribute `{}`, use [schema.type=]#[attribute]", s)),
+		}
+	}
+}
+
+/// The types of common extensions that are mapped without being given with `--extension-type`, as (extension, type, rust type)
+///
+/// These are only used for types which belong to the extension so a type with the same name made some other way isn't mapped
+pub const BUILTIN_EXTENSION_TYPES: &[(&str, &str, &str)] = &[
+	("citext", "citext", "String"),
+	("hstore", "hstore", "std::collections::HashMap<String, Option<String>>"),
+	("ltree", "ltree", "crate::orm::Ltree"),
+	("vector", "vector", "crate::orm::Vector"),
+];
+
+/// The types sqlx has for extension types, used instead of `BUILTIN_EXTENSION_TYPES` with the sqlx backend
+pub const BUILTIN_SQLX_EXTENSION_TYPES: &[(&str, &str, &str)] = &[
+	("citext", "citext", "String"),
+	("hstore", "hstore", "::sqlx::postgres::types::PgHstore"),
+	("ltree", "ltree", "::sqlx::postgres::types::PgLTree"),
+];
+
+/// The extension types diesel has sql types for, used instead of `BUILTIN_EXTENSION_TYPES` with the diesel backend
+pub const BUILTIN_DIESEL_EXTENSION_TYPES: &[(&str, &str, &str)] = &[("citext", "citext", "String")];
+
+/// The dependency of a generated crate on sql_db_mapper_core, requiring the version this was built with as the generated
+/// code uses what that version has. `options` are added after the version
+fn core_dependency(options: &str) -> String {
+	format!(
+		r#"sql_db_mapper_core = {{ version = "{}"{} }}"#,
+		sql_db_mapper_core::VERSION,
+		options
+	)
+}
+
 impl Opt {
 	/// Produce the Cargo.toml file contents (the dependecies of the generated code)
 	pub fn get_cargo_toml(&self) -> String {
-		let package_name = self
-			.output
-			.as_ref()
-			.map(|v| v.file_name())
-			.flatten()
-			.map(|v| v.to_str())
-			.flatten()
-			.unwrap_or("my_db_mapping");
+		let package_name = self.get_package_name();
+
+		match self.backend {
+			Backend::Postgres => (),
+			Backend::Sqlx => return self.get_sqlx_cargo_toml(package_name),
+			Backend::Diesel => return self.get_diesel_cargo_toml(None),
+			Backend::Rusqlite => return self.get_rusqlite_cargo_toml(package_name),
+			Backend::Mysql => return self.get_mysql_cargo_toml(package_name),
+		}
 
 		let dependencies = format!("[package]\nname = \"{}\"", package_name)
 			+ r#"
@@ -166,24 +682,201 @@ version = "0.1.0"
 edition = "2018"
 
 [dependencies]
-sql_db_mapper_core = { version = "0.1.0", features = ["#
-			+ &self.get_dependencies()
-			+ r#"] }
+"# + &core_dependency(&format!(", features = [{}]", self.get_dependencies()))
+			+ r#"
 postgres-types = { version = "0.2", features = ["derive"] }
 async-trait = { version = "0.1", optional = true }
 
-serde = { version = "1.0", features = ["derive"] }
+"# + if self.serde_feature {
+			r#"serde = { version = "1.0", features = ["derive"], optional = true }"#
+		} else {
+			r#"serde = { version = "1.0", features = ["derive"] }"#
+		} + r#"
 
 [features]
 sync = []
 async = ["async-trait"]
-"#;
+# let async functions take deadpool-postgres or bb8 pools and their connections
+deadpool = ["async", "sql_db_mapper_core/with-deadpool-postgres-0_14"]
+bb8 = ["async", "sql_db_mapper_core/with-bb8-0_8"]
+"# + if self.serde_feature {
+			r#"serde = ["dep:serde", "sql_db_mapper_core/serde"]
+"#
+		} else {
+			""
+		};
 
 		dependencies
 	}
 
+	/// The name of the generated crate, from package_name or the output directory
+	fn get_package_name(&self) -> &str {
+		self.package_name
+			.as_deref()
+			.or_else(|| self.output.as_ref().and_then(|v| v.file_name()).and_then(|v| v.to_str()))
+			.unwrap_or("my_db_mapping")
+	}
+
+	/// The Cargo.toml of a mapping for the sqlx backend, which only uses sql_db_mapper_core for its error types and regex and
+	/// doesn't derive serde's traits
+	fn get_sqlx_cargo_toml(&self, package_name: &str) -> String {
+		let core_features = if self.third_party.contains(&ThirdParty::Regex) {
+			r#""with-regex-1""#
+		} else {
+			""
+		};
+		let mut sqlx_features = String::from(r#""postgres", "macros", "ipnetwork""#);
+		for (lib, feature) in [
+			(ThirdParty::BitVec, "bit-vec"),
+			(ThirdParty::Chrono, "chrono"),
+			(ThirdParty::Eui48, "mac_address"),
+			(ThirdParty::RustDecimal, "rust_decimal"),
+			(ThirdParty::SerdeJson, "json"),
+			(ThirdParty::Time, "time"),
+			(ThirdParty::Uuid, "uuid"),
+		] {
+			if self.third_party.contains(&lib) {
+				sqlx_features += &format!(r#", "{}""#, feature);
+			}
+		}
+
+		format!("[package]\nname = \"{}\"", package_name)
+			+ r#"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+"# + &core_dependency(&format!(", default-features = false, features = [{}]", core_features))
+			+ r#"
+# add a runtime feature of sqlx (i.e. runtime-tokio) in the crate using the mapping
+# 0.8.3 is the first version with the geometric types
+sqlx = { version = "0.8.3", default-features = false, features = ["#
+			+ &sqlx_features
+			+ r#"] }
+"#
+	}
+
+	/// The Cargo.toml of a mapping for the diesel backend, the crates with types diesel maps are dependencies of the mapping
+	/// as sql_db_mapper_core's versions of them aren't all ones diesel supports
+	///
+	/// `column_feature` is the feature of diesel the mapping's widest table needs
+	pub(crate) fn get_diesel_cargo_toml(&self, column_feature: Option<&str>) -> String {
+		let package_name = self.get_package_name();
+		let core_features = if self.third_party.contains(&ThirdParty::Regex) {
+			r#""with-regex-1""#
+		} else {
+			""
+		};
+		let mut diesel_features = String::from(r#""postgres", "network-address""#);
+		if let Some(feature) = column_feature {
+			diesel_features += &format!(r#", "{}""#, feature);
+		}
+		let mut dependencies = String::from("ipnetwork = \"0.21\"\n");
+		for (lib, feature, dependency) in [
+			(ThirdParty::Chrono, "chrono", r#"chrono = "0.4""#),
+			(ThirdParty::SerdeJson, "serde_json", r#"serde_json = "1.0""#),
+			(ThirdParty::Time, "time", r#"time = "0.3""#),
+			(ThirdParty::Uuid, "uuid", r#"uuid = "1.0""#),
+		] {
+			if self.third_party.contains(&lib) {
+				diesel_features += &format!(r#", "{}""#, feature);
+				dependencies += dependency;
+				dependencies += "\n";
+			}
+		}
+		if self.third_party.contains(&ThirdParty::RustDecimal) {
+			dependencies += r#"rust_decimal = { version = "1.10", features = ["db-diesel2-postgres"] }"#;
+			dependencies += "\n";
+		}
+
+		format!("[package]\nname = \"{}\"", package_name)
+			+ r#"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+"# + &core_dependency(&format!(", default-features = false, features = [{}]", core_features))
+			+ r#"
+diesel = { version = "2.2", features = ["#
+			+ &diesel_features
+			+ "] }\n"
+			+ &dependencies
+	}
+
+	/// The Cargo.toml of a mapping for the rusqlite backend, like diesel the crates rusqlite maps are dependencies of the mapping
+	fn get_rusqlite_cargo_toml(&self, package_name: &str) -> String {
+		let mut rusqlite_features = Vec::new();
+		let mut dependencies = String::new();
+		for (lib, feature, dependency) in [
+			(ThirdParty::Chrono, "chrono", r#"chrono = "0.4""#),
+			(ThirdParty::SerdeJson, "serde_json", r#"serde_json = "1.0""#),
+			(ThirdParty::Time, "time", r#"time = "0.3""#),
+		] {
+			if self.third_party.contains(&lib) {
+				rusqlite_features.push(format!(r#""{}""#, feature));
+				dependencies += dependency;
+				dependencies += "\n";
+			}
+		}
+
+		format!("[package]\nname = \"{}\"", package_name)
+			+ r#"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+"# + &core_dependency(", default-features = false")
+			+ r#"
+# add the bundled feature of rusqlite to build SQLite along with the crate
+rusqlite = { version = "0.32", features = ["#
+			+ &rusqlite_features.join(", ")
+			+ "] }\n"
+			+ &dependencies
+	}
+
+	/// The Cargo.toml of a mapping for the mysql backend, the types are those of mysql_common which both mysql and mysql_async
+	/// use so those two are behind the sync and async features
+	fn get_mysql_cargo_toml(&self, package_name: &str) -> String {
+		let mut mysql_common_features = Vec::new();
+		for (lib, feature) in [
+			(ThirdParty::Chrono, "chrono"),
+			(ThirdParty::RustDecimal, "rust_decimal"),
+			(ThirdParty::Time, "time"),
+		] {
+			if self.third_party.contains(&lib) {
+				mysql_common_features.push(format!(r#""{}""#, feature));
+			}
+		}
+
+		format!("[package]\nname = \"{}\"", package_name)
+			+ r#"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+"# + &core_dependency(", default-features = false")
+			+ r#"
+mysql_common = { version = "0.32", default-features = false, features = ["#
+			+ &mysql_common_features.join(", ")
+			+ r#"] }
+# mysql_common needs a compression backend, it's the same one the minimal features of mysql and mysql_async pick
+flate2 = { version = "1.0", default-features = false, features = ["zlib"] }
+mysql = { version = "25", default-features = false, features = ["minimal"], optional = true }
+# native-tls-tls can be replaced by rustls-tls, mysql_async needs one of them
+mysql_async = { version = "0.34", default-features = false, features = ["minimal", "native-tls-tls"], optional = true }
+
+[features]
+sync = ["mysql"]
+async = ["mysql_async"]
+"#
+	}
+
 	fn get_dependencies(&self) -> String {
 		let mut ret = String::new();
+		// serde's traits for the types of sql_db_mapper_core, behind the crate's own serde feature with `serde_feature`
+		if !self.serde_feature {
+			ret += r#""serde", "#;
+		}
 		if self.third_party.contains(&ThirdParty::BitVec) {
 			ret += r#""with-bit-vec-0_6", "#;
 		}
@@ -196,6 +889,9 @@ async = ["async-trait"]
 		if self.third_party.contains(&ThirdParty::GeoTypes) {
 			ret += r#""with-geo-types-0_6", "#;
 		}
+		if self.third_party.contains(&ThirdParty::Regex) {
+			ret += r#""with-regex-1", "#;
+		}
 		if self.third_party.contains(&ThirdParty::RustDecimal) {
 			ret += r#""with-rust_decimal-1", "#;
 		}
@@ -214,9 +910,25 @@ async = ["async-trait"]
 	/// Build a call string that could be used to get the same options
 	pub fn get_call_string(&self) -> String {
 		let ugly = if self.ugly { " -u" } else { "" };
+		let formatter = if self.formatter == Formatter::Rustfmt {
+			String::new()
+		} else {
+			format!(" --formatter {}", self.formatter.to_str())
+		};
+		let backend = if self.backend == Backend::Postgres {
+			String::new()
+		} else {
+			format!(" --backend {}", self.backend.to_str())
+		};
 		let dir = if self.dir { " --dir" } else { "" };
 		let rust_case = if self.rust_case { " --rust_case" } else { "" };
 		let no_functions = if self.no_functions { " --no_functions" } else { "" };
+		let schema_check = if self.schema_check { " --schema-check" } else { "" };
+		let query_builder = if self.query_builder { " --query-builder" } else { "" };
+		let private_domain_fields = if self.private_domain_fields { " --private-domain-fields" } else { "" };
+		let enum_unknown_variant = if self.enum_unknown_variant { " --enum-unknown-variant" } else { "" };
+		let copy = if self.copy { " --copy" } else { "" };
+		let mock = if self.mock { " --mock" } else { "" };
 		let use_tuples = if self.use_tuples == Tuples::ForOverloads {
 			String::new()
 		} else {
@@ -228,35 +940,162 @@ async = ["async-trait"]
 			let list = self.third_party.iter().map(|v| v.to_str()).fold(String::new(), |acc, v| acc+v+",");
 			format!(" --third-party \"{}\"", &list[..(list.len()-1)])
 		};
+		let extension_types = self.extension_types.iter().fold(String::new(), |acc, v| {
+			acc + &format!(" --extension-type \"{}={}\"", v.name, v.rust_type)
+		});
+		let derives = self.derives.iter().fold(String::new(), |acc, v| {
+			acc + &format!(" --derive \"{}{}\"", v.target.to_prefix(), v.derives.join(","))
+		});
+		let attributes = self.attributes.iter().fold(String::new(), |acc, v| {
+			acc + &format!(" --attribute '{}{}'", v.target.to_prefix(), v.attribute)
+		});
+		let serde_feature = if self.serde_feature { " --serde-feature" } else { "" };
+		let file_per_type = if self.file_per_type { " --file-per-type" } else { "" };
+		let file_per_function = if self.file_per_function { " --file-per-function" } else { "" };
+		let schema_features = if self.schema_features { " --schema-features" } else { "" };
+		let cargo_template = match &self.cargo_template {
+			Some(path) => format!(" --cargo-template \"{}\"", path.display()),
+			None => String::new(),
+		};
+		let existing_cargo_toml = if self.existing_cargo_toml == ExistingCargoToml::Overwrite {
+			String::new()
+		} else {
+			format!(" --existing-cargo-toml {}", self.existing_cargo_toml.to_str())
+		};
 		format!(
-			"sql_db_mapper{ugly}{dir}{rust_case}{no_functions}{use_tuples}{third_party}",
+			"sql_db_mapper{ugly}{formatter}{backend}{dir}{rust_case}{no_functions}{schema_check}{query_builder}{private_domain_fields}{enum_unknown_variant}{copy}{mock}{use_tuples}{third_party}{extension_types}{derives}{attributes}{serde_feature}{file_per_type}{file_per_function}{schema_features}{cargo_template}{existing_cargo_toml}",
 			ugly = ugly,
+			formatter = formatter,
+			backend = backend,
 			dir = dir,
 			rust_case = rust_case,
 			no_functions = no_functions,
+			schema_check = schema_check,
+			query_builder = query_builder,
+			private_domain_fields = private_domain_fields,
+			enum_unknown_variant = enum_unknown_variant,
+			copy = copy,
+			mock = mock,
 			use_tuples = use_tuples,
 			third_party = third_party,
+			extension_types = extension_types,
+			derives = derives,
+			attributes = attributes,
+			serde_feature = serde_feature,
+			file_per_type = file_per_type,
+			file_per_function = file_per_function,
+			schema_features = schema_features,
+			cargo_template = cargo_template,
+			existing_cargo_toml = existing_cargo_toml,
 		)
 	}
 
-	pub fn get_client(&self) -> connection::MyClient {
-		let client = Client::connect(&self.conn, NoTls)
-			.expect("Failed to connect to database, please check your connection string and try again");
+	/// Connect to the database given by `conn`
+	pub fn get_client(&self) -> Result<connection::MyClient, Error> {
+		let conn = self.conn.as_ref().ok_or(Error::NoSource)?;
+		connect(conn)
+	}
 
-		connection::MyClient::new(client)
+	/// Open the database given by `conn`, which may be PostgreSQL, SQLite, or MySQL
+	pub fn get_introspector(&self) -> Result<Box<dyn Introspect>, Error> {
+		let conn = self.conn.as_ref().ok_or(Error::NoSource)?;
+		introspector(conn)
 	}
 
 	fn uses_lib(&self, lib_name: ThirdParty) -> bool {
 		self.third_party.contains(&lib_name)
 	}
+
+	/// The rust type for a type that doesn't have a fixed oid, types given by the user take precedence over the built in ones
+	fn extension_type(&self, name: &str, extension: Option<&str>) -> Option<&str> {
+		self.extension_types
+			.iter()
+			.rev()
+			.find(|v| v.name == name)
+			.map(|v| v.rust_type.as_str())
+			.or_else(|| {
+				let builtin = match self.backend {
+					Backend::Postgres => BUILTIN_EXTENSION_TYPES,
+					Backend::Sqlx => BUILTIN_SQLX_EXTENSION_TYPES,
+					Backend::Diesel => BUILTIN_DIESEL_EXTENSION_TYPES,
+					Backend::Rusqlite | Backend::Mysql => &[],
+				};
+				builtin
+					.iter()
+					.find(|(ext, typ, _)| Some(*ext) == extension && *typ == name)
+					.map(|(.., rust_type)| *rust_type)
+			})
+	}
+
+	/// Whether an option which only applies with `dir` is in effect
+	fn dir_only(&self, option: bool) -> bool {
+		option && self.dir
+	}
+
+	/// Whether the modules of each schema are behind a feature, the diesel backend's joins between schemas can't be
+	fn uses_schema_features(&self) -> bool {
+		self.dir_only(self.schema_features) && self.backend != Backend::Diesel
+	}
+
+	/// Whether an option which only applies to the postgres backend is in effect, the other backends ignore them
+	fn postgres_only(&self, option: bool) -> bool {
+		option && self.backend == Backend::Postgres
+	}
+
+	/// The traits given with `--derive` for a type
+	fn custom_derives<'a>(&'a self, schema: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+		self.derives
+			.iter()
+			.filter(move |v| v.target.matches(schema, name))
+			.flat_map(|v| v.derives.iter().map(String::as_str))
+	}
+
+	/// The attributes given with `--attribute` for a type
+	fn custom_attributes<'a>(&'a self, schema: &'a str, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+		self.attributes
+			.iter()
+			.filter(move |v| v.target.matches(schema, name))
+			.map(|v| v.attribute.as_str())
+	}
 }
 
-/// Calls rustfmt (the program) on the input
+fn connect(conn: &str) -> Result<connection::MyClient, Error> {
+	let client = Client::connect(conn, NoTls)?;
+
+	Ok(connection::MyClient::new(client)?)
+}
+
+/// Open a SQLite file if `conn` starts with `sqlite:` (or `sqlite://`) and connect to MySQL if it starts with `mysql://`,
+/// otherwise connect to postgres
 ///
-/// On any rustfmt error stderr is written to and a copy of the input is returned
+/// Returns [`Error::NoFeature`] for a SQLite or MySQL database if the feature reading it is turned off
+fn introspector(conn: &str) -> Result<Box<dyn Introspect>, Error> {
+	if ddl::is_ddl_path(conn) {
+		return Ok(Box::new(ddl::DdlReader::open(std::path::Path::new(conn))?));
+	}
+	if conn.starts_with("mysql://") {
+		#[cfg(feature = "mysql")]
+		return Ok(Box::new(crate::mysql::MysqlClient::connect(conn)?));
+		#[cfg(not(feature = "mysql"))]
+		return Err(Error::NoFeature("mysql"));
+	}
+	match conn.strip_prefix("sqlite:") {
+		#[cfg(feature = "sqlite")]
+		Some(path) => {
+			let path = path.strip_prefix("//").unwrap_or(path);
+			Ok(Box::new(sqlite::SqliteClient::open(std::path::Path::new(path))?))
+		},
+		#[cfg(not(feature = "sqlite"))]
+		Some(_) => Err(Error::NoFeature("sqlite")),
+		None => Ok(Box::new(connect(conn)?)),
+	}
+}
+
+/// Calls rustfmt (the program) on the input
 ///
-/// Can panic if acquiring/writing to stdin fails or the the text written to stdout or stderr by rustfmt is not valid utf8
-pub fn format_rust(value: &str, rustfmt_config: Option<&str>, rustfmt_config_path: Option<&str>) -> String {
+/// Returns [`Error::NoRustfmt`] if rustfmt isn't installed and [`Error::Format`] with what rustfmt wrote to stderr if it
+/// fails
+pub fn format_rust(value: &str, rustfmt_config: Option<&str>, rustfmt_config_path: Option<&str>) -> Result<String, Error> {
 	use std::{
 		io::Write,
 		process::{Command, Stdio},
@@ -270,7 +1109,7 @@ pub fn format_rust(value: &str, rustfmt_config: Option<&str>, rustfmt_config_pat
 		args.push("--config-path");
 		args.push(s);
 	}
-	if let Ok(mut proc) = Command::new("rustfmt")
+	let mut proc = Command::new("rustfmt")
 		.arg("--emit=stdout")
 		.arg("--edition=2018")
 		.args(&args)
@@ -278,29 +1117,73 @@ pub fn format_rust(value: &str, rustfmt_config: Option<&str>, rustfmt_config_pat
 		.stdout(Stdio::piped())
 		.stderr(Stdio::piped())
 		.spawn()
+		.map_err(|e| match e.kind() {
+			std::io::ErrorKind::NotFound => Error::NoRustfmt,
+			_ => Error::Format(format!("failed to spawn rustfmt: {}", e)),
+		})?;
 	{
-		{
-			let stdin = proc.stdin.as_mut().unwrap();
-			stdin.write_all(value.as_bytes()).unwrap();
-		}
-		match proc.wait_with_output() {
-			Ok(output) => {
-				if !output.stderr.is_empty() {
-					eprintln!("{}", std::str::from_utf8(&output.stderr).unwrap());
-				}
-				if output.status.success() {
-					return std::str::from_utf8(&output.stdout).unwrap().to_owned().into();
-				} else {
-					eprintln!("{:?}", output.status.code());
-					eprintln!("{}", std::str::from_utf8(&output.stdout).unwrap());
-				}
-			},
-			Err(e) => {
-				eprintln!("Error running rustfmt: {}", e);
-			},
-		}
+		let stdin = proc.stdin.as_mut().expect("stdin is piped");
+		stdin.write_all(value.as_bytes())?;
+	}
+	let output = proc.wait_with_output()?;
+	if output.status.success() {
+		String::from_utf8(output.stdout).map_err(|e| Error::Format(e.to_string()))
 	} else {
-		eprintln!("failed to spawn rustfmt")
+		Err(Error::Format(String::from_utf8_lossy(&output.stderr).into_owned()))
+	}
+}
+
+/// Formats the input with prettyplease without needing any external program
+///
+/// Returns [`Error::Parse`] if the tokens are not a valid rust file
+pub fn format_pretty(tokens: &proc_macro2::TokenStream) -> Result<String, Error> {
+	let file = parse_file(tokens)?;
+	Ok(prettyplease::unparse(&file))
+}
+
+/// Checks that the tokens make a valid rust file
+pub fn parse_file(tokens: &proc_macro2::TokenStream) -> Result<syn::File, Error> {
+	syn::parse2(tokens.clone()).map_err(|e| Error::Parse(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn existing_file_is_a_snapshot() {
+		let path = std::env::temp_dir().join(format!("sql_db_mapper-snapshot-{}.json", std::process::id()));
+		std::fs::write(&path, "{}").unwrap();
+		let source = path.to_str().unwrap().parse::<DbSource>();
+		std::fs::remove_file(&path).unwrap();
+		assert!(matches!(source, Ok(DbSource::Snapshot(v)) if v == path));
+	}
+
+	#[test]
+	fn connection_strings_are_connections() {
+		for conn in [
+			"host=/tmp port=5432 user=postgres",
+			"postgres://user@localhost/db",
+			"mysql://user@localhost/db",
+			"sqlite:data/app.db",
+		] {
+			assert!(matches!(conn.parse::<DbSource>(), Ok(DbSource::Conn(v)) if v == conn));
+		}
+	}
+
+	#[test]
+	#[cfg(not(all(feature = "sqlite", feature = "mysql")))]
+	fn databases_need_their_features() {
+		#[cfg(not(feature = "sqlite"))]
+		assert!(matches!(introspector("sqlite:data/app.db"), Err(Error::NoFeature("sqlite"))));
+		#[cfg(not(feature = "mysql"))]
+		assert!(matches!(introspector("mysql://user@localhost/db"), Err(Error::NoFeature("mysql"))));
+	}
+
+	#[test]
+	fn missing_snapshot_is_an_error() {
+		for path in ["old.json", "snapshots/missing", "./nowhere/old.json"] {
+			assert_eq!(path.parse::<DbSource>().unwrap_err(), format!("{} doesn't exist", path));
+		}
 	}
-	value.to_string()
 }
@@ -0,0 +1,163 @@
+//! Applies versioned `.sql` files to a PostgreSQL database, recording which have been applied so each runs once
+//!
+//! A migration's version is the number its file name starts with, i.e. `0002_add_orders.sql` is version 2. Each file is run
+//! in its own transaction along with the row recording it in [`MIGRATIONS_TABLE`], so a file that fails leaves nothing
+//! behind and the files after it aren't run. Files can't contain statements which can't run in a transaction (i.e.
+//! `CREATE INDEX CONCURRENTLY`) or manage transactions themselves
+use super::{ddl::sql_files, Error};
+use postgres::Client;
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+
+/// The table applied migrations are recorded in, created in the first schema of the search path if it doesn't exist
+pub const MIGRATIONS_TABLE: &str = "sql_db_mapper_migrations";
+
+
+/// A `.sql` file of a migrations directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+	pub version: i64,
+	/// The name of the file
+	pub name: String,
+	pub path: PathBuf,
+}
+
+/// The `.sql` files in `dir` ordered by version
+///
+/// Returns [`Error::Migration`] if a file name doesn't start with a version or two files have the same version
+pub fn migrations(dir: &Path) -> Result<Vec<Migration>, Error> {
+	if !dir.is_dir() {
+		return Err(Error::Migration(format!("{} is not a directory", dir.display())));
+	}
+	let mut migrations = Vec::new();
+	for path in sql_files(dir)? {
+		let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+		let digits: String = name.chars().take_while(char::is_ascii_digit).collect();
+		let version = digits
+			.parse()
+			.map_err(|_| Error::Migration(format!("{} doesn't start with a version number", name)))?;
+		migrations.push(Migration { version, name, path });
+	}
+	migrations.sort_by_key(|v| v.version);
+	if let Some(v) = migrations.windows(2).find(|v| v[0].version == v[1].version) {
+		return Err(Error::Migration(format!(
+			"{} and {} have the same version",
+			v[0].name, v[1].name
+		)));
+	}
+	Ok(migrations)
+}
+
+/// Apply the migrations in `dir` which haven't been applied yet in order of their versions, returning those applied
+///
+/// The migrations table is locked while each file runs so migrations run at the same time by someone else are skipped
+/// rather than applied twice
+pub fn migrate(client: &mut Client, dir: &Path) -> Result<Vec<Migration>, Error> {
+	let migrations = migrations(dir)?;
+	client.batch_execute(&format!(
+		"CREATE TABLE IF NOT EXISTS {} (
+	version bigint PRIMARY KEY,
+	name text NOT NULL,
+	applied_at timestamptz NOT NULL DEFAULT now()
+)",
+		MIGRATIONS_TABLE
+	))?;
+	let lock = format!("LOCK TABLE {} IN EXCLUSIVE MODE", MIGRATIONS_TABLE);
+	let is_applied = format!("SELECT 1 FROM {} WHERE version = $1", MIGRATIONS_TABLE);
+	let record = format!("INSERT INTO {} (version, name) VALUES ($1, $2)", MIGRATIONS_TABLE);
+
+	let mut applied = Vec::new();
+	for migration in migrations {
+		let sql = fs::read_to_string(&migration.path)
+			.map_err(|e| Error::InFile(migration.path.clone(), Box::new(Error::Io(e))))?;
+		let in_file = |e: postgres::Error| Error::InFile(migration.path.clone(), Box::new(Error::Sql(e)));
+
+		let mut transaction = client.transaction()?;
+		transaction.batch_execute(&lock)?;
+		let done = transaction.query_opt(is_applied.as_str(), &[&migration.version])?;
+		if done.is_some() {
+			continue;
+		}
+		transaction.batch_execute(&sql).map_err(in_file)?;
+		transaction.execute(record.as_str(), &[&migration.version, &migration.name])?;
+		transaction.commit().map_err(in_file)?;
+		applied.push(migration);
+	}
+	Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A migrations directory with empty files of these names, removed when dropped
+	struct Dir(PathBuf);
+
+	impl Dir {
+		fn new(name: &str, files: &[&str]) -> Dir {
+			let path = std::env::temp_dir().join(format!("sql_db_mapper-migrate-{}-{}", name, std::process::id()));
+			let _ = fs::remove_dir_all(&path);
+			fs::create_dir(&path).unwrap();
+			for file in files {
+				fs::write(path.join(file), "").unwrap();
+			}
+			Dir(path)
+		}
+	}
+
+	impl Drop for Dir {
+		fn drop(&mut self) {
+			let _ = fs::remove_dir_all(&self.0);
+		}
+	}
+
+	fn versions_and_names(migrations: &[Migration]) -> Vec<(i64, &str)> {
+		migrations.iter().map(|v| (v.version, v.name.as_str())).collect()
+	}
+
+	#[test]
+	fn ordered_by_version_rather_than_name() {
+		let dir = Dir::new("order", &["10_c.sql", "2_b.sql", "0001_a.sql"]);
+		let migrations = migrations(&dir.0).unwrap();
+		assert_eq!(
+			versions_and_names(&migrations),
+			[(1, "0001_a.sql"), (2, "2_b.sql"), (10, "10_c.sql")]
+		);
+		assert_eq!(migrations[2].path, dir.0.join("10_c.sql"));
+	}
+
+	#[test]
+	fn other_files_are_ignored() {
+		let dir = Dir::new("ignored", &["1_a.sql", "2_b.SQL", "README.md", "3_c.sql.bak", "notes"]);
+		fs::create_dir(dir.0.join("4_d.sql")).unwrap();
+		let migrations = migrations(&dir.0).unwrap();
+		assert_eq!(versions_and_names(&migrations), [(1, "1_a.sql"), (2, "2_b.SQL")]);
+	}
+
+	#[test]
+	fn duplicate_versions_are_an_error() {
+		let dir = Dir::new("duplicate", &["1_a.sql", "01_b.sql", "2_c.sql"]);
+		match migrations(&dir.0) {
+			Err(Error::Migration(e)) => assert!(e.contains("01_b.sql") && e.contains("1_a.sql"), "{}", e),
+			v => panic!("expected a migration error: {:?}", v),
+		}
+	}
+
+	#[test]
+	fn missing_version_is_an_error() {
+		let dir = Dir::new("unversioned", &["1_a.sql", "add_orders.sql"]);
+		match migrations(&dir.0) {
+			Err(Error::Migration(e)) => assert!(e.contains("add_orders.sql"), "{}", e),
+			v => panic!("expected a migration error: {:?}", v),
+		}
+	}
+
+	#[test]
+	fn missing_directory_is_an_error() {
+		let dir = Dir::new("file", &["1_a.sql"]);
+		assert!(matches!(migrations(&dir.0.join("1_a.sql")), Err(Error::Migration(_))));
+		assert!(matches!(migrations(&dir.0.join("missing")), Err(Error::Migration(_))));
+	}
+}
@@ -0,0 +1,520 @@
+//! Reads the structure of a MySQL or MariaDB database into a [`FullDB`]
+//!
+//! Each database is a schema, only the one named in the url is read if there is one and otherwise every database besides the
+//! system ones. Columns and parameters are given one of [`MYSQL_TYPES`] from their types in `information_schema`, each
+//! `ENUM(...)` column or parameter gets an enum of its own. Procedures return `void` like those of postgres, the ones with
+//! `OUT` or `INOUT` parameters aren't read
+#[cfg(feature = "mysql")]
+use super::sql_tree::*;
+#[cfg(feature = "mysql")]
+use mysql::{prelude::Queryable, Conn, Opts};
+
+/// The base types columns are given, with the oid of the postgres type holding the same values (unsigned integers are given
+/// the next larger type)
+///
+/// `boolean` is a `TINYINT(1)`, the other names are the `DATA_TYPE` of the column. Only the ones used by a column or
+/// parameter are added to a schema, along with any type that isn't one of these which is given an oid of 0
+pub const MYSQL_TYPES: [(&str, u32); 34] = [
+	("bigint", 20),
+	("bigint_unsigned", 1700),
+	("binary", 17),
+	("bit", 1560),
+	("blob", 17),
+	("boolean", 16),
+	("char", 1042),
+	("date", 1082),
+	("datetime", 1114),
+	("decimal", 1700),
+	("double", 701),
+	("float", 700),
+	("int", 23),
+	("int_unsigned", 20),
+	("json", 114),
+	("longblob", 17),
+	("longtext", 25),
+	("mediumblob", 17),
+	("mediumint", 23),
+	("mediumint_unsigned", 23),
+	("mediumtext", 25),
+	("set", 25),
+	("smallint", 21),
+	("smallint_unsigned", 23),
+	("text", 25),
+	("time", 1083),
+	("timestamp", 1114),
+	("tinyblob", 17),
+	("tinyint", 21),
+	("tinyint_unsigned", 21),
+	("tinytext", 25),
+	("varbinary", 17),
+	("varchar", 1043),
+	("year", 21),
+];
+
+/// The name given to the return type of procedures, an `Other(2278)` type like postgres' `void`
+pub const VOID_TYPE: &str = "void";
+
+#[cfg(feature = "mysql")]
+const GET_SCHEMAS: &str = "SELECT SCHEMA_NAME
+FROM information_schema.SCHEMATA
+WHERE SCHEMA_NAME NOT IN ('information_schema', 'mysql', 'performance_schema', 'sys')
+ORDER BY SCHEMA_NAME ASC";
+
+// MariaDB's system versioned tables are ordinary tables to a query
+#[cfg(feature = "mysql")]
+const GET_RELATIONS: &str = "SELECT TABLE_NAME, TABLE_TYPE = 'VIEW'
+FROM information_schema.TABLES
+WHERE TABLE_SCHEMA = ? AND TABLE_TYPE IN ('BASE TABLE', 'SYSTEM VERSIONED', 'VIEW')
+ORDER BY TABLE_NAME ASC";
+
+#[cfg(feature = "mysql")]
+const GET_COLUMNS: &str = "SELECT TABLE_NAME,
+	ORDINAL_POSITION,
+	COLUMN_NAME,
+	DATA_TYPE,
+	COLUMN_TYPE,
+	IS_NULLABLE,
+	COLUMN_DEFAULT,
+	EXTRA
+FROM information_schema.COLUMNS
+WHERE TABLE_SCHEMA = ?
+ORDER BY TABLE_NAME ASC, ORDINAL_POSITION ASC";
+
+#[cfg(feature = "mysql")]
+const GET_KEYS: &str = "SELECT TABLE_NAME,
+	CONSTRAINT_NAME,
+	COLUMN_NAME,
+	REFERENCED_TABLE_SCHEMA,
+	REFERENCED_TABLE_NAME,
+	REFERENCED_COLUMN_NAME
+FROM information_schema.KEY_COLUMN_USAGE
+WHERE TABLE_SCHEMA = ? AND (CONSTRAINT_NAME = 'PRIMARY' OR REFERENCED_TABLE_NAME IS NOT NULL)
+ORDER BY TABLE_NAME ASC, CONSTRAINT_NAME ASC, ORDINAL_POSITION ASC";
+
+#[cfg(feature = "mysql")]
+const GET_ROUTINES: &str = "SELECT SPECIFIC_NAME, ROUTINE_NAME, ROUTINE_TYPE = 'PROCEDURE'
+FROM information_schema.ROUTINES
+WHERE ROUTINE_SCHEMA = ?
+ORDER BY ROUTINE_NAME ASC";
+
+// the return value of a function is at position 0 without a name or mode
+#[cfg(feature = "mysql")]
+const GET_PARAMETERS: &str =
+	"SELECT SPECIFIC_NAME, ORDINAL_POSITION, PARAMETER_MODE, PARAMETER_NAME, DATA_TYPE, DTD_IDENTIFIER
+FROM information_schema.PARAMETERS
+WHERE SPECIFIC_SCHEMA = ?
+ORDER BY SPECIFIC_NAME ASC, ORDINAL_POSITION ASC";
+
+#[cfg(feature = "mysql")]
+type GetColumns = (String, i16, String, String, String, String, Option<String>, String);
+#[cfg(feature = "mysql")]
+type GetKeys = (String, String, String, Option<String>, Option<String>, Option<String>);
+#[cfg(feature = "mysql")]
+type GetParameters = (String, i32, Option<String>, Option<String>, String, String);
+
+#[cfg(feature = "mysql")]
+/// A connection to a MySQL or MariaDB database which is read the same way as [`MyClient`](super::connection::MyClient)
+/// reads postgres
+pub struct MysqlClient {
+	conn: Conn,
+}
+
+#[cfg(feature = "mysql")]
+impl MysqlClient {
+	pub fn new(conn: Conn) -> MysqlClient {
+		MysqlClient { conn }
+	}
+
+	/// Connect with a `mysql://` url, see mysql::Opts for details
+	pub fn connect(url: &str) -> Result<MysqlClient, mysql::Error> {
+		let conn = Conn::new(Opts::from_url(url)?)?;
+		Ok(MysqlClient::new(conn))
+	}
+
+	/// Read every table, view, and (unless `no_functions` is set) stored routine of the databases
+	pub fn get_all(&mut self, no_functions: bool) -> Result<FullDB, mysql::Error> {
+		let mut full_db = FullDB::new(Dialect::Mysql);
+		for (id, name) in self.get_schemas()?.into_iter().enumerate() {
+			let schema = self.get_schema(id as SchemaId, name, no_functions)?;
+			full_db.add_schema(schema);
+		}
+		Ok(full_db)
+	}
+
+	/// The database of the connection or, if it didn't pick one, all that aren't part of the server
+	fn get_schemas(&mut self) -> Result<Vec<String>, mysql::Error> {
+		let current: Option<Option<String>> = self.conn.query_first("SELECT DATABASE()")?;
+		match current.flatten() {
+			Some(name) => Ok(vec![name]),
+			None => self.conn.query(GET_SCHEMAS),
+		}
+	}
+
+	fn get_schema(&mut self, id: SchemaId, name: String, no_functions: bool) -> Result<Schema, mysql::Error> {
+		let mut types = SchemaTypes::default();
+
+		let relations: Vec<(String, bool)> = self.conn.exec(GET_RELATIONS, (&name,))?;
+		let columns: Vec<GetColumns> = self.conn.exec(GET_COLUMNS, (&name,))?;
+		let keys: Vec<GetKeys> = self.conn.exec(GET_KEYS, (&name,))?;
+		let relations: Vec<_> = relations
+			.into_iter()
+			.map(|(table, is_view)| {
+				let cols = columns
+					.iter()
+					.filter(|v| v.0 == table)
+					.map(|(_, pos, col, data_type, column_type, is_nullable, default, extra)| {
+						let (type_name, type_id) = types.add(&table, col, data_type, column_type);
+						Column {
+							pos: *pos,
+							name: col.clone(),
+							type_id,
+							type_name,
+							type_ns_name: name.clone(),
+							not_null: not_null(is_nullable),
+							num_dimentions: 0,
+							has_default: has_default(default.as_deref(), extra),
+						}
+					})
+					.collect();
+				let table_keys: Vec<_> = keys.iter().filter(|v| v.0 == table).collect();
+				PsqlType {
+					ns: id,
+					typ: PsqlTypType::Composite(PsqlCompositeType {
+						oid: 0,
+						kind: if is_view { RelKind::View } else { RelKind::Table },
+						cols,
+						primary_key: table_keys
+							.iter()
+							.filter(|v| v.1 == "PRIMARY")
+							.map(|v| v.2.clone())
+							.collect(),
+						foreign_keys: foreign_keys(&table_keys),
+					}),
+					name: table,
+				}
+			})
+			.collect();
+
+		let (procs, has_procedures) = if no_functions {
+			(Vec::new(), false)
+		} else {
+			self.get_procs(id, &name, &mut types)?
+		};
+
+		let mut schema_types = types.into_types(id);
+		schema_types.extend(relations);
+		if has_procedures {
+			schema_types.push(PsqlType {
+				name: String::from(VOID_TYPE),
+				ns: id,
+				typ: PsqlTypType::Other(2278),
+			});
+		}
+		Ok(Schema {
+			id,
+			name,
+			owner_name: String::new(),
+			types: schema_types,
+			procs,
+		})
+	}
+
+	/// The stored functions and procedures of a schema, and whether any of them are procedures
+	///
+	/// There's no overloading so each is on its own
+	fn get_procs(
+		&mut self,
+		id: SchemaId,
+		schema: &str,
+		types: &mut SchemaTypes,
+	) -> Result<(Vec<Vec<SqlProc>>, bool), mysql::Error> {
+		let routines: Vec<(String, String, bool)> = self.conn.exec(GET_ROUTINES, (schema,))?;
+		let parameters: Vec<GetParameters> = self.conn.exec(GET_PARAMETERS, (schema,))?;
+
+		let mut procs = Vec::new();
+		let mut has_procedures = false;
+		for (specific_name, name, is_procedure) in routines {
+			let params: Vec<_> = parameters.iter().filter(|v| v.0 == specific_name).collect();
+			if is_procedure && params.iter().any(|v| v.2.as_deref() != Some("IN")) {
+				continue;
+			}
+
+			let inputs = params
+				.iter()
+				.filter(|v| v.1 > 0)
+				.map(|(_, pos, _, param_name, data_type, dtd)| {
+					let param_name = param_name.clone().unwrap_or_else(|| format!("arg{}", pos));
+					let (type_name, _) = types.add(&name, &param_name, data_type, dtd);
+					TypeAndName {
+						typ: FullType {
+							schema: String::from(schema),
+							name: type_name,
+						},
+						name: param_name,
+					}
+				})
+				.collect::<Vec<_>>();
+			let output = if is_procedure {
+				has_procedures = true;
+				String::from(VOID_TYPE)
+			} else {
+				match params.iter().find(|v| v.1 == 0) {
+					Some((.., data_type, dtd)) => types.add(&name, "return", data_type, dtd).0,
+					None => continue,
+				}
+			};
+
+			procs.push(vec![SqlProc {
+				ns: id,
+				ns_name: String::from(schema),
+				oid: procs.len() as u32,
+				name,
+				returns_set: false,
+				num_args: inputs.len() as i16,
+				inputs: NamesAndTypes(inputs),
+				outputs: FullType {
+					schema: String::from(schema),
+					name: output,
+				},
+			}]);
+		}
+		Ok((procs, has_procedures))
+	}
+}
+
+/// Groups the columns of each foreign key, the rows are ordered by constraint then position
+#[cfg(feature = "mysql")]
+fn foreign_keys(keys: &[&GetKeys]) -> Vec<ForeignKey> {
+	let mut foreign_keys: Vec<ForeignKey> = Vec::new();
+	for (_, constraint, col, ref_schema, ref_table, ref_col) in keys.iter().copied() {
+		let (ref_schema, ref_table, ref_col) = match (ref_schema, ref_table, ref_col) {
+			(Some(ref_schema), Some(ref_table), Some(ref_col)) => (ref_schema, ref_table, ref_col),
+			_ => continue,
+		};
+		match foreign_keys.last_mut() {
+			Some(fk) if fk.name == *constraint => {
+				fk.cols.push(col.clone());
+				fk.ref_cols.push(ref_col.clone());
+			},
+			_ => foreign_keys.push(ForeignKey {
+				name: constraint.clone(),
+				cols: vec![col.clone()],
+				ref_schema: ref_schema.clone(),
+				ref_table: ref_table.clone(),
+				ref_cols: vec![ref_col.clone()],
+			}),
+		}
+	}
+	foreign_keys
+}
+
+/// The types used by the columns and parameters of a schema
+#[cfg(feature = "mysql")]
+#[derive(Default)]
+struct SchemaTypes {
+	base: Vec<(String, u32)>,
+	enums: Vec<(String, Vec<String>)>,
+}
+
+#[cfg(feature = "mysql")]
+impl SchemaTypes {
+	/// The name and oid of the type of a column or parameter named `name` of `owner`, which is added if it's new
+	///
+	/// An enum is named `{owner}_{name}` and has an oid of 0
+	fn add(&mut self, owner: &str, name: &str, data_type: &str, column_type: &str) -> (String, u32) {
+		match parse_type(data_type, column_type) {
+			ParsedType::Enum(labels) => {
+				let enum_name = format!("{}_{}", owner, name);
+				self.enums.push((enum_name.clone(), labels));
+				(enum_name, 0)
+			},
+			ParsedType::Base(type_name, oid) => {
+				if !self.base.iter().any(|(v, _)| *v == type_name) {
+					self.base.push((type_name.clone(), oid));
+				}
+				(type_name, oid)
+			},
+		}
+	}
+
+	/// The base types ordered by name followed by the enums
+	fn into_types(mut self, id: SchemaId) -> Vec<PsqlType> {
+		self.base.sort();
+		let base = self.base.into_iter().map(|(name, oid)| PsqlType {
+			name: name.clone(),
+			ns: id,
+			typ: PsqlTypType::Base(PsqlBaseType {
+				oid,
+				name,
+				extension: None,
+			}),
+		});
+		let enums = self.enums.into_iter().map(|(name, labels)| PsqlType {
+			name,
+			ns: id,
+			typ: PsqlTypType::Enum(PsqlEnumType { oid: 0, labels }),
+		});
+		base.chain(enums).collect()
+	}
+}
+
+/// The type of a column or parameter read from `information_schema`
+#[cfg(feature = "mysql")]
+#[derive(Debug, PartialEq)]
+enum ParsedType {
+	/// An `ENUM(...)` with its labels in order
+	Enum(Vec<String>),
+	/// The name and oid of a base type, see [`base_type`]
+	Base(String, u32),
+}
+
+/// The type of a column from its `DATA_TYPE` and `COLUMN_TYPE` (or `DTD_IDENTIFIER` of a parameter)
+///
+/// A `SET(...)` holds its labels joined by commas so it's the `set` base type (text) rather than an enum
+#[cfg(feature = "mysql")]
+fn parse_type(data_type: &str, column_type: &str) -> ParsedType {
+	if data_type.eq_ignore_ascii_case("enum") {
+		ParsedType::Enum(enum_labels(column_type))
+	} else {
+		let (name, oid) = base_type(data_type, column_type);
+		ParsedType::Base(name, oid)
+	}
+}
+
+/// Whether a column is `NOT NULL` from its `IS_NULLABLE`
+#[cfg(feature = "mysql")]
+fn not_null(is_nullable: &str) -> bool {
+	is_nullable.eq_ignore_ascii_case("NO")
+}
+
+/// Whether a column has a default from its `COLUMN_DEFAULT` and `EXTRA`, auto increment and generated columns count as
+/// having one
+///
+/// MariaDB reports the implicit NULL default of a nullable column as the default `'NULL'`, which is a default as well
+#[cfg(feature = "mysql")]
+fn has_default(default: Option<&str>, extra: &str) -> bool {
+	let extra = extra.to_ascii_lowercase();
+	default.is_some() || extra.contains("auto_increment") || extra.contains("generated")
+}
+
+/// The name and oid of the type in [`MYSQL_TYPES`] for a `DATA_TYPE` and its `COLUMN_TYPE` (or `DTD_IDENTIFIER` of a
+/// parameter), types that aren't in it keep their name and are given an oid of 0
+pub fn base_type(data_type: &str, column_type: &str) -> (String, u32) {
+	let data_type = data_type.to_ascii_lowercase();
+	let column_type = column_type.to_ascii_lowercase();
+	let name = if column_type.starts_with("tinyint(1)") {
+		String::from("boolean")
+	} else if column_type.contains("unsigned") && data_type.ends_with("int") {
+		format!("{}_unsigned", data_type)
+	} else {
+		data_type
+	};
+	match MYSQL_TYPES.iter().find(|(v, _)| *v == name) {
+		Some(&(_, oid)) => (name, oid),
+		None => (name, 0),
+	}
+}
+
+/// The labels of an `enum('a','b')` type in order, quotes and backslashes in them are escaped
+#[cfg(feature = "mysql")]
+fn enum_labels(column_type: &str) -> Vec<String> {
+	let mut labels = Vec::new();
+	let mut chars = column_type.chars().skip_while(|c| *c != '(').peekable();
+	while let Some(c) = chars.next() {
+		if c != '\'' {
+			continue;
+		}
+		let mut label = String::new();
+		loop {
+			match chars.next() {
+				Some('\'') if chars.peek() == Some(&'\'') => {
+					chars.next();
+					label.push('\'');
+				},
+				Some('\\') => label.extend(chars.next()),
+				Some('\'') | None => break,
+				Some(c) => label.push(c),
+			}
+		}
+		labels.push(label);
+	}
+	labels
+}
+
+#[cfg(all(test, feature = "mysql"))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn enum_labels_in_order() {
+		assert_eq!(
+			enum_labels("enum('small','medium','large')"),
+			["small", "medium", "large"]
+		);
+		assert_eq!(enum_labels("ENUM('A','')"), ["A", ""]);
+	}
+
+	#[test]
+	fn enum_labels_with_commas_and_parentheses() {
+		assert_eq!(enum_labels("enum('a,b','c)','(d,')"), ["a,b", "c)", "(d,"]);
+	}
+
+	#[test]
+	fn enum_labels_with_escaped_quotes() {
+		// MySQL doubles quotes in COLUMN_TYPE and escapes backslashes, a backslash escaped quote is read the same
+		assert_eq!(enum_labels(r"enum('it''s','a\\b','\'q\'')"), ["it's", r"a\b", "'q'"]);
+		assert_eq!(enum_labels("enum('''','x')"), ["'", "x"]);
+	}
+
+	#[test]
+	fn enums_and_sets() {
+		assert_eq!(
+			parse_type("enum", "enum('a,b','c')"),
+			ParsedType::Enum(vec![String::from("a,b"), String::from("c")])
+		);
+		assert_eq!(
+			parse_type("set", "set('a,b','c')"),
+			ParsedType::Base(String::from("set"), 25)
+		);
+	}
+
+	#[test]
+	fn type_names() {
+		let base = |data_type, column_type| match parse_type(data_type, column_type) {
+			ParsedType::Base(name, oid) => (name, oid),
+			v => panic!("{} isn't a base type: {:?}", column_type, v),
+		};
+		assert_eq!(base("tinyint", "tinyint(1)"), (String::from("boolean"), 16));
+		assert_eq!(base("tinyint", "tinyint(4)"), (String::from("tinyint"), 21));
+		assert_eq!(base("int", "int(10) unsigned"), (String::from("int_unsigned"), 20));
+		assert_eq!(
+			base("bigint", "bigint unsigned zerofill"),
+			(String::from("bigint_unsigned"), 1700)
+		);
+		assert_eq!(base("VARCHAR", "VARCHAR(255)"), (String::from("varchar"), 1043));
+		// only integers have unsigned types
+		assert_eq!(
+			base("decimal", "decimal(10,2) unsigned"),
+			(String::from("decimal"), 1700)
+		);
+		assert_eq!(base("geometry", "geometry"), (String::from("geometry"), 0));
+	}
+
+	#[test]
+	fn nullability() {
+		assert!(not_null("NO"));
+		assert!(!not_null("YES"));
+	}
+
+	#[test]
+	fn defaults() {
+		assert!(!has_default(None, ""));
+		assert!(has_default(Some("0"), ""));
+		// MariaDB's implicit default of a nullable column
+		assert!(has_default(Some("NULL"), ""));
+		assert!(has_default(None, "auto_increment"));
+		assert!(has_default(None, "VIRTUAL GENERATED"));
+		assert!(has_default(None, "STORED GENERATED"));
+		assert!(!has_default(None, "on update CURRENT_TIMESTAMP"));
+	}
+}
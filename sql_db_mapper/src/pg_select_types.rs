@@ -2,52 +2,50 @@
 use sql_db_mapper_core::*;
 
 
-pub const GET_SCHEMAS: &str = "SELECT ns.oid, nspname, nspowner, rolname
+pub const GET_SCHEMAS: &str = "SELECT ns.oid, nspname, rolname
 FROM pg_namespace ns
 LEFT JOIN pg_roles r
 ON nspowner = r.oid
-ORDER BY ns.oid ASC";
+ORDER BY nspname::text COLLATE \"C\" ASC";
 #[derive(Debug, TryFromRow)]
 pub struct GetSchemas {
 	pub oid: u32,
 	pub name: String,
-	pub owner: u32,
 	pub rolname: String,
 }
 
 pub const GET_TYPES: &str = "SELECT oid,
 	typname,
-	typlen,
-	typbyval,
 	typtype,
 	typrelid,
-	typalign
+	(SELECT relkind FROM pg_class WHERE pg_class.oid = typrelid),
+	(SELECT extname
+		FROM pg_depend
+		JOIN pg_extension ON pg_extension.oid = refobjid
+		WHERE classid = 'pg_type'::regclass AND objid = pg_type.oid
+			AND refclassid = 'pg_extension'::regclass AND deptype = 'e')
 FROM pg_type
 WHERE typnamespace = $1 AND
 	(typarray != 0 OR
 	typtype = 'd' OR
 	oid = 2278)
-ORDER BY oid ASC";
+ORDER BY typname::text COLLATE \"C\" ASC";
 #[derive(Debug, TryFromRow)]
 pub struct GetTypes {
 	pub oid: u32,
 	pub name: String,
-	pub len: i16,
-	pub by_val: bool,
 	pub typ: i8,
 	pub rel_id: u32,
-	pub align: i8,
+	pub rel_kind: Option<i8>,
+	pub extension: Option<String>,
 }
 
-pub const GET_ENUM: &str = "SELECT oid, enumtypid, enumsortorder, enumlabel
+pub const GET_ENUM: &str = "SELECT enumlabel
 FROM pg_enum
 WHERE enumtypid = $1
 ORDER BY enumsortorder ASC";
 #[derive(Debug, TryFromRow)]
 pub struct GetEnum {
-	pub oid: u32,
-	pub typ_id: u32,
-	pub sort_order: f32,
 	pub label: String,
 }
 
@@ -56,10 +54,9 @@ pub const GET_COLUMNS: &str = "SELECT attnum,
 	atttypid,
 	typname,
 	nspname,
-	attlen,
-	atttypmod,
-	attnotnull,
-	attndims
+	attnotnull OR (b.typtype = 'd' AND b.typnotnull),
+	attndims,
+	atthasdef OR attidentity <> ''
 FROM pg_attribute a
 LEFT JOIN pg_type b ON atttypid = b.oid
 LEFT JOIN pg_namespace c ON typnamespace = c.oid
@@ -73,15 +70,55 @@ pub struct GetColumns {
 	pub typ_id: u32,
 	pub typ_name: String,
 	pub nspname: String,
-	pub len: i16,
-	pub typ_mod: i32,
 	pub not_null: bool,
 	pub num_dimentions: i32,
+	pub has_default: bool,
+}
+
+pub const GET_PRIMARY_KEY: &str = "SELECT a.attname::text
+FROM pg_constraint c
+CROSS JOIN LATERAL unnest(c.conkey) WITH ORDINALITY AS k(attnum, ord)
+JOIN pg_attribute a
+	ON a.attrelid = c.conrelid AND a.attnum = k.attnum
+WHERE c.conrelid = $1 AND c.contype = 'p'
+ORDER BY k.ord ASC";
+#[derive(Debug, TryFromRow)]
+pub struct GetPrimaryKey {
+	pub name: String,
+}
+
+pub const GET_FOREIGN_KEYS: &str = "SELECT c.conname::text,
+	ARRAY(SELECT a.attname::text
+		FROM unnest(c.conkey) WITH ORDINALITY AS k(attnum, ord)
+		JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = k.attnum
+		ORDER BY k.ord),
+	ns.nspname::text,
+	cl.relname::text,
+	ARRAY(SELECT a.attname::text
+		FROM unnest(c.confkey) WITH ORDINALITY AS k(attnum, ord)
+		JOIN pg_attribute a ON a.attrelid = c.confrelid AND a.attnum = k.attnum
+		ORDER BY k.ord)
+FROM pg_constraint c
+JOIN pg_class cl
+	ON cl.oid = c.confrelid
+JOIN pg_namespace ns
+	ON ns.oid = cl.relnamespace
+WHERE c.conrelid = $1 AND c.contype = 'f'
+ORDER BY c.conname::text COLLATE \"C\" ASC";
+#[derive(Debug, TryFromRow)]
+pub struct GetForeignKeys {
+	pub name: String,
+	pub cols: Vec<String>,
+	pub ref_schema: String,
+	pub ref_table: String,
+	pub ref_cols: Vec<String>,
 }
 
 pub const GET_DOMAIN_BASE: &str = "SELECT t2.oid,
 	ns.nspname,
-	t2.typname
+	t2.typname,
+	t.typnotnull,
+	t.typdefault
 FROM pg_type AS t
 JOIN pg_type AS t2
 	ON t2.oid = t.typbasetype
@@ -93,10 +130,22 @@ pub struct GetDomainBase {
 	pub oid: u32,
 	pub ns_name: String,
 	pub typ_name: String,
+	pub not_null: bool,
+	pub default: Option<String>,
+}
+
+pub const GET_DOMAIN_CHECKS: &str = "SELECT conname,
+	pg_get_expr(conbin, 0)
+FROM pg_constraint
+WHERE contypid = $1 AND contype = 'c'
+ORDER BY conname::text COLLATE \"C\" ASC";
+#[derive(Debug, TryFromRow)]
+pub struct GetDomainChecks {
+	pub name: String,
+	pub expr: String,
 }
 
-pub const GET_PROC_NAMES: &str = "SELECT MIN(p.oid) as p_oid,
-	p.proname
+pub const GET_PROC_NAMES: &str = "SELECT p.proname
 FROM pg_proc AS p
 JOIN pg_namespace AS ns
 	ON ns.oid = p.pronamespace
@@ -105,10 +154,9 @@ WHERE pronamespace = $1 AND
 	ns.nspname != 'information_schema' AND
 	ns.nspname != 'public'
 GROUP BY p.proname
-ORDER BY p_oid ASC";
+ORDER BY p.proname::text COLLATE \"C\" ASC";
 #[derive(Debug, TryFromRow)]
 pub struct GetProcNames {
-	pub oid: u32,
 	pub name: String,
 }
 
@@ -119,7 +167,6 @@ pub const GET_PROCS: &str = "SELECT ns.oid as ns_oid,
 	p.proretset,
 	p.pronargs,
 	p.prorettype,
-	t.typname,
 	p.proargtypes,
 	p.proallargtypes,
 	p.proargmodes,
@@ -127,14 +174,11 @@ pub const GET_PROCS: &str = "SELECT ns.oid as ns_oid,
 FROM pg_proc AS p
 JOIN pg_namespace AS ns
 	ON ns.oid = p.pronamespace
-JOIN pg_type AS t
-	ON p.prorettype = t.oid
 WHERE pronamespace = $1 AND
 	proname = $2 AND
 	pronamespace != 11 AND
 	ns.nspname != 'information_schema' AND
-	ns.nspname != 'public'
-ORDER BY p_oid ASC";
+	ns.nspname != 'public'";
 #[derive(Debug, TryFromRow)]
 pub struct GetProcs {
 	pub ns_oid: u32,
@@ -144,7 +188,6 @@ pub struct GetProcs {
 	pub returns_set: bool,
 	pub num_args: i16,
 	pub ret_type_id: u32,
-	pub ret_type_name: String,
 	pub arg_types: Vec<u32>,
 	pub all_arg_types: Option<Vec<u32>>,
 	pub arg_modes: Option<Vec<i8>>,
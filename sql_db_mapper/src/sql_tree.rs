@@ -1,12 +1,46 @@
-//! A simple AST of a PostgreSQL database
+//! A simple AST of a PostgreSQL database, SQLite and MySQL databases are described with the same nodes
+//!
+//! The whole tree can be saved to and loaded from a JSON snapshot so mappings can be generated or compared without a live database
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io, path::Path};
 
 /// The root Node of the database, contains all the schemas
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FullDB {
 	pub schemas: Vec<Schema>,
+	/// The kind of database it was read from, snapshots from before this was recorded are from PostgreSQL
+	#[serde(default)]
+	pub dialect: Dialect,
+}
+
+/// The kind of database a [`FullDB`] was read from, each backend can only map one of them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dialect {
+	#[default]
+	Postgres,
+	/// The types of columns are those of `sqlite::SQLITE_TYPES`, found from their declared types
+	Sqlite,
+	/// Schemas are databases and the types of columns are those of `mysql::MYSQL_TYPES` or an enum per `ENUM(...)` column
+	Mysql,
+}
+impl Dialect {
+	pub fn to_str(self) -> &'static str {
+		match self {
+			Dialect::Postgres => "PostgreSQL",
+			Dialect::Sqlite => "SQLite",
+			Dialect::Mysql => "MySQL",
+		}
+	}
 }
 
 impl FullDB {
+	pub fn new(dialect: Dialect) -> FullDB {
+		FullDB {
+			schemas: Vec::new(),
+			dialect,
+		}
+	}
+
 	pub fn add_schema(&mut self, schema: Schema) {
 		self.schemas.push(schema);
 	}
@@ -18,12 +52,30 @@ impl FullDB {
 	pub fn no_procs(&self) -> bool {
 		self.schemas.iter().all(|v| v.no_procs())
 	}
+
+	/// Load a snapshot previously written by [`FullDB::save_snapshot`]
+	pub fn load_snapshot(path: &Path) -> io::Result<FullDB> {
+		let file = File::open(path)?;
+		let mut full_db: FullDB = serde_json::from_reader(io::BufReader::new(file))?;
+		// snapshots from before overloads were sorted have them in the order they were made
+		for overloads in full_db.schemas.iter_mut().flat_map(|v| v.procs.iter_mut()) {
+			sort_overloads(overloads);
+		}
+		Ok(full_db)
+	}
+
+	/// Write the database tree as JSON so it can be used in place of a connection later
+	pub fn save_snapshot(&self, path: &Path) -> io::Result<()> {
+		let file = File::create(path)?;
+		serde_json::to_writer_pretty(io::BufWriter::new(file), self)?;
+		Ok(())
+	}
 }
 
 /// Database schema. Contains all Types and procedures defined inside
 ///
 /// All sql procures with overloading (the same name) are stored in a Vec the length of the `procs` Vec is the number of unique procedure names in the schema
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Schema {
 	pub id: SchemaId,
 	pub name: String,
@@ -51,7 +103,7 @@ impl Schema {
 
 pub type SchemaId = u32;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PsqlType {
 	pub name: String,
 	pub ns: SchemaId,
@@ -62,7 +114,7 @@ pub struct PsqlType {
 	// pub align : i8
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PsqlTypType {
 	/// pg_type.typtype e
 	Enum(PsqlEnumType),
@@ -78,44 +130,120 @@ pub enum PsqlTypType {
 	SimpleComposite(NamesAndTypes),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PsqlEnumType {
 	pub oid: u32,
 	pub labels: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PsqlCompositeType {
 	pub oid: u32,
+	/// Snapshots from before this was recorded are assumed to be tables
+	#[serde(default)]
+	pub kind: RelKind,
 	pub cols: Vec<Column>,
+	/// The columns of the primary key in order, empty if there isn't one
+	#[serde(default)]
+	pub primary_key: Vec<String>,
+	/// Foreign keys ordered by name
+	#[serde(default)]
+	pub foreign_keys: Vec<ForeignKey>,
+}
+
+/// A FOREIGN KEY constraint of a table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForeignKey {
+	pub name: String,
+	pub cols: Vec<String>,
+	pub ref_schema: String,
+	pub ref_table: String,
+	/// The referenced columns, in the same order as `cols`
+	pub ref_cols: Vec<String>,
+}
+
+/// What a composite type belongs to, from pg_class.relkind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RelKind {
+	/// r and p (partitioned tables)
+	#[default]
+	Table,
+	/// v
+	View,
+	/// m
+	MaterializedView,
+	/// f
+	ForeignTable,
+	/// c, a type made with CREATE TYPE
+	Type,
 }
 
-#[derive(Debug, Clone)]
+impl RelKind {
+	pub fn from_relkind(relkind: Option<i8>) -> RelKind {
+		match relkind.map(|v| v as u8) {
+			Some(b'r') | Some(b'p') => RelKind::Table,
+			Some(b'v') => RelKind::View,
+			Some(b'm') => RelKind::MaterializedView,
+			Some(b'f') => RelKind::ForeignTable,
+			_ => RelKind::Type,
+		}
+	}
+
+	/// Whether rows can be selected from it
+	pub fn is_relation(self) -> bool {
+		self != RelKind::Type
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Column {
 	pub pos: i16,
 	pub name: String,
 	pub type_id: u32,
 	pub type_name: String,
 	pub type_ns_name: String,
+	/// The column or its domain is NOT NULL
 	pub not_null: bool,
 	pub num_dimentions: i32,
+	/// Has a default, is an identity, or is a generated column so can be left out of inserts
+	#[serde(default)]
+	pub has_default: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PsqlBaseType {
 	pub oid: u32,
 	pub name: String,
+	/// The extension the type belongs to, from pg_depend
+	#[serde(default)]
+	pub extension: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PsqlDomain {
 	pub oid: u32,
 	pub base_oid: u32,
 	pub base_name: String,
 	pub base_ns_name: String,
+	/// The domain was declared NOT NULL
+	#[serde(default)]
+	pub not_null: bool,
+	/// The DEFAULT expression as printed by postgres
+	#[serde(default)]
+	pub default: Option<String>,
+	/// CHECK constraints ordered by name
+	#[serde(default)]
+	pub checks: Vec<DomainCheck>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DomainCheck {
+	pub name: String,
+	/// The expression as printed by postgres, i.e. `(VALUE > 0)`
+	pub expr: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SqlProc {
 	pub ns: u32,
 	pub ns_name: String,
@@ -126,17 +254,34 @@ pub struct SqlProc {
 	pub inputs: NamesAndTypes,
 	pub outputs: FullType,
 }
+impl SqlProc {
+	/// The schema and name of the type of each argument, which tell overloads apart
+	pub fn input_types(&self) -> Vec<(&str, &str)> {
+		self.inputs.0.iter().map(|v| (v.typ.schema.as_str(), v.typ.name.as_str())).collect()
+	}
+}
+
+/// Order the overloads of a function by the types of their arguments, which unlike their oids are the same in every
+/// database made by the same SQL
+pub fn sort_overloads(overloads: &mut [SqlProc]) {
+	overloads.sort_by(|a, b| a.input_types().cmp(&b.input_types()));
+}
+
+/// The overload that was made first, which is the only one mapped with `Tuples::OldestOverload`
+pub fn oldest_overload(overloads: &[SqlProc]) -> Option<&SqlProc> {
+	overloads.iter().min_by_key(|v| v.oid)
+}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NamesAndTypes(pub Vec<TypeAndName>);
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TypeAndName {
 	pub typ: FullType,
 	pub name: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FullType {
 	pub schema: String,
 	pub name: String,
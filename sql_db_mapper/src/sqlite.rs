@@ -0,0 +1,499 @@
+//! Reads the structure of a SQLite database into a [`FullDB`]
+//!
+//! SQLite has no types of its own so each column is given one of [`SQLITE_TYPES`] from its declared type, mostly by SQLite's
+//! rules for type affinity. Everything is read from the `main` schema and there are no functions to map
+#[cfg(feature = "sqlite")]
+use super::sql_tree::*;
+#[cfg(feature = "sqlite")]
+use rusqlite::{Connection, OpenFlags};
+#[cfg(feature = "sqlite")]
+use std::path::Path;
+
+/// The base types columns are given, with the oid of the postgres type the rusqlite backend maps the same way
+///
+/// Only the ones used by a column are added to the schema
+pub const SQLITE_TYPES: [(&str, u32); 11] = [
+	// columns without a declared type can hold anything
+	("any", 2276),
+	("blob", 17),
+	("boolean", 16),
+	("date", 1082),
+	("datetime", 1114),
+	("integer", 20),
+	("json", 114),
+	("numeric", 1700),
+	("real", 701),
+	("text", 25),
+	("time", 1083),
+];
+
+/// The name SQLite gives the schema of the database that was opened
+pub const MAIN_SCHEMA: &str = "main";
+
+#[cfg(feature = "sqlite")]
+const GET_RELATIONS: &str = "SELECT name, type = 'view'
+FROM sqlite_master
+WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\'
+ORDER BY name ASC";
+
+// table_xinfo is table_info with generated columns, which are hidden 2 and 3 (hidden 1 are the hidden columns of virtual tables)
+#[cfg(feature = "sqlite")]
+const GET_COLUMNS: &str = "SELECT cid, name, type, \"notnull\", dflt_value IS NOT NULL OR hidden IN (2, 3), pk
+FROM pragma_table_xinfo(?1)
+WHERE hidden <> 1
+ORDER BY cid ASC";
+
+#[cfg(feature = "sqlite")]
+const GET_FOREIGN_KEYS: &str = "SELECT id, \"table\", \"from\", \"to\"
+FROM pragma_foreign_key_list(?1)
+ORDER BY id ASC, seq ASC";
+
+// the primary key of a table only has its own index when it isn't the rowid
+#[cfg(feature = "sqlite")]
+const HAS_PRIMARY_KEY_INDEX: &str = "SELECT EXISTS(SELECT 1 FROM pragma_index_list(?1) WHERE origin = 'pk')";
+
+#[cfg(feature = "sqlite")]
+struct GetColumns {
+	column: Column,
+	/// Position in the primary key starting at 1, 0 if it isn't part of it
+	pk: i32,
+	declared_type: String,
+}
+
+#[cfg(feature = "sqlite")]
+/// A connection to a SQLite database which is read the same way as [`MyClient`](super::connection::MyClient) reads postgres
+pub struct SqliteClient {
+	conn: Connection,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteClient {
+	pub fn new(conn: Connection) -> SqliteClient {
+		SqliteClient { conn }
+	}
+
+	/// Open the database file at `path` without writing to it or creating it
+	pub fn open(path: &Path) -> Result<SqliteClient, rusqlite::Error> {
+		let conn =
+			Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)?;
+		Ok(SqliteClient::new(conn))
+	}
+
+	/// Read every table and view, as SQLite has no stored functions the only schema is `main` and it has no procs
+	pub fn get_all(&mut self) -> Result<FullDB, rusqlite::Error> {
+		let mut relations = Vec::new();
+		for (name, is_view) in self.get_relations()? {
+			let cols = self.get_columns(&name)?;
+			let kind = if is_view { RelKind::View } else { RelKind::Table };
+			let mut primary_key: Vec<_> = cols.iter().filter(|v| v.pk > 0).collect();
+			primary_key.sort_by_key(|v| v.pk);
+			let primary_key: Vec<_> = primary_key.into_iter().map(|v| v.column.name.clone()).collect();
+
+			// an INTEGER PRIMARY KEY is the rowid so it's given one when it's left out
+			let rowid_alias = match (primary_key.as_slice(), kind) {
+				([key], RelKind::Table) => {
+					let col = cols
+						.iter()
+						.find(|v| &v.column.name == key)
+						.expect("the key is one of the columns");
+					col.declared_type.eq_ignore_ascii_case("INTEGER") && !self.has_primary_key_index(&name)?
+				},
+				_ => false,
+			};
+			let cols = cols
+				.into_iter()
+				.map(|v| {
+					let mut column = v.column;
+					if rowid_alias && v.pk == 1 {
+						column.not_null = true;
+						column.has_default = true;
+					}
+					column
+				})
+				.collect();
+
+			let foreign_keys = if kind == RelKind::Table {
+				self.get_foreign_keys(&name)?
+			} else {
+				Vec::new()
+			};
+			relations.push((
+				name,
+				PsqlCompositeType {
+					oid: 0,
+					kind,
+					cols,
+					primary_key,
+					foreign_keys,
+				},
+			));
+		}
+
+		// foreign keys without columns reference the primary key
+		let primary_keys: Vec<_> = relations
+			.iter()
+			.map(|(name, c)| (name.clone(), c.primary_key.clone()))
+			.collect();
+		for (_, c) in &mut relations {
+			for fk in &mut c.foreign_keys {
+				if fk.ref_cols.iter().all(String::is_empty) {
+					if let Some((_, key)) = primary_keys.iter().find(|(name, _)| *name == fk.ref_table) {
+						fk.ref_cols = key.clone();
+					}
+				}
+			}
+		}
+
+		let mut types: Vec<_> = SQLITE_TYPES
+			.iter()
+			.filter(|(name, _)| {
+				relations
+					.iter()
+					.any(|(_, c)| c.cols.iter().any(|col| col.type_name == *name))
+			})
+			.map(|&(name, oid)| PsqlType {
+				name: String::from(name),
+				ns: 0,
+				typ: PsqlTypType::Base(PsqlBaseType {
+					oid,
+					name: String::from(name),
+					extension: None,
+				}),
+			})
+			.collect();
+		types.extend(relations.into_iter().map(|(name, c)| PsqlType {
+			name,
+			ns: 0,
+			typ: PsqlTypType::Composite(c),
+		}));
+
+		let mut full_db = FullDB::new(Dialect::Sqlite);
+		full_db.add_schema(Schema {
+			id: 0,
+			name: String::from(MAIN_SCHEMA),
+			owner_name: String::new(),
+			types,
+			procs: Vec::new(),
+		});
+		Ok(full_db)
+	}
+
+	/// The tables and views with whether each is a view
+	fn get_relations(&self) -> Result<Vec<(String, bool)>, rusqlite::Error> {
+		let mut stmt = self.conn.prepare(GET_RELATIONS)?;
+		let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+		rows.collect()
+	}
+
+	fn get_columns(&self, table: &str) -> Result<Vec<GetColumns>, rusqlite::Error> {
+		let mut stmt = self.conn.prepare(GET_COLUMNS)?;
+		let rows = stmt.query_map([table], |row| {
+			let declared_type: Option<String> = row.get(2)?;
+			let declared_type = declared_type.unwrap_or_default();
+			let (type_name, type_id) = base_type(&declared_type);
+			Ok(GetColumns {
+				column: Column {
+					pos: row.get::<_, i16>(0)? + 1,
+					name: row.get(1)?,
+					type_id,
+					type_name: String::from(type_name),
+					type_ns_name: String::from(MAIN_SCHEMA),
+					not_null: row.get(3)?,
+					num_dimentions: 0,
+					has_default: row.get(4)?,
+				},
+				pk: row.get(5)?,
+				declared_type,
+			})
+		})?;
+		rows.collect()
+	}
+
+	fn get_foreign_keys(&self, table: &str) -> Result<Vec<ForeignKey>, rusqlite::Error> {
+		let mut stmt = self.conn.prepare(GET_FOREIGN_KEYS)?;
+		let rows = stmt.query_map([table], |row| {
+			let to: Option<String> = row.get(3)?;
+			Ok((
+				row.get::<_, i64>(0)?,
+				row.get::<_, String>(1)?,
+				row.get::<_, String>(2)?,
+				to,
+			))
+		})?;
+
+		// each column of a foreign key is its own row, they share an id
+		let mut foreign_keys: Vec<(i64, ForeignKey)> = Vec::new();
+		for row in rows {
+			let (id, ref_table, from, to) = row?;
+			match foreign_keys.last_mut() {
+				Some((last_id, fk)) if *last_id == id => {
+					fk.cols.push(from);
+					fk.ref_cols.push(to.unwrap_or_default());
+				},
+				_ => foreign_keys.push((
+					id,
+					ForeignKey {
+						name: String::new(),
+						cols: vec![from],
+						ref_schema: String::from(MAIN_SCHEMA),
+						ref_table,
+						ref_cols: vec![to.unwrap_or_default()],
+					},
+				)),
+			}
+		}
+		// SQLite doesn't keep the names of constraints, they're named the way postgres names them by default
+		Ok(foreign_keys
+			.into_iter()
+			.map(|(_, mut fk)| {
+				fk.name = format!("{}_{}_fkey", table, fk.cols.join("_"));
+				fk
+			})
+			.collect())
+	}
+
+	fn has_primary_key_index(&self, table: &str) -> Result<bool, rusqlite::Error> {
+		self.conn.query_row(HAS_PRIMARY_KEY_INDEX, [table], |row| row.get(0))
+	}
+}
+
+/// The name and oid of the type in [`SQLITE_TYPES`] a column with the declared type is given
+///
+/// A few names used for dates, booleans, and json are recognized, the rest use the column's affinity
+pub fn base_type(declared_type: &str) -> (&'static str, u32) {
+	let upper = declared_type.to_ascii_uppercase();
+	// without the size, i.e. `VARCHAR(20)`
+	let name = upper.split('(').next().unwrap_or_default().trim();
+	let type_name = match name {
+		"" => "any",
+		"BOOLEAN" | "BOOL" => "boolean",
+		"DATE" => "date",
+		"DATETIME" | "TIMESTAMP" => "datetime",
+		"TIME" => "time",
+		"JSON" => "json",
+		_ if name.contains("INT") => "integer",
+		_ if ["CHAR", "CLOB", "TEXT"].iter().any(|v| name.contains(v)) => "text",
+		_ if name.contains("BLOB") => "blob",
+		_ if ["REAL", "FLOA", "DOUB"].iter().any(|v| name.contains(v)) => "real",
+		_ => "numeric",
+	};
+	SQLITE_TYPES
+		.iter()
+		.copied()
+		.find(|(name, _)| *name == type_name)
+		.expect("every type name is in SQLITE_TYPES")
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+	use super::*;
+	use crate::connection::Introspect;
+
+	/// Introspect a database file made by `sql`
+	fn read(name: &str, sql: &str) -> FullDB {
+		let path = std::env::temp_dir().join(format!("sql_db_mapper-{}-{}.sqlite", name, std::process::id()));
+		let _ = std::fs::remove_file(&path);
+		Connection::open(&path).unwrap().execute_batch(sql).unwrap();
+		let full_db = SqliteClient::open(&path).unwrap().introspect(false).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		full_db
+	}
+
+	fn relation<'a>(full_db: &'a FullDB, name: &str) -> &'a PsqlCompositeType {
+		let schema = &full_db.schemas[0];
+		match schema.types.iter().find(|v| v.name == name).map(|v| &v.typ) {
+			Some(PsqlTypType::Composite(v)) => v,
+			v => panic!("`{}` isn't a table: {:?}", name, v),
+		}
+	}
+
+	/// Name, type, not null, and has default of each column
+	fn columns(table: &PsqlCompositeType) -> Vec<(&str, &str, bool, bool)> {
+		table
+			.cols
+			.iter()
+			.map(|v| (v.name.as_str(), v.type_name.as_str(), v.not_null, v.has_default))
+			.collect()
+	}
+
+	fn foreign_keys(table: &PsqlCompositeType) -> Vec<(&str, Vec<&str>, &str, Vec<&str>)> {
+		table
+			.foreign_keys
+			.iter()
+			.map(|v| {
+				(
+					v.name.as_str(),
+					v.cols.iter().map(String::as_str).collect(),
+					v.ref_table.as_str(),
+					v.ref_cols.iter().map(String::as_str).collect(),
+				)
+			})
+			.collect()
+	}
+
+	const LIBRARY: &str = "CREATE TABLE authors(
+			id INTEGER PRIMARY KEY,
+			name TEXT NOT NULL,
+			bio VARCHAR(200),
+			created DATETIME DEFAULT CURRENT_TIMESTAMP
+		);
+		CREATE TABLE books(
+			isbn TEXT NOT NULL,
+			edition INT NOT NULL,
+			author_id INTEGER REFERENCES authors,
+			price NUMERIC(10, 2),
+			cover BLOB,
+			extra,
+			PRIMARY KEY(isbn, edition)
+		);
+		CREATE TABLE reviews(
+			id INT PRIMARY KEY,
+			isbn TEXT,
+			edition INT,
+			score REAL NOT NULL DEFAULT 0,
+			FOREIGN KEY(isbn, edition) REFERENCES books(isbn, edition)
+		);
+		CREATE VIEW author_names AS SELECT id, name FROM authors;";
+
+	#[test]
+	fn tables_and_views() {
+		let full_db = read("library", LIBRARY);
+		assert_eq!(full_db.dialect, Dialect::Sqlite);
+		assert_eq!(full_db.schemas.len(), 1);
+		let schema = &full_db.schemas[0];
+		assert_eq!(schema.name, MAIN_SCHEMA);
+		assert!(schema.procs.is_empty());
+		// the base types used by a column, then the relations by name
+		let types: Vec<_> = schema.types.iter().map(|v| v.name.as_str()).collect();
+		assert_eq!(
+			types,
+			[
+				"any",
+				"blob",
+				"datetime",
+				"integer",
+				"numeric",
+				"real",
+				"text",
+				"author_names",
+				"authors",
+				"books",
+				"reviews"
+			]
+		);
+
+		let authors = relation(&full_db, "authors");
+		assert_eq!(authors.kind, RelKind::Table);
+		// the INTEGER PRIMARY KEY is the rowid so it's never null and SQLite gives it a value
+		assert_eq!(
+			columns(authors),
+			[
+				("id", "integer", true, true),
+				("name", "text", true, false),
+				("bio", "text", false, false),
+				("created", "datetime", false, true),
+			]
+		);
+		assert_eq!(authors.primary_key, ["id"]);
+		assert!(authors.foreign_keys.is_empty());
+
+		let books = relation(&full_db, "books");
+		assert_eq!(
+			columns(books),
+			[
+				("isbn", "text", true, false),
+				("edition", "integer", true, false),
+				("author_id", "integer", false, false),
+				("price", "numeric", false, false),
+				("cover", "blob", false, false),
+				("extra", "any", false, false),
+			]
+		);
+		assert_eq!(books.primary_key, ["isbn", "edition"]);
+		// without columns the foreign key references the primary key
+		assert_eq!(
+			foreign_keys(books),
+			[("books_author_id_fkey", vec!["author_id"], "authors", vec!["id"])]
+		);
+
+		let reviews = relation(&full_db, "reviews");
+		// an INT PRIMARY KEY isn't the rowid
+		assert_eq!(
+			columns(reviews),
+			[
+				("id", "integer", false, false),
+				("isbn", "text", false, false),
+				("edition", "integer", false, false),
+				("score", "real", true, true),
+			]
+		);
+		assert_eq!(
+			foreign_keys(reviews),
+			[(
+				"reviews_isbn_edition_fkey",
+				vec!["isbn", "edition"],
+				"books",
+				vec!["isbn", "edition"]
+			)]
+		);
+		assert!(reviews.foreign_keys.iter().all(|v| v.ref_schema == MAIN_SCHEMA));
+
+		let author_names = relation(&full_db, "author_names");
+		assert_eq!(author_names.kind, RelKind::View);
+		assert_eq!(
+			columns(author_names),
+			[("id", "integer", false, false), ("name", "text", false, false)]
+		);
+		assert!(author_names.primary_key.is_empty());
+		assert!(author_names.foreign_keys.is_empty());
+	}
+
+	#[test]
+	fn generated_columns_have_defaults() {
+		let full_db = read(
+			"generated",
+			"CREATE TABLE t(a INTEGER NOT NULL, b INTEGER GENERATED ALWAYS AS (a * 2) STORED, c AS (a + 1));",
+		);
+		assert_eq!(
+			columns(relation(&full_db, "t")),
+			[
+				("a", "integer", true, false),
+				("b", "integer", false, true),
+				("c", "any", false, true),
+			]
+		);
+	}
+
+	#[test]
+	fn base_types() {
+		let names: Vec<_> = [
+			"",
+			"int",
+			"BIGINT",
+			"varchar(20)",
+			"Text",
+			"CLOB",
+			"blob",
+			"double precision",
+			"float",
+			"decimal(10, 5)",
+			"BOOL",
+			"boolean",
+			"date",
+			"timestamp",
+			"datetime",
+			"time",
+			"json",
+		]
+		.iter()
+		.map(|v| base_type(v).0)
+		.collect();
+		assert_eq!(
+			names,
+			[
+				"any", "integer", "integer", "text", "text", "text", "blob", "real", "real", "numeric", "boolean",
+				"boolean", "date", "datetime", "datetime", "time", "json"
+			]
+		);
+	}
+}
@@ -0,0 +1,108 @@
+//! Regenerates a mapping whenever the structure of a PostgreSQL database changes
+//!
+//! Changes are found by listening for notifications on [`CHANNEL`], which the event trigger made by [`install_trigger`]
+//! sends at the end of every DDL command. Notifications that arrive close together (i.e. from a migration) are handled
+//! once, after which the database is read again and only the output files whose contents changed are written
+use super::{diff::diff, generator::write_if_changed, sql_tree::FullDB, Error, GeneratedOutput, Opt};
+use postgres::{fallible_iterator::FallibleIterator, Client, NoTls};
+use std::{
+	path::{Path, PathBuf},
+	time::Duration,
+};
+
+/// The channel notified when the structure of the database changes
+pub const CHANNEL: &str = "sql_db_mapper";
+
+/// Creates the function and event trigger which notify [`CHANNEL`], the trigger is only made if it doesn't exist
+const INSTALL_TRIGGER: &str = "CREATE OR REPLACE FUNCTION public.sql_db_mapper_notify() RETURNS event_trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+	PERFORM pg_notify('sql_db_mapper', tg_tag);
+END
+$$;
+DO $$
+BEGIN
+	IF NOT EXISTS (SELECT FROM pg_event_trigger WHERE evtname = 'sql_db_mapper_notify') THEN
+		CREATE EVENT TRIGGER sql_db_mapper_notify ON ddl_command_end EXECUTE FUNCTION public.sql_db_mapper_notify();
+	END IF;
+END
+$$";
+
+/// Install an event trigger sending `NOTIFY sql_db_mapper` on `ddl_command_end`, which requires a superuser
+///
+/// The trigger and its function `public.sql_db_mapper_notify` are left in the database, functions in public aren't mapped
+pub fn install_trigger(client: &mut Client) -> Result<(), Error> {
+	Ok(client.batch_execute(INSTALL_TRIGGER)?)
+}
+
+/// Block until a notification arrives on the channels `client` listens to, then until none arrive for `debounce`
+///
+/// Returns false if the server disconnected
+pub fn wait_for_change(client: &mut Client, debounce: Duration) -> Result<bool, Error> {
+	let mut notifications = client.notifications();
+	if notifications.blocking_iter().next()?.is_none() {
+		return Ok(false);
+	}
+	while notifications.timeout_iter(debounce).next()?.is_some() {}
+	Ok(true)
+}
+
+/// Generate the mapping of the database given by `opt.conn` to `opt.output`, then regenerate it each time the database
+/// changes printing what changed in the generated API. Only returns if the server disconnects or reading it fails
+pub fn watch(opt: &Opt, install: bool, debounce: Duration) -> Result<(), Error> {
+	let output_path = opt.output.as_ref().ok_or(Error::NoOutput)?;
+	let conn = opt.conn.as_ref().ok_or(Error::NoSource)?;
+	let mut listener = Client::connect(conn, NoTls)?;
+	if install {
+		install_trigger(&mut listener)?;
+	}
+	listener.batch_execute(&format!("LISTEN {}", CHANNEL))?;
+	let mut client = opt.get_client()?;
+
+	let mut full_db = client.get_all(opt.no_functions)?;
+	write(&generate(&full_db, opt)?, output_path, opt.dir)?;
+	eprintln!("watching for changes to the database");
+
+	while wait_for_change(&mut listener, debounce)? {
+		let new_db = client.get_all(opt.no_functions)?;
+		// a mapping that can't be generated is reported and the last one kept until the database changes again
+		let output = match generate(&new_db, opt) {
+			Ok(output) => output,
+			Err(e) => {
+				eprintln!("{}", e);
+				continue;
+			},
+		};
+		let written = write(&output, output_path, opt.dir)?;
+		eprint!("{}", diff(&full_db, &new_db, opt.use_tuples));
+		if !written.is_empty() {
+			let written: Vec<_> = written.iter().map(|v| v.display().to_string()).collect();
+			eprintln!("wrote {}", written.join(", "));
+		}
+		full_db = new_db;
+	}
+	Ok(())
+}
+
+/// Generate the mapping, printing the warnings in debug mode the same as generating without watching
+fn generate(full_db: &FullDB, opt: &Opt) -> Result<GeneratedOutput, Error> {
+	let output = full_db.generate(opt)?;
+	if opt.debug {
+		for warning in &output.warnings {
+			eprintln!("{}", warning);
+		}
+	}
+	Ok(output)
+}
+
+/// Write the files whose contents changed, returning their paths. Without `dir` only `src/lib.rs` is written, to
+/// `output_path`
+fn write(output: &GeneratedOutput, output_path: &Path, dir: bool) -> Result<Vec<PathBuf>, Error> {
+	if dir {
+		return Ok(output.write_to(output_path)?);
+	}
+	match write_if_changed(output_path, output.lib_rs())? {
+		true => Ok(vec![output_path.to_path_buf()]),
+		false => Ok(Vec::new()),
+	}
+}
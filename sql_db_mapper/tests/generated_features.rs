@@ -0,0 +1,150 @@
+//! Checks that the code generated for every third party option compiles with the matching features of
+//! sql_db_mapper_core
+//!
+//! Building the generated crates needs each option's dependencies from crates.io so this is ignored by default, run it
+//! with `cargo test --test generated_features -- --ignored`
+use sql_db_mapper::{Generator, ThirdParty};
+use std::{
+	path::{Path, PathBuf},
+	process::Command,
+};
+
+/// Types mapped without any option
+const BASE: &str = "
+CREATE SCHEMA typed;
+
+CREATE TABLE typed.base(
+	id SERIAL PRIMARY KEY,
+	name TEXT NOT NULL,
+	created TIMESTAMP NOT NULL,
+	updated TIMESTAMPTZ,
+	ip INET,
+	network CIDR
+);
+
+CREATE FUNCTION typed.named(prefix TEXT) RETURNS TABLE(id INTEGER, name TEXT) AS $$
+	SELECT id, name FROM typed.base WHERE name LIKE prefix || '%'
+$$ LANGUAGE SQL;
+";
+
+/// A table of the types each option maps, only included in the database when the option is used
+const OPTION_TYPES: [(ThirdParty, &str); 9] = [
+	(ThirdParty::BitVec, "flags BIT(8), more_flags BIT VARYING"),
+	(ThirdParty::Chrono, "born DATE, wakes TIME"),
+	(ThirdParty::Eui48, "mac MACADDR"),
+	(ThirdParty::GeoTypes, "location POINT, area BOX, route PATH"),
+	(ThirdParty::Regex, "code typed.code"),
+	(ThirdParty::RustDecimal, "price NUMERIC(10, 2), amount typed.positive"),
+	(ThirdParty::SerdeJson, "doc JSON, docb JSONB"),
+	(ThirdParty::Time, "born DATE, wakes TIME"),
+	(ThirdParty::Uuid, "uid UUID"),
+];
+
+fn manifest_dir() -> PathBuf {
+	PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+/// The database with the types of each of `third_party`
+fn schema_sql(third_party: &[ThirdParty]) -> String {
+	let mut sql = String::from(BASE);
+	if third_party.contains(&ThirdParty::RustDecimal) {
+		sql += "\nCREATE DOMAIN typed.positive AS NUMERIC CHECK (VALUE > 0);\n";
+	}
+	if third_party.contains(&ThirdParty::Regex) {
+		sql += "\nCREATE DOMAIN typed.code AS TEXT CHECK (VALUE ~ '^[A-Z]{3}$');\n";
+	}
+	for (i, (lib, columns)) in OPTION_TYPES.iter().enumerate() {
+		if third_party.contains(lib) {
+			sql += &format!(
+				"\nCREATE TABLE typed.option_{}(id SERIAL PRIMARY KEY, {});\n",
+				i, columns
+			);
+		}
+	}
+	sql
+}
+
+/// Generate a crate using `third_party` in `dir` which depends on the sql_db_mapper_core of this workspace
+fn generate(dir: &Path, third_party: Vec<ThirdParty>, serde_feature: bool) {
+	std::fs::create_dir_all(dir).unwrap();
+	let schema = dir.join("schema.sql");
+	std::fs::write(&schema, schema_sql(&third_party)).unwrap();
+	let core = manifest_dir().join("../sql_db_mapper_core").canonicalize().unwrap();
+	let template = dir.join("template.toml");
+	std::fs::write(
+		&template,
+		format!(
+			"[workspace]\n\n[patch.crates-io]\nsql_db_mapper_core = {{ path = {:?} }}\n",
+			core
+		),
+	)
+	.unwrap();
+
+	let output = Generator::new()
+		.conn(schema.to_str().unwrap())
+		.dir(true)
+		.third_party(third_party)
+		.query_builder(true)
+		.schema_check(true)
+		.serde_feature(serde_feature)
+		.package_name("generated_features")
+		.cargo_template(&template)
+		.generate()
+		.unwrap();
+	output.write_to(dir).unwrap();
+}
+
+/// `cargo check` the crate in `dir` with `features`
+fn check(dir: &Path, features: &str) -> Result<(), String> {
+	let target_dir = manifest_dir().join("../target/generated_features");
+	let output = Command::new(env!("CARGO"))
+		.args(["check", "--quiet", "--features", features])
+		.arg("--target-dir")
+		.arg(target_dir)
+		.current_dir(dir)
+		.output()
+		.unwrap();
+	match output.status.success() {
+		true => Ok(()),
+		false => Err(String::from_utf8_lossy(&output.stderr).into_owned()),
+	}
+}
+
+#[test]
+#[ignore = "builds generated crates with dependencies from crates.io"]
+fn every_feature_combination_compiles() {
+	let root = std::env::temp_dir().join(format!("sql_db_mapper_features_{}", std::process::id()));
+	// no options, each option on its own, and all of them together (where chrono is used for dates rather than time)
+	let all: Vec<_> = OPTION_TYPES
+		.iter()
+		.map(|v| v.0)
+		.filter(|v| *v != ThirdParty::Time)
+		.collect();
+	let mut combinations = vec![Vec::new()];
+	combinations.extend(OPTION_TYPES.iter().map(|v| vec![v.0]));
+	combinations.push(all.clone());
+
+	let mut failures = Vec::new();
+	for (i, third_party) in combinations.into_iter().enumerate() {
+		let dir = root.join(format!("combination_{}", i));
+		let name = format!("{:?}", third_party);
+		generate(&dir, third_party, false);
+		if let Err(e) = check(&dir, "sync,async") {
+			failures.push(format!("{}:\n{}", name, e));
+		}
+	}
+	// with `serde_feature` the serde derives of the generated types and sql_db_mapper_core's are turned on together
+	let dir = root.join("serde_feature");
+	generate(&dir, all, true);
+	for features in ["sync,async", "sync,async,serde"] {
+		if let Err(e) = check(&dir, features) {
+			failures.push(format!("serde_feature with {}:\n{}", features, e));
+		}
+	}
+	std::fs::remove_dir_all(&root).ok();
+	assert!(
+		failures.is_empty(),
+		"generated code failed to compile with {}",
+		failures.join("\n\n")
+	);
+}
@@ -0,0 +1,146 @@
+//! Bulk loading and exporting of tables with binary `COPY`
+//!
+//! Generated code implements [`CopyIn`] for the `New{Table}` structs (the columns of a table without defaults) and [`CopyOut`]
+//! for the struct of every table and view, the functions here do the rest
+use super::{query::quote_ident, AsyncClient, SqlError, SyncClient, ToSql};
+use postgres::fallible_iterator::FallibleIterator;
+pub use futures_util::stream::Stream;
+use futures_util::{pin_mut, StreamExt};
+pub use postgres_types::Type;
+pub use tokio_postgres::binary_copy::BinaryCopyOutRow;
+
+/// The columns copied to or from a table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CopyTable {
+	pub schema: &'static str,
+	pub name: &'static str,
+	pub columns: &'static [&'static str],
+	/// The oid of the type of each column
+	pub type_oids: &'static [u32],
+}
+
+/// A row that can be written with `COPY ... FROM STDIN`
+pub trait CopyIn {
+	const TABLE: CopyTable;
+
+	/// The value of each column in `TABLE`, in order
+	fn values(&self) -> Vec<&(dyn ToSql + Sync)>;
+}
+
+/// A row that can be read with `COPY ... TO STDOUT`
+pub trait CopyOut: Sized {
+	const TABLE: CopyTable;
+
+	fn from_copy_row(row: &BinaryCopyOutRow) -> Result<Self, SqlError>;
+}
+
+impl CopyTable {
+	fn column_list(&self) -> String {
+		let mut ret = String::new();
+		for (i, column) in self.columns.iter().enumerate() {
+			if i != 0 {
+				ret.push_str(", ");
+			}
+			quote_ident(&mut ret, column);
+		}
+		ret
+	}
+
+	fn table_name(&self) -> String {
+		let mut ret = String::new();
+		quote_ident(&mut ret, self.schema);
+		ret.push('.');
+		quote_ident(&mut ret, self.name);
+		ret
+	}
+
+	fn select_sql(&self) -> String {
+		format!("SELECT {} FROM {}", self.column_list(), self.table_name())
+	}
+
+	fn copy_in_sql(&self) -> String {
+		format!("COPY {} ({}) FROM STDIN (FORMAT binary)", self.table_name(), self.column_list())
+	}
+
+	fn copy_out_sql(&self) -> String {
+		// copying from a query rather than the table also works for views
+		format!("COPY ({}) TO STDOUT (FORMAT binary)", self.select_sql())
+	}
+
+	/// The types of the columns if they're all built in to postgres
+	///
+	/// Other types (enums, composites, domains, and extension types) need their name and kind to be looked up for `ToSql` and
+	/// `FromSql` to accept them so the types of a prepared select on the table are used instead
+	fn builtin_types(&self) -> Option<Vec<Type>> {
+		self.type_oids.iter().map(|oid| Type::from_oid(*oid)).collect()
+	}
+
+	fn types(&self, client: &mut SyncClient) -> Result<Vec<Type>, SqlError> {
+		match self.builtin_types() {
+			Some(types) => Ok(types),
+			None => Ok(client
+				.prepare(&self.select_sql())?
+				.columns()
+				.iter()
+				.map(|v| v.type_().clone())
+				.collect()),
+		}
+	}
+
+	async fn types_async(&self, client: &AsyncClient) -> Result<Vec<Type>, SqlError> {
+		match self.builtin_types() {
+			Some(types) => Ok(types),
+			None => Ok(client
+				.prepare(&self.select_sql())
+				.await?
+				.columns()
+				.iter()
+				.map(|v| v.type_().clone())
+				.collect()),
+		}
+	}
+}
+
+/// Insert every row with `COPY ... FROM STDIN (FORMAT binary)`, returns the number of rows inserted
+pub fn copy_in<R: CopyIn>(client: &mut SyncClient, rows: impl IntoIterator<Item = R>) -> Result<u64, SqlError> {
+	let types = R::TABLE.types(client)?;
+	let sink = client.copy_in(R::TABLE.copy_in_sql().as_str())?;
+	let mut writer = postgres::binary_copy::BinaryCopyInWriter::new(sink, &types);
+	for row in rows {
+		writer.write(&row.values())?;
+	}
+	writer.finish()
+}
+
+/// Read every row with `COPY ... TO STDOUT (FORMAT binary)`
+pub fn copy_out<T: CopyOut>(
+	client: &mut SyncClient,
+) -> Result<impl Iterator<Item = Result<T, SqlError>> + '_, SqlError> {
+	let types = T::TABLE.types(client)?;
+	let reader = client.copy_out(T::TABLE.copy_out_sql().as_str())?;
+	Ok(postgres::binary_copy::BinaryCopyOutIter::new(reader, &types)
+		.iterator()
+		.map(|row| T::from_copy_row(&row?)))
+}
+
+/// Insert every row with `COPY ... FROM STDIN (FORMAT binary)`, returns the number of rows inserted
+pub async fn copy_in_async<R: CopyIn>(client: &AsyncClient, rows: impl IntoIterator<Item = R>) -> Result<u64, SqlError> {
+	let types = R::TABLE.types_async(client).await?;
+	let sink = client.copy_in(R::TABLE.copy_in_sql().as_str()).await?;
+	let writer = tokio_postgres::binary_copy::BinaryCopyInWriter::new(sink, &types);
+	pin_mut!(writer);
+	for row in rows {
+		writer.as_mut().write(&row.values()).await?;
+	}
+	writer.finish().await
+}
+
+/// Read every row with `COPY ... TO STDOUT (FORMAT binary)`
+pub async fn copy_out_async<T: CopyOut>(
+	client: &AsyncClient,
+) -> Result<impl Stream<Item = Result<T, SqlError>>, SqlError> {
+	let types = T::TABLE.types_async(client).await?;
+	let stream = client.copy_out(T::TABLE.copy_out_sql().as_str()).await?;
+	Ok(tokio_postgres::binary_copy::BinaryCopyOutStream::new(stream, &types)
+		.map(|row| T::from_copy_row(&row?)))
+}
@@ -0,0 +1,45 @@
+//! Support for the `TryFrom` constructors of domains which check the domain's constraints
+use std::{error::Error, fmt};
+
+/// A value that doesn't satisfy one of the CHECK constraints of a domain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DomainError {
+	/// The domain as `schema.name`
+	pub domain: &'static str,
+	/// The name of the constraint that failed
+	pub constraint: &'static str,
+	/// The constraint's expression as postgres prints it
+	pub check: &'static str,
+}
+
+impl DomainError {
+	pub const fn new(domain: &'static str, constraint: &'static str, check: &'static str) -> DomainError {
+		DomainError {
+			domain,
+			constraint,
+			check,
+		}
+	}
+}
+
+impl fmt::Display for DomainError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"value for domain {} violates check constraint \"{}\": {}",
+			self.domain, self.constraint, self.check
+		)
+	}
+}
+
+impl Error for DomainError {}
+
+/// Whether `text` matches `pattern`, compiling it the first time it's used
+///
+/// Generated code only passes patterns that were checked to compile when the mapping was generated
+#[cfg(feature = "regex")]
+pub fn regex_is_match(cache: &std::sync::OnceLock<regex::Regex>, pattern: &str, text: &str) -> bool {
+	cache
+		.get_or_init(|| regex::Regex::new(pattern).expect("domain check patterns are validated when generated"))
+		.is_match(text)
+}
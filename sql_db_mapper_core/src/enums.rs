@@ -0,0 +1,57 @@
+//! Support for generated enums
+//!
+//! Enums with an `Unknown` variant hold any label so they can't use the postgres-types derives, their `FromSql` and `ToSql`
+//! implementations are built from these
+#[cfg(feature = "postgres")]
+use postgres_types::Kind;
+use std::{error::Error, fmt};
+
+#[cfg(feature = "postgres")]
+pub use bytes::BytesMut;
+#[cfg(feature = "postgres")]
+pub use postgres_types::{to_sql_checked, IsNull, Type};
+
+pub type BoxError = Box<dyn Error + Sync + Send>;
+
+/// A string which isn't one of the labels of an enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownLabel {
+	/// The enum as `schema.name`
+	pub enum_name: &'static str,
+	pub label: String,
+}
+
+impl UnknownLabel {
+	pub fn new(enum_name: &'static str, label: &str) -> UnknownLabel {
+		UnknownLabel {
+			enum_name,
+			label: String::from(label),
+		}
+	}
+}
+
+impl fmt::Display for UnknownLabel {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "\"{}\" is not a label of enum {}", self.label, self.enum_name)
+	}
+}
+
+impl Error for UnknownLabel {}
+
+/// Whether `ty` is the postgres enum called `name`, whatever its labels are
+#[cfg(feature = "postgres")]
+pub fn accepts(ty: &Type, name: &str) -> bool {
+	ty.name() == name && matches!(ty.kind(), Kind::Enum(_))
+}
+
+/// Enum values are sent as their label
+#[cfg(feature = "postgres")]
+pub fn label_from_sql(raw: &[u8]) -> Result<&str, BoxError> {
+	Ok(std::str::from_utf8(raw)?)
+}
+
+#[cfg(feature = "postgres")]
+pub fn label_to_sql(label: &str, out: &mut BytesMut) -> IsNull {
+	out.extend_from_slice(label.as_bytes());
+	IsNull::No
+}
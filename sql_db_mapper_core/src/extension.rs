@@ -0,0 +1,137 @@
+//! Types from postgres extensions which don't have a [`FromSql`] implementation in `postgres-types`
+//!
+//! Extension types don't have fixed oids so they're accepted by name
+//!
+//! [`FromSql`]: https://docs.rs/postgres-types/0.2/postgres_types/trait.FromSql.html
+use bytes::{BufMut, BytesMut};
+use postgres_types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
+use std::{convert::TryFrom, error::Error, fmt, ops::Deref};
+
+/// A label path from the `ltree` extension (i.e. `Top.Science.Astronomy`)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ltree(pub String);
+
+impl Ltree {
+	/// The labels of the path
+	pub fn labels(&self) -> impl Iterator<Item = &str> {
+		self.0.split('.').filter(|v| !v.is_empty())
+	}
+}
+
+impl Deref for Ltree {
+	type Target = str;
+
+	fn deref(&self) -> &str {
+		&self.0
+	}
+}
+
+impl fmt::Display for Ltree {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+impl From<String> for Ltree {
+	fn from(path: String) -> Ltree {
+		Ltree(path)
+	}
+}
+
+// the binary format of ltree is a version number followed by the text
+const LTREE_VERSION: u8 = 1;
+
+impl<'a> FromSql<'a> for Ltree {
+	fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+		match raw.split_first() {
+			Some((&LTREE_VERSION, text)) => Ok(Ltree(String::from(std::str::from_utf8(text)?))),
+			Some((version, _)) => Err(format!("unsupported ltree version {}", version).into()),
+			None => Err("invalid message length: empty ltree".into()),
+		}
+	}
+
+	fn accepts(ty: &Type) -> bool {
+		ty.name() == "ltree"
+	}
+}
+
+impl ToSql for Ltree {
+	fn to_sql(&self, _: &Type, w: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		w.put_u8(LTREE_VERSION);
+		w.put_slice(self.0.as_bytes());
+		Ok(IsNull::No)
+	}
+
+	fn accepts(ty: &Type) -> bool {
+		ty.name() == "ltree"
+	}
+
+	to_sql_checked!();
+}
+
+/// An embedding from the `vector` extension (pgvector)
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vector(pub Vec<f32>);
+
+impl Vector {
+	pub fn into_inner(self) -> Vec<f32> {
+		self.0
+	}
+}
+
+impl Deref for Vector {
+	type Target = [f32];
+
+	fn deref(&self) -> &[f32] {
+		&self.0
+	}
+}
+
+impl From<Vec<f32>> for Vector {
+	fn from(values: Vec<f32>) -> Vector {
+		Vector(values)
+	}
+}
+
+// the binary format of vector is the number of dimensions, an unused u16, then each value
+impl<'a> FromSql<'a> for Vector {
+	fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+		if raw.len() < 4 {
+			return Err("invalid message length: vector header".into());
+		}
+		let dim = usize::from(u16::from_be_bytes([raw[0], raw[1]]));
+		let values = &raw[4..];
+		if values.len() != dim * 4 {
+			return Err("invalid message length: vector size mismatch".into());
+		}
+		let values = values
+			.chunks_exact(4)
+			.map(|v| f32::from_be_bytes(<[u8; 4]>::try_from(v).unwrap()))
+			.collect();
+		Ok(Vector(values))
+	}
+
+	fn accepts(ty: &Type) -> bool {
+		ty.name() == "vector"
+	}
+}
+
+impl ToSql for Vector {
+	fn to_sql(&self, _: &Type, w: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		let dim = u16::try_from(self.0.len()).map_err(|_| "vector has too many dimensions")?;
+		w.put_u16(dim);
+		w.put_u16(0);
+		for value in &self.0 {
+			w.put_f32(*value);
+		}
+		Ok(IsNull::No)
+	}
+
+	fn accepts(ty: &Type) -> bool {
+		ty.name() == "vector"
+	}
+
+	to_sql_checked!();
+}
@@ -0,0 +1,248 @@
+//! The postgres `interval` type
+use bytes::{BufMut, BytesMut};
+use postgres_types::{accepts, to_sql_checked, FromSql, IsNull, ToSql, Type};
+use std::{convert::TryFrom, error::Error, fmt, time::Duration};
+
+/// A postgres `interval`
+///
+/// Stored the same way as postgres stores them as the length of a month or day depends on when the interval is applied. Only
+/// intervals without months or days can be converted to durations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PgInterval {
+	pub months: i32,
+	pub days: i32,
+	pub microseconds: i64,
+}
+
+/// A conversion to or from [`PgInterval`] that would lose information
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntervalConversionError;
+
+impl fmt::Display for IntervalConversionError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "interval can't be converted without losing information")
+	}
+}
+
+impl Error for IntervalConversionError {}
+
+impl PgInterval {
+	pub fn new(months: i32, days: i32, microseconds: i64) -> PgInterval {
+		PgInterval {
+			months,
+			days,
+			microseconds,
+		}
+	}
+
+	/// The microseconds if the interval doesn't have months or days
+	fn exact_microseconds(self) -> Result<i64, IntervalConversionError> {
+		if self.months == 0 && self.days == 0 {
+			Ok(self.microseconds)
+		} else {
+			Err(IntervalConversionError)
+		}
+	}
+
+	fn from_microseconds(microseconds: i64) -> PgInterval {
+		PgInterval::new(0, 0, microseconds)
+	}
+}
+
+impl<'a> FromSql<'a> for PgInterval {
+	fn from_sql(_: &Type, mut raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+		if raw.len() != 16 {
+			return Err("invalid message length: interval size mismatch".into());
+		}
+		let mut read = |len: usize| {
+			let (bytes, rest) = raw.split_at(len);
+			raw = rest;
+			bytes
+		};
+		let microseconds = i64::from_be_bytes(<[u8; 8]>::try_from(read(8))?);
+		let days = i32::from_be_bytes(<[u8; 4]>::try_from(read(4))?);
+		let months = i32::from_be_bytes(<[u8; 4]>::try_from(read(4))?);
+		Ok(PgInterval::new(months, days, microseconds))
+	}
+
+	accepts!(INTERVAL);
+}
+
+impl ToSql for PgInterval {
+	fn to_sql(&self, _: &Type, w: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		w.put_i64(self.microseconds);
+		w.put_i32(self.days);
+		w.put_i32(self.months);
+		Ok(IsNull::No)
+	}
+
+	accepts!(INTERVAL);
+	to_sql_checked!();
+}
+
+impl TryFrom<PgInterval> for Duration {
+	type Error = IntervalConversionError;
+
+	fn try_from(interval: PgInterval) -> Result<Duration, IntervalConversionError> {
+		let microseconds = u64::try_from(interval.exact_microseconds()?).map_err(|_| IntervalConversionError)?;
+		Ok(Duration::from_micros(microseconds))
+	}
+}
+
+impl TryFrom<Duration> for PgInterval {
+	type Error = IntervalConversionError;
+
+	fn try_from(duration: Duration) -> Result<PgInterval, IntervalConversionError> {
+		let microseconds = i64::try_from(duration.as_micros()).map_err(|_| IntervalConversionError)?;
+		if duration.subsec_nanos() != duration.subsec_micros() * 1000 {
+			return Err(IntervalConversionError);
+		}
+		Ok(PgInterval::from_microseconds(microseconds))
+	}
+}
+
+#[cfg(feature = "with-chrono-0_4")]
+mod chrono_impls {
+	use super::*;
+
+	impl TryFrom<PgInterval> for chrono::Duration {
+		type Error = IntervalConversionError;
+
+		fn try_from(interval: PgInterval) -> Result<chrono::Duration, IntervalConversionError> {
+			Ok(chrono::Duration::microseconds(interval.exact_microseconds()?))
+		}
+	}
+
+	impl TryFrom<chrono::Duration> for PgInterval {
+		type Error = IntervalConversionError;
+
+		fn try_from(duration: chrono::Duration) -> Result<PgInterval, IntervalConversionError> {
+			let microseconds = duration.num_microseconds().ok_or(IntervalConversionError)?;
+			if chrono::Duration::microseconds(microseconds) != duration {
+				return Err(IntervalConversionError);
+			}
+			Ok(PgInterval::from_microseconds(microseconds))
+		}
+	}
+}
+
+#[cfg(feature = "with-time-0_2")]
+mod time_impls {
+	use super::*;
+
+	impl TryFrom<PgInterval> for time::Duration {
+		type Error = IntervalConversionError;
+
+		fn try_from(interval: PgInterval) -> Result<time::Duration, IntervalConversionError> {
+			Ok(time::Duration::microseconds(interval.exact_microseconds()?))
+		}
+	}
+
+	impl TryFrom<time::Duration> for PgInterval {
+		type Error = IntervalConversionError;
+
+		fn try_from(duration: time::Duration) -> Result<PgInterval, IntervalConversionError> {
+			let microseconds = i64::try_from(duration.whole_microseconds()).map_err(|_| IntervalConversionError)?;
+			if time::Duration::microseconds(microseconds) != duration {
+				return Err(IntervalConversionError);
+			}
+			Ok(PgInterval::from_microseconds(microseconds))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn to_bytes(interval: PgInterval) -> Vec<u8> {
+		let mut bytes = BytesMut::new();
+		interval.to_sql(&Type::INTERVAL, &mut bytes).unwrap();
+		bytes.to_vec()
+	}
+
+	#[test]
+	fn binary_layout() {
+		let interval = PgInterval::new(14, -3, 1_500_000);
+		// microseconds, then days, then months, all big endian
+		let bytes = to_bytes(interval);
+		assert_eq!(
+			bytes,
+			[0, 0, 0, 0, 0, 0x16, 0xe3, 0x60, 0xff, 0xff, 0xff, 0xfd, 0, 0, 0, 14]
+		);
+		assert_eq!(PgInterval::from_sql(&Type::INTERVAL, &bytes).unwrap(), interval);
+		assert!(PgInterval::from_sql(&Type::INTERVAL, &bytes[..15]).is_err());
+	}
+
+	#[test]
+	fn duration() {
+		let interval = PgInterval::new(0, 0, 2_000_001);
+		assert_eq!(Duration::try_from(interval), Ok(Duration::from_micros(2_000_001)));
+		assert_eq!(PgInterval::try_from(Duration::from_micros(2_000_001)), Ok(interval));
+
+		// a month or a day isn't always the same length
+		assert_eq!(
+			Duration::try_from(PgInterval::new(1, 0, 0)),
+			Err(IntervalConversionError)
+		);
+		assert_eq!(
+			Duration::try_from(PgInterval::new(0, 1, 0)),
+			Err(IntervalConversionError)
+		);
+		assert_eq!(
+			Duration::try_from(PgInterval::new(0, 0, -1)),
+			Err(IntervalConversionError)
+		);
+		// postgres only keeps microseconds
+		assert_eq!(
+			PgInterval::try_from(Duration::from_nanos(1_500)),
+			Err(IntervalConversionError)
+		);
+		assert_eq!(PgInterval::try_from(Duration::MAX), Err(IntervalConversionError));
+	}
+
+	#[cfg(feature = "with-chrono-0_4")]
+	#[test]
+	fn chrono_duration() {
+		let interval = PgInterval::new(0, 0, -2_000_001);
+		assert_eq!(
+			chrono::Duration::try_from(interval),
+			Ok(chrono::Duration::microseconds(-2_000_001))
+		);
+		assert_eq!(
+			PgInterval::try_from(chrono::Duration::microseconds(-2_000_001)),
+			Ok(interval)
+		);
+		assert_eq!(
+			chrono::Duration::try_from(PgInterval::new(0, 1, 0)),
+			Err(IntervalConversionError)
+		);
+		assert_eq!(
+			PgInterval::try_from(chrono::Duration::nanoseconds(1_500)),
+			Err(IntervalConversionError)
+		);
+	}
+
+	#[cfg(feature = "with-time-0_2")]
+	#[test]
+	fn time_duration() {
+		let interval = PgInterval::new(0, 0, -2_000_001);
+		assert_eq!(
+			time::Duration::try_from(interval),
+			Ok(time::Duration::microseconds(-2_000_001))
+		);
+		assert_eq!(
+			PgInterval::try_from(time::Duration::microseconds(-2_000_001)),
+			Ok(interval)
+		);
+		assert_eq!(
+			time::Duration::try_from(PgInterval::new(1, 0, 0)),
+			Err(IntervalConversionError)
+		);
+		assert_eq!(
+			PgInterval::try_from(time::Duration::nanoseconds(1_500)),
+			Err(IntervalConversionError)
+		);
+	}
+}
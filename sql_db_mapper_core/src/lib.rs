@@ -9,30 +9,99 @@
 //! [`tokio_postgres::Row`]: https://docs.rs/tokio-postgres/0.7/tokio_postgres/row/struct.Row.html
 //! [`TryFromRow::from_row`]: ./trait.TryFromRow.html#tymethod.from_row
 //! [`TryFromRow`]: ./trait.TryFromRow.html
-//! [`Interval`]: ./struct.Interval.html
+//!
+//! Provides [`PgInterval`], [`PgMoney`], and [`Cidr`] for postgres types which `postgres-types` doesn't map and [`Ltree`] and
+//! [`Vector`] for types from the ltree and pgvector extensions
+//!
+//! [`DomainError`] is returned by the `TryFrom` constructors of domains when a value fails one of the domain's CHECK constraints
+//!
+//! [`MockFn`] backs the mocks generated for the per schema function traits so code using them can be tested without a database
+//!
+//! Generated async functions run on any [`AsyncConnection`], with features that includes deadpool-postgres and bb8 pools
+//!
+//! Everything but [`DomainError`], [`UnknownLabel`], and [`MockFn`] needs the default `postgres` feature, code generated for
+//! the sqlx backend uses this crate without it
+
+/// The version of this crate, which code generated by the same version of sql_db_mapper requires
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 //reexports
+#[cfg(feature = "postgres")]
 pub use postgres::Client as SyncClient;
+#[cfg(feature = "postgres")]
 pub use postgres_types::{FromSql, ToSql};
+#[cfg(feature = "postgres")]
 pub use sql_db_mapper_derive::*;
+#[cfg(feature = "postgres")]
 pub use tokio_postgres::{row::Row, Client as AsyncClient, Error as SqlError};
 
+#[cfg(feature = "postgres")]
 pub use postgres;
+#[cfg(feature = "postgres")]
 pub use tokio_postgres;
 
 #[cfg(feature = "bit-vec")]
 pub use bit_vec;
 #[cfg(feature = "chrono")]
 pub use chrono;
+#[cfg(feature = "eui48")]
+pub use eui48;
 #[cfg(feature = "geo-types")]
 pub use geo_types;
+#[cfg(feature = "regex")]
+pub use regex;
 #[cfg(feature = "rust_decimal")]
 pub use rust_decimal;
 #[cfg(feature = "serde_json")]
 pub use serde_json;
+#[cfg(feature = "time")]
+pub use time;
 #[cfg(feature = "uuid")]
 pub use uuid;
 
 /// Implementation of `TryFromRow` for various types
+#[cfg(feature = "postgres")]
 mod try_from_row;
+#[cfg(feature = "postgres")]
 pub use try_from_row::TryFromRow;
+
+/// Types for postgres network addresses
+#[cfg(feature = "postgres")]
+mod net;
+#[cfg(feature = "postgres")]
+pub use net::Cidr;
+
+/// The postgres interval type
+#[cfg(feature = "postgres")]
+mod interval;
+#[cfg(feature = "postgres")]
+pub use interval::{IntervalConversionError, PgInterval};
+
+/// The postgres money type
+#[cfg(feature = "postgres")]
+mod money;
+#[cfg(feature = "postgres")]
+pub use money::PgMoney;
+
+/// Types from postgres extensions
+#[cfg(feature = "postgres")]
+mod extension;
+#[cfg(feature = "postgres")]
+pub use extension::{Ltree, Vector};
+
+#[cfg(feature = "postgres")]
+pub mod copy;
+pub mod domain;
+pub use domain::DomainError;
+pub mod enums;
+pub use enums::UnknownLabel;
+pub mod mock;
+pub use mock::MockFn;
+#[cfg(feature = "postgres")]
+pub mod pool;
+#[cfg(feature = "postgres")]
+pub use pool::AsyncConnection;
+#[cfg(feature = "postgres")]
+pub mod query;
+#[cfg(feature = "postgres")]
+pub mod schema_check;
@@ -0,0 +1,215 @@
+//! Support for the generated mocks of the per schema function traits
+//!
+//! Each method of a generated `Mock{Schema}Fns` is a [`MockFn`] which records the arguments it was called with and returns
+//! the values queued on it
+#[cfg(feature = "postgres")]
+use super::query::Query;
+use std::{collections::VecDeque, fmt, sync::Mutex};
+
+type Handler<A, R> = Box<dyn FnMut(&A) -> R + Send>;
+
+/// A mocked function taking the owned arguments `A` and returning `R`
+///
+/// Queued return values are used first, in order, then the function from [`returning_with`](MockFn::returning_with).
+/// A call with neither panics
+pub struct MockFn<A, R> {
+	name: &'static str,
+	calls: Mutex<Vec<A>>,
+	returns: Mutex<VecDeque<R>>,
+	handler: Mutex<Option<Handler<A, R>>>,
+	expected_calls: Mutex<Option<usize>>,
+}
+
+impl<A, R> MockFn<A, R> {
+	/// `name` is used in the panic messages
+	pub fn new(name: &'static str) -> Self {
+		MockFn {
+			name,
+			calls: Mutex::new(Vec::new()),
+			returns: Mutex::new(VecDeque::new()),
+			handler: Mutex::new(None),
+			expected_calls: Mutex::new(None),
+		}
+	}
+
+	/// Queue a value to be returned by one call
+	pub fn returning(&self, value: R) -> &Self {
+		self.returns.lock().unwrap().push_back(value);
+		self
+	}
+
+	/// Compute the value returned once the queued values run out
+	pub fn returning_with(&self, f: impl FnMut(&A) -> R + Send + 'static) -> &Self {
+		*self.handler.lock().unwrap() = Some(Box::new(f));
+		self
+	}
+
+	/// Expect exactly `count` calls, checked when the mock is dropped
+	pub fn times(&self, count: usize) -> &Self {
+		*self.expected_calls.lock().unwrap() = Some(count);
+		self
+	}
+
+	/// Expect the function to never be called
+	pub fn never(&self) -> &Self {
+		self.times(0)
+	}
+
+	/// The number of calls so far
+	pub fn call_count(&self) -> usize {
+		self.calls.lock().unwrap().len()
+	}
+
+	/// Forget the calls, queued values, and expectations
+	pub fn reset(&self) {
+		self.calls.lock().unwrap().clear();
+		self.returns.lock().unwrap().clear();
+		*self.handler.lock().unwrap() = None;
+		*self.expected_calls.lock().unwrap() = None;
+	}
+
+	/// Record a call and get its return value, used by the generated trait implementations
+	pub fn call(&self, args: A) -> R {
+		let queued = self.returns.lock().unwrap().pop_front();
+		let ret = match queued {
+			Some(v) => v,
+			None => match self.handler.lock().unwrap().as_mut() {
+				Some(f) => f(&args),
+				None => panic!("unexpected call to mocked function {}", self.name),
+			},
+		};
+		self.calls.lock().unwrap().push(args);
+		ret
+	}
+}
+
+impl<A: Clone, R> MockFn<A, R> {
+	/// The arguments of every call so far
+	pub fn calls(&self) -> Vec<A> {
+		self.calls.lock().unwrap().clone()
+	}
+
+	/// The arguments of the most recent call
+	pub fn last_call(&self) -> Option<A> {
+		self.calls.lock().unwrap().last().cloned()
+	}
+}
+
+impl<A, R> fmt::Debug for MockFn<A, R> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MockFn")
+			.field("name", &self.name)
+			.field("call_count", &self.call_count())
+			.field("queued_returns", &self.returns.lock().unwrap().len())
+			.finish()
+	}
+}
+
+impl<A, R> Drop for MockFn<A, R> {
+	fn drop(&mut self) {
+		if std::thread::panicking() {
+			return;
+		}
+		let expected = *self.expected_calls.get_mut().unwrap();
+		if let Some(expected) = expected {
+			let actual = self.calls.get_mut().unwrap().len();
+			assert_eq!(
+				expected, actual,
+				"mocked function {} expected {} calls but got {}",
+				self.name, expected, actual
+			);
+		}
+	}
+}
+
+/// The arguments a mocked select was called with
+#[cfg(feature = "postgres")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryCall {
+	pub sql: String,
+	/// The `Debug` output of each parameter
+	pub params: Vec<String>,
+}
+
+#[cfg(feature = "postgres")]
+impl QueryCall {
+	pub fn new<T>(query: &Query<T>) -> Self {
+		let (sql, params) = query.to_sql();
+		QueryCall {
+			sql,
+			params: params.iter().map(|v| format!("{:?}", v)).collect(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn queued_values_come_before_the_handler() {
+		let f = MockFn::<(i32, String), i32>::new("people.add");
+		f.returning(1).returning(2).returning_with(|(v, _)| v * 10);
+		assert_eq!(f.call((3, String::from("a"))), 1);
+		assert_eq!(f.call((4, String::from("b"))), 2);
+		assert_eq!(f.call((5, String::from("c"))), 50);
+		assert_eq!(f.call((6, String::from("d"))), 60);
+		assert_eq!(f.call_count(), 4);
+	}
+
+	#[test]
+	fn calls_are_recorded_in_order() {
+		let f = MockFn::<(i32,), ()>::new("people.remove");
+		f.returning_with(|_| ());
+		assert_eq!(f.last_call(), None);
+		f.call((1,));
+		f.call((2,));
+		assert_eq!(f.calls(), [(1,), (2,)]);
+		assert_eq!(f.last_call(), Some((2,)));
+	}
+
+	#[test]
+	#[should_panic(expected = "unexpected call to mocked function people.add")]
+	fn call_without_a_return_value_panics() {
+		let f = MockFn::<(i32,), i32>::new("people.add");
+		f.returning(1);
+		f.call((1,));
+		f.call((2,));
+	}
+
+	#[test]
+	fn unexpected_call_is_not_recorded() {
+		let f = MockFn::<(i32,), i32>::new("people.add");
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f.call((1,))));
+		assert!(result.is_err());
+		assert_eq!(f.call_count(), 0);
+	}
+
+	#[test]
+	#[should_panic(expected = "mocked function people.add expected 2 calls but got 1")]
+	fn wrong_call_count_panics_on_drop() {
+		let f = MockFn::<(i32,), i32>::new("people.add");
+		f.returning(1).returning(2).times(2);
+		f.call((1,));
+	}
+
+	#[test]
+	fn expected_call_count() {
+		let f = MockFn::<(), i32>::new("people.count");
+		f.returning_with(|_| 0).times(2);
+		f.call(());
+		f.call(());
+		MockFn::<(), i32>::new("people.count").never();
+	}
+
+	#[test]
+	fn reset_forgets_everything() {
+		let f = MockFn::<(), i32>::new("people.count");
+		f.returning(1).returning_with(|_| 2).times(5);
+		f.call(());
+		f.reset();
+		assert_eq!(f.call_count(), 0);
+		let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f.call(())));
+		assert!(result.is_err());
+	}
+}
@@ -0,0 +1,84 @@
+//! The postgres `money` type
+use bytes::{BufMut, BytesMut};
+use postgres_types::{accepts, to_sql_checked, FromSql, IsNull, ToSql, Type};
+use std::{convert::TryFrom, error::Error, fmt};
+
+/// A postgres `money`, stored as a whole number of cents
+///
+/// Postgres scales money by the number of fractional digits of the database's `lc_monetary` locale, this assumes the usual 2
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PgMoney(pub i64);
+
+impl PgMoney {
+	pub fn from_cents(cents: i64) -> PgMoney {
+		PgMoney(cents)
+	}
+
+	pub fn cents(self) -> i64 {
+		self.0
+	}
+}
+
+impl fmt::Display for PgMoney {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let sign = if self.0 < 0 { "-" } else { "" };
+		let abs = self.0.unsigned_abs();
+		write!(f, "{}{}.{:02}", sign, abs / 100, abs % 100)
+	}
+}
+
+impl<'a> FromSql<'a> for PgMoney {
+	fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+		let bytes = <[u8; 8]>::try_from(raw).map_err(|_| "invalid message length: money size mismatch")?;
+		Ok(PgMoney(i64::from_be_bytes(bytes)))
+	}
+
+	accepts!(MONEY);
+}
+
+impl ToSql for PgMoney {
+	fn to_sql(&self, _: &Type, w: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		w.put_i64(self.0);
+		Ok(IsNull::No)
+	}
+
+	accepts!(MONEY);
+	to_sql_checked!();
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn display() {
+		let shown: Vec<_> = [0, 5, 100, 123_456, -5, -100, -123_456, i64::MIN]
+			.iter()
+			.map(|&v| PgMoney::from_cents(v).to_string())
+			.collect();
+		assert_eq!(
+			shown,
+			[
+				"0.00",
+				"0.05",
+				"1.00",
+				"1234.56",
+				"-0.05",
+				"-1.00",
+				"-1234.56",
+				"-92233720368547758.08"
+			]
+		);
+	}
+
+	#[test]
+	fn binary_layout() {
+		let money = PgMoney::from_cents(-123_456);
+		let mut bytes = BytesMut::new();
+		money.to_sql(&Type::MONEY, &mut bytes).unwrap();
+		assert_eq!(bytes.as_ref(), (-123_456i64).to_be_bytes());
+		assert_eq!(PgMoney::from_sql(&Type::MONEY, &bytes).unwrap(), money);
+		assert!(PgMoney::from_sql(&Type::MONEY, &bytes[..4]).is_err());
+	}
+}
@@ -0,0 +1,42 @@
+//! Network address types without a [`FromSql`] implementation in `postgres-types`
+//!
+//! [`FromSql`]: https://docs.rs/postgres-types/0.2/postgres_types/trait.FromSql.html
+use bytes::BytesMut;
+use postgres_types::{accepts, to_sql_checked, FromSql, IsNull, ToSql, Type};
+use std::{error::Error, fmt, net::IpAddr};
+
+/// A postgres `cidr`, a network given by its address and the number of bits in its netmask
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cidr {
+	pub addr: IpAddr,
+	pub prefix: u8,
+}
+
+impl fmt::Display for Cidr {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}/{}", self.addr, self.prefix)
+	}
+}
+
+impl<'a> FromSql<'a> for Cidr {
+	fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+		let inet = postgres_protocol::types::inet_from_sql(raw)?;
+		Ok(Cidr {
+			addr: inet.addr(),
+			prefix: inet.netmask(),
+		})
+	}
+
+	accepts!(CIDR);
+}
+
+impl ToSql for Cidr {
+	fn to_sql(&self, _: &Type, w: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+		postgres_protocol::types::inet_to_sql(self.addr, self.prefix, w);
+		Ok(IsNull::No)
+	}
+
+	accepts!(CIDR);
+	to_sql_checked!();
+}
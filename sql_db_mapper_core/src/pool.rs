@@ -0,0 +1,258 @@
+//! Connections the generated async functions can run on
+//!
+//! Generated async functions take any [`AsyncConnection`], which is implemented for the tokio-postgres client and
+//! transactions, and with the `with-deadpool-postgres-0_14` and `with-bb8-0_8` features for the pools of those crates and
+//! the connections taken from them. Passing a pool takes a connection for the length of the call
+use super::{AsyncClient, Row, SqlError, ToSql};
+use futures_util::future::BoxFuture;
+
+/// Something which can run the statements of the generated async functions
+pub trait AsyncConnection: Sync {
+	/// Errors from running a statement, pools also fail to get a connection
+	type Error: From<SqlError> + Send;
+
+	fn query<'a>(
+		&'a self,
+		sql: &'a str,
+		params: &'a [&'a (dyn ToSql + Sync)],
+	) -> BoxFuture<'a, Result<Vec<Row>, Self::Error>>;
+
+	fn query_opt<'a>(
+		&'a self,
+		sql: &'a str,
+		params: &'a [&'a (dyn ToSql + Sync)],
+	) -> BoxFuture<'a, Result<Option<Row>, Self::Error>>;
+}
+
+/// Implements `AsyncConnection` for a type which derefs to the tokio-postgres `$client`, preparing each statement with
+/// `$prepare`
+macro_rules! impl_client {
+	($typ:ty, $client:ty, $prepare:ident $(, [$($generics:tt)*] $(where [$($bounds:tt)*])?)?) => {
+		impl<$($($generics)*)?> AsyncConnection for $typ $($(where $($bounds)*)?)? {
+			type Error = SqlError;
+
+			fn query<'a>(
+				&'a self,
+				sql: &'a str,
+				params: &'a [&'a (dyn ToSql + Sync)],
+			) -> BoxFuture<'a, Result<Vec<Row>, Self::Error>> {
+				Box::pin(async move {
+					let stmt = self.$prepare(sql).await?;
+					<$client>::query(self, &stmt, params).await
+				})
+			}
+
+			fn query_opt<'a>(
+				&'a self,
+				sql: &'a str,
+				params: &'a [&'a (dyn ToSql + Sync)],
+			) -> BoxFuture<'a, Result<Option<Row>, Self::Error>> {
+				Box::pin(async move {
+					let stmt = self.$prepare(sql).await?;
+					<$client>::query_opt(self, &stmt, params).await
+				})
+			}
+		}
+	};
+}
+
+/// Implements `AsyncConnection` for a pool by running each statement on a connection taken from it
+#[cfg(any(feature = "deadpool-postgres", feature = "bb8"))]
+macro_rules! impl_pool {
+	($typ:ty, $error:ty $(, [$($generics:tt)*] $(where [$($bounds:tt)*])?)?) => {
+		impl<$($($generics)*)?> AsyncConnection for $typ $($(where $($bounds)*)?)? {
+			type Error = $error;
+
+			fn query<'a>(
+				&'a self,
+				sql: &'a str,
+				params: &'a [&'a (dyn ToSql + Sync)],
+			) -> BoxFuture<'a, Result<Vec<Row>, Self::Error>> {
+				Box::pin(async move {
+					let client = self.get().await?;
+					Ok(AsyncConnection::query(&client, sql, params).await?)
+				})
+			}
+
+			fn query_opt<'a>(
+				&'a self,
+				sql: &'a str,
+				params: &'a [&'a (dyn ToSql + Sync)],
+			) -> BoxFuture<'a, Result<Option<Row>, Self::Error>> {
+				Box::pin(async move {
+					let client = self.get().await?;
+					Ok(AsyncConnection::query_opt(&client, sql, params).await?)
+				})
+			}
+		}
+	};
+}
+
+impl_client!(AsyncClient, AsyncClient, prepare);
+impl_client!(tokio_postgres::Transaction<'t>, tokio_postgres::Transaction<'t>, prepare, ['t]);
+
+/// Pooled clients prepare statements through deadpool's statement cache
+#[cfg(feature = "deadpool-postgres")]
+mod with_deadpool {
+	use super::*;
+	use deadpool_postgres::{Client, Pool, PoolError, Transaction};
+
+	impl_client!(Client, AsyncClient, prepare_cached);
+	impl_client!(Transaction<'t>, tokio_postgres::Transaction<'t>, prepare_cached, ['t]);
+	impl_pool!(Pool, PoolError);
+}
+
+/// bb8-postgres doesn't cache statements so they're prepared for every call
+#[cfg(feature = "bb8")]
+mod with_bb8 {
+	use super::*;
+	use bb8::{Pool, PooledConnection, RunError};
+	use bb8_postgres::PostgresConnectionManager;
+	use tokio_postgres::{
+		tls::{MakeTlsConnect, TlsConnect},
+		Socket,
+	};
+
+	impl_client!(
+		PooledConnection<'p, PostgresConnectionManager<Tls>>,
+		AsyncClient,
+		prepare,
+		['p, Tls] where [
+			Tls: MakeTlsConnect<Socket> + Clone + Send + Sync + 'static,
+			Tls::Stream: Send + Sync,
+			Tls::TlsConnect: Send,
+			<Tls::TlsConnect as TlsConnect<Socket>>::Future: Send,
+		]
+	);
+	impl_pool!(
+		Pool<PostgresConnectionManager<Tls>>,
+		RunError<SqlError>,
+		[Tls] where [
+			Tls: MakeTlsConnect<Socket> + Clone + Send + Sync + 'static,
+			Tls::Stream: Send + Sync,
+			Tls::TlsConnect: Send,
+			<Tls::TlsConnect as TlsConnect<Socket>>::Future: Send,
+		]
+	);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::future::Future;
+	use tokio_postgres::NoTls;
+
+	/// A socket directory without a server in it
+	#[cfg(any(feature = "deadpool-postgres", feature = "bb8"))]
+	const NO_SERVER: &str = "host=/nonexistent port=1 user=postgres";
+
+	fn block_on<F: Future>(future: F) -> F::Output {
+		tokio::runtime::Builder::new_current_thread()
+			.enable_all()
+			.build()
+			.unwrap()
+			.block_on(future)
+	}
+
+	fn test_db() -> String {
+		std::env::var("SQL_DB_MAPPER_TEST_DB").expect("SQL_DB_MAPPER_TEST_DB isn't set")
+	}
+
+	/// Run a query through each method the generated functions use
+	async fn run_queries<C: AsyncConnection>(conn: &C) -> Result<(), C::Error> {
+		let rows = conn.query("SELECT $1::int4 + 1", &[&1i32]).await?;
+		assert_eq!(rows.len(), 1);
+		assert_eq!(rows[0].get::<_, i32>(0), 2);
+		let row = conn.query_opt("SELECT $1::text WHERE $2", &[&"a", &true]).await?;
+		assert_eq!(row.map(|v| v.get::<_, String>(0)).as_deref(), Some("a"));
+		assert!(conn.query_opt("SELECT 1 WHERE $1", &[&false]).await?.is_none());
+		Ok(())
+	}
+
+	#[test]
+	#[ignore = "connects to the database given by SQL_DB_MAPPER_TEST_DB"]
+	fn client_and_transaction() {
+		block_on(async {
+			let (mut client, connection) = tokio_postgres::connect(&test_db(), NoTls).await.unwrap();
+			tokio::spawn(connection);
+			run_queries(&client).await.unwrap();
+			let transaction = client.transaction().await.unwrap();
+			run_queries(&transaction).await.unwrap();
+		});
+	}
+
+	#[cfg(feature = "deadpool-postgres")]
+	fn deadpool(conn: &str) -> deadpool_postgres::Pool {
+		let manager = deadpool_postgres::Manager::new(conn.parse().unwrap(), NoTls);
+		deadpool_postgres::Pool::builder(manager).max_size(1).build().unwrap()
+	}
+
+	#[test]
+	#[cfg(feature = "deadpool-postgres")]
+	#[ignore = "connects to the database given by SQL_DB_MAPPER_TEST_DB"]
+	fn deadpool_checks_out_a_connection_per_call() {
+		block_on(async {
+			let pool = deadpool(&test_db());
+			run_queries(&pool).await.unwrap();
+			// the one connection was returned after each query
+			let status = pool.status();
+			assert_eq!((status.size, status.available), (1, 1));
+
+			let mut client = pool.get().await.unwrap();
+			assert_eq!(pool.status().available, 0);
+			run_queries(&client).await.unwrap();
+			let transaction = client.transaction().await.unwrap();
+			run_queries(&transaction).await.unwrap();
+		});
+	}
+
+	#[test]
+	#[cfg(feature = "deadpool-postgres")]
+	fn deadpool_checkout_error() {
+		let pool = deadpool(NO_SERVER);
+		let result = block_on(AsyncConnection::query(&pool, "SELECT 1", &[]));
+		assert!(
+			matches!(result, Err(deadpool_postgres::PoolError::Backend(_))),
+			"{:?}",
+			result
+		);
+	}
+
+	#[cfg(feature = "bb8")]
+	async fn bb8(conn: &str) -> bb8::Pool<bb8_postgres::PostgresConnectionManager<NoTls>> {
+		let manager = bb8_postgres::PostgresConnectionManager::new_from_stringlike(conn, NoTls).unwrap();
+		bb8::Pool::builder()
+			.max_size(1)
+			.connection_timeout(std::time::Duration::from_millis(500))
+			.build(manager)
+			.await
+			.unwrap()
+	}
+
+	#[test]
+	#[cfg(feature = "bb8")]
+	#[ignore = "connects to the database given by SQL_DB_MAPPER_TEST_DB"]
+	fn bb8_checks_out_a_connection_per_call() {
+		block_on(async {
+			let pool = bb8(&test_db()).await;
+			run_queries(&pool).await.unwrap();
+			// the one connection was returned after each query
+			let state = pool.state();
+			assert_eq!((state.connections, state.idle_connections), (1, 1));
+
+			let conn = pool.get().await.unwrap();
+			assert_eq!(pool.state().idle_connections, 0);
+			run_queries(&conn).await.unwrap();
+		});
+	}
+
+	#[test]
+	#[cfg(feature = "bb8")]
+	fn bb8_checkout_error() {
+		let result = block_on(async {
+			let pool = bb8(NO_SERVER).await;
+			AsyncConnection::query(&pool, "SELECT 1", &[]).await
+		});
+		assert!(matches!(result, Err(bb8::RunError::TimedOut)), "{:?}", result);
+	}
+}
@@ -0,0 +1,421 @@
+//! A small typed query builder used by the per table `query` functions in generated code
+//!
+//! Each table gets a set of [`TypedColumn`]s which carry the rust type of the column so filter values are checked at compile
+//! time. All values are sent as query parameters, never formatted into the SQL
+//!
+//! ```ignore
+//! let people = query::people::people::query()
+//!     .filter(Column::Birthday.lt(date))
+//!     .order_by(Column::Name)
+//!     .limit(50)
+//!     .fetch(&mut client)?;
+//! ```
+use super::{AsyncConnection, SqlError, SyncClient, ToSql, TryFromRow};
+use std::{fmt::Write, marker::PhantomData};
+
+type Param = Box<dyn ToSql + Sync + Send>;
+
+/// The name and columns of a table or view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Table {
+	pub schema: &'static str,
+	pub name: &'static str,
+	/// Every column in the order of the fields of the mapped struct
+	pub columns: &'static [&'static str],
+}
+
+/// A column of the table mapped to `T` holding values of type `V`
+///
+/// Nullable columns use the type of the value (i.e. not wrapped in an Option), use [`TypedColumn::is_null`] to check for nulls
+pub struct TypedColumn<T, V> {
+	name: &'static str,
+	_marker: PhantomData<fn() -> (T, V)>,
+}
+
+impl<T, V> Clone for TypedColumn<T, V> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+impl<T, V> Copy for TypedColumn<T, V> {}
+
+impl<T, V> TypedColumn<T, V> {
+	pub const fn new(name: &'static str) -> Self {
+		TypedColumn {
+			name,
+			_marker: PhantomData,
+		}
+	}
+
+	/// The name of the column in the database
+	pub fn name(&self) -> &'static str {
+		self.name
+	}
+
+	pub fn is_null(self) -> Filter<T> {
+		Filter::new(Expr::IsNull(self.name, true))
+	}
+
+	pub fn is_not_null(self) -> Filter<T> {
+		Filter::new(Expr::IsNull(self.name, false))
+	}
+
+	/// Order by this column ascending
+	pub fn asc(self) -> Order<T> {
+		Order::new(self.name, false)
+	}
+
+	/// Order by this column descending
+	pub fn desc(self) -> Order<T> {
+		Order::new(self.name, true)
+	}
+}
+
+impl<T, V: ToSql + Sync + Send + 'static> TypedColumn<T, V> {
+	fn compare(self, op: &'static str, value: V) -> Filter<T> {
+		Filter::new(Expr::Compare(self.name, op, Box::new(value)))
+	}
+
+	pub fn eq(self, value: V) -> Filter<T> {
+		self.compare("=", value)
+	}
+
+	pub fn ne(self, value: V) -> Filter<T> {
+		self.compare("<>", value)
+	}
+
+	pub fn lt(self, value: V) -> Filter<T> {
+		self.compare("<", value)
+	}
+
+	pub fn le(self, value: V) -> Filter<T> {
+		self.compare("<=", value)
+	}
+
+	pub fn gt(self, value: V) -> Filter<T> {
+		self.compare(">", value)
+	}
+
+	pub fn ge(self, value: V) -> Filter<T> {
+		self.compare(">=", value)
+	}
+
+	/// The column is equal to any of the values, an empty list matches nothing
+	pub fn in_list(self, values: impl IntoIterator<Item = V>) -> Filter<T> {
+		let values = values.into_iter().map(|v| Box::new(v) as Param).collect();
+		Filter::new(Expr::In(self.name, values))
+	}
+}
+
+impl<T> TypedColumn<T, String> {
+	/// SQL `LIKE` pattern match
+	pub fn like(self, pattern: impl Into<String>) -> Filter<T> {
+		self.compare("LIKE", pattern.into())
+	}
+}
+
+enum Expr {
+	Compare(&'static str, &'static str, Param),
+	IsNull(&'static str, bool),
+	In(&'static str, Vec<Param>),
+	And(Vec<Expr>),
+	Or(Vec<Expr>),
+	Not(Box<Expr>),
+}
+
+/// A condition on the rows of the table mapped to `T`
+pub struct Filter<T> {
+	expr: Expr,
+	_marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Filter<T> {
+	fn new(expr: Expr) -> Self {
+		Filter {
+			expr,
+			_marker: PhantomData,
+		}
+	}
+
+	pub fn and(self, other: Filter<T>) -> Filter<T> {
+		Filter::new(Expr::And(vec![self.expr, other.expr]))
+	}
+
+	pub fn or(self, other: Filter<T>) -> Filter<T> {
+		Filter::new(Expr::Or(vec![self.expr, other.expr]))
+	}
+
+	#[allow(clippy::should_implement_trait)]
+	pub fn not(self) -> Filter<T> {
+		Filter::new(Expr::Not(Box::new(self.expr)))
+	}
+}
+
+/// A column to order the results of a query by
+pub struct Order<T> {
+	column: &'static str,
+	desc: bool,
+	_marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Order<T> {
+	fn new(column: &'static str, desc: bool) -> Self {
+		Order {
+			column,
+			desc,
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl<T, V> From<TypedColumn<T, V>> for Order<T> {
+	fn from(column: TypedColumn<T, V>) -> Self {
+		column.asc()
+	}
+}
+
+/// A select on a single table returning rows as `T`
+pub struct Query<T> {
+	table: &'static Table,
+	filters: Vec<Expr>,
+	order: Vec<Order<T>>,
+	limit: Option<i64>,
+	offset: Option<i64>,
+}
+
+pub(crate) fn quote_ident(out: &mut String, ident: &str) {
+	out.push('"');
+	out.push_str(&ident.replace('"', "\"\""));
+	out.push('"');
+}
+
+impl Expr {
+	fn write_sql<'a>(&'a self, sql: &mut String, params: &mut Vec<&'a (dyn ToSql + Sync)>) {
+		match self {
+			Expr::Compare(column, op, value) => {
+				quote_ident(sql, column);
+				params.push(value.as_ref());
+				write!(sql, " {} ${}", op, params.len()).unwrap();
+			},
+			Expr::IsNull(column, is_null) => {
+				quote_ident(sql, column);
+				sql.push_str(if *is_null { " IS NULL" } else { " IS NOT NULL" });
+			},
+			Expr::In(_, values) if values.is_empty() => sql.push_str("FALSE"),
+			Expr::In(column, values) => {
+				quote_ident(sql, column);
+				sql.push_str(" IN (");
+				for (i, value) in values.iter().enumerate() {
+					params.push(value.as_ref());
+					if i != 0 {
+						sql.push_str(", ");
+					}
+					write!(sql, "${}", params.len()).unwrap();
+				}
+				sql.push(')');
+			},
+			Expr::And(exprs) => Expr::write_list(exprs, " AND ", sql, params),
+			Expr::Or(exprs) => Expr::write_list(exprs, " OR ", sql, params),
+			Expr::Not(expr) => {
+				sql.push_str("NOT (");
+				expr.write_sql(sql, params);
+				sql.push(')');
+			},
+		}
+	}
+
+	fn write_list<'a>(exprs: &'a [Expr], sep: &str, sql: &mut String, params: &mut Vec<&'a (dyn ToSql + Sync)>) {
+		for (i, expr) in exprs.iter().enumerate() {
+			if i != 0 {
+				sql.push_str(sep);
+			}
+			sql.push('(');
+			expr.write_sql(sql, params);
+			sql.push(')');
+		}
+	}
+}
+
+impl<T> Query<T> {
+	pub fn new(table: &'static Table) -> Self {
+		Query {
+			table,
+			filters: Vec::new(),
+			order: Vec::new(),
+			limit: None,
+			offset: None,
+		}
+	}
+
+	/// Only return rows matching the filter, multiple filters are combined with AND
+	pub fn filter(mut self, filter: Filter<T>) -> Self {
+		self.filters.push(filter.expr);
+		self
+	}
+
+	/// Order by a column, can be called multiple times to order by several columns
+	pub fn order_by(mut self, order: impl Into<Order<T>>) -> Self {
+		self.order.push(order.into());
+		self
+	}
+
+	pub fn limit(mut self, limit: i64) -> Self {
+		self.limit = Some(limit);
+		self
+	}
+
+	pub fn offset(mut self, offset: i64) -> Self {
+		self.offset = Some(offset);
+		self
+	}
+
+	/// The SQL for the query and the parameters to send with it
+	pub fn to_sql(&self) -> (String, Vec<&(dyn ToSql + Sync)>) {
+		let mut sql = String::from("SELECT ");
+		let mut params = Vec::new();
+		for (i, column) in self.table.columns.iter().enumerate() {
+			if i != 0 {
+				sql.push_str(", ");
+			}
+			quote_ident(&mut sql, column);
+		}
+		sql.push_str(" FROM ");
+		quote_ident(&mut sql, self.table.schema);
+		sql.push('.');
+		quote_ident(&mut sql, self.table.name);
+		if !self.filters.is_empty() {
+			sql.push_str(" WHERE ");
+			Expr::write_list(&self.filters, " AND ", &mut sql, &mut params);
+		}
+		for (i, order) in self.order.iter().enumerate() {
+			sql.push_str(if i == 0 { " ORDER BY " } else { ", " });
+			quote_ident(&mut sql, order.column);
+			sql.push_str(if order.desc { " DESC" } else { " ASC" });
+		}
+		if let Some(limit) = self.limit {
+			write!(sql, " LIMIT {}", limit).unwrap();
+		}
+		if let Some(offset) = self.offset {
+			write!(sql, " OFFSET {}", offset).unwrap();
+		}
+		(sql, params)
+	}
+}
+
+impl<T: TryFromRow> Query<T> {
+	/// Run the query
+	pub fn fetch(&self, client: &mut SyncClient) -> Result<Vec<T>, SqlError> {
+		let (sql, params) = self.to_sql();
+		client.query(sql.as_str(), &params)?.iter().map(T::from_row).collect()
+	}
+
+	/// Run the query on a client or, with their features, a deadpool or bb8 pool or connection
+	pub async fn fetch_async<C: AsyncConnection>(&self, client: &C) -> Result<Vec<T>, C::Error> {
+		let (sql, params) = self.to_sql();
+		let rows = client.query(sql.as_str(), &params).await?;
+		Ok(rows.iter().map(T::from_row).collect::<Result<_, SqlError>>()?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct Person;
+
+	static PEOPLE: Table = Table {
+		schema: "people",
+		name: "people",
+		columns: &["id", "name", "age"],
+	};
+
+	const ID: TypedColumn<Person, i32> = TypedColumn::new("id");
+	const NAME: TypedColumn<Person, String> = TypedColumn::new("name");
+	const AGE: TypedColumn<Person, i32> = TypedColumn::new("age");
+
+	/// The SQL of a query along with its parameters as they're debug printed
+	fn sql(query: &Query<Person>) -> (String, Vec<String>) {
+		let (sql, params) = query.to_sql();
+		(sql, params.iter().map(|v| format!("{:?}", v)).collect())
+	}
+
+	#[test]
+	fn selects_every_column() {
+		let (sql, params) = sql(&Query::new(&PEOPLE));
+		assert_eq!(sql, r#"SELECT "id", "name", "age" FROM "people"."people""#);
+		assert!(params.is_empty());
+	}
+
+	#[test]
+	fn identifiers_are_quoted() {
+		static TABLE: Table = Table {
+			schema: "my schema",
+			name: "say \"hi\"",
+			columns: &["a\"b"],
+		};
+		let (sql, _) = Query::<Person>::new(&TABLE).to_sql();
+		assert_eq!(sql, r#"SELECT "a""b" FROM "my schema"."say ""hi""""#);
+	}
+
+	#[test]
+	fn filters_are_combined_with_and() {
+		let query = Query::new(&PEOPLE)
+			.filter(AGE.ge(18))
+			.filter(NAME.like("A%"))
+			.filter(ID.is_not_null());
+		let (sql, params) = sql(&query);
+		assert_eq!(
+			sql,
+			r#"SELECT "id", "name", "age" FROM "people"."people" WHERE ("age" >= $1) AND ("name" LIKE $2) AND ("id" IS NOT NULL)"#
+		);
+		assert_eq!(params, ["18", "\"A%\""]);
+	}
+
+	#[test]
+	fn placeholders_are_numbered_in_order() {
+		let query = Query::new(&PEOPLE)
+			.filter(ID.in_list(vec![1, 2, 3]).or(NAME.eq(String::from("Bob")).not()))
+			.filter(AGE.lt(65).and(AGE.ne(40)));
+		let (sql, params) = sql(&query);
+		assert_eq!(
+			sql,
+			r#"SELECT "id", "name", "age" FROM "people"."people" WHERE (("id" IN ($1, $2, $3)) OR (NOT ("name" = $4))) AND (("age" < $5) AND ("age" <> $6))"#
+		);
+		assert_eq!(params, ["1", "2", "3", "\"Bob\"", "65", "40"]);
+	}
+
+	#[test]
+	fn empty_in_list_matches_nothing() {
+		let (sql, params) = sql(&Query::new(&PEOPLE).filter(ID.in_list(Vec::new())).filter(AGE.gt(1)));
+		assert_eq!(
+			sql,
+			r#"SELECT "id", "name", "age" FROM "people"."people" WHERE (FALSE) AND ("age" > $1)"#
+		);
+		assert_eq!(params, ["1"]);
+	}
+
+	#[test]
+	fn ordering_limit_and_offset() {
+		let query = Query::new(&PEOPLE)
+			.filter(NAME.is_null())
+			.order_by(AGE.desc())
+			.order_by(ID)
+			.limit(10)
+			.offset(20);
+		let (sql, params) = sql(&query);
+		assert_eq!(
+			sql,
+			r#"SELECT "id", "name", "age" FROM "people"."people" WHERE ("name" IS NULL) ORDER BY "age" DESC, "id" ASC LIMIT 10 OFFSET 20"#
+		);
+		assert!(params.is_empty());
+	}
+
+	#[test]
+	fn offset_without_limit() {
+		let (sql, _) = sql(&Query::new(&PEOPLE).offset(5).order_by(NAME.asc()).filter(AGE.le(3)));
+		assert_eq!(
+			sql,
+			r#"SELECT "id", "name", "age" FROM "people"."people" WHERE ("age" <= $1) ORDER BY "name" ASC OFFSET 5"#
+		);
+	}
+}
@@ -0,0 +1,702 @@
+//! Runtime verification that a database still matches the one a mapping was generated from
+//!
+//! Generated code embeds a [`SchemaFingerprint`] describing every mapped table and function. Checking it against the catalog of
+//! the database being connected to returns a [`SchemaReport`] listing everything that no longer matches
+//!
+//! Types are compared by schema and name rather than oid so that a fingerprint is valid for every database built from the same
+//! schema
+use super::{AsyncClient, SqlError, SyncClient};
+use std::fmt;
+
+/// A type as it's named in the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeRef {
+	pub schema: &'static str,
+	pub name: &'static str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnFingerprint {
+	pub name: &'static str,
+	pub typ: TypeRef,
+	pub not_null: bool,
+	pub dimensions: i32,
+}
+
+/// The columns of a table, view, or composite type, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableFingerprint {
+	pub schema: &'static str,
+	pub name: &'static str,
+	pub columns: &'static [ColumnFingerprint],
+}
+
+/// A single function (one overload of an overloaded function)
+///
+/// `returns` is `pg_catalog.record` for functions returning an anonymous table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FunctionFingerprint {
+	pub schema: &'static str,
+	pub name: &'static str,
+	pub args: &'static [TypeRef],
+	pub returns: TypeRef,
+	pub returns_set: bool,
+	/// The types of the columns of an anonymous table (`RETURNS TABLE`) in order, empty for other functions. Only the types
+	/// are compared as the rows are read by position
+	pub returns_columns: &'static [TypeRef],
+}
+
+/// Everything mapped by a generated crate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchemaFingerprint {
+	pub tables: &'static [TableFingerprint],
+	pub functions: &'static [FunctionFingerprint],
+}
+
+/// A difference between the fingerprint and the database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+	MissingTable {
+		table: String,
+	},
+	MissingColumn {
+		table: String,
+		column: String,
+	},
+	/// A column the mapping doesn't know about, only reported when it's not after all the mapped columns as rows are read by position
+	UnexpectedColumn {
+		table: String,
+		column: String,
+	},
+	ColumnPosition {
+		table: String,
+		column: String,
+		expected: usize,
+		found: usize,
+	},
+	ColumnType {
+		table: String,
+		column: String,
+		expected: String,
+		found: String,
+	},
+	ColumnNullability {
+		table: String,
+		column: String,
+		expected_not_null: bool,
+	},
+	MissingFunction {
+		function: String,
+	},
+	FunctionReturn {
+		function: String,
+		expected: String,
+		found: String,
+	},
+}
+
+impl fmt::Display for Mismatch {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		use Mismatch::*;
+		match self {
+			MissingTable { table } => write!(f, "table {} does not exist", table),
+			MissingColumn { table, column } => write!(f, "column {}.{} does not exist", table, column),
+			UnexpectedColumn { table, column } => write!(f, "column {}.{} is not in the mapping", table, column),
+			ColumnPosition {
+				table,
+				column,
+				expected,
+				found,
+			} => write!(
+				f,
+				"column {}.{} is at position {} (expected {})",
+				table, column, found, expected
+			),
+			ColumnType {
+				table,
+				column,
+				expected,
+				found,
+			} => write!(f, "column {}.{} has type {} (expected {})", table, column, found, expected),
+			ColumnNullability {
+				table,
+				column,
+				expected_not_null,
+			} => {
+				if *expected_not_null {
+					write!(f, "column {}.{} is nullable (expected NOT NULL)", table, column)
+				} else {
+					write!(f, "column {}.{} is NOT NULL (expected nullable)", table, column)
+				}
+			},
+			MissingFunction { function } => write!(f, "function {} does not exist", function),
+			FunctionReturn {
+				function,
+				expected,
+				found,
+			} => write!(f, "function {} returns {} (expected {})", function, found, expected),
+		}
+	}
+}
+
+/// The result of checking a [`SchemaFingerprint`] against a database
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaReport {
+	pub mismatches: Vec<Mismatch>,
+}
+
+impl SchemaReport {
+	/// True if the database matches the mapping
+	pub fn is_ok(&self) -> bool {
+		self.mismatches.is_empty()
+	}
+}
+
+impl fmt::Display for SchemaReport {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if self.is_ok() {
+			return write!(f, "database schema matches the mapping");
+		}
+		writeln!(f, "database schema does not match the mapping:")?;
+		for mismatch in &self.mismatches {
+			writeln!(f, "\t{}", mismatch)?;
+		}
+		Ok(())
+	}
+}
+
+const GET_COLUMNS: &str = "SELECT a.attname,
+	tn.nspname,
+	t.typname,
+	a.attnotnull OR (t.typtype = 'd' AND t.typnotnull),
+	a.attndims
+FROM pg_attribute a
+JOIN pg_class c ON a.attrelid = c.oid
+JOIN pg_namespace n ON c.relnamespace = n.oid
+JOIN pg_type t ON a.atttypid = t.oid
+JOIN pg_namespace tn ON t.typnamespace = tn.oid
+WHERE n.nspname = $1 AND c.relname = $2
+	AND a.attnum > 0 AND NOT a.attisdropped
+ORDER BY a.attnum ASC";
+
+const GET_FUNCTIONS: &str = "SELECT ARRAY(
+		SELECT tn.nspname || '.' || t.typname
+		FROM unnest(COALESCE(p.proallargtypes, p.proargtypes::oid[])) WITH ORDINALITY AS a(typ, i)
+		JOIN pg_type t ON t.oid = a.typ
+		JOIN pg_namespace tn ON tn.oid = t.typnamespace
+		WHERE COALESCE(p.proargmodes[a.i], 'i') = 'i'
+		ORDER BY a.i
+	),
+	rn.nspname || '.' || rt.typname,
+	p.proretset,
+	ARRAY(
+		SELECT tn.nspname || '.' || t.typname
+		FROM unnest(p.proallargtypes) WITH ORDINALITY AS a(typ, i)
+		JOIN pg_type t ON t.oid = a.typ
+		JOIN pg_namespace tn ON tn.oid = t.typnamespace
+		WHERE p.proargmodes[a.i] = 't'
+		ORDER BY a.i
+	)
+FROM pg_proc p
+JOIN pg_namespace n ON p.pronamespace = n.oid
+JOIN pg_type rt ON p.prorettype = rt.oid
+JOIN pg_namespace rn ON rt.typnamespace = rn.oid
+WHERE n.nspname = $1 AND p.proname = $2";
+
+type ColumnRow = (String, String, String, bool, i32);
+type FunctionRow = (Vec<String>, String, bool, Vec<String>);
+
+impl fmt::Display for TypeRef {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}.{}", self.schema, self.name)
+	}
+}
+
+fn type_string(schema: &str, name: &str, dimensions: i32) -> String {
+	let mut ret = format!("{}.{}", schema, name);
+	for _ in 0..dimensions {
+		ret += "[]";
+	}
+	ret
+}
+
+/// The return type as it's reported, an anonymous table is given by the types of its columns
+fn return_string(typ: &str, returns_set: bool, columns: &[String]) -> String {
+	if !columns.is_empty() {
+		format!("TABLE({})", columns.join(", "))
+	} else if returns_set {
+		format!("SETOF {}", typ)
+	} else {
+		String::from(typ)
+	}
+}
+
+impl TableFingerprint {
+	fn check(&self, rows: &[ColumnRow], mismatches: &mut Vec<Mismatch>) {
+		let table = format!("{}.{}", self.schema, self.name);
+		if rows.is_empty() {
+			mismatches.push(Mismatch::MissingTable { table });
+			return;
+		}
+		for (i, col) in self.columns.iter().enumerate() {
+			let found = rows.iter().enumerate().find(|(_, v)| v.0 == col.name);
+			let (pos, (_, typ_schema, typ_name, not_null, dimensions)) = match found {
+				Some(v) => v,
+				None => {
+					mismatches.push(Mismatch::MissingColumn {
+						table: table.clone(),
+						column: String::from(col.name),
+					});
+					continue;
+				},
+			};
+			if pos != i {
+				mismatches.push(Mismatch::ColumnPosition {
+					table: table.clone(),
+					column: String::from(col.name),
+					expected: i,
+					found: pos,
+				});
+			}
+			let expected = type_string(col.typ.schema, col.typ.name, col.dimensions);
+			let found = type_string(typ_schema, typ_name, *dimensions);
+			if expected != found {
+				mismatches.push(Mismatch::ColumnType {
+					table: table.clone(),
+					column: String::from(col.name),
+					expected,
+					found,
+				});
+			}
+			if *not_null != col.not_null {
+				mismatches.push(Mismatch::ColumnNullability {
+					table: table.clone(),
+					column: String::from(col.name),
+					expected_not_null: col.not_null,
+				});
+			}
+		}
+		// extra columns after the mapped ones are harmless as rows are read by position
+		for (name, ..) in rows.iter().take(self.columns.len()) {
+			if !self.columns.iter().any(|v| v.name == name) {
+				mismatches.push(Mismatch::UnexpectedColumn {
+					table: table.clone(),
+					column: name.clone(),
+				});
+			}
+		}
+	}
+}
+
+impl FunctionFingerprint {
+	fn check(&self, rows: &[FunctionRow], mismatches: &mut Vec<Mismatch>) {
+		let args: Vec<String> = self.args.iter().map(|v| v.to_string()).collect();
+		let function = format!("{}.{}({})", self.schema, self.name, args.join(", "));
+		match rows.iter().find(|v| v.0 == args) {
+			Some((_, returns, returns_set, columns)) => {
+				let expected_columns: Vec<String> = self.returns_columns.iter().map(|v| v.to_string()).collect();
+				let expected = return_string(&self.returns.to_string(), self.returns_set, &expected_columns);
+				let found = return_string(returns, *returns_set, columns);
+				if expected != found {
+					mismatches.push(Mismatch::FunctionReturn {
+						function,
+						expected,
+						found,
+					});
+				}
+			},
+			None => mismatches.push(Mismatch::MissingFunction { function }),
+		}
+	}
+}
+
+impl SchemaFingerprint {
+	/// Compare the fingerprint to the database the client is connected to
+	pub fn verify(&self, client: &mut SyncClient) -> Result<SchemaReport, SqlError> {
+		let mut mismatches = Vec::new();
+		let columns_stmt = client.prepare(GET_COLUMNS)?;
+		for table in self.tables {
+			let rows = client
+				.query(&columns_stmt, &[&table.schema, &table.name])?
+				.iter()
+				.map(|v| Ok((v.try_get(0)?, v.try_get(1)?, v.try_get(2)?, v.try_get(3)?, v.try_get(4)?)))
+				.collect::<Result<Vec<ColumnRow>, SqlError>>()?;
+			table.check(&rows, &mut mismatches);
+		}
+		let functions_stmt = client.prepare(GET_FUNCTIONS)?;
+		for function in self.functions {
+			let rows = client
+				.query(&functions_stmt, &[&function.schema, &function.name])?
+				.iter()
+				.map(|v| Ok((v.try_get(0)?, v.try_get(1)?, v.try_get(2)?, v.try_get(3)?)))
+				.collect::<Result<Vec<FunctionRow>, SqlError>>()?;
+			function.check(&rows, &mut mismatches);
+		}
+		Ok(SchemaReport { mismatches })
+	}
+
+	/// Compare the fingerprint to the database the client is connected to
+	pub async fn verify_async(&self, client: &AsyncClient) -> Result<SchemaReport, SqlError> {
+		let mut mismatches = Vec::new();
+		let columns_stmt = client.prepare(GET_COLUMNS).await?;
+		for table in self.tables {
+			let rows = client
+				.query(&columns_stmt, &[&table.schema, &table.name])
+				.await?
+				.iter()
+				.map(|v| Ok((v.try_get(0)?, v.try_get(1)?, v.try_get(2)?, v.try_get(3)?, v.try_get(4)?)))
+				.collect::<Result<Vec<ColumnRow>, SqlError>>()?;
+			table.check(&rows, &mut mismatches);
+		}
+		let functions_stmt = client.prepare(GET_FUNCTIONS).await?;
+		for function in self.functions {
+			let rows = client
+				.query(&functions_stmt, &[&function.schema, &function.name])
+				.await?
+				.iter()
+				.map(|v| Ok((v.try_get(0)?, v.try_get(1)?, v.try_get(2)?, v.try_get(3)?)))
+				.collect::<Result<Vec<FunctionRow>, SqlError>>()?;
+			function.check(&rows, &mut mismatches);
+		}
+		Ok(SchemaReport { mismatches })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const INT4: TypeRef = TypeRef {
+		schema: "pg_catalog",
+		name: "int4",
+	};
+	const TEXT: TypeRef = TypeRef {
+		schema: "pg_catalog",
+		name: "text",
+	};
+	const RECORD: TypeRef = TypeRef {
+		schema: "pg_catalog",
+		name: "record",
+	};
+	/// A domain declared NOT NULL, its columns are reported as NOT NULL whether or not they're declared that way
+	const EMAIL: TypeRef = TypeRef {
+		schema: "public",
+		name: "email",
+	};
+
+	const fn column(name: &'static str, typ: TypeRef, not_null: bool, dimensions: i32) -> ColumnFingerprint {
+		ColumnFingerprint {
+			name,
+			typ,
+			not_null,
+			dimensions,
+		}
+	}
+
+	static PEOPLE: TableFingerprint = TableFingerprint {
+		schema: "people",
+		name: "people",
+		columns: &[
+			column("id", INT4, true, 0),
+			column("name", TEXT, true, 0),
+			column("tags", TEXT, false, 1),
+			column("email", EMAIL, true, 0),
+		],
+	};
+
+	fn row(name: &str, typ: TypeRef, not_null: bool, dimensions: i32) -> ColumnRow {
+		(
+			String::from(name),
+			String::from(typ.schema),
+			String::from(typ.name),
+			not_null,
+			dimensions,
+		)
+	}
+
+	/// The rows of a database matching `PEOPLE`
+	fn people_rows() -> Vec<ColumnRow> {
+		vec![
+			row("id", INT4, true, 0),
+			row("name", TEXT, true, 0),
+			row("tags", TEXT, false, 1),
+			row("email", EMAIL, true, 0),
+		]
+	}
+
+	fn check_table(rows: &[ColumnRow]) -> Vec<Mismatch> {
+		let mut mismatches = Vec::new();
+		PEOPLE.check(rows, &mut mismatches);
+		mismatches
+	}
+
+	fn table() -> String {
+		String::from("people.people")
+	}
+
+	#[test]
+	fn table_matches() {
+		assert_eq!(check_table(&people_rows()), []);
+		// columns added after the mapped ones aren't read
+		let mut rows = people_rows();
+		rows.push(row("added", TEXT, false, 0));
+		assert_eq!(check_table(&rows), []);
+	}
+
+	#[test]
+	fn missing_table() {
+		assert_eq!(check_table(&[]), [Mismatch::MissingTable { table: table() }]);
+	}
+
+	#[test]
+	fn missing_and_unexpected_columns() {
+		let mut rows = people_rows();
+		rows[1].0 = String::from("nickname");
+		assert_eq!(
+			check_table(&rows),
+			[
+				Mismatch::MissingColumn {
+					table: table(),
+					column: String::from("name"),
+				},
+				Mismatch::UnexpectedColumn {
+					table: table(),
+					column: String::from("nickname"),
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn column_positions() {
+		// a column added between the mapped ones moves the rest
+		let mut rows = people_rows();
+		rows.insert(1, row("added", TEXT, false, 0));
+		let moved = |column: &str, expected, found| Mismatch::ColumnPosition {
+			table: table(),
+			column: String::from(column),
+			expected,
+			found,
+		};
+		assert_eq!(
+			check_table(&rows),
+			[
+				moved("name", 1, 2),
+				moved("tags", 2, 3),
+				moved("email", 3, 4),
+				Mismatch::UnexpectedColumn {
+					table: table(),
+					column: String::from("added"),
+				},
+			]
+		);
+
+		// swapped columns don't leave any unexpected
+		let mut rows = people_rows();
+		rows.swap(0, 1);
+		assert_eq!(check_table(&rows), [moved("id", 0, 1), moved("name", 1, 0)]);
+	}
+
+	#[test]
+	fn column_types() {
+		let mut rows = people_rows();
+		rows[0].2 = String::from("int8");
+		rows[2].4 = 0;
+		let changed = |column: &str, expected: &str, found: &str| Mismatch::ColumnType {
+			table: table(),
+			column: String::from(column),
+			expected: String::from(expected),
+			found: String::from(found),
+		};
+		assert_eq!(
+			check_table(&rows),
+			[
+				changed("id", "pg_catalog.int4", "pg_catalog.int8"),
+				changed("tags", "pg_catalog.text[]", "pg_catalog.text"),
+			]
+		);
+	}
+
+	#[test]
+	fn column_nullability() {
+		let mut rows = people_rows();
+		rows[1].3 = false;
+		rows[2].3 = true;
+		// the domain was altered to allow nulls
+		rows[3].3 = false;
+		let changed = |column: &str, expected_not_null| Mismatch::ColumnNullability {
+			table: table(),
+			column: String::from(column),
+			expected_not_null,
+		};
+		assert_eq!(
+			check_table(&rows),
+			[changed("name", true), changed("tags", false), changed("email", true)]
+		);
+	}
+
+	static GREET: FunctionFingerprint = FunctionFingerprint {
+		schema: "public",
+		name: "greet",
+		args: &[INT4, TEXT],
+		returns: TEXT,
+		returns_set: false,
+		returns_columns: &[],
+	};
+
+	static PAIRS: FunctionFingerprint = FunctionFingerprint {
+		schema: "public",
+		name: "pairs",
+		args: &[],
+		returns: RECORD,
+		returns_set: true,
+		returns_columns: &[INT4, TEXT],
+	};
+
+	fn function_row(args: &[&str], returns: &str, returns_set: bool, columns: &[&str]) -> FunctionRow {
+		(
+			args.iter().map(|v| String::from(*v)).collect(),
+			String::from(returns),
+			returns_set,
+			columns.iter().map(|v| String::from(*v)).collect(),
+		)
+	}
+
+	fn check_function(function: &FunctionFingerprint, rows: &[FunctionRow]) -> Vec<Mismatch> {
+		let mut mismatches = Vec::new();
+		function.check(rows, &mut mismatches);
+		mismatches
+	}
+
+	#[test]
+	fn function_matches_its_overload() {
+		let rows = [
+			function_row(&["pg_catalog.text"], "pg_catalog.int4", true, &[]),
+			function_row(&["pg_catalog.int4", "pg_catalog.text"], "pg_catalog.text", false, &[]),
+		];
+		assert_eq!(check_function(&GREET, &rows), []);
+
+		let pairs = [function_row(
+			&[],
+			"pg_catalog.record",
+			true,
+			&["pg_catalog.int4", "pg_catalog.text"],
+		)];
+		assert_eq!(check_function(&PAIRS, &pairs), []);
+	}
+
+	#[test]
+	fn missing_function() {
+		let rows = [function_row(
+			&["pg_catalog.text", "pg_catalog.int4"],
+			"pg_catalog.text",
+			false,
+			&[],
+		)];
+		let missing = Mismatch::MissingFunction {
+			function: String::from("public.greet(pg_catalog.int4, pg_catalog.text)"),
+		};
+		assert_eq!(check_function(&GREET, &rows), vec![missing.clone()]);
+		assert_eq!(check_function(&GREET, &[]), [missing]);
+	}
+
+	#[test]
+	fn function_returns() {
+		let returns = |function: &str, expected: &str, found: &str| Mismatch::FunctionReturn {
+			function: String::from(function),
+			expected: String::from(expected),
+			found: String::from(found),
+		};
+		let greet = "public.greet(pg_catalog.int4, pg_catalog.text)";
+		let rows = [function_row(
+			&["pg_catalog.int4", "pg_catalog.text"],
+			"pg_catalog.text",
+			true,
+			&[],
+		)];
+		assert_eq!(
+			check_function(&GREET, &rows),
+			[returns(greet, "pg_catalog.text", "SETOF pg_catalog.text")]
+		);
+		let rows = [function_row(
+			&["pg_catalog.int4", "pg_catalog.text"],
+			"pg_catalog.varchar",
+			false,
+			&[],
+		)];
+		assert_eq!(
+			check_function(&GREET, &rows),
+			[returns(greet, "pg_catalog.text", "pg_catalog.varchar")]
+		);
+
+		// only the types of the columns of a `TABLE(...)` are compared
+		let rows = [function_row(
+			&[],
+			"pg_catalog.record",
+			true,
+			&["pg_catalog.int4", "pg_catalog.int8"],
+		)];
+		assert_eq!(
+			check_function(&PAIRS, &rows),
+			[returns(
+				"public.pairs()",
+				"TABLE(pg_catalog.int4, pg_catalog.text)",
+				"TABLE(pg_catalog.int4, pg_catalog.int8)"
+			)]
+		);
+		let rows = [function_row(&[], "pg_catalog.record", true, &[])];
+		assert_eq!(
+			check_function(&PAIRS, &rows),
+			[returns(
+				"public.pairs()",
+				"TABLE(pg_catalog.int4, pg_catalog.text)",
+				"SETOF pg_catalog.record"
+			)]
+		);
+	}
+
+	#[test]
+	fn report_display() {
+		assert_eq!(
+			SchemaReport::default().to_string(),
+			"database schema matches the mapping"
+		);
+		let report = SchemaReport {
+			mismatches: check_table(&[]),
+		};
+		assert!(!report.is_ok());
+		assert_eq!(
+			report.to_string(),
+			"database schema does not match the mapping:\n\ttable people.people does not exist\n"
+		);
+	}
+
+	#[test]
+	#[ignore = "adds a schema to the database given by SQL_DB_MAPPER_TEST_DB"]
+	fn not_null_domains_in_database() {
+		let conn = std::env::var("SQL_DB_MAPPER_TEST_DB").expect("SQL_DB_MAPPER_TEST_DB isn't set");
+		let mut client = SyncClient::connect(&conn, postgres::NoTls).unwrap();
+		client
+			.batch_execute(
+				"DROP SCHEMA IF EXISTS check_domains CASCADE;
+				CREATE SCHEMA check_domains;
+				CREATE DOMAIN check_domains.email AS text NOT NULL;
+				CREATE TABLE check_domains.t(a check_domains.email, b text);",
+			)
+			.unwrap();
+		const DOMAIN: TypeRef = TypeRef {
+			schema: "check_domains",
+			name: "email",
+		};
+		static FINGERPRINT: SchemaFingerprint = SchemaFingerprint {
+			tables: &[TableFingerprint {
+				schema: "check_domains",
+				name: "t",
+				columns: &[column("a", DOMAIN, true, 0), column("b", TEXT, false, 0)],
+			}],
+			functions: &[],
+		};
+		assert_eq!(FINGERPRINT.verify(&mut client).unwrap(), SchemaReport::default());
+		client.batch_execute("DROP SCHEMA check_domains CASCADE").unwrap();
+	}
+}
@@ -70,6 +70,11 @@ try_from_row!(Vec<u8>);
 try_from_row!(std::collections::HashMap<String, Option<String>>);
 try_from_row!(std::time::SystemTime);
 try_from_row!(std::net::IpAddr);
+try_from_row!(super::Cidr);
+try_from_row!(super::PgInterval);
+try_from_row!(super::PgMoney);
+try_from_row!(super::Ltree);
+try_from_row!(super::Vector);
 
 
 // Provide auto implementations for tuples (usefule for when doing quick+dirty sql)